@@ -1,5 +1,9 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("cargo:rerun-if-changed=src/proto/cdk_ldk_management.proto");
-    tonic_build::compile_protos("src/proto/cdk_ldk_management.proto")?;
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+    // The descriptor set is served by the reflection service
+    tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("cdk_ldk_management_descriptor.bin"))
+        .compile(&["src/proto/cdk_ldk_management.proto"], &["src/proto"])?;
     Ok(())
 }
@@ -0,0 +1,113 @@
+//! Outgoing payments abandoned by the operator
+//!
+//! A melt whose payment stays pending holds the user's proofs until the
+//! payment resolves. LDK Node has no way to abandon an outgoing payment, so
+//! an abandoned payment is only recorded here: the mint is told it failed and
+//! releases the melt, while LDK keeps tracking the HTLCs.
+//!
+//! Payments can only be abandoned once they have been pending for the melt
+//! cancellation window, which should exceed the longest HTLC timeout, so the
+//! HTLCs have failed back by then. Should an abandoned payment succeed after
+//! all, an alert is raised as the mint has released the melt.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// File abandoned payments are kept in, inside the storage directory
+pub const ABANDONED_PAYMENTS_FILE_NAME: &str = "abandoned_payments.jsonl";
+
+/// Default time a payment must be pending before it can be abandoned, above
+/// LDK's default maximum HTLC timeout of 1008 blocks
+pub const DEFAULT_MELT_CANCELLATION_WINDOW: Duration = Duration::from_secs(8 * 24 * 60 * 60);
+
+/// Outgoing payment the mint is told has failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbandonedPayment {
+    /// Hex encoded payment id
+    pub payment_id: String,
+    pub amount_msat: Option<u64>,
+    /// Unix time the payment was abandoned
+    pub abandoned_at: u64,
+}
+
+/// Persistent record of abandoned payments
+#[derive(Debug)]
+pub struct AbandonedPayments {
+    path: PathBuf,
+    payments: Mutex<BTreeMap<String, AbandonedPayment>>,
+}
+
+impl AbandonedPayments {
+    /// Open the record in the storage directory, starting empty if none exists
+    pub fn new(storage_dir: PathBuf) -> Self {
+        let path = storage_dir.join(ABANDONED_PAYMENTS_FILE_NAME);
+
+        let payments = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .filter_map(
+                    |line| match serde_json::from_str::<AbandonedPayment>(line) {
+                        Ok(payment) => Some((payment.payment_id.clone(), payment)),
+                        Err(err) => {
+                            tracing::warn!(
+                                "Skipping unreadable abandoned payment in {}: {}",
+                                path.display(),
+                                err
+                            );
+                            None
+                        }
+                    },
+                )
+                .collect(),
+            Err(_) => BTreeMap::new(),
+        };
+
+        Self {
+            path,
+            payments: Mutex::new(payments),
+        }
+    }
+
+    /// Record a payment as abandoned, abandoning it again keeps the first record
+    pub fn abandon(&self, payment: AbandonedPayment) -> Result<AbandonedPayment> {
+        let mut payments = self.payments.lock().expect("abandoned lock poisoned");
+
+        if let Some(existing) = payments.get(&payment.payment_id) {
+            return Ok(existing.clone());
+        }
+
+        let mut line = serde_json::to_string(&payment)?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .with_context(|| {
+                format!(
+                    "Could not write abandoned payment to {}",
+                    self.path.display()
+                )
+            })?;
+
+        payments.insert(payment.payment_id.clone(), payment.clone());
+        Ok(payment)
+    }
+
+    /// Whether a payment, by hex encoded id, was abandoned
+    pub fn contains(&self, payment_id: &str) -> bool {
+        self.payments
+            .lock()
+            .expect("abandoned lock poisoned")
+            .contains_key(payment_id)
+    }
+}
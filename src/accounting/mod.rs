@@ -0,0 +1,104 @@
+//! Monthly fee income and expense of the node
+//!
+//! Routing fee income comes from the forwards in the activity log, fees paid
+//! from the outgoing payments in the payment index. Months are calendar
+//! months in UTC.
+//!
+//! Onchain fees cover every transaction the onchain wallet sent: channel
+//! opens, sweeps of closed channels and onchain melts. Fees of cooperative
+//! and force closes are taken from the channel balance rather than paid by
+//! the wallet, so LDK Node does not report them and they are not included.
+
+use std::collections::BTreeMap;
+
+/// Default period of a fee breakdown, the retention of the activity log
+pub const DEFAULT_FEE_BREAKDOWN_PERIOD_SECS: u64 = 365 * 24 * 60 * 60;
+
+/// Fees paid in a month, as read from the payment index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeesPaid {
+    /// Month as `YYYY-MM`
+    pub month: String,
+    /// Routing fees of outgoing Lightning payments
+    pub lightning_msat: u64,
+    /// Fees of onchain transactions sent by the wallet
+    pub onchain_msat: u64,
+}
+
+/// Fee income and expense of one month
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MonthlyFees {
+    /// Month as `YYYY-MM`
+    pub month: String,
+    /// Fees earned forwarding payments
+    pub routing_income_msat: u64,
+    /// Routing fees of outgoing Lightning payments, melts included
+    pub lightning_fees_paid_msat: u64,
+    /// Fees of onchain transactions sent by the wallet
+    pub onchain_fees_paid_msat: u64,
+}
+
+impl MonthlyFees {
+    /// Income less expenses, negative when the node paid more than it earned
+    pub fn net_msat(&self) -> i64 {
+        self.routing_income_msat as i64
+            - self.lightning_fees_paid_msat as i64
+            - self.onchain_fees_paid_msat as i64
+    }
+}
+
+/// Month of a unix timestamp as `YYYY-MM`, in UTC
+pub fn month_of(timestamp: u64) -> String {
+    // Civil date from days since the epoch, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}")
+}
+
+/// Combine forward fees and fees paid into a breakdown per month, oldest first
+///
+/// `forward_fees` are the time and fee of every forward in the period.
+/// Months without income or expenses are left out.
+pub fn monthly_fees(
+    forward_fees: impl IntoIterator<Item = (u64, u64)>,
+    fees_paid: Vec<FeesPaid>,
+) -> Vec<MonthlyFees> {
+    let mut months: BTreeMap<String, MonthlyFees> = BTreeMap::new();
+
+    for (timestamp, fee_msat) in forward_fees {
+        let month = month_of(timestamp);
+        months
+            .entry(month.clone())
+            .or_insert_with(|| MonthlyFees {
+                month,
+                ..MonthlyFees::default()
+            })
+            .routing_income_msat += fee_msat;
+    }
+
+    for paid in fees_paid {
+        let entry = months
+            .entry(paid.month.clone())
+            .or_insert_with(|| MonthlyFees {
+                month: paid.month,
+                ..MonthlyFees::default()
+            });
+        entry.lightning_fees_paid_msat += paid.lightning_msat;
+        entry.onchain_fees_paid_msat += paid.onchain_msat;
+    }
+
+    months.into_values().collect()
+}
@@ -0,0 +1,516 @@
+//! Channel activity history for utilization reports
+//!
+//! LDK Node keeps no history of forwards or channel availability, so the
+//! event handler and the peer monitor append activity records to a JSON
+//! lines file in the storage directory. Reports aggregate the records over a
+//! period.
+//!
+//! LDK Node does not tell which channel an incoming or outgoing payment used,
+//! so payment volume and fees paid are reported for the node as a whole.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use cdk_common::util::unix_time;
+use ldk_node::ChannelDetails;
+use serde::{Deserialize, Serialize};
+
+/// File activity records are appended to, inside the storage directory
+const ACTIVITY_FILE_NAME: &str = "channel_activity.jsonl";
+
+/// Records older than this are dropped when the history is loaded
+const RETENTION_SECS: u64 = 365 * 24 * 60 * 60;
+
+/// Recorded channel or payment activity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActivityRecord {
+    /// HTLC forwarded from one channel to another
+    Forwarded {
+        timestamp: u64,
+        prev_channel_id: String,
+        next_channel_id: String,
+        amount_msat: u64,
+        fee_msat: u64,
+    },
+    /// Outgoing payment succeeded
+    PaymentSent {
+        timestamp: u64,
+        amount_msat: u64,
+        fee_msat: u64,
+    },
+    /// Incoming payment received
+    PaymentReceived { timestamp: u64, amount_msat: u64 },
+    /// Outgoing payment failed
+    PaymentFailed { timestamp: u64 },
+    /// Channel opened and ready for use
+    ChannelOpened { timestamp: u64, channel_id: String },
+    /// Channel closed
+    ChannelClosed { timestamp: u64, channel_id: String },
+    /// Channel became usable or unusable
+    Usability {
+        timestamp: u64,
+        channel_id: String,
+        usable: bool,
+    },
+    /// Channel balance changed, the channel was used by a payment or forward
+    Balance {
+        timestamp: u64,
+        channel_id: String,
+        outbound_msat: u64,
+    },
+}
+
+impl ActivityRecord {
+    fn timestamp(&self) -> u64 {
+        match self {
+            ActivityRecord::Forwarded { timestamp, .. }
+            | ActivityRecord::PaymentSent { timestamp, .. }
+            | ActivityRecord::PaymentReceived { timestamp, .. }
+            | ActivityRecord::PaymentFailed { timestamp }
+            | ActivityRecord::ChannelOpened { timestamp, .. }
+            | ActivityRecord::ChannelClosed { timestamp, .. }
+            | ActivityRecord::Usability { timestamp, .. }
+            | ActivityRecord::Balance { timestamp, .. } => *timestamp,
+        }
+    }
+}
+
+/// Last observed state of a channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelActivity {
+    /// Unix time the channel was first observed
+    pub first_seen: u64,
+    /// Unix time the channel balance last changed, or `first_seen`
+    pub last_active_at: u64,
+    /// Unix time the channel became unusable, `None` while usable
+    pub unusable_since: Option<u64>,
+    /// Outbound balance when last observed
+    outbound_msat: u64,
+}
+
+impl ChannelActivity {
+    fn apply(&mut self, record: &ActivityRecord) {
+        match record {
+            ActivityRecord::Usability {
+                timestamp, usable, ..
+            } => {
+                self.unusable_since = match usable {
+                    true => None,
+                    false => self.unusable_since.or(Some(*timestamp)),
+                };
+            }
+            ActivityRecord::Balance {
+                timestamp,
+                outbound_msat,
+                ..
+            } => {
+                self.outbound_msat = *outbound_msat;
+                self.last_active_at = self.last_active_at.max(*timestamp);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Utilization of a single channel over a report period
+#[derive(Debug, Clone, Default)]
+pub struct ChannelUsage {
+    pub channel_id: String,
+    pub counterparty_node_id: String,
+    pub channel_value_sat: u64,
+    pub is_usable: bool,
+    /// Amount forwarded into the node over this channel
+    pub forwarded_in_msat: u64,
+    /// Amount forwarded out of the node over this channel
+    pub forwarded_out_msat: u64,
+    /// Number of forwards using this channel in either direction
+    pub num_forwards: u64,
+    /// Fees earned on forwards leaving over this channel
+    pub fees_earned_msat: u64,
+    /// Seconds the channel was observed unusable
+    pub downtime_secs: u64,
+}
+
+/// Utilization of all open channels over a period
+#[derive(Debug, Clone, Default)]
+pub struct ChannelReport {
+    pub period_start: u64,
+    pub period_end: u64,
+    pub channels: Vec<ChannelUsage>,
+    /// Amount paid by successful outgoing payments, across all channels
+    pub payments_sent_msat: u64,
+    /// Routing fees paid by successful outgoing payments, across all channels
+    pub fees_paid_msat: u64,
+    /// Amount received by incoming payments, across all channels
+    pub payments_received_msat: u64,
+}
+
+/// Node wide activity over a period
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ActivityTotals {
+    pub payments_received: u64,
+    pub received_msat: u64,
+    pub payments_sent: u64,
+    pub sent_msat: u64,
+    pub payments_failed: u64,
+    /// Routing fees paid by outgoing payments
+    pub fees_paid_msat: u64,
+    pub forwards: u64,
+    /// Fees earned on forwards
+    pub fees_earned_msat: u64,
+    pub channels_opened: u64,
+    pub channels_closed: u64,
+}
+
+/// Append-only log of channel activity
+#[derive(Debug)]
+pub struct ActivityLog {
+    path: PathBuf,
+    records: Mutex<Vec<ActivityRecord>>,
+    /// Last observed state per channel, to record changes only
+    channels: Mutex<HashMap<String, ChannelActivity>>,
+}
+
+impl ActivityLog {
+    /// Load the history from the storage directory
+    pub fn new(storage_dir: PathBuf) -> Self {
+        let path = storage_dir.join(ACTIVITY_FILE_NAME);
+        let cutoff = unix_time().saturating_sub(RETENTION_SECS);
+
+        let records: Vec<ActivityRecord> = std::fs::read_to_string(&path)
+            .map(|contents| {
+                contents
+                    .lines()
+                    .filter_map(|line| serde_json::from_str::<ActivityRecord>(line).ok())
+                    .filter(|record| record.timestamp() >= cutoff)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Restore the channel state so a restart does not reset idle and
+        // downtime tracking
+        let mut channels: HashMap<String, ChannelActivity> = HashMap::new();
+        for record in &records {
+            let (ActivityRecord::Usability {
+                timestamp,
+                channel_id,
+                ..
+            }
+            | ActivityRecord::Balance {
+                timestamp,
+                channel_id,
+                ..
+            }) = record
+            else {
+                continue;
+            };
+
+            channels
+                .entry(channel_id.clone())
+                .or_insert(ChannelActivity {
+                    first_seen: *timestamp,
+                    last_active_at: *timestamp,
+                    unusable_since: None,
+                    outbound_msat: 0,
+                })
+                .apply(record);
+        }
+
+        Self {
+            path,
+            records: Mutex::new(records),
+            channels: Mutex::new(channels),
+        }
+    }
+
+    /// Last observed state of a channel, `None` if it was never observed
+    pub fn channel_activity(&self, channel_id: &str) -> Option<ChannelActivity> {
+        self.channels
+            .lock()
+            .expect("activity lock poisoned")
+            .get(channel_id)
+            .copied()
+    }
+
+    /// Record activity and append it to the history file
+    pub fn record(&self, record: ActivityRecord) {
+        // Append under the lock so a concurrent prune does not drop the record
+        let mut records = self.records.lock().expect("activity lock poisoned");
+
+        if let Err(err) = self.append(&record) {
+            tracing::warn!("Could not persist channel activity: {}", err);
+        }
+
+        records.push(record);
+    }
+
+    /// Drop records older than `cutoff` and rewrite the history file
+    ///
+    /// The latest usability and balance record of each channel is kept, so
+    /// idle and downtime tracking survive a restart. Returns the number of
+    /// dropped records.
+    pub fn prune(&self, cutoff: u64, dry_run: bool) -> anyhow::Result<usize> {
+        let mut records = self.records.lock().expect("activity lock poisoned");
+
+        let mut latest: HashMap<(&str, bool), usize> = HashMap::new();
+        for (index, record) in records.iter().enumerate() {
+            match record {
+                ActivityRecord::Usability { channel_id, .. } => {
+                    latest.insert((channel_id, false), index);
+                }
+                ActivityRecord::Balance { channel_id, .. } => {
+                    latest.insert((channel_id, true), index);
+                }
+                _ => (),
+            }
+        }
+        let latest: HashSet<usize> = latest.into_values().collect();
+        let keep: Vec<bool> = records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| record.timestamp() >= cutoff || latest.contains(&index))
+            .collect();
+
+        let pruned = keep.iter().filter(|keep| !**keep).count();
+        if pruned == 0 || dry_run {
+            return Ok(pruned);
+        }
+
+        let retained: Vec<ActivityRecord> = records
+            .iter()
+            .zip(&keep)
+            .filter(|(_, keep)| **keep)
+            .map(|(record, _)| record.clone())
+            .collect();
+
+        let mut contents = String::new();
+        for record in &retained {
+            contents.push_str(&serde_json::to_string(record)?);
+            contents.push('\n');
+        }
+
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        std::fs::write(&tmp_path, contents)?;
+        std::fs::rename(&tmp_path, &self.path)?;
+
+        *records = retained;
+
+        Ok(pruned)
+    }
+
+    /// Record channels whose usability or balance changed since the last observation
+    pub fn observe_channels(&self, channels: &[ChannelDetails], now: u64) {
+        let changed: Vec<ActivityRecord> = {
+            let mut tracked = self.channels.lock().expect("activity lock poisoned");
+            let mut changed = Vec::new();
+
+            for channel in channels {
+                let channel_id = channel.channel_id.to_string();
+                let previous = tracked.get(&channel_id).copied();
+                let state = tracked
+                    .entry(channel_id.clone())
+                    .or_insert(ChannelActivity {
+                        first_seen: now,
+                        last_active_at: now,
+                        unusable_since: None,
+                        outbound_msat: channel.outbound_capacity_msat,
+                    });
+
+                let mut records = Vec::new();
+                if previous.map(|p| p.unusable_since.is_none()) != Some(channel.is_usable) {
+                    records.push(ActivityRecord::Usability {
+                        timestamp: now,
+                        channel_id: channel_id.clone(),
+                        usable: channel.is_usable,
+                    });
+                }
+                if previous.map(|p| p.outbound_msat) != Some(channel.outbound_capacity_msat) {
+                    records.push(ActivityRecord::Balance {
+                        timestamp: now,
+                        channel_id,
+                        outbound_msat: channel.outbound_capacity_msat,
+                    });
+                }
+
+                for record in &records {
+                    state.apply(record);
+                }
+                changed.extend(records);
+            }
+
+            changed
+        };
+
+        for record in changed {
+            self.record(record);
+        }
+    }
+
+    /// Aggregate the activity of the given channels since `period_start`
+    pub fn report(
+        &self,
+        channels: &[ChannelDetails],
+        period_start: u64,
+        now: u64,
+    ) -> ChannelReport {
+        let records = self.records.lock().expect("activity lock poisoned");
+
+        let mut report = ChannelReport {
+            period_start,
+            period_end: now,
+            ..Default::default()
+        };
+
+        let mut usage: HashMap<String, ChannelUsage> = channels
+            .iter()
+            .map(|channel| {
+                (
+                    channel.channel_id.to_string(),
+                    ChannelUsage {
+                        channel_id: channel.channel_id.to_string(),
+                        counterparty_node_id: channel.counterparty_node_id.to_string(),
+                        channel_value_sat: channel.channel_value_sats,
+                        is_usable: channel.is_usable,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+
+        // Start of the current unusable stretch per channel
+        let mut down_since: HashMap<String, u64> = HashMap::new();
+
+        for record in records.iter() {
+            match record {
+                ActivityRecord::Usability {
+                    timestamp,
+                    channel_id,
+                    usable,
+                } => {
+                    let Some(channel) = usage.get_mut(channel_id) else {
+                        continue;
+                    };
+                    if *usable {
+                        if let Some(since) = down_since.remove(channel_id) {
+                            let start = since.max(period_start);
+                            channel.downtime_secs += timestamp.saturating_sub(start);
+                        }
+                    } else {
+                        down_since.entry(channel_id.clone()).or_insert(*timestamp);
+                    }
+                }
+                ActivityRecord::Balance { .. } => (),
+                record if record.timestamp() < period_start => (),
+                ActivityRecord::Forwarded {
+                    prev_channel_id,
+                    next_channel_id,
+                    amount_msat,
+                    fee_msat,
+                    ..
+                } => {
+                    if let Some(channel) = usage.get_mut(prev_channel_id) {
+                        channel.forwarded_in_msat += amount_msat + fee_msat;
+                        channel.num_forwards += 1;
+                    }
+                    if let Some(channel) = usage.get_mut(next_channel_id) {
+                        channel.forwarded_out_msat += amount_msat;
+                        channel.fees_earned_msat += fee_msat;
+                        channel.num_forwards += 1;
+                    }
+                }
+                ActivityRecord::PaymentSent {
+                    amount_msat,
+                    fee_msat,
+                    ..
+                } => {
+                    report.payments_sent_msat += amount_msat;
+                    report.fees_paid_msat += fee_msat;
+                }
+                ActivityRecord::PaymentReceived { amount_msat, .. } => {
+                    report.payments_received_msat += amount_msat;
+                }
+                ActivityRecord::PaymentFailed { .. }
+                | ActivityRecord::ChannelOpened { .. }
+                | ActivityRecord::ChannelClosed { .. } => (),
+            }
+        }
+
+        for (channel_id, since) in down_since {
+            if let Some(channel) = usage.get_mut(&channel_id) {
+                channel.downtime_secs += now.saturating_sub(since.max(period_start));
+            }
+        }
+
+        report.channels = usage.into_values().collect();
+        report
+            .channels
+            .sort_by(|a, b| a.channel_id.cmp(&b.channel_id));
+        report
+    }
+
+    /// Total activity of the node between `period_start` and `now`
+    pub fn totals(&self, period_start: u64, now: u64) -> ActivityTotals {
+        let records = self.records.lock().expect("activity lock poisoned");
+        let mut totals = ActivityTotals::default();
+
+        for record in records
+            .iter()
+            .filter(|record| (period_start..=now).contains(&record.timestamp()))
+        {
+            match record {
+                ActivityRecord::Forwarded { fee_msat, .. } => {
+                    totals.forwards += 1;
+                    totals.fees_earned_msat += fee_msat;
+                }
+                ActivityRecord::PaymentSent {
+                    amount_msat,
+                    fee_msat,
+                    ..
+                } => {
+                    totals.payments_sent += 1;
+                    totals.sent_msat += amount_msat;
+                    totals.fees_paid_msat += fee_msat;
+                }
+                ActivityRecord::PaymentReceived { amount_msat, .. } => {
+                    totals.payments_received += 1;
+                    totals.received_msat += amount_msat;
+                }
+                ActivityRecord::PaymentFailed { .. } => totals.payments_failed += 1,
+                ActivityRecord::ChannelOpened { .. } => totals.channels_opened += 1,
+                ActivityRecord::ChannelClosed { .. } => totals.channels_closed += 1,
+                ActivityRecord::Usability { .. } | ActivityRecord::Balance { .. } => (),
+            }
+        }
+
+        totals
+    }
+
+    /// Time and fee of every forward between `from` and `to`
+    pub fn forward_fees(&self, from: u64, to: u64) -> Vec<(u64, u64)> {
+        let records = self.records.lock().expect("activity lock poisoned");
+        records
+            .iter()
+            .filter_map(|record| match record {
+                ActivityRecord::Forwarded {
+                    timestamp,
+                    fee_msat,
+                    ..
+                } if (from..to).contains(timestamp) => Some((*timestamp, *fee_msat)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn append(&self, record: &ActivityRecord) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+}
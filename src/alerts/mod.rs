@@ -0,0 +1,199 @@
+//! Alert rules evaluated against the node state
+//!
+//! Each rule is a condition with a name. Rules are evaluated on an interval,
+//! an alert is raised when a condition starts to hold and resolved once it
+//! no longer does. Alerts are dispatched as [`NodeNotification::Alert`] to the
+//! notification sinks, so they reach the configured webhooks and Nostr
+//! Wallet Connect clients.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use ldk_node::bitcoin::secp256k1::PublicKey;
+
+use crate::notifications::sinks::NodeNotification;
+use crate::peers::PeerConnectionStats;
+
+/// Default interval between evaluations of the alert rules
+pub const DEFAULT_ALERT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Condition an alert rule raises an alert for
+#[derive(Debug, Clone)]
+pub enum AlertCondition {
+    /// More than `threshold` melts failed within `window`
+    FailedMelts { threshold: usize, window: Duration },
+    /// A peer has been disconnected for longer than `after`
+    PeerDisconnected { node_id: PublicKey, after: Duration },
+    /// The total onchain balance is below an amount
+    OnchainBalanceBelow { sat: u64 },
+    /// The total lightning balance is below an amount
+    LightningBalanceBelow { sat: u64 },
+}
+
+/// Named condition
+#[derive(Debug, Clone)]
+pub struct AlertRule {
+    pub name: String,
+    pub condition: AlertCondition,
+}
+
+/// Alert rules and how often they are evaluated
+#[derive(Debug, Clone)]
+pub struct AlertsConfig {
+    pub rules: Vec<AlertRule>,
+    pub interval: Duration,
+}
+
+/// Node state the rules are evaluated against
+#[derive(Debug, Clone, Default)]
+pub struct AlertSnapshot {
+    pub onchain_balance_sat: u64,
+    pub lightning_balance_sat: u64,
+    /// Connection history of the node's peers
+    pub peers: HashMap<PublicKey, PeerConnectionStats>,
+}
+
+/// Evaluates alert rules and tracks which alerts are raised
+#[derive(Debug)]
+pub struct AlertEngine {
+    config: AlertsConfig,
+    /// Unix time the engine was created, peers never seen connected count as
+    /// disconnected from then
+    started_at: u64,
+    failed_melts: Mutex<VecDeque<u64>>,
+    raised: Mutex<HashSet<String>>,
+}
+
+impl AlertEngine {
+    pub fn new(config: AlertsConfig, now: u64) -> Self {
+        Self {
+            config,
+            started_at: now,
+            failed_melts: Mutex::new(VecDeque::new()),
+            raised: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn config(&self) -> &AlertsConfig {
+        &self.config
+    }
+
+    /// Record a failed melt
+    pub fn record_failed_melt(&self, now: u64) {
+        let longest_window = self
+            .config
+            .rules
+            .iter()
+            .filter_map(|rule| match rule.condition {
+                AlertCondition::FailedMelts { window, .. } => Some(window.as_secs()),
+                _ => None,
+            })
+            .max();
+
+        let Some(longest_window) = longest_window else {
+            return;
+        };
+
+        let mut failed_melts = self.failed_melts.lock().expect("alerts lock poisoned");
+        failed_melts.push_back(now);
+        while failed_melts
+            .front()
+            .is_some_and(|timestamp| timestamp + longest_window < now)
+        {
+            failed_melts.pop_front();
+        }
+    }
+
+    /// Evaluate the rules, returning an alert for every rule that was raised
+    /// or resolved since the last evaluation
+    pub fn evaluate(&self, snapshot: &AlertSnapshot, now: u64) -> Vec<NodeNotification> {
+        let mut raised = self.raised.lock().expect("alerts lock poisoned");
+        let mut alerts = vec![];
+
+        for rule in &self.config.rules {
+            let message = self.check(&rule.condition, snapshot, now);
+            let was_raised = raised.contains(&rule.name);
+
+            match message {
+                Some(message) if !was_raised => {
+                    tracing::warn!("Alert {} raised: {}", rule.name, message);
+                    raised.insert(rule.name.clone());
+                    alerts.push(NodeNotification::Alert {
+                        rule: rule.name.clone(),
+                        message,
+                        resolved: false,
+                        timestamp: now,
+                    });
+                }
+                None if was_raised => {
+                    tracing::info!("Alert {} resolved", rule.name);
+                    raised.remove(&rule.name);
+                    alerts.push(NodeNotification::Alert {
+                        rule: rule.name.clone(),
+                        message: "Resolved".to_string(),
+                        resolved: true,
+                        timestamp: now,
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        alerts
+    }
+
+    /// Description of the condition when it holds, `None` otherwise
+    fn check(
+        &self,
+        condition: &AlertCondition,
+        snapshot: &AlertSnapshot,
+        now: u64,
+    ) -> Option<String> {
+        match condition {
+            AlertCondition::FailedMelts { threshold, window } => {
+                let failed = self
+                    .failed_melts
+                    .lock()
+                    .expect("alerts lock poisoned")
+                    .iter()
+                    .filter(|timestamp| *timestamp + window.as_secs() >= now)
+                    .count();
+
+                (failed > *threshold).then(|| {
+                    format!(
+                        "{failed} melts failed in the last {}s, more than {threshold}",
+                        window.as_secs()
+                    )
+                })
+            }
+            AlertCondition::PeerDisconnected { node_id, after } => {
+                let stats = snapshot.peers.get(node_id).copied().unwrap_or_default();
+                if stats.connected_since.is_some() {
+                    return None;
+                }
+
+                let disconnected_since = stats.last_disconnected_at.unwrap_or(self.started_at);
+                let disconnected_for = now.saturating_sub(disconnected_since);
+
+                (disconnected_for > after.as_secs())
+                    .then(|| format!("Peer {node_id} disconnected for {disconnected_for}s"))
+            }
+            AlertCondition::OnchainBalanceBelow { sat } => (snapshot.onchain_balance_sat < *sat)
+                .then(|| {
+                    format!(
+                        "Onchain balance of {} sat is below {} sat",
+                        snapshot.onchain_balance_sat, sat
+                    )
+                }),
+            AlertCondition::LightningBalanceBelow { sat } => {
+                (snapshot.lightning_balance_sat < *sat).then(|| {
+                    format!(
+                        "Lightning balance of {} sat is below {} sat",
+                        snapshot.lightning_balance_sat, sat
+                    )
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Approval queue for large withdrawals
+//!
+//! Onchain sends and payments made through the management API above a
+//! threshold are not executed right away. They are held as pending
+//! operations until a second operator approves them with the approver
+//! token, so a compromised management client alone cannot drain the
+//! treasury. The token should be held by someone other than the operator
+//! issuing the withdrawals.
+//!
+//! Pending operations are kept in memory, restarting the node drops them
+//! without executing them.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use cdk_common::util::{hex, unix_time};
+use ldk_node::bitcoin::hashes::{sha256, Hash};
+
+/// Default time a pending operation waits for approval
+pub const DEFAULT_APPROVAL_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Approval policy settings
+#[derive(Clone)]
+pub struct ApprovalPolicy {
+    /// Withdrawals above this amount need approval
+    pub threshold_sat: u64,
+    /// Token the approver authenticates with
+    pub approver_token: String,
+    /// Time a pending operation waits for approval before it is dropped
+    pub expiry: Duration,
+}
+
+impl std::fmt::Debug for ApprovalPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApprovalPolicy")
+            .field("threshold_sat", &self.threshold_sat)
+            .field("expiry", &self.expiry)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Operation waiting for approval
+#[derive(Debug, Clone)]
+pub struct PendingOperation {
+    pub id: String,
+    /// RPC the operation was submitted through
+    pub rpc: String,
+    /// Encoded request, executed as is once approved
+    pub request: Vec<u8>,
+    pub amount_sat: u64,
+    /// Human readable summary shown to the approver
+    pub description: String,
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+/// Approval queue error
+#[derive(Debug, thiserror::Error)]
+pub enum ApprovalError {
+    /// Approver token missing or wrong
+    #[error("Invalid approver token")]
+    Unauthorized,
+    /// No pending operation with the id, or it expired
+    #[error("No pending operation {0}")]
+    NotFound(String),
+}
+
+/// Operations waiting for a second operator's approval
+#[derive(Debug)]
+pub struct ApprovalQueue {
+    policy: ApprovalPolicy,
+    operations: Mutex<BTreeMap<String, PendingOperation>>,
+    next_nonce: AtomicU64,
+}
+
+impl ApprovalQueue {
+    pub fn new(policy: ApprovalPolicy) -> Self {
+        Self {
+            policy,
+            operations: Mutex::new(BTreeMap::new()),
+            next_nonce: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether a withdrawal of `amount_sat` needs approval
+    pub fn requires_approval(&self, amount_sat: u64) -> bool {
+        amount_sat > self.policy.threshold_sat
+    }
+
+    /// Hold an operation until it is approved
+    pub fn submit(
+        &self,
+        rpc: &str,
+        request: Vec<u8>,
+        amount_sat: u64,
+        description: String,
+    ) -> PendingOperation {
+        let now = unix_time();
+        let nonce = self.next_nonce.fetch_add(1, Ordering::Relaxed);
+
+        let mut preimage = Vec::with_capacity(request.len() + 16);
+        preimage.extend_from_slice(&now.to_be_bytes());
+        preimage.extend_from_slice(&nonce.to_be_bytes());
+        preimage.extend_from_slice(&request);
+        let id = hex::encode(&sha256::Hash::hash(&preimage).to_byte_array()[..8]);
+
+        let operation = PendingOperation {
+            id: id.clone(),
+            rpc: rpc.to_string(),
+            request,
+            amount_sat,
+            description,
+            created_at: now,
+            expires_at: now + self.policy.expiry.as_secs(),
+        };
+
+        tracing::warn!(
+            "Operation {} needs approval: {} ({} sats)",
+            operation.id,
+            operation.description,
+            operation.amount_sat
+        );
+
+        self.operations
+            .lock()
+            .expect("approvals lock poisoned")
+            .insert(id, operation.clone());
+
+        operation
+    }
+
+    /// Operations waiting for approval, oldest first
+    pub fn list(&self) -> Vec<PendingOperation> {
+        let mut operations = self.operations.lock().expect("approvals lock poisoned");
+        Self::prune(&mut operations);
+
+        let mut pending: Vec<PendingOperation> = operations.values().cloned().collect();
+        pending.sort_by_key(|operation| operation.created_at);
+        pending
+    }
+
+    /// Approve an operation, removing it from the queue for execution
+    pub fn approve(&self, id: &str, token: &str) -> Result<PendingOperation, ApprovalError> {
+        let operation = self.take(id, token)?;
+        tracing::info!("Operation {} approved: {}", id, operation.description);
+        Ok(operation)
+    }
+
+    /// Reject an operation, removing it from the queue
+    pub fn reject(&self, id: &str, token: &str) -> Result<PendingOperation, ApprovalError> {
+        let operation = self.take(id, token)?;
+        tracing::info!("Operation {} rejected: {}", id, operation.description);
+        Ok(operation)
+    }
+
+    /// Check the approver token
+    pub fn verify_token(&self, token: &str) -> Result<(), ApprovalError> {
+        // Compare hashes so the comparison time does not reveal the token
+        if sha256::Hash::hash(token.as_bytes())
+            != sha256::Hash::hash(self.policy.approver_token.as_bytes())
+        {
+            return Err(ApprovalError::Unauthorized);
+        }
+
+        Ok(())
+    }
+
+    fn take(&self, id: &str, token: &str) -> Result<PendingOperation, ApprovalError> {
+        if let Err(err) = self.verify_token(token) {
+            tracing::warn!(
+                "Rejected approval of operation {} with an invalid token",
+                id
+            );
+            return Err(err);
+        }
+
+        let mut operations = self.operations.lock().expect("approvals lock poisoned");
+        Self::prune(&mut operations);
+
+        operations
+            .remove(id)
+            .ok_or_else(|| ApprovalError::NotFound(id.to_string()))
+    }
+
+    fn prune(operations: &mut BTreeMap<String, PendingOperation>) {
+        let now = unix_time();
+        operations.retain(|id, operation| {
+            let keep = operation.expires_at > now;
+            if !keep {
+                tracing::info!("Operation {} expired without approval", id);
+            }
+            keep
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "approver";
+
+    fn queue(expiry: Duration) -> ApprovalQueue {
+        ApprovalQueue::new(ApprovalPolicy {
+            threshold_sat: 1_000,
+            approver_token: TOKEN.to_string(),
+            expiry,
+        })
+    }
+
+    #[test]
+    fn only_amounts_above_the_threshold_need_approval() {
+        let queue = queue(DEFAULT_APPROVAL_EXPIRY);
+
+        assert!(!queue.requires_approval(1_000));
+        assert!(queue.requires_approval(1_001));
+    }
+
+    #[test]
+    fn approved_operations_leave_the_queue() {
+        let queue = queue(DEFAULT_APPROVAL_EXPIRY);
+        let first = queue.submit("send_onchain", vec![1], 2_000, "first".to_string());
+        let second = queue.submit("send_onchain", vec![1], 3_000, "second".to_string());
+
+        assert_ne!(first.id, second.id);
+        assert_eq!(queue.list().len(), 2);
+
+        let approved = queue.approve(&first.id, TOKEN).unwrap();
+        assert_eq!(approved.request, vec![1]);
+        assert_eq!(approved.amount_sat, 2_000);
+
+        let pending = queue.list();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, second.id);
+
+        assert!(matches!(
+            queue.approve(&first.id, TOKEN),
+            Err(ApprovalError::NotFound(_))
+        ));
+        assert!(queue.reject(&second.id, TOKEN).is_ok());
+        assert!(queue.list().is_empty());
+    }
+
+    #[test]
+    fn operations_need_the_approver_token() {
+        let queue = queue(DEFAULT_APPROVAL_EXPIRY);
+        let operation = queue.submit("pay_bolt11_invoice", vec![], 2_000, "invoice".to_string());
+
+        assert!(matches!(
+            queue.approve(&operation.id, "wrong"),
+            Err(ApprovalError::Unauthorized)
+        ));
+        assert!(matches!(
+            queue.reject(&operation.id, "wrong"),
+            Err(ApprovalError::Unauthorized)
+        ));
+        assert_eq!(queue.list().len(), 1);
+    }
+
+    #[test]
+    fn expired_operations_cannot_be_approved() {
+        let queue = queue(Duration::ZERO);
+        let operation = queue.submit("send_onchain", vec![], 2_000, "expired".to_string());
+
+        assert!(queue.list().is_empty());
+        assert!(matches!(
+            queue.approve(&operation.id, TOKEN),
+            Err(ApprovalError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,105 @@
+//! Automatic closing of idle or unusable channels
+//!
+//! Channels that were not used for a while, or were unusable for a while,
+//! tie up capital the mint could deploy in better channels. The policy closes
+//! them cooperatively, skipping channels with peers on the allowlist.
+//!
+//! A channel counts as used whenever its balance changes. Commitment fee
+//! updates also change the balance, so channels the node funded may look
+//! busier than they are, which errs on the side of keeping them open.
+
+use std::time::Duration;
+
+use ldk_node::bitcoin::secp256k1::PublicKey;
+use ldk_node::{ChannelDetails, UserChannelId};
+
+use crate::activity::ActivityLog;
+
+/// Default interval between policy checks
+pub const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Automatic channel close policy settings
+#[derive(Debug, Clone)]
+pub struct AutoCloseConfig {
+    /// Close channels not used for this long, `None` keeps idle channels
+    pub idle_after: Option<Duration>,
+    /// Close channels unusable for this long, `None` keeps unusable channels
+    pub unusable_after: Option<Duration>,
+    /// Peers whose channels are never closed
+    pub exclude_peers: Vec<PublicKey>,
+    /// Only log the channels that would be closed
+    pub dry_run: bool,
+    /// Interval between policy checks
+    pub interval: Duration,
+}
+
+/// Why a channel is closed by the policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    /// Not used for the given number of seconds
+    Idle(u64),
+    /// Unusable for the given number of seconds
+    Unusable(u64),
+}
+
+impl std::fmt::Display for CloseReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CloseReason::Idle(secs) => write!(f, "idle for {secs}s"),
+            CloseReason::Unusable(secs) => write!(f, "unusable for {secs}s"),
+        }
+    }
+}
+
+/// Channel the policy selected for closing
+#[derive(Debug, Clone)]
+pub struct CloseCandidate {
+    pub channel_id: String,
+    pub user_channel_id: UserChannelId,
+    pub counterparty_node_id: PublicKey,
+    pub reason: CloseReason,
+}
+
+/// Channels the policy selects for closing
+///
+/// Channels the activity log has not observed yet are never selected.
+pub fn close_candidates(
+    channels: &[ChannelDetails],
+    activity: &ActivityLog,
+    config: &AutoCloseConfig,
+    now: u64,
+) -> Vec<CloseCandidate> {
+    channels
+        .iter()
+        .filter(|channel| !config.exclude_peers.contains(&channel.counterparty_node_id))
+        .filter_map(|channel| {
+            let channel_id = channel.channel_id.to_string();
+            let state = activity.channel_activity(&channel_id)?;
+
+            let unusable = state
+                .unusable_since
+                .map(|since| now.saturating_sub(since))
+                .filter(|secs| {
+                    config
+                        .unusable_after
+                        .is_some_and(|after| *secs >= after.as_secs())
+                })
+                .map(CloseReason::Unusable);
+
+            let idle = Some(now.saturating_sub(state.last_active_at))
+                .filter(|secs| {
+                    config
+                        .idle_after
+                        .is_some_and(|after| *secs >= after.as_secs())
+                })
+                .map(CloseReason::Idle);
+
+            Some(CloseCandidate {
+                channel_id,
+                user_channel_id: channel.user_channel_id,
+                counterparty_node_id: channel.counterparty_node_id,
+                reason: unusable.or(idle)?,
+            })
+        })
+        .collect()
+}
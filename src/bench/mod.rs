@@ -0,0 +1,227 @@
+//! Load test of the payment path
+//!
+//! Drives two running nodes through their management services: the payee
+//! creates BOLT11 invoices and the payer pays them, with a fixed number of
+//! payments in flight. Each payment records how long the invoice took to
+//! create, how long the pay call took to return, and how long until the payee
+//! reported the payment as received.
+//!
+//! Meant for a regtest cluster with a funded channel from payer to payee, it
+//! spends real funds on any other network.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use futures::stream::{self, StreamExt};
+
+use crate::proto::client::CdkLdkClient;
+
+/// Interval between polls of the payee for a received payment
+const RECEIVE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Parameters of a bench run
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    /// Number of payments to make
+    pub payments: usize,
+    /// Payments in flight at once
+    pub concurrency: usize,
+    pub amount_msat: u64,
+    /// How long to wait for the payee to report a payment as received
+    pub receive_timeout: Duration,
+}
+
+/// Latency distribution of one step of the payment path
+#[derive(Debug, Clone, Default)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub min: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencyStats {
+    /// Compute the distribution of a set of samples, nearest rank percentiles
+    pub fn from_samples(mut samples: Vec<Duration>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        samples.sort();
+        let count = samples.len();
+        let percentile = |p: usize| samples[((count * p).div_ceil(100)).clamp(1, count) - 1];
+
+        Self {
+            count,
+            min: samples[0],
+            mean: samples.iter().sum::<Duration>() / count as u32,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: samples[count - 1],
+        }
+    }
+}
+
+/// Result of a bench run
+#[derive(Debug, Clone, Default)]
+pub struct BenchReport {
+    pub payments: usize,
+    pub concurrency: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Wall clock time of the whole run
+    pub elapsed: Duration,
+    pub create_invoice: LatencyStats,
+    pub pay: LatencyStats,
+    /// From the pay call until the payee reported the payment as received
+    pub receive: LatencyStats,
+    /// Distinct failure reasons with how often they occurred
+    pub failures: Vec<(String, usize)>,
+}
+
+impl BenchReport {
+    /// Successful payments per second
+    pub fn throughput(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+
+        self.succeeded as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Timings of a single successful payment
+struct PaymentTimings {
+    create_invoice: Duration,
+    pay: Duration,
+    receive: Duration,
+}
+
+/// Run the bench, the payer must have a channel with enough outbound
+/// liquidity to the payee
+pub async fn run(
+    payer: CdkLdkClient,
+    payee: CdkLdkClient,
+    config: &BenchConfig,
+) -> Result<BenchReport> {
+    if config.payments == 0 || config.concurrency == 0 {
+        bail!("Payments and concurrency must be at least 1");
+    }
+
+    let start = Instant::now();
+
+    let results: Vec<Result<PaymentTimings>> = stream::iter(0..config.payments)
+        .map(|i| {
+            let payer = payer.clone();
+            let payee = payee.clone();
+            async move { make_payment(payer, payee, i, config).await }
+        })
+        .buffer_unordered(config.concurrency)
+        .collect()
+        .await;
+
+    let elapsed = start.elapsed();
+
+    let mut create_invoice = vec![];
+    let mut pay = vec![];
+    let mut receive = vec![];
+    let mut failures: Vec<(String, usize)> = vec![];
+
+    for result in results {
+        match result {
+            Ok(timings) => {
+                create_invoice.push(timings.create_invoice);
+                pay.push(timings.pay);
+                receive.push(timings.receive);
+            }
+            Err(err) => {
+                let reason = format!("{err:#}");
+                match failures.iter_mut().find(|(r, _)| *r == reason) {
+                    Some((_, count)) => *count += 1,
+                    None => failures.push((reason, 1)),
+                }
+            }
+        }
+    }
+
+    failures.sort_by(|a, b| b.1.cmp(&a.1));
+
+    Ok(BenchReport {
+        payments: config.payments,
+        concurrency: config.concurrency,
+        succeeded: pay.len(),
+        failed: config.payments - pay.len(),
+        elapsed,
+        create_invoice: LatencyStats::from_samples(create_invoice),
+        pay: LatencyStats::from_samples(pay),
+        receive: LatencyStats::from_samples(receive),
+        failures,
+    })
+}
+
+async fn make_payment(
+    mut payer: CdkLdkClient,
+    mut payee: CdkLdkClient,
+    index: usize,
+    config: &BenchConfig,
+) -> Result<PaymentTimings> {
+    let start = Instant::now();
+    let invoice = payee
+        .create_bolt11_invoice(config.amount_msat, format!("bench {index}"), None, false)
+        .await?;
+    let create_invoice = start.elapsed();
+
+    let start = Instant::now();
+    let payment = payer
+        .pay_bolt11_invoice(
+            invoice.invoice,
+            None,
+            Some(format!("bench-{index}")),
+            None,
+            None,
+            None,
+        )
+        .await?;
+    let pay = start.elapsed();
+
+    if !payment.success {
+        bail!(
+            "{}",
+            payment
+                .failure_reason
+                .unwrap_or_else(|| "Payment failed".to_string())
+        );
+    }
+
+    loop {
+        let status = payee
+            .check_incoming_payments(vec![invoice.payment_hash.clone()])
+            .await?;
+
+        match status.first() {
+            Some(status) if status.amount_msat > 0 => break,
+            Some(status) => {
+                if let Some(error) = &status.error {
+                    bail!("{error}");
+                }
+            }
+            None => bail!("Payee returned no status for the payment"),
+        }
+
+        if start.elapsed() > config.receive_timeout {
+            bail!("Payee did not report the payment as received");
+        }
+
+        tokio::time::sleep(RECEIVE_POLL_INTERVAL).await;
+    }
+
+    Ok(PaymentTimings {
+        create_invoice,
+        pay,
+        receive: start.elapsed(),
+    })
+}
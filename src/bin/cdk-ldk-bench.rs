@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use cdk_ldk_node::bench::{self, BenchConfig};
+use cdk_ldk_node::proto::client::CdkLdkClient;
+use cdk_ldk_node::utils;
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(author, version, about = "CDK LDK Bench - Load test the payment path of two CDK LDK Nodes", long_about = None)]
+struct Args {
+    #[arg(
+        long,
+        default_value = "http://127.0.0.1:50051",
+        help = "Address of the paying node's gRPC server"
+    )]
+    payer_address: String,
+
+    #[arg(
+        long,
+        default_value = "http://127.0.0.1:50052",
+        help = "Address of the receiving node's gRPC server"
+    )]
+    payee_address: String,
+
+    #[arg(
+        short,
+        long,
+        default_value = "~/.cdk-ldk-cli",
+        help = "Specify a custom working directory containing the client TLS files"
+    )]
+    work_dir: String,
+
+    /// Number of payments to make
+    #[arg(short = 'n', long, default_value_t = 100)]
+    payments: usize,
+
+    /// Payments in flight at once
+    #[arg(short, long, default_value_t = 10)]
+    concurrency: usize,
+
+    /// Amount of each payment
+    #[arg(short, long, default_value_t = 1_000)]
+    amount_msat: u64,
+
+    /// Seconds to wait for the payee to report a payment as received
+    #[arg(long, default_value_t = 30)]
+    receive_timeout_secs: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let work_dir: PathBuf = args.work_dir.parse()?;
+
+    let payer = CdkLdkClient::create_with_work_dir(args.payer_address, work_dir.clone()).await?;
+    let payee = CdkLdkClient::create_with_work_dir(args.payee_address, work_dir).await?;
+
+    let config = BenchConfig {
+        payments: args.payments,
+        concurrency: args.concurrency,
+        amount_msat: args.amount_msat,
+        receive_timeout: Duration::from_secs(args.receive_timeout_secs),
+    };
+
+    println!(
+        "Making {} payments of {} msat, {} at a time",
+        config.payments, config.amount_msat, config.concurrency
+    );
+
+    let report = bench::run(payer, payee, &config).await?;
+    print!("{}", utils::format_bench_report(&report));
+
+    Ok(())
+}
@@ -1,9 +1,20 @@
+use std::fs::File;
+use std::io::{BufWriter, IsTerminal, Write};
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use cdk_ldk_node::proto::client::CdkLdkClient;
+use cdk_ldk_node::proto::{ListPaymentsRequest, SERVER_VERSION};
 use cdk_ldk_node::utils;
 use clap::{Parser, Subcommand};
+use ldk_node::lightning_invoice::Bolt11Invoice;
+use tonic::Code;
+
+/// Interval between polls when following the node logs
+const LOG_FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Parser)]
 #[command(author, version, about = "CDK LDK CLI - Command-line interface for the CDK LDK Node", long_about = None)]
@@ -24,6 +35,15 @@ struct Cli {
     )]
     work_dir: String,
 
+    /// Skip the confirmation of commands that move funds or close channels
+    #[arg(short, long, global = true)]
+    yes: bool,
+
+    /// File holding a tenant token, scoping commands to the tenant's invoices
+    /// and payments
+    #[arg(long, global = true)]
+    tenant_token_file: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -31,9 +51,73 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Get node info
-    GetInfo,
+    GetInfo {
+        /// Print pubkey@host:port connect strings and a QR code
+        #[arg(long)]
+        connect_string: bool,
+    },
+    /// Get node sync and chain source status
+    GetNodeStatus,
+    /// Report network graph size and staleness, optionally waiting for it to load
+    WarmupGraph {
+        #[arg(short, long)]
+        timeout_seconds: Option<u32>,
+    },
+    /// Sync the onchain and Lightning wallets now
+    SyncWallets,
+    /// Show recent node logs
+    Logs {
+        /// Minimum level: error, warn, info, debug or trace
+        #[arg(short, long)]
+        level: Option<String>,
+        /// Maximum number of entries
+        #[arg(short = 'n', long)]
+        limit: Option<u32>,
+        /// Keep polling for new entries
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Show the largest amounts the node can currently receive and pay
+    GetPaymentLimits,
+    /// Restore a backup into a temporary directory and check that it loads
+    VerifyBackup {
+        /// Backup of the storage directory, defaults to the node's storage directory
+        #[arg(short, long)]
+        backup_dir: Option<String>,
+    },
+    /// Export the node seed, requires allow_export in the node's [seed] config
+    ExportSeed {
+        /// File holding the seed export token
+        #[arg(long)]
+        token_file: PathBuf,
+    },
+    /// Show the public descriptors of the onchain wallet for watch-only monitoring
+    GetWalletDescriptors,
     /// Get a new bitcoin address
-    GetNewAddress,
+    GetNewAddress {
+        /// Address type, bech32 or bech32m, defaults to the node config
+        #[arg(short, long)]
+        address_type: Option<String>,
+        /// Return the last issued address again if it is still unused
+        #[arg(short, long)]
+        reuse_unused: bool,
+        /// Label attached to the address
+        #[arg(short, long)]
+        label: Option<String>,
+    },
+    /// Label an onchain address or transaction, an empty label removes it
+    SetLabel {
+        /// Kind of the labeled object, addr or tx
+        #[arg(short, long = "type")]
+        kind: String,
+        /// Address or txid
+        #[arg(short, long = "ref")]
+        reference: String,
+        #[arg(short, long)]
+        label: String,
+    },
+    /// Export onchain labels in the BIP329 format
+    ExportLabels,
     /// Open a new channel
     OpenChannel {
         #[arg(short, long)]
@@ -42,10 +126,29 @@ enum Commands {
         address: String,
         #[arg(short, long)]
         port: u32,
+        /// Channel size in sats
+        #[arg(long, alias = "amount-msats")]
+        amount_sat: u64,
+        #[arg(long, alias = "push-msats")]
+        push_msat: Option<u64>,
+        /// Wait and show progress until the channel is ready
         #[arg(long)]
-        amount_msats: u64,
+        wait: bool,
+        /// Show what would be done without opening the channel
+        #[arg(long, conflicts_with = "wait")]
+        dry_run: bool,
+        /// Key identifying this request, a retry with the same key is not executed twice
+        #[arg(long, conflicts_with = "wait")]
+        idempotency_key: Option<String>,
+    },
+    /// Open channels to several peers in one request
+    BatchOpenChannels {
+        /// Channel to open as NODE_ID@HOST:PORT=AMOUNT_SAT, repeat for every peer
+        #[arg(long = "channel", required = true)]
+        channels: Vec<String>,
+        /// Key identifying this request, a retry with the same key is not executed twice
         #[arg(long)]
-        push_msats: Option<u64>,
+        idempotency_key: Option<String>,
     },
     /// Close a channel
     CloseChannel {
@@ -53,87 +156,534 @@ enum Commands {
         channel_id: String,
         #[arg(short, long)]
         node_pubkey: String,
+        /// Show what would be done without closing the channel
+        #[arg(long)]
+        dry_run: bool,
     },
     /// List balances
     ListBalance,
     /// List channels
     ListChannels,
+    /// Sum channel capacity, spendable balances and reserves per counterparty
+    ListCounterpartyBalances,
+    /// Report forwarded volume, fees and downtime per channel
+    ChannelReport {
+        /// Number of days to report on, defaults to 30
+        #[arg(short, long)]
+        days: Option<u64>,
+    },
+    /// Break down fee income and expense per month
+    GetFeeBreakdown {
+        /// Start of the period as a unix timestamp, defaults to a year ago
+        #[arg(long)]
+        from: Option<u64>,
+        /// End of the period as a unix timestamp, defaults to now
+        #[arg(long)]
+        to: Option<u64>,
+    },
     /// Send bitcoin on-chain
     SendOnchain {
         #[arg(long)]
         amount_sat: u64,
         #[arg(long)]
         address: String,
+        /// Label attached to the transaction
+        #[arg(long)]
+        label: Option<String>,
+        /// Show what would be done without sending
+        #[arg(long)]
+        dry_run: bool,
+        /// Key identifying this request, a retry with the same key is not executed twice
+        #[arg(long)]
+        idempotency_key: Option<String>,
+        /// File holding the approver token, sends above the management limit
+        #[arg(long)]
+        override_limit_token_file: Option<PathBuf>,
     },
     /// Pay a bolt11 invoice
     PayBolt11 {
         #[arg(short, long)]
         invoice: String,
-        #[arg(short, long)]
-        amount_msats: Option<u64>,
+        #[arg(short, long, alias = "amount-msats")]
+        amount_msat: Option<u64>,
+        /// Id attached to the node's logs for this payment
+        #[arg(long)]
+        correlation_id: Option<String>,
+        /// Key identifying this request, a retry with the same key is not executed twice
+        #[arg(long)]
+        idempotency_key: Option<String>,
+        /// File holding the approver token, pays above the management limit
+        #[arg(long)]
+        override_limit_token_file: Option<PathBuf>,
+        /// Seconds to wait for the payment before reporting it pending
+        #[arg(long)]
+        timeout_secs: Option<u64>,
     },
     /// Pay a bolt12 offer
     PayBolt12 {
         #[arg(short, long)]
         offer: String,
-        #[arg(short, long)]
-        amount_msats: u64,
+        /// Amount to pay, defaults to the offer amount
+        #[arg(short, long, alias = "amount-msats")]
+        amount_msat: Option<u64>,
+        /// Id attached to the node's logs for this payment
+        #[arg(long)]
+        correlation_id: Option<String>,
+        /// Key identifying this request, a retry with the same key is not executed twice
+        #[arg(long)]
+        idempotency_key: Option<String>,
+        /// File holding the approver token, pays above the management limit
+        #[arg(long)]
+        override_limit_token_file: Option<PathBuf>,
+        /// Seconds to wait for the payment before reporting it pending
+        #[arg(long)]
+        timeout_secs: Option<u64>,
     },
     /// Create a BOLT11 invoice
     CreateBolt11Invoice {
-        #[arg(short, long)]
-        amount_msats: u64,
+        #[arg(short, long, alias = "amount-msats")]
+        amount_msat: u64,
         #[arg(short, long)]
         description: String,
         #[arg(short, long)]
         expiry_seconds: Option<u32>,
+        /// Hold the payment until it is settled or cancelled
+        #[arg(long)]
+        hold: bool,
+        /// Hex payment hash of a hold invoice whose preimage you keep
+        #[arg(long, requires = "hold")]
+        payment_hash: Option<String>,
+        /// Put the hash of the description in the invoice instead of the description
+        #[arg(long)]
+        description_hash: bool,
     },
     /// Create a BOLT12 offer
     CreateBolt12Offer {
-        #[arg(short, long)]
-        amount_msats: Option<u64>,
+        #[arg(short, long, alias = "amount-msats")]
+        amount_msat: Option<u64>,
         #[arg(short, long)]
         description: String,
         #[arg(short, long)]
         expiry_seconds: Option<u32>,
+        /// Unix timestamp the offer expires at, takes precedence over expiry_seconds
+        #[arg(long)]
+        absolute_expiry: Option<u64>,
+        /// Maximum number of items per payment, fixed-amount offers only
+        #[arg(short, long)]
+        quantity: Option<u64>,
+    },
+    /// Decode a BOLT12 offer
+    DecodeOffer {
+        #[arg(short, long)]
+        offer: String,
+    },
+    /// Check the status of incoming payments
+    CheckIncomingPayments {
+        /// Hex payment ids, the payment hash for BOLT11 invoices
+        #[arg(required = true)]
+        payment_ids: Vec<String>,
+    },
+    /// List peers with their channels and connection history
+    ListPeers,
+    /// List withdrawals waiting for approval
+    ListPendingOperations,
+    /// Approve a withdrawal waiting for approval, executing it
+    ApproveOperation {
+        /// Id of the pending operation
+        id: String,
+        /// File holding the approver token
+        #[arg(long)]
+        token_file: PathBuf,
+    },
+    /// Reject a withdrawal waiting for approval
+    RejectOperation {
+        /// Id of the pending operation
+        id: String,
+        /// File holding the approver token
+        #[arg(long)]
+        token_file: PathBuf,
+    },
+    /// Prune old payment records and activity history
+    ///
+    /// Pruned payment records are exported to the node's export directory first.
+    Prune {
+        /// Prune final payment records older than this many days, at least 7
+        #[arg(long)]
+        payment_retention_days: Option<u64>,
+        /// Prune activity records older than this many days
+        #[arg(long)]
+        activity_retention_days: Option<u64>,
+        /// Only count the records that would be pruned
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Reload the TLS certificates of the management service and payment processor
+    ///
+    /// The servers are restarted with the certificates in their TLS
+    /// directories, the node keeps running.
+    RotateTls,
+    /// List payments, newest first
+    ListPayments {
+        /// Only payments created at or after this unix timestamp
+        #[arg(long)]
+        created_from: Option<u64>,
+        /// Only payments created before this unix timestamp
+        #[arg(long)]
+        created_to: Option<u64>,
+        /// Only payments settled at or after this unix timestamp
+        #[arg(long)]
+        settled_from: Option<u64>,
+        /// Only payments settled before this unix timestamp
+        #[arg(long)]
+        settled_to: Option<u64>,
+        #[arg(long)]
+        min_amount_msat: Option<u64>,
+        #[arg(long)]
+        max_amount_msat: Option<u64>,
+        /// inbound or outbound
+        #[arg(long)]
+        direction: Option<String>,
+        /// pending, succeeded or failed
+        #[arg(long)]
+        status: Option<String>,
+        /// Number of payments to list, defaults to 100
+        #[arg(long)]
+        limit: Option<u32>,
+        /// Number of payments to skip
+        #[arg(long)]
+        offset: Option<u32>,
+    },
+    /// Export the payment ledger as CSV, oldest payment first
+    ///
+    /// The ledger is streamed in batches and written as it arrives.
+    ExportLedger {
+        /// File to write, stdout when not set
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Only payments created at or after this unix timestamp
+        #[arg(long)]
+        created_from: Option<u64>,
+        /// Only payments created before this unix timestamp
+        #[arg(long)]
+        created_to: Option<u64>,
+        /// Payments per batch, defaults to 1000
+        #[arg(long)]
+        batch_size: Option<u32>,
+    },
+    /// Report the melt of a stuck outgoing payment as failed
+    ///
+    /// Only for payments pending for longer than the melt cancellation
+    /// window. LDK keeps tracking the payment.
+    AbandonPayment {
+        /// Hex encoded payment id
+        #[arg(short, long)]
+        payment_id: String,
+    },
+    /// Settle a payment held for a hold invoice
+    SettleHoldInvoice {
+        #[arg(long)]
+        payment_hash: String,
+        /// Hex preimage, required for invoices created for a payment hash
+        #[arg(long)]
+        preimage: Option<String>,
+    },
+    /// Cancel a hold invoice, failing a held payment back to the payer
+    CancelHoldInvoice {
+        #[arg(long)]
+        payment_hash: String,
+    },
+    /// List hold invoices
+    ListHoldInvoices,
+    /// Summarise payment volume, fees, channels and failures
+    GetSummary {
+        /// Number of days to summarise, defaults to 1
+        #[arg(short, long)]
+        days: Option<u64>,
+    },
+    /// Diagnose the connection to a peer
+    DiagnosePeer {
+        #[arg(short, long)]
+        node_id: String,
+        /// Address as host:port, defaults to an address announced in gossip
+        #[arg(short, long)]
+        address: Option<String>,
     },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
+    let address = cli.address.clone();
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            let (code, message) = describe_error(&err, &address);
+            eprintln!("Error: {message}");
+            ExitCode::from(code)
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
     let work_dir: PathBuf = cli.work_dir.parse()?;
 
     // Use the new method from the client to create a client with the work_dir
-    let mut client = CdkLdkClient::create_with_work_dir(cli.address.to_string(), work_dir).await?;
+    let mut client = match &cli.tenant_token_file {
+        Some(path) => {
+            let token = read_token(path, "tenant token")?;
+            CdkLdkClient::create_with_tenant_token(cli.address.to_string(), work_dir, &token)
+                .await?
+        }
+        None => CdkLdkClient::create_with_work_dir(cli.address.to_string(), work_dir).await?,
+    };
+
+    if client.check_api_version().await?.is_none() {
+        eprintln!(
+            "Warning: the server predates API versioning, some commands may not be supported. \
+             Consider upgrading cdk-ldk-node on the server"
+        );
+    }
+
+    run_command(&mut client, cli.command, cli.yes).await
+}
+
+/// Exit code and concise message of a failed command
+///
+/// gRPC status codes map to distinct exit codes so scripts can branch on the
+/// kind of failure, see [`exit_codes`].
+fn describe_error(err: &anyhow::Error, address: &str) -> (u8, String) {
+    let connection_refused = err.chain().any(|cause| {
+        cause
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::ConnectionRefused)
+    });
+    if connection_refused {
+        return (
+            exit_codes::CONNECTION,
+            format!("Connection to {address} refused, is cdk-ldk-node running?"),
+        );
+    }
+
+    if let Some(status) = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<tonic::Status>())
+    {
+        let message = status.message();
+
+        return match status.code() {
+            Code::Unavailable => (
+                exit_codes::CONNECTION,
+                format!("Node unavailable: {message}"),
+            ),
+            Code::Unauthenticated => (
+                exit_codes::UNAUTHENTICATED,
+                format!("Not authenticated: {message}"),
+            ),
+            Code::PermissionDenied => (
+                exit_codes::UNAUTHENTICATED,
+                format!("Permission denied: {message}"),
+            ),
+            // The message names the rejected field, the status wrapping adds nothing
+            Code::InvalidArgument => (exit_codes::INVALID_ARGUMENT, message.to_string()),
+            Code::NotFound => (exit_codes::NOT_FOUND, format!("Not found: {message}")),
+            Code::AlreadyExists => (exit_codes::ALREADY_EXISTS, message.to_string()),
+            Code::FailedPrecondition => (exit_codes::FAILED_PRECONDITION, message.to_string()),
+            Code::DeadlineExceeded => (exit_codes::TIMEOUT, format!("Timed out: {message}")),
+            Code::Unimplemented => (
+                exit_codes::UNSUPPORTED,
+                format!(
+                    "The server does not support this command. Upgrade cdk-ldk-node on the \
+                     server to match this CLI ({SERVER_VERSION})"
+                ),
+            ),
+            code => (exit_codes::FAILURE, format!("{code:?}: {message}")),
+        };
+    }
+
+    if err
+        .chain()
+        .any(|cause| cause.downcast_ref::<tonic::transport::Error>().is_some())
+    {
+        return (
+            exit_codes::CONNECTION,
+            format!("Could not connect to {address}: {err:#}"),
+        );
+    }
+
+    (exit_codes::FAILURE, format!("{err:#}"))
+}
+
+/// Process exit codes of failed commands
+mod exit_codes {
+    /// Any failure without a more specific code
+    pub const FAILURE: u8 = 1;
+    // 2 is used by clap for usage errors
+    /// The node could not be reached
+    pub const CONNECTION: u8 = 3;
+    /// Client certificate or approver token rejected
+    pub const UNAUTHENTICATED: u8 = 4;
+    /// A request field was rejected
+    pub const INVALID_ARGUMENT: u8 = 5;
+    pub const NOT_FOUND: u8 = 6;
+    pub const ALREADY_EXISTS: u8 = 7;
+    /// The node is not in a state to run the command, e.g. insufficient funds
+    pub const FAILED_PRECONDITION: u8 = 8;
+    pub const TIMEOUT: u8 = 9;
+    /// The server is too old for the command
+    pub const UNSUPPORTED: u8 = 10;
+}
 
-    match cli.command {
-        Commands::GetInfo => {
+async fn run_command(client: &mut CdkLdkClient, command: Commands, yes: bool) -> Result<()> {
+    match command {
+        Commands::GetInfo { connect_string } => {
             let info = client.get_info().await?;
-            print!("{}", utils::format_node_info(&info));
+
+            if connect_string {
+                let connect_strings = utils::node_connect_strings(&info);
+
+                match connect_strings.first() {
+                    Some(first) => {
+                        for connect_string in &connect_strings {
+                            println!("{connect_string}");
+                        }
+                        println!();
+                        print!("{}", utils::format_qr(first)?);
+                    }
+                    None => println!("Node has no listening or announced addresses"),
+                }
+            } else {
+                print!("{}", utils::format_node_info(&info));
+            }
+        }
+        Commands::GetNodeStatus => {
+            let status = client.get_node_status().await?;
+            print!("{}", utils::format_node_status(&status));
+        }
+        Commands::VerifyBackup { backup_dir } => {
+            let report = client.verify_backup(backup_dir).await?;
+            print!("{}", utils::format_backup_report(&report));
+        }
+        Commands::ExportSeed { token_file } => {
+            let token = read_token(&token_file, "seed export token")?;
+            let seed = client.export_seed(&token).await?;
+            print!("{}", utils::format_seed_export(&seed));
+        }
+        Commands::GetWalletDescriptors => {
+            let descriptors = client.get_wallet_descriptors().await?;
+            print!("{}", utils::format_wallet_descriptors(&descriptors));
+        }
+        Commands::GetPaymentLimits => {
+            let limits = client.get_payment_limits().await?;
+            print!("{}", utils::format_payment_limits(&limits));
+        }
+        Commands::Logs {
+            level,
+            limit,
+            follow,
+        } => {
+            let mut logs = client.get_recent_logs(level.clone(), limit, None).await?;
+            print!("{}", utils::format_log_entries(&logs.entries));
+
+            while follow {
+                tokio::time::sleep(LOG_FOLLOW_INTERVAL).await;
+                logs = client
+                    .get_recent_logs(level.clone(), None, Some(logs.next_seq))
+                    .await?;
+                print!("{}", utils::format_log_entries(&logs.entries));
+            }
+        }
+        Commands::SyncWallets => {
+            let report = client.sync_wallets().await?;
+            print!("{}", utils::format_wallet_sync(&report));
         }
-        Commands::GetNewAddress => {
-            let address = client.get_new_address().await?;
-            println!("New address: {address}");
+        Commands::WarmupGraph { timeout_seconds } => {
+            let graph = client.warmup_graph(timeout_seconds).await?;
+            print!("{}", utils::format_graph_status(&graph));
+        }
+        Commands::GetNewAddress {
+            address_type,
+            reuse_unused,
+            label,
+        } => {
+            let response = client
+                .get_new_address(address_type, reuse_unused.then_some(true), label)
+                .await?;
+            if response.reused {
+                println!("Unused address: {}", response.address);
+            } else {
+                println!("New address: {}", response.address);
+            }
         }
         Commands::OpenChannel {
             node_id,
             address,
             port,
-            amount_msats,
-            push_msats,
+            amount_sat,
+            push_msat,
+            wait,
+            dry_run,
+            idempotency_key,
+        } => {
+            if dry_run {
+                let plan = client
+                    .plan_open_channel(node_id, address, port, amount_sat, push_msat)
+                    .await?;
+                print!("{}", utils::format_dry_run_plan(&plan));
+            } else if wait {
+                let mut progress = client
+                    .open_channel_stream(node_id, address, port, amount_sat, push_msat)
+                    .await?;
+                while let Some(update) = progress.message().await? {
+                    println!("{}", utils::format_open_channel_progress(&update));
+                }
+            } else {
+                let channel_id = client
+                    .open_channel(
+                        node_id,
+                        address,
+                        port,
+                        amount_sat,
+                        push_msat,
+                        idempotency_key,
+                    )
+                    .await?;
+                println!("Opened channel with ID: {channel_id}");
+            }
+        }
+        Commands::BatchOpenChannels {
+            channels,
+            idempotency_key,
         } => {
-            let channel_id = client
-                .open_channel(node_id, address, port, amount_msats, push_msats)
+            let channels = channels
+                .iter()
+                .map(|channel| utils::parse_batch_channel(channel))
+                .collect::<Result<Vec<_>>>()?;
+            let response = client
+                .batch_open_channels(channels, idempotency_key)
                 .await?;
-            println!("Opened channel with ID: {channel_id}");
+            print!("{}", utils::format_batch_open_channels(&response));
         }
         Commands::CloseChannel {
             channel_id,
             node_pubkey,
+            dry_run,
         } => {
-            client.close_channel(channel_id, node_pubkey).await?;
-            println!("Channel closed successfully");
+            if dry_run {
+                let plan = client.plan_close_channel(channel_id, node_pubkey).await?;
+                print!("{}", utils::format_dry_run_plan(&plan));
+            } else {
+                if !yes {
+                    let plan = client
+                        .plan_close_channel(channel_id.clone(), node_pubkey.clone())
+                        .await?;
+                    if !confirm(&utils::format_plan(&plan))? {
+                        return Ok(());
+                    }
+                }
+                client.close_channel(channel_id, node_pubkey).await?;
+                println!("Channel closed successfully");
+            }
         }
         Commands::ListBalance => {
             let balance = client.list_balance().await?;
@@ -143,35 +693,157 @@ async fn main() -> Result<()> {
             let response = client.list_channels().await?;
             print!("{}", utils::format_channels_info(&response));
         }
+        Commands::ListCounterpartyBalances => {
+            let response = client.list_counterparty_balances().await?;
+            print!("{}", utils::format_counterparty_balances(&response));
+        }
+        Commands::ChannelReport { days } => {
+            let report = client
+                .channel_report(days.map(|days| days * 24 * 60 * 60))
+                .await?;
+            print!("{}", utils::format_channel_report(&report));
+        }
+        Commands::GetFeeBreakdown { from, to } => {
+            let breakdown = client.get_fee_breakdown(from, to).await?;
+            print!("{}", utils::format_fee_breakdown(&breakdown));
+        }
+        Commands::SetLabel {
+            kind,
+            reference,
+            label,
+        } => {
+            client.set_label(kind, reference, label).await?;
+            println!("Label updated");
+        }
+        Commands::ExportLabels => {
+            let labels = client.list_labels().await?;
+            print!("{}", utils::format_labels_bip329(&labels)?);
+        }
         Commands::SendOnchain {
             amount_sat,
             address,
+            label,
+            dry_run,
+            idempotency_key,
+            override_limit_token_file,
         } => {
-            let txid = client.send_onchain(amount_sat, address).await?;
-            println!("Transaction sent with txid: {txid}");
+            if dry_run {
+                let plan = client.plan_send_onchain(amount_sat, address).await?;
+                print!("{}", utils::format_dry_run_plan(&plan));
+            } else {
+                if !yes {
+                    let plan = client
+                        .plan_send_onchain(amount_sat, address.clone())
+                        .await?;
+                    if !confirm(&utils::format_plan(&plan))? {
+                        return Ok(());
+                    }
+                }
+                let override_token = override_limit_token_file
+                    .as_ref()
+                    .map(read_approver_token)
+                    .transpose()?;
+                let response = client
+                    .send_onchain(
+                        amount_sat,
+                        address,
+                        label,
+                        idempotency_key,
+                        override_token.as_deref(),
+                    )
+                    .await?;
+                print!("{}", utils::format_send_onchain(&response));
+            }
         }
         Commands::PayBolt11 {
             invoice,
-            amount_msats,
+            amount_msat,
+            correlation_id,
+            idempotency_key,
+            override_limit_token_file,
+            timeout_secs,
         } => {
-            let payment = client.pay_bolt11_invoice(invoice, amount_msats).await?;
+            if !yes {
+                let bolt11 = Bolt11Invoice::from_str(&invoice)
+                    .map_err(|e| anyhow!("Invalid invoice: {e}"))?;
+                if !confirm(&utils::format_bolt11_confirmation(&bolt11, amount_msat))? {
+                    return Ok(());
+                }
+            }
+            let override_token = override_limit_token_file
+                .as_ref()
+                .map(read_approver_token)
+                .transpose()?;
+            let payment = client
+                .pay_bolt11_invoice(
+                    invoice,
+                    amount_msat,
+                    correlation_id,
+                    idempotency_key,
+                    override_token.as_deref(),
+                    timeout_secs,
+                )
+                .await?;
             print!("{}", utils::format_payment_response(&payment));
         }
         Commands::PayBolt12 {
             offer,
-            amount_msats,
+            amount_msat,
+            correlation_id,
+            idempotency_key,
+            override_limit_token_file,
+            timeout_secs,
         } => {
-            let payment = client.pay_bolt12_offer(offer, amount_msats).await?;
+            if !yes {
+                let decoded = client.decode_offer(offer.clone()).await?;
+                if !confirm(&utils::format_bolt12_confirmation(&decoded, amount_msat))? {
+                    return Ok(());
+                }
+            }
+            let override_token = override_limit_token_file
+                .as_ref()
+                .map(read_approver_token)
+                .transpose()?;
+            let payment = client
+                .pay_bolt12_offer(
+                    offer,
+                    amount_msat,
+                    correlation_id,
+                    idempotency_key,
+                    override_token.as_deref(),
+                    timeout_secs,
+                )
+                .await?;
             print!("{}", utils::format_payment_response(&payment));
         }
         Commands::CreateBolt11Invoice {
-            amount_msats,
+            amount_msat,
             description,
             expiry_seconds,
+            hold,
+            payment_hash,
+            description_hash,
         } => {
-            let invoice = client
-                .create_bolt11_invoice(amount_msats, description, expiry_seconds)
-                .await?;
+            let invoice = if hold {
+                client
+                    .create_hold_invoice(
+                        amount_msat,
+                        description,
+                        expiry_seconds,
+                        payment_hash,
+                        description_hash,
+                    )
+                    .await?
+            } else {
+                client
+                    .create_bolt11_invoice(
+                        amount_msat,
+                        description,
+                        expiry_seconds,
+                        description_hash,
+                    )
+                    .await?
+            };
             println!("Invoice created successfully!");
             println!("Payment hash: {}", invoice.payment_hash);
             println!("Invoice: {}", invoice.invoice);
@@ -180,12 +852,20 @@ async fn main() -> Result<()> {
             println!("Expires: {}", invoice.expiry_time);
         }
         Commands::CreateBolt12Offer {
-            amount_msats,
+            amount_msat,
             description,
             expiry_seconds,
+            absolute_expiry,
+            quantity,
         } => {
             let offer = client
-                .create_bolt12_offer(amount_msats, description, expiry_seconds)
+                .create_bolt12_offer(
+                    amount_msat,
+                    description,
+                    expiry_seconds,
+                    absolute_expiry,
+                    quantity,
+                )
                 .await?;
             println!("Offer created successfully!");
             println!("Offer ID: {}", offer.offer_id);
@@ -194,7 +874,166 @@ async fn main() -> Result<()> {
             // Format expiry time as human-readable date
             println!("Expires: {}", offer.expiry_time);
         }
+        Commands::DecodeOffer { offer } => {
+            let offer = client.decode_offer(offer).await?;
+            print!("{}", utils::format_decoded_offer(&offer));
+        }
+        Commands::CheckIncomingPayments { payment_ids } => {
+            let payments = client.check_incoming_payments(payment_ids).await?;
+            print!("{}", utils::format_incoming_payments(&payments));
+        }
+        Commands::ListPeers => {
+            let peers = client.list_peers().await?;
+            print!("{}", utils::format_peers(&peers));
+        }
+        Commands::ListPendingOperations => {
+            let operations = client.list_pending_operations().await?;
+            print!("{}", utils::format_pending_operations(&operations));
+        }
+        Commands::ApproveOperation { id, token_file } => {
+            let token = read_approver_token(&token_file)?;
+            let response = client.approve_operation(id, &token).await?;
+            print!("{}", utils::format_approved_operation(&response));
+        }
+        Commands::RejectOperation { id, token_file } => {
+            let token = read_approver_token(&token_file)?;
+            client.reject_operation(id.clone(), &token).await?;
+            println!("Operation {id} rejected");
+        }
+        Commands::Prune {
+            payment_retention_days,
+            activity_retention_days,
+            dry_run,
+        } => {
+            let response = client
+                .prune(payment_retention_days, activity_retention_days, dry_run)
+                .await?;
+            print!("{}", utils::format_prune_report(&response));
+        }
+        Commands::RotateTls => {
+            let response = client.rotate_tls().await?;
+            print!("{}", utils::format_tls_rotation(&response));
+        }
+        Commands::ListPayments {
+            created_from,
+            created_to,
+            settled_from,
+            settled_to,
+            min_amount_msat,
+            max_amount_msat,
+            direction,
+            status,
+            limit,
+            offset,
+        } => {
+            let response = client
+                .list_payments(ListPaymentsRequest {
+                    created_from,
+                    created_to,
+                    settled_from,
+                    settled_to,
+                    min_amount_msat,
+                    max_amount_msat,
+                    direction,
+                    status,
+                    limit,
+                    offset,
+                })
+                .await?;
+            print!("{}", utils::format_payments(&response));
+        }
+        Commands::ExportLedger {
+            output,
+            created_from,
+            created_to,
+            batch_size,
+        } => {
+            let mut batches = client
+                .export_ledger(created_from, created_to, batch_size)
+                .await?;
+
+            let mut writer: Box<dyn Write> = match &output {
+                Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            let mut rows = 0;
+            while let Some(batch) = batches.message().await? {
+                writer.write_all(batch.csv.as_bytes())?;
+                rows += batch.rows;
+            }
+            writer.flush()?;
+
+            if let Some(path) = output {
+                println!("Exported {} payments to {}", rows, path.display());
+            }
+        }
+        Commands::AbandonPayment { payment_id } => {
+            let abandoned = client.abandon_payment(payment_id).await?;
+            println!(
+                "Abandoned payment {} at {}, its melt is reported failed",
+                abandoned.payment_id, abandoned.abandoned_at
+            );
+        }
+        Commands::SettleHoldInvoice {
+            payment_hash,
+            preimage,
+        } => {
+            let invoice = client.settle_hold_invoice(payment_hash, preimage).await?;
+            print!("{}", utils::format_hold_invoice(&invoice));
+        }
+        Commands::CancelHoldInvoice { payment_hash } => {
+            let invoice = client.cancel_hold_invoice(payment_hash).await?;
+            print!("{}", utils::format_hold_invoice(&invoice));
+        }
+        Commands::ListHoldInvoices => {
+            let invoices = client.list_hold_invoices().await?;
+            print!("{}", utils::format_hold_invoices(&invoices));
+        }
+        Commands::GetSummary { days } => {
+            let summary = client
+                .get_summary(days.map(|days| days * 24 * 60 * 60))
+                .await?;
+            print!("{}", utils::format_summary(&summary));
+        }
+        Commands::DiagnosePeer { node_id, address } => {
+            let diagnosis = client.diagnose_peer(node_id, address).await?;
+            print!("{}", utils::format_peer_diagnosis(&diagnosis));
+        }
     }
 
     Ok(())
 }
+
+/// Read the approver token from a file
+/// Show what a command is about to do and ask to go ahead
+///
+/// Refuses when stdin is not a terminal, scripts have to pass `--yes`.
+fn confirm(summary: &str) -> Result<bool> {
+    print!("{summary}");
+
+    if !std::io::stdin().is_terminal() {
+        bail!("Not asking for confirmation without a terminal, pass --yes to go ahead");
+    }
+
+    print!("Proceed? [y/N] ");
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+
+    let confirmed = matches!(answer.trim().to_lowercase().as_str(), "y" | "yes");
+    if !confirmed {
+        println!("Aborted");
+    }
+    Ok(confirmed)
+}
+
+fn read_approver_token(path: &PathBuf) -> Result<String> {
+    read_token(path, "approver token")
+}
+
+fn read_token(path: &PathBuf, name: &str) -> Result<String> {
+    let token = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Could not read {name} from {}: {e}", path.display()))?;
+    Ok(token.trim().to_string())
+}
@@ -1,12 +1,24 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
-use cdk_common::common::FeeReserve;
 use cdk_ldk_node::config::Config;
-use clap::Parser;
+use cdk_ldk_node::lock::StorageLock;
+use cdk_ldk_node::logs::LogBuffer;
+use cdk_ldk_node::seed::NodeSeed;
+use cdk_ldk_node::tenants::TenantProcessor;
+use cdk_ldk_node::utils::format_store_report;
+use cdk_ldk_node::{payment, CdkLdkNodeBuilder, MintPayment};
+use clap::{Parser, Subcommand};
+use tokio::runtime::{Handle, Runtime};
 use tokio::signal;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
+/// Attempts to bind the payment processor when it is restarted with rotated TLS
+const PAYMENT_PROCESSOR_RESTART_ATTEMPTS: u32 = 50;
+
 #[derive(Parser, Debug)]
 #[command(author, version, about = "CDK LDK Node - A Lightning Network node implementation", long_about = None)]
 struct Args {
@@ -18,89 +30,412 @@ struct Args {
         help = "Specify a custom working directory containing the config.toml file"
     )]
     work_dir: Option<PathBuf>,
+
+    /// Remove a storage directory lock left behind by a node that is not running
+    #[arg(long)]
+    force_unlock: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check the storage directory of a stopped node and report issues
+    CheckStore {
+        /// Quarantine partially written files and keep their readable records
+        #[arg(long)]
+        repair: bool,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
     // Parse command line arguments
     let args = Args::parse();
 
-    let runtime = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()?;
+    let default_filter = "debug";
 
-    let runtime = Arc::new(runtime);
+    let hyper_filter = "hyper=warn";
+    let h2_filter = "h2=warn";
+    let rustls_filter = "rustls=warn";
 
-    let runtime_clone = runtime.clone();
+    let env_filter = EnvFilter::new(format!(
+        "{default_filter},{hyper_filter},{h2_filter},{rustls_filter}"
+    ));
 
-    runtime.block_on(async {
-        let default_filter = "debug";
+    // Keep recent events in memory for the management service
+    let log_buffer = LogBuffer::default();
+
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(log_buffer.layer())
+        .init();
 
-        let hyper_filter = "hyper=warn";
-        let h2_filter = "h2=warn";
-        let rustls_filter = "rustls=warn";
+    // Load configuration before the runtime, the performance profile sizes it
+    let config = if let Some(work_dir) = &args.work_dir {
+        Config::load_with_path(work_dir)?
+    } else {
+        Config::load()?
+    };
 
-        let env_filter = EnvFilter::new(format!(
-            "{default_filter},{hyper_filter},{h2_filter},{rustls_filter}"
-        ));
+    let performance_profile = config.performance_profile()?;
+    tracing::info!("Using {} performance profile", performance_profile);
 
-        tracing_subscriber::fmt().with_env_filter(env_filter).init();
+    let runtime = Arc::new(build_runtime("node", config.node_worker_threads()?)?);
 
-        // Load configuration
-        let config = if let Some(work_dir) = &args.work_dir {
-            Config::load_with_path(work_dir)?
-        } else {
-            Config::load()?
-        };
+    // Dedicated runtimes keep RPC load from starving the node's event handling
+    let management_runtime = config
+        .management_worker_threads()?
+        .map(|threads| build_runtime("management", Some(threads)))
+        .transpose()?;
+    let payment_processor_runtime = config
+        .payment_processor_worker_threads()?
+        .map(|threads| build_runtime("payment-processor", Some(threads)))
+        .transpose()?;
+
+    let runtime_clone = runtime.clone();
+
+    runtime.block_on(async {
+        if let Some(Command::CheckStore { repair }) = args.command {
+            return check_store(&config, repair, args.force_unlock).await;
+        }
 
         // Extract configuration values
         let listen_addr = config.payment_processor_listen_host();
         let listen_port = config.payment_processor_listen_port();
-        let chain_source = config.chain_source();
+        let chain_sources = config.chain_sources()?;
         let network = config.bitcoin_network();
         let storage_dir_path = config.storage_dir_path();
-        let gossip_source = config.gossip_source();
+        let gossip_source = config.gossip_source()?;
+
+        let seed = NodeSeed::load_or_create(
+            Path::new(&storage_dir_path),
+            config.seed_import_mnemonic()?,
+            config.seed_passphrase()?,
+        )?;
 
         let ldk_node_listen_addr = config.ldk_node_listen_addr()?;
 
-        let cdk_ldk = cdk_ldk_node::CdkLdkNode::new(
+        // Pick the first usable chain source before exposing the payment processor
+        let chain_source =
+            cdk_ldk_node::chain::select_chain_source(&chain_sources, network).await?;
+        let fallback_chain_sources = chain_sources
+            .into_iter()
+            .filter(|s| *s != chain_source)
+            .collect();
+
+        let mut builder = CdkLdkNodeBuilder::new(
             network,
             chain_source,
             gossip_source,
             storage_dir_path,
-            FeeReserve {
-                min_fee_reserve: 2.into(),
-                percent_fee_reserve: 0.02,
-            },
+            config.fee_reserve(),
             vec![ldk_node_listen_addr],
-        )?;
+        );
+        builder.set_seed(seed);
+        builder.set_log_buffer(log_buffer);
+        builder.set_force_unlock(args.force_unlock);
+        if let Some(token) = config.seed_export_token()? {
+            builder.set_seed_export_token(&token);
+        }
+        builder.set_address_type(config.onchain_address_type()?);
+        builder.set_reuse_unused_addresses(config.reuse_unused_address());
+        builder.set_onchain_reserve(config.onchain_reserve_sat());
+        if let Some(policy) = config.approval_policy()? {
+            builder.set_approval_policy(policy);
+        }
+        builder.set_management_limits(config.management_limits());
+        builder.set_fee_strategy(config.fee_strategy()?);
+        if let Some(limits) = config.bolt12_limits()? {
+            builder.set_invoice_request_policy(Arc::new(limits));
+        }
+        if let Some(rotation) = config.offer_rotation() {
+            builder.set_offer_rotation(rotation);
+        }
+        builder.set_trusted_peers_0conf(config.trusted_peers_0conf()?);
+        builder.set_redact_preimages(config.redact_preimages());
+        if let Some(alerts) = config.alerts()? {
+            builder.set_alerts(alerts);
+        }
+        if let Some(tls_dir) = config.grpc_tls_dir() {
+            builder.set_management_tls_dir(tls_dir);
+        }
+        if let Some(tls_dir) = config.payment_processor_tls_dir() {
+            builder.set_payment_processor_tls_dir(tls_dir);
+        }
+        if let Some(onchain_payments) = config.onchain_payments() {
+            builder.set_onchain_payments(onchain_payments);
+        }
+        builder.set_notification_capacity(config.notification_capacity());
+        for sink in config.notification_sinks()? {
+            builder.add_notification_sink(sink);
+        }
+        if let Some(policy) = config.prune_policy()? {
+            builder.set_prune_policy(policy);
+        }
+        if let Some(sync_intervals) = performance_profile.sync_intervals() {
+            builder.set_sync_intervals(sync_intervals);
+        }
+        builder.set_peer_monitor_interval(performance_profile.peer_monitor_interval());
+        builder.set_quote_cache_ttl(config.quote_cache_ttl());
+        builder.set_melt_cancellation_window(config.melt_cancellation_window());
+        builder.set_payment_send_timeout(config.payment_send_timeout()?);
+        builder.set_retry_policy(config.retry_policy());
+        builder.set_pathfinding_config(config.pathfinding());
+        builder.set_channel_config(config.channel_config());
+        if let Some(default_description) = config.default_invoice_description() {
+            builder.set_default_description(default_description);
+        }
+        builder.set_hold_mint_invoices(config.hold_mint_invoices());
+        builder.set_hash_descriptions(config.hash_invoice_descriptions());
+        builder.set_request_descriptions(config.request_invoice_descriptions());
+        builder.set_default_incoming_expiry(config.default_incoming_expiry());
+        builder.set_incoming_expiry_margin(config.incoming_expiry_margin());
+        builder.set_tenants(config.tenants()?);
+        builder.set_unit(config.unit()?);
+        if let Some(management_runtime) = &management_runtime {
+            builder.set_management_runtime(management_runtime.handle().clone());
+        }
+
+        let cdk_ldk = builder.build()?;
 
         cdk_ldk.start(Some(runtime_clone))?;
 
+        cdk_ldk.start_chain_source_monitor(
+            fallback_chain_sources,
+            config.chain_source_health_check_interval(),
+        );
+
         let cdk_ldk = Arc::new(cdk_ldk);
 
         // Start payment processor server
-        let mut payment_server = cdk_payment_processor::PaymentProcessorServer::new(
+        let payment_processor_runtime = payment_processor_runtime
+            .as_ref()
+            .map_or_else(Handle::current, |runtime| runtime.handle().clone());
+
+        let tls_dir = config.payment_processor_tls_dir();
+
+        let mut payment_server = start_payment_processor(
+            &payment_processor_runtime,
             cdk_ldk.clone(),
             &listen_addr,
             listen_port,
-        )?;
-
-        let tls_dir = config.payment_processor_tls_dir();
+            tls_dir.clone(),
+        )
+        .await?;
 
-        payment_server.start(tls_dir).await?;
+        // Start a payment processor for every tenant with a port
+        let mut tenant_servers = Vec::new();
+        for tenant in cdk_ldk.tenants().iter() {
+            let Some(port) = tenant.listen_port else {
+                continue;
+            };
+            let processor = Arc::new(TenantProcessor::new(cdk_ldk.clone(), tenant.id.clone()));
+            let server = start_payment_processor(
+                &payment_processor_runtime,
+                processor.clone(),
+                &listen_addr,
+                port,
+                tls_dir.clone(),
+            )
+            .await?;
+            tracing::info!(
+                "Started payment processor of tenant {} on port {}",
+                tenant.id,
+                port
+            );
+            tenant_servers.push((processor, port, server));
+        }
 
         // Start gRPC management server
         let grpc_addr = config.grpc_socket_addr()?;
         cdk_ldk.start_management_service(grpc_addr)?;
 
-        // Wait for shutdown signal
-        signal::ctrl_c().await?;
+        if let Some(interval) = config.backup_verify_interval() {
+            cdk_ldk.start_backup_verification(interval);
+        }
+
+        if let Some(route_health) = config.route_health()? {
+            cdk_ldk.start_route_health_monitor(route_health);
+        }
+
+        if let Some(auto_close) = config.auto_close()? {
+            if auto_close.dry_run {
+                tracing::info!("Channel auto close running in dry run mode");
+            }
+            cdk_ldk.start_auto_close(auto_close);
+        }
+
+        if config.prune_scheduled() {
+            cdk_ldk.start_pruning();
+        }
+
+        if cdk_ldk.onchain_payments().is_some() {
+            cdk_ldk.start_deposit_watcher();
+        }
+
+        if config.alerts()?.is_some() {
+            cdk_ldk.start_alerts();
+        }
+
+        if let Some(period) = config.summary_period()? {
+            cdk_ldk.start_summaries(period);
+        }
+
+        if let Some(watchdog) = config.watchdog()? {
+            cdk_ldk.start_payment_watchdog(watchdog);
+        }
+
+        // Start HTTP server for metrics
+        if let Some(http_addr) = config.http_socket_addr()? {
+            cdk_ldk.start_http_server(http_addr, config.http_status_page())?;
+        }
+
+        // Wait for shutdown signal, restarting the payment processor when its
+        // TLS material is rotated
+        loop {
+            tokio::select! {
+                result = signal::ctrl_c() => {
+                    result?;
+                    break;
+                }
+                _ = cdk_ldk.tls_rotated() => {
+                    tracing::info!("Restarting payment processor with rotated TLS");
+                    payment_server.stop().await?;
+                    payment_server = restart_payment_processor(
+                        &payment_processor_runtime,
+                        cdk_ldk.clone(),
+                        &listen_addr,
+                        listen_port,
+                        tls_dir.clone(),
+                    )
+                    .await?;
+
+                    for (processor, port, server) in tenant_servers.iter_mut() {
+                        server.stop().await?;
+                        *server = restart_payment_processor(
+                            &payment_processor_runtime,
+                            processor.clone(),
+                            &listen_addr,
+                            *port,
+                            tls_dir.clone(),
+                        )
+                        .await?;
+                    }
+                    tracing::info!("Restarted payment processor with rotated TLS");
+                }
+            }
+        }
 
         // Stop both servers
         tracing::info!("Received shutdown signal, stopping servers");
         payment_server.stop().await?;
-        cdk_ldk.stop()?;
+        for (_, _, server) in tenant_servers.iter_mut() {
+            server.stop().await?;
+        }
+        cdk_ldk.shutdown().await?;
 
         Ok(())
     })
 }
+
+/// Build a multi-threaded runtime, with one worker per CPU core when
+/// `worker_threads` is `None`
+fn build_runtime(name: &str, worker_threads: Option<usize>) -> std::io::Result<Runtime> {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    if let Some(worker_threads) = worker_threads {
+        builder.worker_threads(worker_threads);
+    }
+    builder
+        .thread_name(format!("{name}-worker"))
+        .enable_all()
+        .build()
+}
+
+/// Start a payment processor server on a runtime
+async fn start_payment_processor(
+    runtime: &Handle,
+    processor: Arc<dyn MintPayment<Err = payment::Error> + Send + Sync>,
+    listen_addr: &str,
+    listen_port: u16,
+    tls_dir: Option<PathBuf>,
+) -> anyhow::Result<cdk_payment_processor::PaymentProcessorServer> {
+    let listen_addr = listen_addr.to_string();
+
+    // The server spawns its tasks on the runtime it is started from
+    runtime
+        .spawn(async move {
+            let mut server = cdk_payment_processor::PaymentProcessorServer::new(
+                processor,
+                &listen_addr,
+                listen_port,
+            )?;
+            server.start(tls_dir).await?;
+            Ok::<_, anyhow::Error>(server)
+        })
+        .await?
+}
+
+/// Start a payment processor again after its previous server was stopped
+async fn restart_payment_processor(
+    runtime: &Handle,
+    processor: Arc<dyn MintPayment<Err = payment::Error> + Send + Sync>,
+    listen_addr: &str,
+    listen_port: u16,
+    tls_dir: Option<PathBuf>,
+) -> anyhow::Result<cdk_payment_processor::PaymentProcessorServer> {
+    let mut attempt = 1;
+    loop {
+        // The previous server may still hold the port for a moment
+        match start_payment_processor(
+            runtime,
+            processor.clone(),
+            listen_addr,
+            listen_port,
+            tls_dir.clone(),
+        )
+        .await
+        {
+            Ok(server) => return Ok(server),
+            Err(err) if attempt < PAYMENT_PROCESSOR_RESTART_ATTEMPTS => {
+                tracing::debug!("Could not restart payment processor yet: {}", err);
+                attempt += 1;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Check the storage directory, holding its lock so the node cannot start meanwhile
+async fn check_store(config: &Config, repair: bool, force_unlock: bool) -> anyhow::Result<()> {
+    let storage_dir = PathBuf::from(config.storage_dir_path());
+    let network = config.bitcoin_network();
+    let passphrase = config.seed_passphrase()?;
+
+    let _lock = StorageLock::acquire(&storage_dir, force_unlock)?;
+
+    let report = tokio::task::spawn_blocking(move || {
+        cdk_ldk_node::store::check_store(&storage_dir, network, passphrase)
+    })
+    .await?;
+
+    println!("{}", format_store_report(&report));
+
+    if repair {
+        let quarantined = cdk_ldk_node::store::repair(&report)?;
+        for path in &quarantined {
+            println!("Quarantined {}", path.display());
+        }
+        if quarantined.is_empty() {
+            println!("Nothing to repair");
+        }
+    }
+
+    if report.has_errors() {
+        anyhow::bail!("Storage directory has errors, the node should not be started");
+    }
+
+    Ok(())
+}
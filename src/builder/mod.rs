@@ -0,0 +1,646 @@
+//! Builder for [`CdkLdkNode`]
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use anyhow::bail;
+use cdk_common::common::FeeReserve;
+use cdk_common::util::unix_time;
+use cdk_common::CurrencyUnit;
+use ldk_node::bitcoin::secp256k1::PublicKey;
+use ldk_node::bitcoin::Network;
+use ldk_node::config::{ChannelConfig, EsploraSyncConfig};
+use ldk_node::lightning::ln::msgs::SocketAddress;
+use ldk_node::Builder;
+use tokio::runtime::Handle;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::abandon::{AbandonedPayments, DEFAULT_MELT_CANCELLATION_WINDOW};
+use crate::activity::ActivityLog;
+use crate::alerts::{AlertEngine, AlertsConfig};
+use crate::approvals::{ApprovalPolicy, ApprovalQueue};
+use crate::chain::ChainSourceHealth;
+use crate::deposits::{DepositStore, OnchainPaymentsConfig, OutputCache};
+use crate::fees::{FeeStrategy, StaticFeeStrategy};
+use crate::hold::HoldInvoices;
+use crate::hooks::LifecycleHooks;
+use crate::idempotency::IdempotencyStore;
+use crate::labels::LabelStore;
+use crate::limits::ManagementLimits;
+use crate::lock::StorageLock;
+use crate::logs::LogBuffer;
+use crate::metrics::PaymentMetrics;
+use crate::notifications::sinks::{NotificationSink, NotificationSinks};
+use crate::notifications::{
+    NotificationJournal, PaymentNotification, PaymentNotifier, DEFAULT_NOTIFICATION_CAPACITY,
+};
+use crate::offers::pool::{OfferPool, OfferRotation};
+use crate::offers::InvoiceRequestPolicy;
+use crate::onchain::{AddressIssuer, AddressType};
+use crate::payments::in_flight::InFlightPayments;
+use crate::payments::tracker::PaymentTracker;
+use crate::payments::PaymentIndex;
+use crate::peers::PeerTracker;
+use crate::performance::SyncIntervals;
+use crate::prune::PrunePolicy;
+use crate::quotes::{QuoteCache, DEFAULT_QUOTE_CACHE_TTL};
+use crate::retry::{FailureReasons, RetryPolicy};
+use crate::seed::export::{SeedExportAudit, SeedExportToken};
+use crate::seed::NodeSeed;
+use crate::tenants::{TenantTags, Tenants};
+use crate::{
+    BitcoinRpcConfig, CdkLdkNode, ChainSource, GossipSource, NodeState, PathfindingConfig,
+    DEFAULT_INCOMING_EXPIRY_SECS, DEFAULT_PAYMENT_SEND_TIMEOUT, DEFAULT_PEER_MONITOR_INTERVAL,
+};
+
+/// Builder for [`CdkLdkNode`]
+///
+/// Required settings are passed to [`CdkLdkNodeBuilder::new`], optional ones
+/// are set with the `set_*` methods and fall back to LDK Node defaults.
+pub struct CdkLdkNodeBuilder {
+    network: Network,
+    chain_source: ChainSource,
+    gossip_source: GossipSource,
+    storage_dir_path: String,
+    fee_reserve: FeeReserve,
+    listening_addresses: Vec<SocketAddress>,
+    pathfinding: PathfindingConfig,
+    channel_config: ChannelConfig,
+    default_description: Option<String>,
+    hold_mint_invoices: bool,
+    hash_descriptions: bool,
+    request_descriptions: bool,
+    unit: CurrencyUnit,
+    default_incoming_expiry_secs: u32,
+    incoming_expiry_margin_secs: u32,
+    tenants: Tenants,
+    seed: Option<NodeSeed>,
+    seed_export_token: Option<SeedExportToken>,
+    address_type: AddressType,
+    reuse_unused_addresses: bool,
+    onchain_reserve_sat: u64,
+    quote_cache_ttl: Duration,
+    melt_cancellation_window: Duration,
+    payment_send_timeout: Duration,
+    retry_policy: RetryPolicy,
+    log_buffer: Option<LogBuffer>,
+    approval_policy: Option<ApprovalPolicy>,
+    management_limits: ManagementLimits,
+    force_unlock: bool,
+    prune_policy: Option<PrunePolicy>,
+    sync_intervals: Option<SyncIntervals>,
+    peer_monitor_interval: Duration,
+    notification_capacity: usize,
+    notification_sinks: Vec<Arc<dyn NotificationSink>>,
+    hooks: LifecycleHooks,
+    fee_strategy: Option<Arc<dyn FeeStrategy>>,
+    invoice_request_policy: Option<Arc<dyn InvoiceRequestPolicy>>,
+    offer_rotation: Option<OfferRotation>,
+    trusted_peers_0conf: Vec<PublicKey>,
+    management_tls_dir: Option<PathBuf>,
+    management_runtime: Option<Handle>,
+    payment_processor_tls_dir: Option<PathBuf>,
+    alerts: Option<AlertsConfig>,
+    onchain_payments: Option<OnchainPaymentsConfig>,
+    redact_preimages: bool,
+}
+
+impl CdkLdkNodeBuilder {
+    pub fn new(
+        network: Network,
+        chain_source: ChainSource,
+        gossip_source: GossipSource,
+        storage_dir_path: String,
+        fee_reserve: FeeReserve,
+        listening_addresses: Vec<SocketAddress>,
+    ) -> Self {
+        Self {
+            network,
+            chain_source,
+            gossip_source,
+            storage_dir_path,
+            fee_reserve,
+            listening_addresses,
+            pathfinding: PathfindingConfig::default(),
+            channel_config: ChannelConfig::default(),
+            default_description: None,
+            hold_mint_invoices: false,
+            hash_descriptions: false,
+            request_descriptions: true,
+            unit: CurrencyUnit::Sat,
+            default_incoming_expiry_secs: DEFAULT_INCOMING_EXPIRY_SECS,
+            incoming_expiry_margin_secs: 0,
+            tenants: Tenants::default(),
+            seed: None,
+            seed_export_token: None,
+            address_type: AddressType::default(),
+            reuse_unused_addresses: false,
+            onchain_reserve_sat: 0,
+            quote_cache_ttl: DEFAULT_QUOTE_CACHE_TTL,
+            melt_cancellation_window: DEFAULT_MELT_CANCELLATION_WINDOW,
+            payment_send_timeout: DEFAULT_PAYMENT_SEND_TIMEOUT,
+            retry_policy: RetryPolicy::default(),
+            log_buffer: None,
+            approval_policy: None,
+            management_limits: ManagementLimits::default(),
+            force_unlock: false,
+            prune_policy: None,
+            sync_intervals: None,
+            peer_monitor_interval: DEFAULT_PEER_MONITOR_INTERVAL,
+            notification_capacity: DEFAULT_NOTIFICATION_CAPACITY,
+            notification_sinks: vec![],
+            hooks: LifecycleHooks::new(),
+            fee_strategy: None,
+            invoice_request_policy: None,
+            offer_rotation: None,
+            trusted_peers_0conf: vec![],
+            management_tls_dir: None,
+            management_runtime: None,
+            payment_processor_tls_dir: None,
+            alerts: None,
+            onchain_payments: None,
+            redact_preimages: false,
+        }
+    }
+
+    /// Set pathfinding and probing parameters
+    pub fn set_pathfinding_config(&mut self, pathfinding: PathfindingConfig) -> &mut Self {
+        self.pathfinding = pathfinding;
+        self
+    }
+
+    /// Set the channel config used for channels opened through the management service
+    pub fn set_channel_config(&mut self, channel_config: ChannelConfig) -> &mut Self {
+        self.channel_config = channel_config;
+        self
+    }
+
+    /// Set the unit of the mint, `sat` by default
+    ///
+    /// Only `sat` and `msat` are supported. Received payments are reported to
+    /// the mint in this unit, so msat mints keep sub-sat amounts.
+    pub fn set_unit(&mut self, unit: CurrencyUnit) -> &mut Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Set the description template used for incoming payment requests without a description
+    ///
+    /// `{amount}` and `{unit}` in the template are replaced with the requested
+    /// amount and its unit.
+    pub fn set_default_description(&mut self, default_description: String) -> &mut Self {
+        self.default_description = Some(default_description);
+        self
+    }
+
+    /// Create the BOLT11 invoices of mint quotes as hold invoices
+    ///
+    /// The mint is only told about a payment once it is settled through the
+    /// management API, see [`crate::hold`].
+    pub fn set_hold_mint_invoices(&mut self, hold: bool) -> &mut Self {
+        self.hold_mint_invoices = hold;
+        self
+    }
+
+    /// Commit to the descriptions of mint quote BOLT11 invoices by their hash
+    ///
+    /// Descriptions too long for an invoice are always committed to by their
+    /// hash, see [`crate::bolt11_description`].
+    pub fn set_hash_descriptions(&mut self, hash: bool) -> &mut Self {
+        self.hash_descriptions = hash;
+        self
+    }
+
+    /// Put the descriptions wallets request in mint quote invoices and offers
+    ///
+    /// When disabled the mint is told descriptions are not supported and the
+    /// default description is used instead.
+    pub fn set_request_descriptions(&mut self, enabled: bool) -> &mut Self {
+        self.request_descriptions = enabled;
+        self
+    }
+
+    /// Set the expiry of mint quote payment requests when the mint does not set one
+    pub fn set_default_incoming_expiry(&mut self, expiry_secs: u32) -> &mut Self {
+        self.default_incoming_expiry_secs = expiry_secs;
+        self
+    }
+
+    /// Set how long before their quote mint quote payment requests expire
+    pub fn set_incoming_expiry_margin(&mut self, margin_secs: u32) -> &mut Self {
+        self.incoming_expiry_margin_secs = margin_secs;
+        self
+    }
+
+    /// Set how many payment notifications are kept for mints that have not
+    /// read them
+    ///
+    /// A mint lagging further behind gets the payments it missed re-read from
+    /// the node.
+    pub fn set_notification_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.notification_capacity = capacity;
+        self
+    }
+
+    /// Set the tenants sharing the node, see [`crate::tenants`]
+    pub fn set_tenants(&mut self, tenants: Tenants) -> &mut Self {
+        self.tenants = tenants;
+        self
+    }
+
+    /// Set the seed the node keys are derived from
+    ///
+    /// Defaults to the raw seed file LDK Node creates in the storage directory.
+    pub fn set_seed(&mut self, seed: NodeSeed) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Allow exporting the node seed through the management service with
+    /// the given export token
+    pub fn set_seed_export_token(&mut self, token: &str) -> &mut Self {
+        self.seed_export_token = Some(SeedExportToken::new(token));
+        self
+    }
+
+    /// Set the default onchain address type
+    pub fn set_address_type(&mut self, address_type: AddressType) -> &mut Self {
+        self.address_type = address_type;
+        self
+    }
+
+    /// Reuse the last issued onchain address until it has been used by default
+    pub fn set_reuse_unused_addresses(&mut self, reuse: bool) -> &mut Self {
+        self.reuse_unused_addresses = reuse;
+        self
+    }
+
+    /// Set the onchain balance that sends and channel opens must leave untouched
+    pub fn set_onchain_reserve(&mut self, reserve_sat: u64) -> &mut Self {
+        self.onchain_reserve_sat = reserve_sat;
+        self
+    }
+
+    /// Hold withdrawals made through the management API above a threshold for approval
+    pub fn set_approval_policy(&mut self, policy: ApprovalPolicy) -> &mut Self {
+        self.approval_policy = Some(policy);
+        self
+    }
+
+    /// Set the largest payments the management API makes without an override
+    ///
+    /// Unset limits leave the amounts unbounded.
+    pub fn set_management_limits(&mut self, limits: ManagementLimits) -> &mut Self {
+        self.management_limits = limits;
+        self
+    }
+
+    /// Set the retention policy for payment records and activity history
+    ///
+    /// Used as the default of the prune management command and by scheduled
+    /// pruning, see [`CdkLdkNode::start_pruning`].
+    pub fn set_prune_policy(&mut self, policy: PrunePolicy) -> &mut Self {
+        self.prune_policy = Some(policy);
+        self
+    }
+
+    /// Enable onchain mint and melt quotes
+    ///
+    /// Disabled by default, see [`CdkLdkNode::create_incoming_onchain_request`].
+    pub fn set_onchain_payments(&mut self, onchain_payments: OnchainPaymentsConfig) -> &mut Self {
+        self.onchain_payments = Some(onchain_payments);
+        self
+    }
+
+    /// Set the wallet sync intervals used with an Esplora chain source
+    ///
+    /// Defaults to the LDK Node intervals. LDK Node does not support custom
+    /// intervals for the bitcoind RPC chain source.
+    pub fn set_sync_intervals(&mut self, intervals: SyncIntervals) -> &mut Self {
+        self.sync_intervals = Some(intervals);
+        self
+    }
+
+    /// Set the interval between samples of the peer and channel lists
+    pub fn set_peer_monitor_interval(&mut self, interval: Duration) -> &mut Self {
+        self.peer_monitor_interval = interval;
+        self
+    }
+
+    /// Add a sink receiving payment and channel notifications
+    ///
+    /// Sinks are started with the event handler, see
+    /// [`notifications::sinks`](crate::notifications::sinks).
+    pub fn add_notification_sink(&mut self, sink: Arc<dyn NotificationSink>) -> &mut Self {
+        self.notification_sinks.push(sink);
+        self
+    }
+
+    /// Call `hook` once the node has started, see [`hooks`](crate::hooks)
+    pub fn on_started(&mut self, hook: impl Fn(&CdkLdkNode) + Send + Sync + 'static) -> &mut Self {
+        self.hooks.add_started(Arc::new(hook));
+        self
+    }
+
+    /// Call `hook` for every received payment queued for the mint
+    pub fn on_payment_received(
+        &mut self,
+        hook: impl Fn(&PaymentNotification) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.hooks.add_payment_received(Arc::new(hook));
+        self
+    }
+
+    /// Call `hook` once the node has stopped
+    pub fn on_shutdown(&mut self, hook: impl Fn() + Send + Sync + 'static) -> &mut Self {
+        self.hooks.add_shutdown(Arc::new(hook));
+        self
+    }
+
+    /// Set the strategy computing the fee reserve of outgoing payment quotes
+    ///
+    /// Defaults to a [`StaticFeeStrategy`] with the fee reserve passed to
+    /// [`CdkLdkNodeBuilder::new`].
+    pub fn set_fee_strategy(&mut self, fee_strategy: Arc<dyn FeeStrategy>) -> &mut Self {
+        self.fee_strategy = Some(fee_strategy);
+        self
+    }
+
+    /// Set the policy checking BOLT12 offers and the invoice requests paying them
+    ///
+    /// See [`offers`](crate::offers) for what the policy can reject.
+    pub fn set_invoice_request_policy(
+        &mut self,
+        policy: Arc<dyn InvoiceRequestPolicy>,
+    ) -> &mut Self {
+        self.invoice_request_policy = Some(policy);
+        self
+    }
+
+    /// Share rotating offers between variable amount mint quotes
+    ///
+    /// By default every BOLT12 mint quote gets its own offer, see
+    /// [`offers::pool`](crate::offers::pool).
+    pub fn set_offer_rotation(&mut self, rotation: OfferRotation) -> &mut Self {
+        self.offer_rotation = Some(rotation);
+        self
+    }
+
+    /// Set the trusted LSPs, the only peers zero-conf inbound channels are accepted from
+    pub fn set_trusted_peers_0conf(&mut self, node_ids: Vec<PublicKey>) -> &mut Self {
+        self.trusted_peers_0conf = node_ids;
+        self
+    }
+
+    /// Serve the management service over TLS with the material in a directory
+    ///
+    /// See [`tls`](crate::tls) for the files expected.
+    pub fn set_management_tls_dir(&mut self, tls_dir: PathBuf) -> &mut Self {
+        self.management_tls_dir = Some(tls_dir);
+        self
+    }
+
+    /// Run the management service on a dedicated runtime
+    ///
+    /// Keeps heavy management traffic from starving the runtime the node and
+    /// its event handling run on. By default the service runs on the runtime
+    /// it is started from.
+    pub fn set_management_runtime(&mut self, runtime: Handle) -> &mut Self {
+        self.management_runtime = Some(runtime);
+        self
+    }
+
+    /// Set the TLS directory of the payment processor, checked when TLS is rotated
+    pub fn set_payment_processor_tls_dir(&mut self, tls_dir: PathBuf) -> &mut Self {
+        self.payment_processor_tls_dir = Some(tls_dir);
+        self
+    }
+
+    /// Leave preimages out of notifications, pruned payment exports and
+    /// management responses
+    ///
+    /// A preimage proves a payment was made, which the mint may not want to
+    /// disclose. Preimages are revealed by default.
+    pub fn set_redact_preimages(&mut self, redact_preimages: bool) -> &mut Self {
+        self.redact_preimages = redact_preimages;
+        self
+    }
+
+    /// Set the alert rules, evaluated once started with
+    /// [`CdkLdkNode::start_alerts`]
+    pub fn set_alerts(&mut self, alerts: AlertsConfig) -> &mut Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    /// Set how long outgoing payment quotes are cached, zero disables the cache
+    pub fn set_quote_cache_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.quote_cache_ttl = ttl;
+        self
+    }
+
+    /// Set how long an outgoing payment must be pending before it can be
+    /// abandoned, see [`CdkLdkNode::abandon_payment`]
+    ///
+    /// Should exceed the longest HTLC timeout of the node's payments.
+    pub fn set_melt_cancellation_window(&mut self, window: Duration) -> &mut Self {
+        self.melt_cancellation_window = window;
+        self
+    }
+
+    /// Set how long melts and management payments wait for a payment to
+    /// complete before reporting it pending, 10 seconds by default
+    pub fn set_payment_send_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.payment_send_timeout = timeout;
+        self
+    }
+
+    /// Set when failed BOLT11 melt payments are sent again, not retried by default
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Set the buffer of recent log events served by the management service
+    ///
+    /// The buffer's layer must be installed in the tracing subscriber.
+    pub fn set_log_buffer(&mut self, log_buffer: LogBuffer) -> &mut Self {
+        self.log_buffer = Some(log_buffer);
+        self
+    }
+
+    /// Remove an existing storage directory lock before building
+    ///
+    /// Only for recovering from a lock left behind by a node that is known
+    /// not to be running, see [`StorageLock::acquire`].
+    pub fn set_force_unlock(&mut self, force_unlock: bool) -> &mut Self {
+        self.force_unlock = force_unlock;
+        self
+    }
+
+    /// Build the LDK node and wrap it in a [`CdkLdkNode`]
+    ///
+    /// Fails if another process holds the lock on the storage directory, or
+    /// the unit is not supported.
+    pub fn build(self) -> anyhow::Result<CdkLdkNode> {
+        if !matches!(self.unit, CurrencyUnit::Sat | CurrencyUnit::Msat) {
+            bail!("Unsupported unit {}, expected sat or msat", self.unit);
+        }
+
+        let storage_lock =
+            StorageLock::acquire(Path::new(&self.storage_dir_path), self.force_unlock)?;
+
+        let mut ldk_config = ldk_node::config::Config::default();
+        if let Some(multiplier) = self.pathfinding.probing_liquidity_limit_multiplier {
+            ldk_config.probing_liquidity_limit_multiplier = multiplier;
+        }
+        ldk_config.sending_parameters = Some(self.pathfinding.sending_parameters());
+        ldk_config.trusted_peers_0conf = self.trusted_peers_0conf.clone();
+
+        let mut builder = Builder::from_config(ldk_config);
+        builder.set_network(self.network);
+        builder.set_storage_dir_path(self.storage_dir_path.clone());
+
+        if let Some(seed) = &self.seed {
+            seed.apply(&mut builder);
+        }
+
+        match self.chain_source.clone() {
+            ChainSource::Esplora(esplora_url) => {
+                let sync_config = self.sync_intervals.map(|intervals| EsploraSyncConfig {
+                    onchain_wallet_sync_interval_secs: intervals.onchain_wallet_secs,
+                    lightning_wallet_sync_interval_secs: intervals.lightning_wallet_secs,
+                    fee_rate_cache_update_interval_secs: intervals.fee_rate_cache_secs,
+                });
+                builder.set_chain_source_esplora(esplora_url, sync_config);
+            }
+            ChainSource::BitcoinRpc(BitcoinRpcConfig { host, port, auth }) => {
+                let (user, password) = auth.credentials()?;
+                builder.set_chain_source_bitcoind_rpc(host, port, user, password);
+            }
+        }
+
+        match self.gossip_source {
+            GossipSource::P2P => {
+                builder.set_gossip_source_p2p();
+            }
+            GossipSource::RapidGossipSync(rgs_url) => {
+                builder.set_gossip_source_rgs(rgs_url);
+            }
+        }
+
+        builder.set_listening_addresses(self.listening_addresses)?;
+
+        builder.set_node_alias("cdk-ldk-node".to_string())?;
+
+        let node = builder.build()?;
+
+        let id = node.node_id();
+
+        let adr = node.announcement_addresses();
+
+        tracing::info!("Created node {} with address {:?}", id, adr);
+
+        let node = Arc::new(node);
+        let metrics = Arc::new(PaymentMetrics::new()?);
+        let journal = Arc::new(NotificationJournal::open(Path::new(
+            &self.storage_dir_path,
+        ))?);
+        let notifier = Arc::new(
+            PaymentNotifier::with_reconciler(CdkLdkNode::payment_reconciler(
+                node.clone(),
+                metrics.clone(),
+                self.unit.clone(),
+            ))
+            .with_capacity(self.notification_capacity)
+            .with_journal(journal),
+        );
+
+        Ok(CdkLdkNode {
+            inner: node,
+            fee_strategy: self
+                .fee_strategy
+                .unwrap_or_else(|| Arc::new(StaticFeeStrategy::new(self.fee_reserve))),
+            wait_invoice_cancel_token: CancellationToken::new(),
+            wait_invoice_is_active: Arc::new(AtomicBool::new(false)),
+            notifier,
+            notification_sinks: Arc::new(NotificationSinks::new(
+                self.notification_sinks,
+                self.redact_preimages,
+            )),
+            redact_preimages: self.redact_preimages,
+            abandoned: Arc::new(AbandonedPayments::new(self.storage_dir_path.clone().into())),
+            melt_cancellation_window: self.melt_cancellation_window,
+            payment_send_timeout: self.payment_send_timeout,
+            retry_policy: self.retry_policy,
+            payment_tracker: Arc::new(PaymentTracker::new()),
+            in_flight: Arc::new(InFlightPayments::new()),
+            failure_reasons: Arc::new(FailureReasons::new()),
+            invoice_request_policy: self.invoice_request_policy,
+            trusted_peers_0conf: self.trusted_peers_0conf,
+            offer_pool: self.offer_rotation.map(|rotation| {
+                Arc::new(OfferPool::new(
+                    self.storage_dir_path.clone().into(),
+                    rotation,
+                ))
+            }),
+            events_cancel_token: CancellationToken::new(),
+            management_service_cancel_token: Arc::new(CancellationToken::new()),
+            management_server: Arc::new(Mutex::new(None)),
+            management_tls_dir: self.management_tls_dir,
+            management_runtime: self.management_runtime,
+            payment_processor_tls_dir: self.payment_processor_tls_dir,
+            tls_rotation: Arc::new(Notify::new()),
+            alerts: self
+                .alerts
+                .map(|alerts| Arc::new(AlertEngine::new(alerts, unix_time()))),
+            chain_source: self.chain_source,
+            chain_source_health: Arc::new(RwLock::new(ChainSourceHealth::default())),
+            pathfinding: self.pathfinding,
+            channel_config: self.channel_config,
+            metrics,
+            http_cancel_token: CancellationToken::new(),
+            state: Arc::new(Mutex::new(NodeState::Created)),
+            default_description: self.default_description,
+            hold_invoices: Arc::new(HoldInvoices::new(self.storage_dir_path.clone().into())),
+            hold_mint_invoices: self.hold_mint_invoices,
+            hash_descriptions: self.hash_descriptions,
+            request_descriptions: self.request_descriptions,
+            unit: self.unit,
+            default_incoming_expiry_secs: self.default_incoming_expiry_secs,
+            incoming_expiry_margin_secs: self.incoming_expiry_margin_secs,
+            tenants: Arc::new(self.tenants),
+            tenant_tags: Arc::new(TenantTags::new(self.storage_dir_path.clone().into())),
+            hooks: Arc::new(self.hooks),
+            addresses: Arc::new(AddressIssuer::new(self.storage_dir_path.clone().into())),
+            peer_tracker: Arc::new(PeerTracker::new()),
+            activity: Arc::new(ActivityLog::new(self.storage_dir_path.clone().into())),
+            payment_index: Arc::new(PaymentIndex::open(Path::new(&self.storage_dir_path))?),
+            quote_cache: Arc::new(QuoteCache::new(self.quote_cache_ttl)),
+            log_buffer: self.log_buffer,
+            labels: Arc::new(LabelStore::new(self.storage_dir_path.clone().into())),
+            onchain_payments: self.onchain_payments,
+            deposits: Arc::new(DepositStore::new(self.storage_dir_path.clone().into())),
+            deposit_outputs: Arc::new(OutputCache::new()),
+            approvals: self
+                .approval_policy
+                .map(|policy| Arc::new(ApprovalQueue::new(policy))),
+            management_limits: self.management_limits,
+            prune_policy: self.prune_policy,
+            peer_monitor_interval: self.peer_monitor_interval,
+            idempotency: Arc::new(IdempotencyStore::new(self.storage_dir_path.clone().into())),
+            seed_export_audit: SeedExportAudit::new(Path::new(&self.storage_dir_path)),
+            storage_dir_path: self.storage_dir_path.into(),
+            seed: self.seed,
+            seed_export_token: self.seed_export_token,
+            address_type: self.address_type,
+            reuse_unused_addresses: self.reuse_unused_addresses,
+            onchain_reserve_sat: self.onchain_reserve_sat,
+            _storage_lock: Arc::new(storage_lock),
+            embedder_handle: Some(Arc::new(())),
+        })
+    }
+}
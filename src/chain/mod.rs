@@ -0,0 +1,426 @@
+//! Chain source health checks
+//!
+//! The chain source is selected from the failover list at startup only. LDK
+//! Node cannot change its chain source while running, so later health checks
+//! report an unhealthy source instead of switching away from it.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use ldk_node::bitcoin::blockdata::constants::genesis_block;
+use ldk_node::bitcoin::consensus::encode::deserialize_hex;
+use ldk_node::bitcoin::{BlockHash, FeeRate, Network, Transaction, Txid};
+use serde::Deserialize;
+
+use crate::{BitcoinRpcConfig, ChainSource};
+
+/// Timeout applied to every request made while checking a chain source
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Result of a successful chain source check
+#[derive(Debug, Clone)]
+pub struct ChainSourceInfo {
+    /// Height of the chain tip reported by the source
+    pub tip_height: u64,
+}
+
+/// Subset of the `getblockchaininfo` response we care about
+#[derive(Debug, Deserialize)]
+struct BlockchainInfo {
+    chain: String,
+    blocks: u64,
+    headers: u64,
+}
+
+/// JSON-RPC response envelope returned by bitcoind
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<serde_json::Value>,
+}
+
+/// Verify that the chain source is reachable and serves the configured network
+///
+/// This is meant to be run before the payment processor is exposed so a
+/// misconfigured chain source fails fast instead of surfacing later as failed
+/// invoice creation or wallet syncs.
+pub async fn verify_chain_source(
+    chain_source: &ChainSource,
+    network: Network,
+) -> Result<ChainSourceInfo> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+
+    let info = match chain_source {
+        ChainSource::Esplora(url) => verify_esplora(&client, url, network).await?,
+        ChainSource::BitcoinRpc(rpc_config) => {
+            verify_bitcoind(&client, rpc_config, network).await?
+        }
+    };
+
+    tracing::debug!(
+        "Chain source {} is reachable with tip height {}",
+        chain_source,
+        info.tip_height
+    );
+
+    Ok(info)
+}
+
+async fn verify_esplora(
+    client: &reqwest::Client,
+    url: &str,
+    network: Network,
+) -> Result<ChainSourceInfo> {
+    let base_url = url.trim_end_matches('/');
+
+    let genesis_hash = client
+        .get(format!("{base_url}/block-height/0"))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| {
+            anyhow!("Esplora server at {url} is unreachable: {e}. Check [chain_source] esplora_url")
+        })?
+        .text()
+        .await?;
+
+    let genesis_hash: BlockHash = genesis_hash
+        .trim()
+        .parse()
+        .map_err(|e| anyhow!("Esplora server at {url} returned an invalid block hash: {e}"))?;
+
+    let expected_genesis_hash = genesis_block(network).block_hash();
+
+    if genesis_hash != expected_genesis_hash {
+        bail!(
+            "Esplora server at {url} serves a different chain than the configured network {network} \
+             (genesis {genesis_hash}, expected {expected_genesis_hash}). \
+             Check [network] bitcoin_network and [chain_source] esplora_url"
+        );
+    }
+
+    let tip_height = client
+        .get(format!("{base_url}/blocks/tip/height"))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| anyhow!("Could not get chain tip from Esplora server at {url}: {e}"))?
+        .text()
+        .await?;
+
+    let tip_height: u64 = tip_height
+        .trim()
+        .parse()
+        .map_err(|e| anyhow!("Esplora server at {url} returned an invalid tip height: {e}"))?;
+
+    if tip_height == 0 && network != Network::Regtest {
+        bail!("Esplora server at {url} reports a chain tip at height 0 on {network}");
+    }
+
+    Ok(ChainSourceInfo { tip_height })
+}
+
+async fn verify_bitcoind(
+    client: &reqwest::Client,
+    rpc_config: &BitcoinRpcConfig,
+    network: Network,
+) -> Result<ChainSourceInfo> {
+    let BitcoinRpcConfig { host, port, auth } = rpc_config;
+
+    let (user, password) = auth.credentials()?;
+
+    let url = format!("http://{host}:{port}/");
+
+    let response = client
+        .post(&url)
+        .basic_auth(&user, Some(&password))
+        .json(&serde_json::json!({
+            "jsonrpc": "1.0",
+            "id": "cdk-ldk-node",
+            "method": "getblockchaininfo",
+            "params": [],
+        }))
+        .send()
+        .await
+        .map_err(|e| {
+            anyhow!("bitcoind RPC at {host}:{port} is unreachable: {e}. Check [chain_source.bitcoinrpc]")
+        })?;
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        bail!(
+            "bitcoind RPC at {host}:{port} rejected the configured credentials. \
+             Check [chain_source.bitcoinrpc] user and password or cookie_path"
+        );
+    }
+
+    let response: RpcResponse<BlockchainInfo> = response
+        .error_for_status()
+        .map_err(|e| anyhow!("bitcoind RPC at {host}:{port} returned an error: {e}"))?
+        .json()
+        .await?;
+
+    let info = match (response.result, response.error) {
+        (Some(info), None) => info,
+        (_, Some(err)) => bail!("bitcoind RPC at {host}:{port} returned an error: {err}"),
+        (None, None) => bail!("bitcoind RPC at {host}:{port} returned an empty response"),
+    };
+
+    let expected_chain = network.to_core_arg();
+
+    if info.chain != expected_chain {
+        bail!(
+            "bitcoind at {host}:{port} is running on chain {} but the configured network is {network}. \
+             Check [network] bitcoin_network and [chain_source.bitcoinrpc]",
+            info.chain
+        );
+    }
+
+    if info.blocks < info.headers {
+        tracing::warn!(
+            "bitcoind at {}:{} is still syncing ({} of {} blocks)",
+            host,
+            port,
+            info.blocks,
+            info.headers
+        );
+    }
+
+    Ok(ChainSourceInfo {
+        tip_height: info.blocks,
+    })
+}
+
+/// Select the first reachable chain source from an ordered failover list
+pub async fn select_chain_source(
+    chain_sources: &[ChainSource],
+    network: Network,
+) -> Result<ChainSource> {
+    let mut errors = Vec::new();
+
+    for chain_source in chain_sources {
+        match verify_chain_source(chain_source, network).await {
+            Ok(_) => {
+                if !errors.is_empty() {
+                    tracing::warn!("Failing over to chain source {}", chain_source);
+                }
+                return Ok(chain_source.clone());
+            }
+            Err(err) => {
+                tracing::warn!("Chain source {} is unusable: {}", chain_source, err);
+                errors.push(format!("{chain_source}: {err}"));
+            }
+        }
+    }
+
+    bail!("No usable chain source configured:\n{}", errors.join("\n"))
+}
+
+/// Health of the chain source the node is using
+#[derive(Debug, Clone, Default)]
+pub struct ChainSourceHealth {
+    /// Fallback sources checked alongside the active one
+    pub fallbacks: Vec<String>,
+    /// Tip height reported by the active source at the last check
+    pub tip_height: Option<u64>,
+    /// Best tip height reported by any fallback source at the last check
+    pub fallback_tip_height: Option<u64>,
+    /// Error returned by the active source at the last check
+    pub last_error: Option<String>,
+    /// Unix timestamp of the last check
+    pub last_checked: Option<u64>,
+}
+
+/// Result of a manually triggered wallet sync
+#[derive(Debug, Clone)]
+pub struct WalletSyncReport {
+    /// Best block height known to the node after the sync
+    pub best_block_height: u32,
+    /// Best block hash known to the node after the sync
+    pub best_block_hash: BlockHash,
+    /// Time taken by the onchain and Lightning wallet sync together
+    pub duration: Duration,
+    /// Unix timestamp of the last successful onchain wallet sync
+    pub latest_onchain_wallet_sync_timestamp: Option<u64>,
+    /// Unix timestamp of the last successful Lightning wallet sync
+    pub latest_lightning_wallet_sync_timestamp: Option<u64>,
+}
+
+/// Number of blocks the active source may trail a fallback before it is considered lagging
+pub const MAX_TIP_LAG: u64 = 3;
+
+impl ChainSourceHealth {
+    /// Whether the active source trails the best fallback by more than [`MAX_TIP_LAG`]
+    pub fn is_lagging(&self) -> bool {
+        match (self.tip_height, self.fallback_tip_height) {
+            (Some(tip_height), Some(fallback_tip_height)) => {
+                fallback_tip_height > tip_height + MAX_TIP_LAG
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the active source answered the last check and is not lagging
+    pub fn is_healthy(&self) -> bool {
+        self.last_error.is_none() && !self.is_lagging()
+    }
+}
+
+/// Check the active chain source and compare its tip against the fallbacks
+pub async fn check_chain_source_health(
+    active: &ChainSource,
+    fallbacks: &[ChainSource],
+    network: Network,
+) -> ChainSourceHealth {
+    let mut health = ChainSourceHealth {
+        fallbacks: fallbacks.iter().map(|s| s.to_string()).collect(),
+        last_checked: Some(cdk_common::util::unix_time()),
+        ..Default::default()
+    };
+
+    match verify_chain_source(active, network).await {
+        Ok(info) => health.tip_height = Some(info.tip_height),
+        Err(err) => health.last_error = Some(err.to_string()),
+    }
+
+    for fallback in fallbacks {
+        if let Ok(info) = verify_chain_source(fallback, network).await {
+            health.fallback_tip_height = health.fallback_tip_height.max(Some(info.tip_height));
+        }
+    }
+
+    health
+}
+
+/// Fetch a transaction from the chain source
+///
+/// bitcoind only serves transactions outside its wallet and mempool when
+/// `txindex` is enabled or the hash of the confirming block is given.
+pub async fn fetch_transaction(
+    chain_source: &ChainSource,
+    txid: &Txid,
+    block_hash: Option<&BlockHash>,
+) -> Result<Transaction> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+
+    let tx_hex: String = match chain_source {
+        ChainSource::Esplora(url) => {
+            client
+                .get(format!("{}/tx/{txid}/hex", url.trim_end_matches('/')))
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| anyhow!("Could not fetch transaction {txid} from Esplora: {e}"))?
+                .text()
+                .await?
+        }
+        ChainSource::BitcoinRpc(rpc_config) => {
+            let mut params = vec![
+                serde_json::json!(txid.to_string()),
+                serde_json::json!(false),
+            ];
+            if let Some(block_hash) = block_hash {
+                params.push(serde_json::json!(block_hash.to_string()));
+            }
+            bitcoind_call(&client, rpc_config, "getrawtransaction", params).await?
+        }
+    };
+
+    deserialize_hex(tx_hex.trim()).map_err(|e| anyhow!("Invalid transaction {txid}: {e}"))
+}
+
+/// Estimate the fee rate for confirmation within `target_blocks`
+///
+/// Returns `None` when the chain source has no estimate, as is common on
+/// regtest.
+pub async fn estimate_fee_rate(
+    chain_source: &ChainSource,
+    target_blocks: u16,
+) -> Result<Option<FeeRate>> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+
+    match chain_source {
+        ChainSource::Esplora(url) => {
+            // Map of confirmation target to sat/vB
+            let estimates: std::collections::HashMap<String, f64> = client
+                .get(format!("{}/fee-estimates", url.trim_end_matches('/')))
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| anyhow!("Could not fetch fee estimates from Esplora: {e}"))?
+                .json()
+                .await?;
+
+            let mut estimates: Vec<(u16, f64)> = estimates
+                .into_iter()
+                .filter_map(|(target, rate)| Some((target.parse().ok()?, rate)))
+                .collect();
+            estimates.sort_by_key(|(target, _)| *target);
+
+            // Closest target not faster than requested, else the slowest known
+            let sat_per_vb = estimates
+                .iter()
+                .find(|(target, _)| *target >= target_blocks)
+                .or(estimates.last())
+                .map(|(_, rate)| *rate);
+
+            Ok(sat_per_vb.map(|rate| FeeRate::from_sat_per_kwu((rate * 250.0).ceil() as u64)))
+        }
+        ChainSource::BitcoinRpc(rpc_config) => {
+            #[derive(Deserialize)]
+            struct SmartFee {
+                /// BTC per kvB
+                feerate: Option<f64>,
+            }
+
+            let estimate: SmartFee = bitcoind_call(
+                &client,
+                rpc_config,
+                "estimatesmartfee",
+                vec![serde_json::json!(target_blocks)],
+            )
+            .await?;
+
+            // BTC/kvB to sat/kwu
+            Ok(estimate
+                .feerate
+                .map(|rate| FeeRate::from_sat_per_kwu((rate * 100_000_000.0 / 4.0).ceil() as u64)))
+        }
+    }
+}
+
+async fn bitcoind_call<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    rpc_config: &BitcoinRpcConfig,
+    method: &str,
+    params: Vec<serde_json::Value>,
+) -> Result<T> {
+    let BitcoinRpcConfig { host, port, auth } = rpc_config;
+    let (user, password) = auth.credentials()?;
+
+    let response: RpcResponse<T> = client
+        .post(format!("http://{host}:{port}/"))
+        .basic_auth(&user, Some(&password))
+        .json(&serde_json::json!({
+            "jsonrpc": "1.0",
+            "id": "cdk-ldk-node",
+            "method": method,
+            "params": params,
+        }))
+        .send()
+        .await
+        .map_err(|e| anyhow!("bitcoind RPC at {host}:{port} is unreachable: {e}"))?
+        .json()
+        .await?;
+
+    match (response.result, response.error) {
+        (Some(result), None) => Ok(result),
+        (_, Some(err)) => bail!("bitcoind RPC {method} returned an error: {err}"),
+        (None, None) => bail!("bitcoind RPC {method} returned an empty response"),
+    }
+}
@@ -1,16 +1,70 @@
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-
-use anyhow::{anyhow, Result};
-use config::{Config as ConfigBuilder, File as ConfigFile};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use bip39::Mnemonic;
+use cdk_common::common::FeeReserve;
+use cdk_common::CurrencyUnit;
+use config::{Config as ConfigBuilder, File as ConfigFile, Map, Source, Value, ValueKind};
+use ldk_node::bitcoin::secp256k1::PublicKey;
 use ldk_node::bitcoin::Network;
+use ldk_node::config::ChannelConfig;
 use ldk_node::lightning::ln::msgs::SocketAddress;
 use serde::Deserialize;
 
-use crate::{BitcoinRpcConfig, ChainSource, GossipSource};
+use crate::abandon::DEFAULT_MELT_CANCELLATION_WINDOW;
+use crate::alerts::{AlertCondition, AlertRule, AlertsConfig, DEFAULT_ALERT_INTERVAL};
+use crate::approvals::{ApprovalPolicy, DEFAULT_APPROVAL_EXPIRY};
+use crate::autoclose::{AutoCloseConfig, DEFAULT_CHECK_INTERVAL};
+use crate::deposits::OnchainPaymentsConfig;
+use crate::fees::{
+    FeeStrategy, FeeTier, ProbeFeeStrategy, StaticFeeStrategy, TieredFeeStrategy,
+    DEFAULT_MIN_FEE_RESERVE, DEFAULT_PERCENT_FEE_RESERVE,
+};
+use crate::limits::ManagementLimits;
+use crate::notifications::sinks::{
+    LogSink, MatrixSink, NotificationSink, NwcSink, SmtpSink, SmtpSinkConfig, SmtpTls,
+    TelegramSink, WebhookSink, DEFAULT_SMTP_TIMEOUT, DEFAULT_WEBHOOK_TIMEOUT,
+};
+use crate::notifications::DEFAULT_NOTIFICATION_CAPACITY;
+use crate::offers::pool::OfferRotation;
+use crate::offers::Bolt12Limits;
+use crate::onchain::AddressType;
+use crate::performance::PerformanceProfile;
+use crate::prune::{self, PrunePolicy, DEFAULT_PRUNE_INTERVAL};
+use crate::quotes::DEFAULT_QUOTE_CACHE_TTL;
+use crate::retry::{RetryPolicy, DEFAULT_MAX_RETRY_BACKOFF, DEFAULT_RETRY_BACKOFF};
+use crate::routes::{
+    RouteHealthConfig, DEFAULT_FAILURE_THRESHOLD, DEFAULT_PROBE_AMOUNT_MSAT, DEFAULT_PROBE_INTERVAL,
+};
+use crate::summary::SummaryPeriod;
+use crate::tenants::{Tenant, Tenants};
+use crate::watchdog::{
+    StuckPaymentPolicy, WatchdogConfig, DEFAULT_STUCK_AFTER, DEFAULT_WATCHDOG_INTERVAL,
+};
+use crate::{
+    BitcoinRpcAuth, BitcoinRpcConfig, ChainSource, GossipSource, PathfindingConfig,
+    DEFAULT_INCOMING_EXPIRY_SECS, DEFAULT_PAYMENT_SEND_TIMEOUT,
+};
 
 // Environment variables
+//
+// Every config field can be set with `CDK_LDK_NODE_<SECTION>__<FIELD>`, nested
+// sections joined with `__`, e.g. `CDK_LDK_NODE_CHAIN_SOURCE__BITCOINRPC__HOST`.
+// List fields take comma separated values. The shorter `CDK_*` variables below
+// are kept for existing deployments and are used when the matching
+// `CDK_LDK_NODE_*` variable is not set.
+
+/// Prefix of the environment variables overriding config fields
+pub const ENV_PREFIX: &str = "CDK_LDK_NODE";
+
+/// Separator between nested config keys in environment variable names
+pub const ENV_SEPARATOR: &str = "__";
+
+/// Not used, the payment processor is always backed by LDK Node
 pub const ENV_LN_BACKEND: &str = "CDK_PAYMENT_PROCESSOR_LN_BACKEND";
 pub const ENV_LISTEN_HOST: &str = "CDK_PAYMENT_PROCESSOR_LISTEN_HOST";
 pub const ENV_LISTEN_PORT: &str = "CDK_PAYMENT_PROCESSOR_LISTEN_PORT";
@@ -25,6 +79,7 @@ pub const ENV_BITCOIN_RPC_HOST: &str = "CDK_BITCOIN_RPC_HOST";
 pub const ENV_BITCOIN_RPC_PORT: &str = "CDK_BITCOIN_RPC_PORT";
 pub const ENV_BITCOIN_RPC_USER: &str = "CDK_BITCOIN_RPC_USER";
 pub const ENV_BITCOIN_RPC_PASS: &str = "CDK_BITCOIN_RPC_PASS";
+pub const ENV_BITCOIN_RPC_COOKIE_PATH: &str = "CDK_BITCOIN_RPC_COOKIE_PATH";
 
 // Network configuration
 pub const ENV_BITCOIN_NETWORK: &str = "CDK_BITCOIN_NETWORK";
@@ -40,9 +95,360 @@ pub const ENV_LDK_NODE_PORT: &str = "CDK_LDK_NODE_PORT";
 pub const ENV_GOSSIP_SOURCE_TYPE: &str = "CDK_GOSSIP_SOURCE_TYPE";
 pub const ENV_RGS_URL: &str = "CDK_RGS_URL";
 
+// Seed configuration, from before nested keys were separated with `__`
+pub const ENV_SEED_PASSPHRASE: &str = "CDK_LDK_NODE_SEED_PASSPHRASE";
+
+/// Short environment variables and the config keys they set
+const LEGACY_ENV_VARS: &[(&str, &str)] = &[
+    (ENV_LISTEN_HOST, "payment_processor.listen_host"),
+    (ENV_LISTEN_PORT, "payment_processor.listen_port"),
+    (ENV_PAYMENT_PROCESSOR_TLS_DIR, "payment_processor.tls_dir"),
+    (ENV_GRPC_HOST, "grpc.host"),
+    (ENV_GRPC_PORT, "grpc.port"),
+    (ENV_CHAIN_SOURCE, "chain_source.source_type"),
+    (ENV_ESPLORA_URL, "chain_source.esplora_url"),
+    (ENV_BITCOIN_RPC_HOST, "chain_source.bitcoinrpc.host"),
+    (ENV_BITCOIN_RPC_PORT, "chain_source.bitcoinrpc.port"),
+    (ENV_BITCOIN_RPC_USER, "chain_source.bitcoinrpc.user"),
+    (ENV_BITCOIN_RPC_PASS, "chain_source.bitcoinrpc.password"),
+    (
+        ENV_BITCOIN_RPC_COOKIE_PATH,
+        "chain_source.bitcoinrpc.cookie_path",
+    ),
+    (ENV_BITCOIN_NETWORK, "network.bitcoin_network"),
+    (ENV_STORAGE_DIR_PATH, "storage.dir_path"),
+    (ENV_LDK_NODE_HOST, "ldk_node.host"),
+    (ENV_LDK_NODE_PORT, "ldk_node.port"),
+    (ENV_GOSSIP_SOURCE_TYPE, "gossip_source.source_type"),
+    (ENV_RGS_URL, "gossip_source.rgs_url"),
+    (ENV_SEED_PASSPHRASE, "seed.passphrase"),
+];
+
+/// Config keys holding lists, split on commas when set from the environment
+const ENV_LIST_KEYS: &[&str] = &[
+    "chain_source.fallback_esplora_urls",
+    "route_health.destinations",
+    "auto_close.exclude_peers",
+    "notifications.webhook_urls",
+    "notifications.smtp_recipients",
+    "lsp.trusted_node_ids",
+];
+
+/// Name of the environment variable overriding a config key
+pub fn env_var_name(key: &str) -> String {
+    format!(
+        "{ENV_PREFIX}_{}",
+        key.to_uppercase().replace('.', ENV_SEPARATOR)
+    )
+}
+
+/// Add the environment variable sources to a config builder
+///
+/// `CDK_LDK_NODE_*` variables take precedence over the legacy `CDK_*`
+/// variables, which take precedence over the config file.
+fn add_env_sources(
+    config_builder: config::ConfigBuilder<config::builder::DefaultState>,
+) -> Result<config::ConfigBuilder<config::builder::DefaultState>> {
+    add_env_sources_from(config_builder, std::env::vars().collect())
+}
+
+/// Add the environment variable sources to a config builder, reading `vars`
+/// instead of the process environment
+fn add_env_sources_from(
+    mut config_builder: config::ConfigBuilder<config::builder::DefaultState>,
+    vars: Map<String, String>,
+) -> Result<config::ConfigBuilder<config::builder::DefaultState>> {
+    // Values are kept as strings and converted to the field type when
+    // deserialized. Parsing them up front would rewrite strings that look
+    // like numbers, such as a passphrase of 000123.
+    let list_vars: Vec<String> = ENV_LIST_KEYS.iter().map(|key| env_var_name(key)).collect();
+    let scalar_vars = vars
+        .iter()
+        .filter(|(name, _)| !list_vars.contains(name))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    let environment = config::Environment::with_prefix(ENV_PREFIX)
+        .prefix_separator("_")
+        .separator(ENV_SEPARATOR)
+        .source(Some(scalar_vars));
+    config_builder = config_builder.add_source(environment);
+
+    for key in ENV_LIST_KEYS {
+        let Some(value) = vars.get(&env_var_name(key)) else {
+            continue;
+        };
+
+        let items: Vec<String> = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect();
+        config_builder = config_builder.set_override(*key, items)?;
+    }
+
+    for (name, key) in LEGACY_ENV_VARS {
+        let Some(value) = vars.get(*name) else {
+            continue;
+        };
+
+        if vars.contains_key(&env_var_name(key)) {
+            tracing::warn!("Ignoring {} as {} is also set", name, env_var_name(key));
+            continue;
+        }
+
+        config_builder = config_builder.set_override(*key, value.as_str())?;
+    }
+
+    Ok(config_builder)
+}
+
+/// Secret config value, redacted from `Debug` output
+///
+/// Secrets can be given inline or, preferably, referenced from a file with the
+/// `<field>_file` key or an environment variable named by the `<field>_env` key.
+#[derive(Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The secret value
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
+
+/// Resolve a secret given inline, in a file or in a named environment variable
+///
+/// At most one source may be set. Values read from a file are trimmed, so a
+/// trailing newline is not part of the secret.
+fn resolve_secret(
+    field: &str,
+    value: Option<&Secret>,
+    file: Option<&PathBuf>,
+    env: Option<&String>,
+) -> Result<Option<Secret>> {
+    match (value, file, env) {
+        (None, None, None) => Ok(None),
+        (Some(value), None, None) => Ok(Some(value.clone())),
+        (None, Some(file), None) => {
+            let value = std::fs::read_to_string(file)
+                .with_context(|| format!("Could not read {field} from {}", file.display()))?;
+            Ok(Some(Secret::new(value.trim())))
+        }
+        (None, None, Some(env)) => {
+            let value = std::env::var(env).with_context(|| {
+                format!("Could not read {field} from environment variable {env}")
+            })?;
+            Ok(Some(Secret::new(value)))
+        }
+        _ => Err(anyhow!(
+            "Only one of {field}, {field}_file and {field}_env may be set"
+        )),
+    }
+}
+
+/// Validate a worker thread count, a runtime needs at least one worker
+fn worker_threads(field: &str, threads: usize) -> Result<usize> {
+    if threads == 0 {
+        bail!("{field} must be at least 1");
+    }
+    Ok(threads)
+}
+
 // TOML configuration file
 const CONFIG_FILENAME: &str = "config.toml";
 
+/// Version of the config file format written by this release
+///
+/// Files without a `config_version` key are version 0.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Keys renamed or moved when upgrading to a config version
+///
+/// Each rename moves the value at an old dotted key, a single field or a whole
+/// section, to its new key.
+struct Migration {
+    /// Version the migration upgrades to
+    version: u32,
+    renames: &'static [(&'static str, &'static str)],
+}
+
+/// Migrations applied in order to files older than [`CONFIG_VERSION`]
+const MIGRATIONS: &[Migration] = &[
+    // Version 1 only introduced `config_version`
+    Migration {
+        version: 1,
+        renames: &[],
+    },
+];
+
+/// Top level sections of the config file
+const CONFIG_SECTIONS: &[&str] = &[
+    "config_version",
+    "payment_processor",
+    "chain_source",
+    "network",
+    "grpc",
+    "http",
+    "storage",
+    "ldk_node",
+    "gossip_source",
+    "pathfinding",
+    "channels",
+    "invoices",
+    "backup",
+    "seed",
+    "onchain",
+    "quotes",
+    "route_health",
+    "auto_close",
+    "approvals",
+    "prune",
+    "performance",
+    "notifications",
+    "fees",
+    "onchain_payments",
+    "bolt12",
+    "lsp",
+    "alerts",
+    "summary",
+    "privacy",
+    "watchdog",
+    "retries",
+    "management_limits",
+    "tenants",
+];
+
+/// Config file source migrated to the current config version
+///
+/// Unknown sections are rejected here, unknown keys within a section when the
+/// section is deserialized.
+#[derive(Debug, Clone)]
+struct VersionedFile {
+    values: Map<String, Value>,
+}
+
+impl VersionedFile {
+    fn load(path: &Path) -> Result<Self> {
+        let mut values = ConfigFile::from(path)
+            .collect()
+            .with_context(|| format!("Could not read config file {}", path.display()))?;
+
+        let version = match values.remove("config_version") {
+            Some(version) => version
+                .into_uint()
+                .ok()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| anyhow!("Invalid config_version in {}", path.display()))?,
+            None => 0,
+        };
+
+        if version > CONFIG_VERSION {
+            bail!(
+                "Config file {} is version {}, this release of cdk-ldk-node only supports up to version {}",
+                path.display(),
+                version,
+                CONFIG_VERSION
+            );
+        }
+
+        for migration in MIGRATIONS
+            .iter()
+            .filter(|migration| migration.version > version)
+        {
+            for (old, new) in migration.renames {
+                if let Some(value) = take_key(&mut values, old) {
+                    insert_key(&mut values, new, value).with_context(|| {
+                        format!("Could not migrate {old} to {new} in {}", path.display())
+                    })?;
+                    tracing::warn!("Config key {} in {} is now {}", old, path.display(), new);
+                }
+            }
+        }
+
+        if version < CONFIG_VERSION {
+            tracing::warn!(
+                "Config file {} is version {} and was migrated to version {}, update the file and set config_version = {}",
+                path.display(),
+                version,
+                CONFIG_VERSION,
+                CONFIG_VERSION
+            );
+        }
+
+        if let Some(unknown) = values
+            .keys()
+            .find(|key| !CONFIG_SECTIONS.contains(&key.as_str()))
+        {
+            bail!(
+                "Unknown config section or key {} in {}",
+                unknown,
+                path.display()
+            );
+        }
+
+        values.insert(
+            "config_version".to_string(),
+            Value::new(None, ValueKind::U64(CONFIG_VERSION.into())),
+        );
+
+        Ok(Self { values })
+    }
+}
+
+impl Source for VersionedFile {
+    fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<Map<String, Value>, config::ConfigError> {
+        Ok(self.values.clone())
+    }
+}
+
+/// Remove the value at a dotted key
+fn take_key(values: &mut Map<String, Value>, key: &str) -> Option<Value> {
+    match key.split_once('.') {
+        None => values.remove(key),
+        Some((section, rest)) => match &mut values.get_mut(section)?.kind {
+            ValueKind::Table(table) => take_key(table, rest),
+            _ => None,
+        },
+    }
+}
+
+/// Insert a value at a dotted key, creating sections as needed
+fn insert_key(values: &mut Map<String, Value>, key: &str, value: Value) -> Result<()> {
+    match key.split_once('.') {
+        None if values.contains_key(key) => bail!("{key} is already set"),
+        None => {
+            values.insert(key.to_string(), value);
+            Ok(())
+        }
+        Some((section, rest)) => {
+            let entry = values
+                .entry(section.to_string())
+                .or_insert_with(|| Value::new(None, ValueKind::Table(Map::new())));
+
+            match &mut entry.kind {
+                ValueKind::Table(table) => insert_key(table, rest, value),
+                _ => bail!("{section} is not a section"),
+            }
+        }
+    }
+}
+
 // Get the default config directory path
 fn get_default_config_dir() -> PathBuf {
     let mut home_dir = home::home_dir().unwrap_or_else(|| PathBuf::from("."));
@@ -50,9 +456,35 @@ fn get_default_config_dir() -> PathBuf {
     home_dir
 }
 
+// Get the default bitcoind cookie file path for a network
+fn get_default_bitcoind_cookie_path(network: Network) -> Option<PathBuf> {
+    let mut data_dir = home::home_dir()?;
+
+    if cfg!(target_os = "macos") {
+        data_dir.push("Library/Application Support/Bitcoin");
+    } else {
+        data_dir.push(".bitcoin");
+    }
+
+    match network {
+        Network::Bitcoin => (),
+        Network::Testnet => data_dir.push("testnet3"),
+        Network::Testnet4 => data_dir.push("testnet4"),
+        Network::Signet => data_dir.push("signet"),
+        Network::Regtest => data_dir.push("regtest"),
+        _ => return None,
+    }
+
+    data_dir.push(".cookie");
+    Some(data_dir)
+}
+
 /// Configuration for the CDK LDK Node
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct Config {
+    /// Version of the config file format
+    pub config_version: Option<u32>,
+
     /// Payment processor configuration
     #[serde(default)]
     pub payment_processor: PaymentProcessorConfig,
@@ -69,6 +501,10 @@ pub struct Config {
     #[serde(default)]
     pub grpc: GrpcConfig,
 
+    /// HTTP endpoint configuration
+    #[serde(default)]
+    pub http: HttpConfig,
+
     /// Storage configuration
     #[serde(default)]
     pub storage: StorageConfig,
@@ -80,10 +516,111 @@ pub struct Config {
     /// Gossip source configuration
     #[serde(default)]
     pub gossip_source: GossipSourceConfig,
+
+    /// Pathfinding and probing configuration
+    #[serde(default)]
+    pub pathfinding: PathfindingConfigInternal,
+
+    /// Channel configuration
+    #[serde(default)]
+    pub channels: ChannelsConfig,
+
+    /// Invoice and offer configuration
+    #[serde(default)]
+    pub invoices: InvoicesConfig,
+
+    /// Backup verification configuration
+    #[serde(default)]
+    pub backup: BackupConfig,
+
+    /// Node seed configuration
+    #[serde(default)]
+    pub seed: SeedConfig,
+
+    /// Onchain wallet configuration
+    #[serde(default)]
+    pub onchain: OnchainConfig,
+
+    /// Payment quote configuration
+    #[serde(default)]
+    pub quotes: QuotesConfig,
+
+    /// Route health monitor configuration
+    #[serde(default)]
+    pub route_health: RouteHealthConfigInternal,
+
+    /// Automatic channel close configuration
+    #[serde(default)]
+    pub auto_close: AutoCloseConfigInternal,
+
+    /// Withdrawal approval configuration
+    #[serde(default)]
+    pub approvals: ApprovalsConfig,
+
+    /// Payment record and activity history retention
+    #[serde(default)]
+    pub prune: PruneConfigInternal,
+
+    /// Performance profile configuration
+    #[serde(default)]
+    pub performance: PerformanceConfig,
+
+    /// Notification sink configuration
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+
+    /// Melt quote fee reserve configuration
+    #[serde(default)]
+    pub fees: FeesConfig,
+
+    /// Onchain mint and melt quote configuration
+    #[serde(default)]
+    pub onchain_payments: OnchainPaymentsConfigInternal,
+
+    /// BOLT12 offer payment limits
+    #[serde(default)]
+    pub bolt12: Bolt12Config,
+
+    /// Trusted LSP configuration
+    #[serde(default)]
+    pub lsp: LspConfig,
+
+    /// Alert rules
+    #[serde(default)]
+    pub alerts: AlertsConfigInternal,
+
+    /// Scheduled activity summaries
+    #[serde(default)]
+    pub summary: SummaryConfig,
+
+    /// Disclosure of payment preimages
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+
+    /// Stuck payment watchdog configuration
+    #[serde(default)]
+    pub watchdog: WatchdogConfigInternal,
+
+    /// Retries of failed melt payments
+    #[serde(default)]
+    pub retries: RetriesConfig,
+
+    /// Outgoing payment configuration
+    #[serde(default)]
+    pub payments: PaymentsConfig,
+
+    /// Largest payments made through the management API without an override
+    #[serde(default)]
+    pub management_limits: ManagementLimitsConfig,
+
+    /// Tenants sharing the node
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
 }
 
 /// Payment processor configuration
 #[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct PaymentProcessorConfig {
     /// Host to listen on
     pub listen_host: Option<String>,
@@ -93,10 +630,14 @@ pub struct PaymentProcessorConfig {
 
     /// TLS directory for certificates
     pub tls_dir: Option<String>,
+
+    /// Unit of the mint, `sat` or `msat`
+    pub unit: Option<String>,
 }
 
 /// Chain source configuration
 #[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct ChainSourceConfig {
     /// Type of chain source (esplora or bitcoinrpc)
     pub source_type: Option<String>,
@@ -104,6 +645,15 @@ pub struct ChainSourceConfig {
     /// Esplora URL
     pub esplora_url: Option<String>,
 
+    /// Additional Esplora URLs tried in order when the primary source is unusable
+    pub fallback_esplora_urls: Option<Vec<String>>,
+
+    /// Fall back to the bitcoind RPC when every Esplora server is unusable
+    pub fallback_to_bitcoinrpc: Option<bool>,
+
+    /// Interval in seconds between chain source health checks
+    pub health_check_interval_secs: Option<u64>,
+
     /// Bitcoin RPC configuration
     #[serde(default)]
     pub bitcoinrpc: BitcoinRpcConfigInternal,
@@ -111,6 +661,7 @@ pub struct ChainSourceConfig {
 
 /// Bitcoin RPC Configuration
 #[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct BitcoinRpcConfigInternal {
     /// RPC host
     pub host: Option<String>,
@@ -122,11 +673,21 @@ pub struct BitcoinRpcConfigInternal {
     pub user: Option<String>,
 
     /// RPC password
-    pub password: Option<String>,
+    pub password: Option<Secret>,
+
+    /// File holding the RPC password
+    pub password_file: Option<PathBuf>,
+
+    /// Environment variable holding the RPC password
+    pub password_env: Option<String>,
+
+    /// Path to the bitcoind cookie file, used instead of user/password
+    pub cookie_path: Option<String>,
 }
 
 /// Network configuration
 #[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct NetworkConfig {
     /// Bitcoin network (mainnet, testnet, signet, regtest)
     pub bitcoin_network: Option<String>,
@@ -134,16 +695,38 @@ pub struct NetworkConfig {
 
 /// GRPC API configuration
 #[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct GrpcConfig {
     /// GRPC host
     pub host: Option<String>,
 
     /// GRPC port
     pub port: Option<String>,
+
+    /// TLS directory of the management service, plaintext when unset
+    pub tls_dir: Option<String>,
+}
+
+/// HTTP endpoint configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Serve the HTTP endpoints
+    pub enabled: Option<bool>,
+
+    /// HTTP host
+    pub host: Option<String>,
+
+    /// HTTP port
+    pub port: Option<u16>,
+
+    /// Serve a read only status page on `/`
+    pub status_page: Option<bool>,
 }
 
 /// Storage configuration
 #[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct StorageConfig {
     /// Directory path for storage
     pub dir_path: Option<String>,
@@ -151,6 +734,7 @@ pub struct StorageConfig {
 
 /// LDK Node configuration
 #[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct LdkNodeConfig {
     /// Host to listen on
     pub host: Option<String>,
@@ -161,11 +745,428 @@ pub struct LdkNodeConfig {
 
 /// Gossip source configuration
 #[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct GossipSourceConfig {
+    /// Type of gossip source (p2p or rgs), inferred from rgs_url when unset
+    pub source_type: Option<String>,
+
     /// Rapid Gossip Sync URL (used when source_type = "rgs")
     pub rgs_url: Option<String>,
 }
 
+/// Pathfinding and probing configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PathfindingConfigInternal {
+    /// Multiplier applied to a channel's outbound capacity to limit what may be probed through it
+    pub probing_liquidity_limit_multiplier: Option<u64>,
+
+    /// Max share of a channel's capacity used by a single path, as a power of 1/2
+    pub max_channel_saturation_power_of_half: Option<u8>,
+
+    /// Max number of paths a payment may be split across
+    pub max_path_count: Option<u8>,
+
+    /// Payments below this amount in msats are sent over a single path
+    pub single_path_below_msat: Option<u64>,
+
+    /// Max total CLTV expiry delta accepted for outgoing routes
+    pub max_total_cltv_expiry_delta: Option<u32>,
+}
+
+/// Channel configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelsConfig {
+    /// CLTV expiry delta required for HTLCs forwarded over our channels
+    pub cltv_expiry_delta: Option<u16>,
+}
+
+/// Invoice and offer configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct InvoicesConfig {
+    /// Description template used when a request has no description
+    pub default_description: Option<String>,
+    /// Create the BOLT11 invoices of mint quotes as hold invoices
+    pub hold: Option<bool>,
+    /// Commit to invoice descriptions by their hash instead of including them
+    pub description_hash: Option<bool>,
+    /// Put the descriptions wallets request in mint quote invoices
+    pub request_descriptions: Option<bool>,
+    /// Expiry of mint quote payment requests when the mint does not set one
+    pub default_expiry_secs: Option<u32>,
+    /// Seconds mint quote payment requests expire before their quote
+    pub expiry_margin_secs: Option<u32>,
+}
+
+/// Backup verification configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BackupConfig {
+    /// Seconds between verifications of the storage directory, disabled when unset
+    pub verify_interval_secs: Option<u64>,
+}
+
+/// Node seed configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SeedConfig {
+    /// File holding a BIP39 mnemonic to create the node from on first start
+    pub import_mnemonic_file: Option<String>,
+    /// Optional BIP39 passphrase used with the mnemonic
+    pub passphrase: Option<Secret>,
+    /// File holding the BIP39 passphrase
+    pub passphrase_file: Option<PathBuf>,
+    /// Environment variable holding the BIP39 passphrase
+    pub passphrase_env: Option<String>,
+    /// Allow exporting the seed through the management service
+    pub allow_export: Option<bool>,
+    /// File holding the token seed exports are authorized with
+    pub export_token_file: Option<PathBuf>,
+    /// Environment variable holding the export token, instead of a file
+    pub export_token_env: Option<String>,
+}
+
+/// Onchain wallet configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct OnchainConfig {
+    /// Default address type, `bech32` or `bech32m`
+    pub address_type: Option<String>,
+    /// Reuse the last issued address until it has been used
+    pub reuse_unused_address: Option<bool>,
+    /// Onchain balance in sats that spends must not dip below
+    pub reserve_sat: Option<u64>,
+}
+
+/// Payment quote configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct QuotesConfig {
+    /// Seconds an outgoing payment quote is cached, 0 disables the cache
+    pub cache_ttl_secs: Option<u64>,
+    /// Seconds an outgoing payment must be pending before it can be abandoned
+    pub melt_cancellation_window_secs: Option<u64>,
+}
+
+/// Route health monitor configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RouteHealthConfigInternal {
+    /// Node ids to probe, the monitor is disabled when empty
+    pub destinations: Option<Vec<String>>,
+    /// Amount probed towards each destination in msats
+    pub amount_msat: Option<u64>,
+    /// Seconds between probe rounds
+    pub interval_secs: Option<u64>,
+    /// Consecutive failed probes before a destination is reported degraded
+    pub failure_threshold: Option<u32>,
+}
+
+/// Withdrawal approval configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ApprovalsConfig {
+    /// Withdrawals above this many sats need approval, unset disables approvals
+    pub threshold_sat: Option<u64>,
+    /// File holding the token the approver authenticates with
+    pub approver_token_file: Option<PathBuf>,
+    /// Environment variable holding the approver token, instead of a file
+    pub approver_token_env: Option<String>,
+    /// Seconds a withdrawal waits for approval before it is dropped
+    pub expiry_secs: Option<u64>,
+}
+
+/// Largest payments made through the management API without an override
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ManagementLimitsConfig {
+    /// Largest BOLT11 invoice payment in sats
+    pub max_bolt11_payment_sat: Option<u64>,
+    /// Largest BOLT12 offer payment in sats
+    pub max_bolt12_payment_sat: Option<u64>,
+    /// Largest onchain send in sats
+    pub max_onchain_send_sat: Option<u64>,
+}
+
+/// Tenant sharing the node
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantConfig {
+    pub id: String,
+    /// Port of the tenant's payment processor
+    pub listen_port: Option<u16>,
+    /// File holding the tenant's management token
+    pub token_file: Option<PathBuf>,
+    /// Environment variable holding the tenant's management token, instead of a file
+    pub token_env: Option<String>,
+}
+
+/// Performance profile configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PerformanceConfig {
+    /// Resource usage profile, `default` or `low`
+    pub profile: Option<String>,
+    /// Worker threads of the node runtime
+    pub node_worker_threads: Option<usize>,
+    /// Worker threads of a dedicated management service runtime
+    pub management_worker_threads: Option<usize>,
+    /// Worker threads of a dedicated payment processor runtime
+    pub payment_processor_worker_threads: Option<usize>,
+}
+
+/// Notification sink configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Payment notifications kept for mints that have not read them
+    pub payment_queue_capacity: Option<usize>,
+    /// Write every notification to the node log
+    pub log: Option<bool>,
+    /// URLs every notification is posted to as JSON
+    pub webhook_urls: Option<Vec<String>>,
+    /// Seconds before a webhook request is abandoned
+    pub webhook_timeout_secs: Option<u64>,
+    /// Relay Nostr Wallet Connect notifications are published to
+    pub nwc_relay_url: Option<String>,
+    /// Public key of the connected NWC client
+    pub nwc_client_pubkey: Option<String>,
+    /// Secret key of the NWC wallet service
+    pub nwc_secret_key: Option<Secret>,
+    /// File holding the NWC wallet service secret key
+    pub nwc_secret_key_file: Option<PathBuf>,
+    /// Environment variable holding the NWC wallet service secret key
+    pub nwc_secret_key_env: Option<String>,
+    /// SMTP server alerts and channel closures are emailed through
+    pub smtp_server: Option<String>,
+    /// SMTP port, the default of `smtp_tls` when unset
+    pub smtp_port: Option<u16>,
+    /// `starttls`, `tls` or `none`
+    pub smtp_tls: Option<String>,
+    /// SMTP username
+    pub smtp_username: Option<String>,
+    /// SMTP password
+    pub smtp_password: Option<Secret>,
+    /// File holding the SMTP password
+    pub smtp_password_file: Option<PathBuf>,
+    /// Environment variable holding the SMTP password
+    pub smtp_password_env: Option<String>,
+    /// Sender address
+    pub smtp_from: Option<String>,
+    /// Addresses emails are sent to
+    pub smtp_recipients: Option<Vec<String>>,
+    /// Telegram bot token alerts and summaries are posted with
+    pub telegram_bot_token: Option<Secret>,
+    /// File holding the Telegram bot token
+    pub telegram_bot_token_file: Option<PathBuf>,
+    /// Environment variable holding the Telegram bot token
+    pub telegram_bot_token_env: Option<String>,
+    /// Telegram chat the bot posts to
+    pub telegram_chat_id: Option<String>,
+    /// Matrix homeserver alerts and summaries are posted through
+    pub matrix_homeserver_url: Option<String>,
+    /// Matrix room posted to
+    pub matrix_room_id: Option<String>,
+    /// Access token of the Matrix account posting
+    pub matrix_access_token: Option<Secret>,
+    /// File holding the Matrix access token
+    pub matrix_access_token_file: Option<PathBuf>,
+    /// Environment variable holding the Matrix access token
+    pub matrix_access_token_env: Option<String>,
+}
+
+/// Onchain mint and melt quote configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct OnchainPaymentsConfigInternal {
+    /// Enable onchain quotes, disabled by default
+    pub enabled: Option<bool>,
+    /// Confirmations before a deposit or melt is settled
+    pub min_confirmations: Option<u32>,
+    /// Seconds a deposit address is watched for when the quote sets no expiry
+    pub deposit_expiry_secs: Option<u64>,
+    /// Seconds between checks of the watched deposit addresses
+    pub watch_interval_secs: Option<u64>,
+    /// Confirmation target of melts
+    pub fee_target_blocks: Option<u16>,
+    /// Fee rate of melts when the chain source has no estimate
+    pub fallback_fee_rate_sat_per_vb: Option<u64>,
+    /// Transaction size assumed when quoting the fee of a melt
+    pub send_vbytes: Option<u64>,
+}
+
+/// BOLT12 offer limits
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Bolt12Config {
+    /// Smallest amount of a fixed amount offer
+    pub min_amount_msat: Option<u64>,
+    /// No longer supported, payments are claimed before it could be checked
+    pub max_quantity: Option<u64>,
+    /// No longer supported, payments are claimed before it could be checked
+    pub max_payer_note_len: Option<usize>,
+    /// Variable amount mint quotes handed an offer before it is rotated
+    pub rotate_after_uses: Option<u32>,
+    /// Seconds after which the offer of variable amount mint quotes is rotated
+    pub rotate_after_secs: Option<u64>,
+}
+
+/// Trusted LSP configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LspConfig {
+    /// Node ids zero-conf inbound channels are accepted from
+    pub trusted_node_ids: Option<Vec<String>>,
+}
+
+/// Disclosure of payment preimages
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PrivacyConfig {
+    /// Leave preimages out of notifications, exports and management responses
+    pub redact_preimages: Option<bool>,
+}
+
+/// Retries of failed melt payments
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RetriesConfig {
+    /// Attempts per melt including the first, 1 disables retries
+    pub max_attempts: Option<u32>,
+    /// Seconds before the first retry, doubled on every further retry
+    pub backoff_secs: Option<u64>,
+    /// Longest delay between retries in seconds
+    pub max_backoff_secs: Option<u64>,
+    /// Max number of paths retries may be split across
+    pub max_path_count: Option<u8>,
+    /// Max share of a channel's capacity used by a path of a retry, as a power of 1/2
+    pub max_channel_saturation_power_of_half: Option<u8>,
+}
+
+/// Outgoing payment configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PaymentsConfig {
+    /// Seconds a payment is waited for before it is reported pending
+    pub send_timeout_secs: Option<u64>,
+}
+
+/// Stuck payment watchdog configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct WatchdogConfigInternal {
+    /// Check for stuck payments, disabled by default
+    pub enabled: Option<bool>,
+    /// Seconds an outgoing payment is pending before it is flagged as stuck
+    pub stuck_after_secs: Option<u64>,
+    /// `alert` or `abandon`
+    pub policy: Option<String>,
+    /// Seconds between checks
+    pub interval_secs: Option<u64>,
+}
+
+/// Scheduled activity summary configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SummaryConfig {
+    /// Write and send a summary at the end of every period, disabled by default
+    pub enabled: Option<bool>,
+    /// `daily` or `weekly`
+    pub period: Option<String>,
+}
+
+/// Alert rules
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AlertsConfigInternal {
+    /// Seconds between evaluations of the rules
+    pub interval_secs: Option<u64>,
+    pub rules: Option<Vec<AlertRuleConfig>>,
+}
+
+/// Alert rule, the fields used depend on the condition
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRuleConfig {
+    pub name: String,
+    /// `failed_melts`, `peer_disconnected`, `onchain_balance_below` or
+    /// `lightning_balance_below`
+    pub condition: String,
+    /// Failed melts tolerated within the window
+    pub threshold: Option<usize>,
+    /// Window failed melts are counted in
+    pub window_secs: Option<u64>,
+    /// Peer watched for disconnects
+    pub node_id: Option<String>,
+    /// Seconds a peer may be disconnected
+    pub after_secs: Option<u64>,
+    /// Balance threshold
+    pub sat: Option<u64>,
+}
+
+/// Melt quote fee reserve configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FeesConfig {
+    /// Fee strategy, `static`, `tiered` or `probe`
+    pub strategy: Option<String>,
+    /// Minimum fee reserve in the quote unit
+    pub min_fee_reserve: Option<u64>,
+    /// Fee reserve as a share of the amount, e.g. 0.02 for 2%
+    pub percent_fee_reserve: Option<f32>,
+    /// Reserves for payments below an amount, used by the tiered strategy
+    pub tiers: Option<Vec<FeeTierConfig>>,
+}
+
+/// Fee reserve for payments below an amount
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeTierConfig {
+    /// The tier applies to payments below this amount
+    pub below_msat: u64,
+    /// Minimum fee reserve in the quote unit
+    pub min_fee_reserve: u64,
+    /// Fee reserve as a share of the amount
+    pub percent_fee_reserve: f32,
+}
+
+/// Payment record and activity history retention
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PruneConfigInternal {
+    /// Prune final payment records older than this many days, at least 7,
+    /// unset keeps them
+    pub payment_retention_days: Option<u64>,
+    /// Prune activity records older than this many days, unset keeps them
+    pub activity_retention_days: Option<u64>,
+    /// Directory pruned payment records are exported to, defaults to the storage directory
+    pub export_dir: Option<PathBuf>,
+    /// Prune on a schedule, disabled by default
+    pub scheduled: Option<bool>,
+    /// Seconds between scheduled prunes
+    pub interval_secs: Option<u64>,
+}
+
+/// Automatic channel close configuration
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AutoCloseConfigInternal {
+    /// Enable the policy, disabled by default
+    pub enabled: Option<bool>,
+    /// Close channels whose balance has not changed for this many days
+    pub idle_days: Option<u64>,
+    /// Close channels that have been unusable for this many hours
+    pub unusable_hours: Option<u64>,
+    /// Node ids of peers whose channels are never closed
+    pub exclude_peers: Option<Vec<String>>,
+    /// Only log the channels that would be closed, enabled by default
+    pub dry_run: Option<bool>,
+    /// Seconds between policy checks
+    pub interval_secs: Option<u64>,
+}
+
 impl Config {
     /// Load configuration from config.toml and environment variables
     /// Environment variables take precedence over config file values
@@ -179,10 +1180,11 @@ impl Config {
         // Add the config file as a source if it exists
         if home_config_path.exists() {
             tracing::info!("Loading config from {}", home_config_path.display());
-            config_builder = config_builder.add_source(ConfigFile::from(home_config_path));
+            config_builder = config_builder.add_source(VersionedFile::load(&home_config_path)?);
         } else if current_dir_config_path.exists() {
             tracing::info!("Loading config from {}", current_dir_config_path.display());
-            config_builder = config_builder.add_source(ConfigFile::from(current_dir_config_path));
+            config_builder =
+                config_builder.add_source(VersionedFile::load(current_dir_config_path)?);
         } else {
             tracing::info!("No config file found, using default configuration");
 
@@ -194,11 +1196,7 @@ impl Config {
         }
 
         // Add environment variables as a source
-        config_builder = config_builder.add_source(
-            config::Environment::with_prefix("CDK_LDK_NODE")
-                .separator("_")
-                .try_parsing(true),
-        );
+        config_builder = add_env_sources(config_builder)?;
 
         // Build the config from all sources
         let config = config_builder.build()?;
@@ -219,7 +1217,7 @@ impl Config {
 
         if config_path.exists() {
             tracing::info!("Loading config from {}", config_path.display());
-            config_builder = config_builder.add_source(ConfigFile::from(config_path));
+            config_builder = config_builder.add_source(VersionedFile::load(&config_path)?);
         } else {
             tracing::warn!(
                 "No config file found at {}, using default configuration",
@@ -228,11 +1226,7 @@ impl Config {
         }
 
         // Add environment variables as a source
-        config_builder = config_builder.add_source(
-            config::Environment::with_prefix("CDK_LDK_NODE")
-                .separator("_")
-                .try_parsing(true),
-        );
+        config_builder = add_env_sources(config_builder)?;
 
         // Build the config from all sources
         let config = config_builder.build()?;
@@ -263,6 +1257,9 @@ impl Config {
 
         let default_config = r#"# CDK-LDK-Node Configuration
 
+# Version of the config file format, used to migrate older files
+config_version = 1
+
 [payment_processor]
 # Host to listen on
 listen_host = "127.0.0.1"
@@ -270,6 +1267,10 @@ listen_host = "127.0.0.1"
 # Port to listen on
 listen_port = 8089
 
+# Unit of the mint, "sat" or "msat". Received payments are reported in it, so
+# msat mints keep sub-sat amounts
+# unit = "sat"
+
 [chain_source]
 # Type of chain source (esplora or bitcoinrpc)
 source_type = "esplora"
@@ -277,21 +1278,48 @@ source_type = "esplora"
 # Esplora URL (used when source_type = "esplora")
 esplora_url = "https://mutinynet.com/api"
 
+# Additional Esplora URLs tried in order if the primary source is unusable at startup
+# fallback_esplora_urls = ["https://mempool.space/signet/api"]
+
+# Fall back to the bitcoind RPC below if every Esplora server is unusable at startup
+# fallback_to_bitcoinrpc = false
+
+# Interval in seconds between chain source health checks. An unhealthy source is
+# reported in the node status, the node only moves to a fallback on restart
+# health_check_interval_secs = 60
+
 # Bitcoin RPC configuration (used when source_type = "bitcoinrpc")
 [chain_source.bitcoinrpc]
 host = "127.0.0.1"
 port = 18443
 user = "testuser"
 password = "testpass"
+# Read the password from a file or an environment variable instead of keeping it here
+# password_file = "/path/to/rpc_password"
+# password_env = "BITCOIN_RPC_PASSWORD"
+# Use the bitcoind cookie file instead of user/password
+# If neither is set the default cookie location for the network is used
+# cookie_path = "/home/user/.bitcoin/regtest/.cookie"
 
 [network]
-# Bitcoin network (mainnet, testnet, signet, regtest)
+# Bitcoin network (mainnet, testnet, testnet4, signet, regtest)
 bitcoin_network = "regtest"
 
 [grpc]
 # GRPC API configuration
 host = "127.0.0.1"
 port = "50051"
+# Serve over TLS with server.pem, server.key and the client CA ca.pem from this
+# directory. Renewed certificates are loaded with the RotateTls RPC
+# tls_dir = "/path/to/tls"
+
+[http]
+# HTTP endpoints (Prometheus metrics on /metrics)
+enabled = false
+host = "127.0.0.1"
+port = 8091
+# Read only status page with balances, channels and recent payments on /
+# status_page = false
 
 [ldk_node]
 # LDK Node configuration
@@ -302,7 +1330,9 @@ port = 8090
 # Type of gossip source (p2p or rgs)
 # - p2p: Use peer-to-peer gossip (default)
 # - rgs: Use Rapid Gossip Sync from a URL
-source_type = "p2p"
+# When unset, rgs_url selects Rapid Gossip Sync, and the low performance
+# profile uses the public LDK server on mainnet and testnet
+# source_type = "p2p"
 
 # Rapid Gossip Sync URL (used when source_type = "rgs")
 # Uncomment and set this only if using source_type = "rgs"
@@ -312,6 +1342,310 @@ source_type = "p2p"
 # [gossip_source]
 # source_type = "rgs"
 # rgs_url = "https://mutinynet.com/api/graphql"
+
+[pathfinding]
+# LDK Node does not expose the scorer's penalties and decay or a probing toggle,
+# only the settings below
+# Multiplier applied to a channel's outbound capacity to limit what may be probed through it
+# probing_liquidity_limit_multiplier = 3
+
+# Max share of a channel's capacity used by a single payment path, as a power of 1/2
+# (0 = whole channel, 1 = half, 2 = a quarter)
+# max_channel_saturation_power_of_half = 2
+
+# Max number of paths a payment may be split across (1 disables MPP)
+# max_path_count = 10
+
+# Payments below this amount are sent over a single path, larger ones may use MPP.
+# Payments no single channel can carry are split regardless
+# single_path_below_msat = 100000000
+
+# Max total CLTV expiry delta accepted for outgoing routes
+# max_total_cltv_expiry_delta = 1008
+
+[channels]
+# CLTV expiry delta required for HTLCs forwarded over channels we open
+# Some LSPs require a minimum value here
+# cltv_expiry_delta = 72
+
+[invoices]
+# Description used for invoices and offers created without one
+# {amount} and {unit} are replaced with the requested amount and its unit
+# default_description = "Mint XYZ quote for {amount} {unit}"
+# Create the BOLT11 invoices of mint quotes as hold invoices. Payments are held
+# until settled with settle-hold-invoice and only then reported to the mint.
+# Held payments not settled before their claim deadline are failed back
+# hold = false
+# Put the SHA256 hash of the description in BOLT11 invoices instead of the
+# description itself, for long metadata. Descriptions longer than 639 bytes
+# are always hashed
+# description_hash = false
+# Put the descriptions wallets request in mint quote invoices and offers. When
+# disabled the mint is told descriptions are unsupported and default_description
+# is used for every mint quote
+# request_descriptions = true
+# Expiry of mint quote invoices and offers when the mint does not set one
+# default_expiry_secs = 36000
+# Seconds mint quote invoices and offers expire before the quote, so payments
+# cannot arrive after the mint considers the quote expired
+# expiry_margin_secs = 0
+
+[backup]
+# Periodically restore the storage directory into a temporary directory and
+# check the seed and channel state load, disabled when unset
+# verify_interval_secs = 86400
+
+[seed]
+# New nodes are created from a BIP39 mnemonic stored in the storage directory
+# To move a node, import its mnemonic into an empty storage directory on first start
+# import_mnemonic_file = "/path/to/mnemonic.txt"
+# Optional BIP39 passphrase, must be the same on every start
+# Prefer setting it with CDK_LDK_NODE_SEED__PASSPHRASE
+# passphrase = ""
+# Or read it from a file or a named environment variable
+# passphrase_file = "/path/to/passphrase"
+# passphrase_env = "SEED_PASSPHRASE"
+# Allow the export-seed management command, disabled by default. Exports also
+# need the export token, and every attempt is recorded in seed_exports.jsonl
+# allow_export = false
+# File holding the export token, keep it offline until an export is needed
+# export_token_file = "/path/to/export_token"
+# Or read it from a named environment variable
+# export_token_env = "SEED_EXPORT_TOKEN"
+
+[onchain]
+# Default address type for new addresses: "bech32" (P2WPKH) or "bech32m" (P2TR)
+# LDK Node's onchain wallet only supports bech32 at the moment
+# address_type = "bech32"
+# Hand out the last issued address again until the wallet sees a transaction,
+# avoids running past the gap limit of external watch-only wallets
+# reuse_unused_address = false
+# Onchain balance in sats kept back for future force close and anchor fees,
+# onchain sends and channel opens are refused if they would dip below it
+# reserve_sat = 0
+
+[quotes]
+# Seconds an outgoing payment quote is served from the cache, 0 disables it
+# Cached quotes are dropped whenever a node event may have moved channel balances
+# cache_ttl_secs = 5
+# Seconds an outgoing payment must be pending before AbandonPayment reports its
+# melt failed, keep above the longest HTLC timeout (1008 blocks by default)
+# melt_cancellation_window_secs = 691200
+
+[route_health]
+# Periodically probe important destinations, such as LSPs or frequent melt
+# targets, and export the results as route_* metrics
+# destinations = ["02abc...", "03def..."]
+# amount_msat = 100000000
+# interval_secs = 600
+# Failed probes in a row before a destination is logged as degraded
+# failure_threshold = 3
+
+[auto_close]
+# Cooperatively close channels that are idle or unusable for too long
+# enabled = false
+# Days without a balance change before a channel is closed, unset keeps idle channels
+# idle_days = 60
+# Hours a channel is unusable, e.g. the peer is offline, before it is closed
+# unusable_hours = 168
+# Peers whose channels are never closed
+# exclude_peers = ["02abc..."]
+# Log the channels that would be closed without closing them
+# dry_run = true
+# interval_secs = 3600
+
+[approvals]
+# Withdrawals through the management API above this amount are held until a
+# second operator approves them with the approver token
+# threshold_sat = 1000000
+# File holding the approver token, keep it away from the operator issuing withdrawals
+# approver_token_file = "/path/to/approver_token"
+# Or read it from a named environment variable
+# approver_token_env = "APPROVER_TOKEN"
+# Seconds a withdrawal waits for approval before it is dropped
+# expiry_secs = 86400
+
+[prune]
+# Final payment records older than this are appended to pruned_payments.jsonl
+# in export_dir and removed from the node. Keep it well above the mint's quote
+# expiry, the mint cannot look up pruned payments. At least 7 days
+# payment_retention_days = 180
+# Channel activity records older than this are dropped
+# activity_retention_days = 365
+# export_dir = "/path/to/exports"
+# Prune on a schedule instead of only with the prune management command
+# scheduled = false
+# interval_secs = 86400
+
+[performance]
+# "default", or "low" for Raspberry Pi class hardware: fewer worker threads,
+# longer wallet sync and monitoring intervals, and Rapid Gossip Sync instead of
+# P2P gossip on mainnet and testnet. Explicit settings above take precedence
+# profile = "default"
+# Worker threads of the runtime the node, its event handling and background
+# tasks run on, one per CPU core by default
+# node_worker_threads = 4
+# Run the management service and the payment processors on dedicated runtimes
+# with this many worker threads, so heavy RPC load cannot starve payment event
+# handling. Unset to share the node runtime
+# management_worker_threads = 1
+# payment_processor_worker_threads = 2
+
+[notifications]
+# Payment and channel notifications, in addition to the mint's payment stream.
+# Delivery is best effort, failed notifications are logged and dropped
+# log = false
+# Payment notifications kept for mints that have not read them. A mint
+# lagging further behind gets the payments it missed re-read from the node
+# payment_queue_capacity = 10000
+# webhook_urls = ["https://example.com/cdk-ldk-node"]
+# webhook_timeout_secs = 10
+# Nostr Wallet Connect (NIP-47) payment_received and payment_sent notifications
+# nwc_relay_url = "wss://relay.example.com"
+# nwc_client_pubkey = "<client public key>"
+# nwc_secret_key_file = "/run/secrets/nwc_secret_key"
+# Email alerts and channel closures
+# smtp_server = "smtp.example.com"
+# smtp_tls = "starttls"
+# smtp_username = "mint@example.com"
+# smtp_password_file = "/run/secrets/smtp_password"
+# smtp_from = "Mint <mint@example.com>"
+# smtp_recipients = ["operator@example.com"]
+# Alerts and summaries in Telegram and Matrix chats
+# telegram_bot_token_file = "/run/secrets/telegram_bot_token"
+# telegram_chat_id = "-1001234567890"
+# matrix_homeserver_url = "https://matrix.example.com"
+# matrix_room_id = "!abcdef:example.com"
+# matrix_access_token_file = "/run/secrets/matrix_access_token"
+
+[fees]
+# Fee reserve of melt quotes, the most a melt may spend on routing.
+# "static" reserves the larger of percent_fee_reserve of the amount and
+# min_fee_reserve. "tiered" picks the reserve from the tiers below, falling back
+# to the static reserve above every tier. "probe" probes BOLT11 destinations
+# and rejects quotes that cannot be routed, then reserves like "tiered" when
+# tiers are set and like "static" otherwise
+# strategy = "static"
+# min_fee_reserve = 2
+# percent_fee_reserve = 0.02
+# [[fees.tiers]]
+# below_msat = 10000000
+# min_fee_reserve = 2
+# percent_fee_reserve = 0.05
+
+[onchain_payments]
+# Onchain mint and melt quotes. Each mint quote gets a fresh deposit address,
+# payments to it are credited to the mint once they have min_confirmations.
+# Deposit addresses are checked every watch_interval_secs. Melts are sent at
+# the chain source's fee estimate for fee_target_blocks, quoting the fee for a
+# transaction of send_vbytes
+# enabled = false
+# min_confirmations = 3
+# deposit_expiry_secs = 86400
+# watch_interval_secs = 30
+# fee_target_blocks = 6
+# fallback_fee_rate_sat_per_vb = 2
+# send_vbytes = 210
+
+[bolt12]
+# Offers with a fixed amount below min_amount_msat are not created. LDK Node
+# answers invoice requests and claims the payments itself, so the amount paid
+# to a variable amount offer and the payer note cannot be limited
+# min_amount_msat = 1000
+# Share one offer between variable amount mint quotes, replaced after
+# rotate_after_uses quotes or rotate_after_secs seconds. Without either every
+# quote gets its own offer
+# rotate_after_uses = 100
+# rotate_after_secs = 86400
+
+[lsp]
+# Node ids of the trusted LSPs, the only peers zero-conf inbound channels are
+# accepted from. Zero-conf channels from other peers are closed
+# trusted_node_ids = ["02abc..."]
+
+[alerts]
+# Alert rules, raised and resolved alerts are sent to the notification sinks,
+# including webhooks and Nostr Wallet Connect direct messages
+# interval_secs = 60
+#
+# Conditions:
+#   failed_melts: more than threshold melts failed within window_secs
+#   peer_disconnected: peer node_id disconnected for more than after_secs
+#   onchain_balance_below / lightning_balance_below: total balance below sat
+#
+# [[alerts.rules]]
+# name = "melt failures"
+# condition = "failed_melts"
+# threshold = 5
+# window_secs = 600
+#
+# [[alerts.rules]]
+# name = "lsp offline"
+# condition = "peer_disconnected"
+# node_id = "02abc..."
+# after_secs = 300
+#
+# [[alerts.rules]]
+# name = "low onchain balance"
+# condition = "onchain_balance_below"
+# sat = 100000
+
+[summary]
+# Summary of payment volume, fees paid and earned, channels opened and closed,
+# payment failure rate and balances. Summaries are appended to summaries.jsonl
+# in the storage directory and sent to the notification sinks
+# enabled = false
+# period = "daily"
+
+[privacy]
+# Leave payment preimages out of notifications (log, webhooks, NWC, chats),
+# pruned payment exports and the payment responses of the management service.
+# A preimage proves a payment was made
+# redact_preimages = false
+
+[watchdog]
+# Flag outgoing payments pending for longer than stuck_after_secs with a
+# stuck_payment alert. With policy = "abandon" stuck payments are also
+# abandoned once pending for the melt cancellation window of [quotes]
+# enabled = false
+# stuck_after_secs = 3600
+# policy = "alert"
+# interval_secs = 300
+
+[retries]
+# Send failed BOLT11 melt payments again when no route was found or LDK ran out
+# of retries, while the invoice has not expired. Attempts include the first,
+# 1 disables retries. The backoff doubles on every retry up to max_backoff_secs
+# max_attempts = 1
+# backoff_secs = 1
+# max_backoff_secs = 10
+# Looser route constraints for retries, the [pathfinding] values when unset
+# max_path_count = 10
+# max_channel_saturation_power_of_half = 1
+
+[payments]
+# Seconds melts and management payments wait for a payment to complete before
+# reporting it pending, the mint then checks on it later. Routing on mainnet
+# can take longer than the default
+# send_timeout_secs = 10
+
+[management_limits]
+# Pay and send requests through the management API above these amounts are
+# refused, a guard against mistyped amounts separate from the mint's limits.
+# A request can override them with --override-limit-token-file, which needs
+# the approver token of [approvals]. Unset leaves the amount unbounded
+# max_bolt11_payment_sat = 1000000
+# max_bolt12_payment_sat = 1000000
+# max_onchain_send_sat = 5000000
+
+# Tenants sharing the node, one [[tenants]] table each. Invoices and offers
+# created for a tenant are tagged with its id. Its mint connects to its own
+# payment processor port and only receives its own payments, and its
+# management token only creates and lists its own invoices and offers
+# [[tenants]]
+# id = "mint-a"
+# listen_port = 8189
+# token_file = "/run/secrets/mint_a_token"
 "#;
 
         std::fs::write(config_path, default_config)?;
@@ -332,57 +1666,180 @@ source_type = "p2p"
         self.payment_processor.listen_port.unwrap_or(8089)
     }
 
+    /// Get the unit of the mint, `sat` unless configured
+    pub fn unit(&self) -> Result<CurrencyUnit> {
+        match self.payment_processor.unit.as_deref() {
+            None => Ok(CurrencyUnit::Sat),
+            Some(unit) => match CurrencyUnit::from_str(unit)? {
+                unit @ (CurrencyUnit::Sat | CurrencyUnit::Msat) => Ok(unit),
+                unit => bail!("Unsupported unit {unit}, expected sat or msat"),
+            },
+        }
+    }
+
     /// Get payment processor TLS directory
     pub fn payment_processor_tls_dir(&self) -> Option<PathBuf> {
         self.payment_processor.tls_dir.clone().map(PathBuf::from)
     }
 
     /// Get chain source
-    pub fn chain_source(&self) -> ChainSource {
+    pub fn chain_source(&self) -> Result<ChainSource> {
         let source_type = self
             .chain_source
             .source_type
             .clone()
             .unwrap_or_else(|| "esplora".to_string());
 
-        if source_type.to_lowercase() == "bitcoinrpc" {
-            let host = self
-                .chain_source
-                .bitcoinrpc
-                .host
-                .clone()
-                .unwrap_or_else(|| "127.0.0.1".to_string());
-            let port = self.chain_source.bitcoinrpc.port.unwrap_or(18443);
-            let user = self
-                .chain_source
-                .bitcoinrpc
-                .user
-                .clone()
-                .unwrap_or_else(|| "testuser".to_string());
-            let password = self
-                .chain_source
-                .bitcoinrpc
-                .password
-                .clone()
-                .unwrap_or_else(|| "testpass".to_string());
+        match source_type.to_lowercase().as_str() {
+            "bitcoinrpc" => Ok(ChainSource::BitcoinRpc(self.bitcoin_rpc_config()?)),
+            "esplora" => {
+                let esplora_url = self
+                    .chain_source
+                    .esplora_url
+                    .clone()
+                    .unwrap_or_else(|| "https://mutinynet.com/api".to_string());
+
+                Ok(ChainSource::Esplora(esplora_url))
+            }
+            // Refuse rather than silently fall back to a trusted Esplora server
+            "cbf" | "bip157" | "bitcoinp2p" => Err(anyhow!(
+                "Compact block filter (BIP157/158) syncing is not supported by the LDK Node version \
+                 used by this crate. Use source_type = \"bitcoinrpc\" with your own bitcoind to \
+                 avoid trusting an Esplora server"
+            )),
+            other => Err(anyhow!(
+                "Unknown chain source type {other}, expected esplora or bitcoinrpc"
+            )),
+        }
+    }
+
+    /// Get the ordered chain source failover list, starting with the primary source
+    pub fn chain_sources(&self) -> Result<Vec<ChainSource>> {
+        let primary = self.chain_source()?;
+
+        let mut chain_sources: Vec<ChainSource> = self
+            .chain_source
+            .fallback_esplora_urls
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .map(ChainSource::Esplora)
+            .collect();
+
+        if self.chain_source.fallback_to_bitcoinrpc.unwrap_or(false)
+            && !matches!(primary, ChainSource::BitcoinRpc(_))
+        {
+            chain_sources.push(ChainSource::BitcoinRpc(self.bitcoin_rpc_config()?));
+        }
+
+        chain_sources.insert(0, primary);
+        Ok(chain_sources)
+    }
 
-            ChainSource::BitcoinRpc(BitcoinRpcConfig {
-                host,
-                port,
-                user,
-                password,
+    /// Get the interval between chain source health checks
+    pub fn chain_source_health_check_interval(&self) -> Duration {
+        self.chain_source
+            .health_check_interval_secs
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| {
+                self.performance_profile()
+                    .unwrap_or_default()
+                    .chain_source_health_check_interval()
             })
-        } else {
-            let esplora_url = self
-                .chain_source
-                .esplora_url
-                .clone()
-                .unwrap_or_else(|| "https://mutinynet.com/api".to_string());
+    }
+
+    /// Get the performance profile
+    pub fn performance_profile(&self) -> Result<PerformanceProfile> {
+        self.performance
+            .profile
+            .as_deref()
+            .map(PerformanceProfile::from_str)
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
 
-            ChainSource::Esplora(esplora_url)
+    /// Get the worker threads of the node runtime, `None` for one per CPU core
+    pub fn node_worker_threads(&self) -> Result<Option<usize>> {
+        match self.performance.node_worker_threads {
+            Some(threads) => worker_threads("node_worker_threads", threads).map(Some),
+            None => Ok(self.performance_profile()?.worker_threads()),
         }
     }
 
+    /// Get the worker threads of the management service runtime, `None` to
+    /// share the node runtime
+    pub fn management_worker_threads(&self) -> Result<Option<usize>> {
+        self.performance
+            .management_worker_threads
+            .map(|threads| worker_threads("management_worker_threads", threads))
+            .transpose()
+    }
+
+    /// Get the worker threads of the payment processor runtime, `None` to
+    /// share the node runtime
+    pub fn payment_processor_worker_threads(&self) -> Result<Option<usize>> {
+        self.performance
+            .payment_processor_worker_threads
+            .map(|threads| worker_threads("payment_processor_worker_threads", threads))
+            .transpose()
+    }
+
+    /// Get bitcoind RPC configuration
+    pub fn bitcoin_rpc_config(&self) -> Result<BitcoinRpcConfig> {
+        let host = self
+            .chain_source
+            .bitcoinrpc
+            .host
+            .clone()
+            .unwrap_or_else(|| "127.0.0.1".to_string());
+        let port = self.chain_source.bitcoinrpc.port.unwrap_or(18443);
+
+        Ok(BitcoinRpcConfig {
+            host,
+            port,
+            auth: self.bitcoin_rpc_auth()?,
+        })
+    }
+
+    /// Get bitcoind RPC authentication
+    ///
+    /// An explicit cookie path takes precedence over user/password. If neither is
+    /// configured the default bitcoind cookie location for the network is used
+    /// when it exists, otherwise the default test credentials.
+    pub fn bitcoin_rpc_auth(&self) -> Result<BitcoinRpcAuth> {
+        let rpc_config = &self.chain_source.bitcoinrpc;
+
+        if let Some(cookie_path) = &rpc_config.cookie_path {
+            return Ok(BitcoinRpcAuth::CookieFile(PathBuf::from(cookie_path)));
+        }
+
+        let password = resolve_secret(
+            "password",
+            rpc_config.password.as_ref(),
+            rpc_config.password_file.as_ref(),
+            rpc_config.password_env.as_ref(),
+        )?;
+
+        if rpc_config.user.is_none() && password.is_none() {
+            if let Some(cookie_path) = get_default_bitcoind_cookie_path(self.bitcoin_network())
+                .filter(|path| path.exists())
+            {
+                tracing::info!("Using bitcoind cookie file at {}", cookie_path.display());
+                return Ok(BitcoinRpcAuth::CookieFile(cookie_path));
+            }
+        }
+
+        Ok(BitcoinRpcAuth::UserPass {
+            user: rpc_config
+                .user
+                .clone()
+                .unwrap_or_else(|| "testuser".to_string()),
+            password: password
+                .map(|password| password.expose().to_string())
+                .unwrap_or_else(|| "testpass".to_string()),
+        })
+    }
+
     /// Get Bitcoin network
     pub fn bitcoin_network(&self) -> Network {
         match self
@@ -395,6 +1852,7 @@ source_type = "p2p"
         {
             "mainnet" | "bitcoin" => Network::Bitcoin,
             "testnet" => Network::Testnet,
+            "testnet4" => Network::Testnet4,
             "signet" => Network::Signet,
             _ => Network::Regtest,
         }
@@ -426,12 +1884,816 @@ source_type = "p2p"
     }
 
     /// Get gossip source (RapidGossipSync if URL is provided, otherwise P2P)
-    pub fn gossip_source(&self) -> GossipSource {
-        if let Some(rgs_url) = self.gossip_source.rgs_url.clone() {
-            GossipSource::RapidGossipSync(rgs_url)
-        } else {
-            GossipSource::P2P
+    pub fn gossip_source(&self) -> Result<GossipSource> {
+        let rgs_url = self.gossip_source.rgs_url.clone();
+
+        match self
+            .gossip_source
+            .source_type
+            .as_deref()
+            .map(str::to_lowercase)
+        {
+            None => {
+                let rgs_url = rgs_url.or_else(|| {
+                    self.performance_profile()
+                        .unwrap_or_default()
+                        .default_rgs_url(self.bitcoin_network())
+                        .map(str::to_string)
+                });
+
+                Ok(rgs_url
+                    .map(GossipSource::RapidGossipSync)
+                    .unwrap_or(GossipSource::P2P))
+            }
+            Some(source_type) if source_type == "p2p" => Ok(GossipSource::P2P),
+            Some(source_type) if source_type == "rgs" => rgs_url
+                .map(GossipSource::RapidGossipSync)
+                .ok_or_else(|| anyhow!("Gossip source type rgs needs rgs_url")),
+            Some(other) => Err(anyhow!(
+                "Unknown gossip source type {other}, expected p2p or rgs"
+            )),
+        }
+    }
+
+    /// Get pathfinding configuration
+    pub fn pathfinding(&self) -> PathfindingConfig {
+        PathfindingConfig {
+            probing_liquidity_limit_multiplier: self.pathfinding.probing_liquidity_limit_multiplier,
+            max_channel_saturation_power_of_half: self
+                .pathfinding
+                .max_channel_saturation_power_of_half,
+            max_path_count: self.pathfinding.max_path_count,
+            single_path_below_msat: self.pathfinding.single_path_below_msat,
+            max_total_cltv_expiry_delta: self.pathfinding.max_total_cltv_expiry_delta,
+        }
+    }
+
+    /// Get the channel config for newly opened channels
+    pub fn channel_config(&self) -> ChannelConfig {
+        let mut channel_config = ChannelConfig::default();
+
+        if let Some(cltv_expiry_delta) = self.channels.cltv_expiry_delta {
+            channel_config.cltv_expiry_delta = cltv_expiry_delta;
+        }
+
+        channel_config
+    }
+
+    /// Get the default invoice description template
+    pub fn default_invoice_description(&self) -> Option<String> {
+        self.invoices
+            .default_description
+            .clone()
+            .filter(|d| !d.is_empty())
+    }
+
+    /// Whether mint quote invoices commit to their descriptions by hash
+    pub fn hash_invoice_descriptions(&self) -> bool {
+        self.invoices.description_hash.unwrap_or(false)
+    }
+
+    /// Whether mint quote invoices use the descriptions wallets request
+    pub fn request_invoice_descriptions(&self) -> bool {
+        self.invoices.request_descriptions.unwrap_or(true)
+    }
+
+    /// Expiry of mint quote payment requests when the mint does not set one
+    pub fn default_incoming_expiry(&self) -> u32 {
+        self.invoices
+            .default_expiry_secs
+            .unwrap_or(DEFAULT_INCOMING_EXPIRY_SECS)
+    }
+
+    /// Seconds mint quote payment requests expire before their quote
+    pub fn incoming_expiry_margin(&self) -> u32 {
+        self.invoices.expiry_margin_secs.unwrap_or(0)
+    }
+
+    /// Whether the BOLT11 invoices of mint quotes are hold invoices
+    pub fn hold_mint_invoices(&self) -> bool {
+        self.invoices.hold.unwrap_or(false)
+    }
+
+    /// Get the interval between backup verifications, `None` when disabled
+    pub fn backup_verify_interval(&self) -> Option<Duration> {
+        self.backup
+            .verify_interval_secs
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+
+    /// Get the mnemonic to import on first start, read from the configured file
+    pub fn seed_import_mnemonic(&self) -> Result<Option<Mnemonic>> {
+        let Some(path) = &self.seed.import_mnemonic_file else {
+            return Ok(None);
+        };
+
+        let words = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read mnemonic file {path}"))?;
+
+        Ok(Some(crate::seed::parse_mnemonic(&words)?))
+    }
+
+    /// Get the BIP39 passphrase
+    pub fn seed_passphrase(&self) -> Result<Option<String>> {
+        let passphrase = resolve_secret(
+            "passphrase",
+            self.seed.passphrase.as_ref(),
+            self.seed.passphrase_file.as_ref(),
+            self.seed.passphrase_env.as_ref(),
+        )?;
+
+        Ok(passphrase
+            .map(|passphrase| passphrase.expose().to_string())
+            .filter(|p| !p.is_empty()))
+    }
+
+    /// Token authorizing seed exports, `None` when export is disabled
+    pub fn seed_export_token(&self) -> Result<Option<String>> {
+        if !self.seed.allow_export.unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let token = resolve_secret(
+            "export_token",
+            None,
+            self.seed.export_token_file.as_ref(),
+            self.seed.export_token_env.as_ref(),
+        )?
+        .ok_or_else(|| {
+            anyhow!(
+                "Seed export needs export_token_file or export_token_env when allow_export is set"
+            )
+        })?
+        .expose()
+        .trim()
+        .to_string();
+
+        if token.is_empty() {
+            bail!("Seed export token is empty");
         }
+
+        Ok(Some(token))
+    }
+
+    /// Get the default onchain address type
+    pub fn onchain_address_type(&self) -> Result<AddressType> {
+        self.onchain
+            .address_type
+            .as_deref()
+            .map(AddressType::from_str)
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Whether the last issued address is reused until it has been used
+    pub fn reuse_unused_address(&self) -> bool {
+        self.onchain.reuse_unused_address.unwrap_or(false)
+    }
+
+    /// Get the onchain balance in sats kept in reserve
+    pub fn onchain_reserve_sat(&self) -> u64 {
+        self.onchain.reserve_sat.unwrap_or(0)
+    }
+
+    /// Get how long outgoing payment quotes are cached
+    pub fn quote_cache_ttl(&self) -> Duration {
+        self.quotes
+            .cache_ttl_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_QUOTE_CACHE_TTL)
+    }
+
+    /// Get how long an outgoing payment must be pending before it can be abandoned
+    pub fn melt_cancellation_window(&self) -> Duration {
+        self.quotes
+            .melt_cancellation_window_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_MELT_CANCELLATION_WINDOW)
+    }
+
+    /// Get how long a payment is waited for before it is reported pending
+    pub fn payment_send_timeout(&self) -> Result<Duration> {
+        match self.payments.send_timeout_secs {
+            None => Ok(DEFAULT_PAYMENT_SEND_TIMEOUT),
+            Some(0) => bail!("payments.send_timeout_secs must be at least 1"),
+            Some(secs) => Ok(Duration::from_secs(secs)),
+        }
+    }
+
+    /// Get the route health monitor settings, `None` when no destinations are configured
+    pub fn route_health(&self) -> Result<Option<RouteHealthConfig>> {
+        let destinations = self
+            .route_health
+            .destinations
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .map(|node_id| {
+                PublicKey::from_str(node_id)
+                    .map_err(|e| anyhow!("Invalid route health destination {node_id}: {e}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if destinations.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(RouteHealthConfig {
+            destinations,
+            amount_msat: self
+                .route_health
+                .amount_msat
+                .unwrap_or(DEFAULT_PROBE_AMOUNT_MSAT),
+            interval: self
+                .route_health
+                .interval_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_PROBE_INTERVAL),
+            failure_threshold: self
+                .route_health
+                .failure_threshold
+                .unwrap_or(DEFAULT_FAILURE_THRESHOLD)
+                .max(1),
+        }))
+    }
+
+    /// Get the automatic channel close policy, `None` when disabled
+    pub fn auto_close(&self) -> Result<Option<AutoCloseConfig>> {
+        if !self.auto_close.enabled.unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let exclude_peers = self
+            .auto_close
+            .exclude_peers
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .map(|node_id| {
+                PublicKey::from_str(node_id)
+                    .map_err(|e| anyhow!("Invalid auto close excluded peer {node_id}: {e}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let idle_after = self
+            .auto_close
+            .idle_days
+            .map(|days| Duration::from_secs(days * 24 * 60 * 60));
+        let unusable_after = self
+            .auto_close
+            .unusable_hours
+            .map(|hours| Duration::from_secs(hours * 60 * 60));
+
+        if idle_after.is_none() && unusable_after.is_none() {
+            return Err(anyhow!(
+                "Auto close is enabled but neither idle_days nor unusable_hours is set"
+            ));
+        }
+
+        Ok(Some(AutoCloseConfig {
+            idle_after,
+            unusable_after,
+            exclude_peers,
+            dry_run: self.auto_close.dry_run.unwrap_or(true),
+            interval: self
+                .auto_close
+                .interval_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_CHECK_INTERVAL),
+        }))
+    }
+
+    /// Get the withdrawal approval policy, `None` when no threshold is set
+    pub fn approval_policy(&self) -> Result<Option<ApprovalPolicy>> {
+        let Some(threshold_sat) = self.approvals.threshold_sat else {
+            return Ok(None);
+        };
+
+        let approver_token = resolve_secret(
+            "approver_token",
+            None,
+            self.approvals.approver_token_file.as_ref(),
+            self.approvals.approver_token_env.as_ref(),
+        )?
+        .ok_or_else(|| {
+            anyhow!("Approvals need approver_token_file or approver_token_env when threshold_sat is set")
+        })?
+        .expose()
+        .trim()
+        .to_string();
+
+        if approver_token.is_empty() {
+            return Err(anyhow!("Approver token is empty"));
+        }
+
+        Ok(Some(ApprovalPolicy {
+            threshold_sat,
+            approver_token,
+            expiry: self
+                .approvals
+                .expiry_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_APPROVAL_EXPIRY),
+        }))
+    }
+
+    /// Get the largest payments made through the management API without an override
+    pub fn management_limits(&self) -> ManagementLimits {
+        ManagementLimits {
+            max_bolt11_payment_sat: self.management_limits.max_bolt11_payment_sat,
+            max_bolt12_payment_sat: self.management_limits.max_bolt12_payment_sat,
+            max_onchain_send_sat: self.management_limits.max_onchain_send_sat,
+        }
+    }
+
+    /// Get the tenants sharing the node
+    pub fn tenants(&self) -> Result<Tenants> {
+        let listen_port = self.payment_processor_listen_port();
+
+        let tenants = self
+            .tenants
+            .iter()
+            .map(|tenant| {
+                if tenant.listen_port == Some(listen_port) {
+                    bail!(
+                        "Tenant {} listens on the payment processor port {}",
+                        tenant.id,
+                        listen_port
+                    );
+                }
+
+                let token = resolve_secret(
+                    "token",
+                    None,
+                    tenant.token_file.as_ref(),
+                    tenant.token_env.as_ref(),
+                )?
+                .map(|token| token.expose().trim().to_string());
+
+                Tenant::new(tenant.id.clone(), tenant.listen_port, token)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Tenants::new(tenants)
+    }
+
+    /// Get the payment record and activity history retention, `None` when neither is set
+    pub fn prune_policy(&self) -> Result<Option<PrunePolicy>> {
+        let payment_retention = self
+            .prune
+            .payment_retention_days
+            .map(prune::payment_retention)
+            .transpose()
+            .context("Invalid prune.payment_retention_days")?;
+        let activity_retention = self
+            .prune
+            .activity_retention_days
+            .map(prune::activity_retention)
+            .transpose()
+            .context("Invalid prune.activity_retention_days")?;
+
+        if payment_retention.is_none() && activity_retention.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(PrunePolicy {
+            payment_retention,
+            activity_retention,
+            export_dir: self
+                .prune
+                .export_dir
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(self.storage_dir_path())),
+            interval: self
+                .prune
+                .interval_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_PRUNE_INTERVAL),
+        }))
+    }
+
+    /// Whether to prune on a schedule
+    pub fn prune_scheduled(&self) -> bool {
+        self.prune.scheduled.unwrap_or(false)
+    }
+
+    /// Get the onchain mint and melt quote settings, `None` when disabled
+    pub fn onchain_payments(&self) -> Option<OnchainPaymentsConfig> {
+        if !self.onchain_payments.enabled.unwrap_or(false) {
+            return None;
+        }
+
+        let defaults = OnchainPaymentsConfig::default();
+        let onchain = &self.onchain_payments;
+
+        Some(OnchainPaymentsConfig {
+            min_confirmations: onchain
+                .min_confirmations
+                .filter(|confirmations| *confirmations > 0)
+                .unwrap_or(defaults.min_confirmations),
+            deposit_expiry: onchain
+                .deposit_expiry_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.deposit_expiry),
+            watch_interval: onchain
+                .watch_interval_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.watch_interval),
+            fee_target_blocks: onchain
+                .fee_target_blocks
+                .filter(|blocks| *blocks > 0)
+                .unwrap_or(defaults.fee_target_blocks),
+            fallback_fee_rate: onchain
+                .fallback_fee_rate_sat_per_vb
+                .and_then(ldk_node::bitcoin::FeeRate::from_sat_per_vb)
+                .unwrap_or(defaults.fallback_fee_rate),
+            send_vbytes: onchain
+                .send_vbytes
+                .filter(|vbytes| *vbytes > 0)
+                .unwrap_or(defaults.send_vbytes),
+        })
+    }
+
+    /// Get the BOLT12 offer payment limits, `None` when no limit is set
+    pub fn bolt12_limits(&self) -> Result<Option<Bolt12Limits>> {
+        let bolt12 = &self.bolt12;
+
+        // LDK Node claims payments to offers before they could be checked,
+        // refusing to credit them would keep the payer's funds
+        if bolt12.max_quantity.is_some() || bolt12.max_payer_note_len.is_some() {
+            bail!(
+                "bolt12.max_quantity and bolt12.max_payer_note_len are no longer supported, \
+                 payments to offers cannot be rejected before LDK Node claims them"
+            );
+        }
+
+        Ok(bolt12.min_amount_msat.map(|min_amount_msat| Bolt12Limits {
+            min_amount_msat: Some(min_amount_msat),
+        }))
+    }
+
+    /// Get the rotation of offers shared by mint quotes, `None` for an offer per quote
+    pub fn offer_rotation(&self) -> Option<OfferRotation> {
+        let max_uses = self.bolt12.rotate_after_uses.filter(|uses| *uses > 0);
+        let max_age = self
+            .bolt12
+            .rotate_after_secs
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs);
+
+        if max_uses.is_none() && max_age.is_none() {
+            return None;
+        }
+
+        Some(OfferRotation { max_uses, max_age })
+    }
+
+    /// Get the node ids zero-conf inbound channels are accepted from
+    pub fn trusted_peers_0conf(&self) -> Result<Vec<PublicKey>> {
+        self.lsp
+            .trusted_node_ids
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .map(|node_id| {
+                PublicKey::from_str(node_id)
+                    .map_err(|e| anyhow!("Invalid trusted LSP node id {node_id}: {e}"))
+            })
+            .collect()
+    }
+
+    /// Get the alert rules, `None` when no rule is configured
+    pub fn alerts(&self) -> Result<Option<AlertsConfig>> {
+        let Some(rules) = self.alerts.rules.clone().filter(|rules| !rules.is_empty()) else {
+            return Ok(None);
+        };
+
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let missing =
+                    |field: &str| anyhow!("Alert rule {} needs {} to be set", rule.name, field);
+
+                let condition = match rule.condition.as_str() {
+                    "failed_melts" => AlertCondition::FailedMelts {
+                        threshold: rule.threshold.ok_or_else(|| missing("threshold"))?,
+                        window: Duration::from_secs(
+                            rule.window_secs.ok_or_else(|| missing("window_secs"))?,
+                        ),
+                    },
+                    "peer_disconnected" => {
+                        let node_id = rule.node_id.as_deref().ok_or_else(|| missing("node_id"))?;
+                        AlertCondition::PeerDisconnected {
+                            node_id: PublicKey::from_str(node_id).map_err(|e| {
+                                anyhow!(
+                                    "Invalid node id {node_id} in alert rule {}: {e}",
+                                    rule.name
+                                )
+                            })?,
+                            after: Duration::from_secs(
+                                rule.after_secs.ok_or_else(|| missing("after_secs"))?,
+                            ),
+                        }
+                    }
+                    "onchain_balance_below" => AlertCondition::OnchainBalanceBelow {
+                        sat: rule.sat.ok_or_else(|| missing("sat"))?,
+                    },
+                    "lightning_balance_below" => AlertCondition::LightningBalanceBelow {
+                        sat: rule.sat.ok_or_else(|| missing("sat"))?,
+                    },
+                    other => {
+                        return Err(anyhow!(
+                            "Unknown condition {other} in alert rule {}",
+                            rule.name
+                        ))
+                    }
+                };
+
+                Ok(AlertRule {
+                    name: rule.name,
+                    condition,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(AlertsConfig {
+            rules,
+            interval: self
+                .alerts
+                .interval_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_ALERT_INTERVAL),
+        }))
+    }
+
+    /// Get the default fee reserve of melt quotes
+    pub fn fee_reserve(&self) -> FeeReserve {
+        FeeReserve {
+            min_fee_reserve: self
+                .fees
+                .min_fee_reserve
+                .unwrap_or(DEFAULT_MIN_FEE_RESERVE)
+                .into(),
+            percent_fee_reserve: self
+                .fees
+                .percent_fee_reserve
+                .unwrap_or(DEFAULT_PERCENT_FEE_RESERVE),
+        }
+    }
+
+    /// Get the strategy computing the fee reserve of melt quotes
+    ///
+    /// The probe strategy reserves fees with the tiers when any are set, and
+    /// with the default reserve otherwise.
+    pub fn fee_strategy(&self) -> Result<Arc<dyn FeeStrategy>> {
+        let tiered = || -> Result<Arc<dyn FeeStrategy>> {
+            let tiers = self
+                .fees
+                .tiers
+                .iter()
+                .flatten()
+                .map(|tier| FeeTier {
+                    below_msat: tier.below_msat,
+                    reserve: FeeReserve {
+                        min_fee_reserve: tier.min_fee_reserve.into(),
+                        percent_fee_reserve: tier.percent_fee_reserve,
+                    },
+                })
+                .collect();
+            Ok(Arc::new(TieredFeeStrategy::new(tiers, self.fee_reserve())?))
+        };
+        let has_tiers = self
+            .fees
+            .tiers
+            .as_ref()
+            .is_some_and(|tiers| !tiers.is_empty());
+
+        match self.fees.strategy.as_deref().unwrap_or("static") {
+            "static" => Ok(Arc::new(StaticFeeStrategy::new(self.fee_reserve()))),
+            "tiered" => tiered(),
+            "probe" => {
+                let inner = match has_tiers {
+                    true => tiered()?,
+                    false => Arc::new(StaticFeeStrategy::new(self.fee_reserve())),
+                };
+                Ok(Arc::new(ProbeFeeStrategy::new(inner)))
+            }
+            other => Err(anyhow!(
+                "Unknown fee strategy {other}, expected static, tiered or probe"
+            )),
+        }
+    }
+
+    /// Get how many payment notifications are kept for mints that have not read them
+    pub fn notification_capacity(&self) -> usize {
+        self.notifications
+            .payment_queue_capacity
+            .unwrap_or(DEFAULT_NOTIFICATION_CAPACITY)
+    }
+
+    /// Get the configured notification sinks
+    pub fn notification_sinks(&self) -> Result<Vec<Arc<dyn NotificationSink>>> {
+        let mut sinks: Vec<Arc<dyn NotificationSink>> = vec![];
+
+        if self.notifications.log.unwrap_or(false) {
+            sinks.push(Arc::new(LogSink));
+        }
+
+        let timeout = self
+            .notifications
+            .webhook_timeout_secs
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_WEBHOOK_TIMEOUT);
+        for url in self.notifications.webhook_urls.iter().flatten() {
+            sinks.push(Arc::new(WebhookSink::new(url.clone(), timeout)?));
+        }
+
+        let secret_key = resolve_secret(
+            "nwc_secret_key",
+            self.notifications.nwc_secret_key.as_ref(),
+            self.notifications.nwc_secret_key_file.as_ref(),
+            self.notifications.nwc_secret_key_env.as_ref(),
+        )?;
+        match (
+            &self.notifications.nwc_relay_url,
+            &self.notifications.nwc_client_pubkey,
+            secret_key,
+        ) {
+            (None, None, None) => {}
+            (Some(relay_url), Some(client_pubkey), Some(secret_key)) => {
+                sinks.push(Arc::new(NwcSink::new(
+                    relay_url.clone(),
+                    secret_key.expose(),
+                    client_pubkey,
+                )?));
+            }
+            _ => bail!(
+                "NWC notifications need nwc_relay_url, nwc_client_pubkey and an nwc_secret_key"
+            ),
+        }
+
+        let bot_token = resolve_secret(
+            "telegram_bot_token",
+            self.notifications.telegram_bot_token.as_ref(),
+            self.notifications.telegram_bot_token_file.as_ref(),
+            self.notifications.telegram_bot_token_env.as_ref(),
+        )?;
+        match (bot_token, &self.notifications.telegram_chat_id) {
+            (None, None) => {}
+            (Some(bot_token), Some(chat_id)) => {
+                sinks.push(Arc::new(TelegramSink::new(
+                    bot_token.expose(),
+                    chat_id.clone(),
+                    timeout,
+                )?));
+            }
+            _ => bail!("Telegram notifications need a telegram_bot_token and telegram_chat_id"),
+        }
+
+        let access_token = resolve_secret(
+            "matrix_access_token",
+            self.notifications.matrix_access_token.as_ref(),
+            self.notifications.matrix_access_token_file.as_ref(),
+            self.notifications.matrix_access_token_env.as_ref(),
+        )?;
+        match (
+            &self.notifications.matrix_homeserver_url,
+            &self.notifications.matrix_room_id,
+            access_token,
+        ) {
+            (None, None, None) => {}
+            (Some(homeserver_url), Some(room_id), Some(access_token)) => {
+                sinks.push(Arc::new(MatrixSink::new(
+                    homeserver_url.clone(),
+                    room_id.clone(),
+                    access_token.expose().to_string(),
+                    timeout,
+                )?));
+            }
+            _ => bail!(
+                "Matrix notifications need matrix_homeserver_url, matrix_room_id and a matrix_access_token"
+            ),
+        }
+
+        if let Some(server) = &self.notifications.smtp_server {
+            let tls = match self.notifications.smtp_tls.as_deref() {
+                None | Some("starttls") => SmtpTls::StartTls,
+                Some("tls") => SmtpTls::Tls,
+                Some("none") => SmtpTls::None,
+                Some(other) => bail!("Unknown smtp_tls {other}, expected starttls, tls or none"),
+            };
+            let password = resolve_secret(
+                "smtp_password",
+                self.notifications.smtp_password.as_ref(),
+                self.notifications.smtp_password_file.as_ref(),
+                self.notifications.smtp_password_env.as_ref(),
+            )?;
+            let credentials = match (&self.notifications.smtp_username, password) {
+                (None, None) => None,
+                (Some(username), Some(password)) => {
+                    Some((username.clone(), password.expose().to_string()))
+                }
+                _ => bail!("SMTP authentication needs both smtp_username and an smtp_password"),
+            };
+            let from = self
+                .notifications
+                .smtp_from
+                .clone()
+                .ok_or_else(|| anyhow!("SMTP notifications need smtp_from"))?;
+
+            sinks.push(Arc::new(SmtpSink::new(SmtpSinkConfig {
+                server: server.clone(),
+                port: self.notifications.smtp_port,
+                tls,
+                credentials,
+                from,
+                recipients: self
+                    .notifications
+                    .smtp_recipients
+                    .clone()
+                    .unwrap_or_default(),
+                timeout: DEFAULT_SMTP_TIMEOUT,
+            })?));
+        }
+
+        Ok(sinks)
+    }
+
+    /// Whether payment preimages are redacted
+    pub fn redact_preimages(&self) -> bool {
+        self.privacy.redact_preimages.unwrap_or(false)
+    }
+
+    /// Get the retry policy of failed melt payments
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.retries.max_attempts.unwrap_or(1).max(1),
+            backoff: self
+                .retries
+                .backoff_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_RETRY_BACKOFF),
+            max_backoff: self
+                .retries
+                .max_backoff_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_MAX_RETRY_BACKOFF),
+            max_path_count: self.retries.max_path_count,
+            max_channel_saturation_power_of_half: self.retries.max_channel_saturation_power_of_half,
+        }
+    }
+
+    /// Get the stuck payment watchdog settings, `None` if disabled
+    pub fn watchdog(&self) -> Result<Option<WatchdogConfig>> {
+        if !self.watchdog.enabled.unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let policy = self
+            .watchdog
+            .policy
+            .as_deref()
+            .map(StuckPaymentPolicy::from_str)
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Some(WatchdogConfig {
+            stuck_after: self
+                .watchdog
+                .stuck_after_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_STUCK_AFTER),
+            policy,
+            interval: self
+                .watchdog
+                .interval_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_WATCHDOG_INTERVAL),
+        }))
+    }
+
+    /// Get the period of scheduled summaries, `None` if disabled
+    pub fn summary_period(&self) -> Result<Option<SummaryPeriod>> {
+        if !self.summary.enabled.unwrap_or(false) {
+            return Ok(None);
+        }
+
+        self.summary
+            .period
+            .as_deref()
+            .map(SummaryPeriod::from_str)
+            .transpose()
+            .map(|period| Some(period.unwrap_or_default()))
     }
 
     /// Get GRPC host
@@ -450,6 +2712,11 @@ source_type = "p2p"
             .unwrap_or_else(|| "50051".to_string())
     }
 
+    /// Get the management service TLS directory
+    pub fn grpc_tls_dir(&self) -> Option<PathBuf> {
+        self.grpc.tls_dir.clone().map(PathBuf::from)
+    }
+
     /// Get GRPC socket address
     pub fn grpc_socket_addr(&self) -> Result<SocketAddr> {
         format!(
@@ -460,4 +2727,198 @@ source_type = "p2p"
         .parse::<SocketAddr>()
         .map_err(|e| anyhow!("Failed to parse GRPC socket address: {}", e))
     }
+
+    /// Whether the HTTP server serves the status page
+    pub fn http_status_page(&self) -> bool {
+        self.http.status_page.unwrap_or(false)
+    }
+
+    /// Get HTTP socket address, `None` when the HTTP endpoints are disabled
+    pub fn http_socket_addr(&self) -> Result<Option<SocketAddr>> {
+        if !self.http.enabled.unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let host = self
+            .http
+            .host
+            .clone()
+            .unwrap_or_else(|| "127.0.0.1".to_string());
+        let port = self.http.port.unwrap_or(8091);
+
+        format!("{host}:{port}")
+            .parse::<SocketAddr>()
+            .map(Some)
+            .map_err(|e| anyhow!("Failed to parse HTTP socket address: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(file: &str, vars: &[(&str, &str)]) -> Config {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        let config_builder = ConfigBuilder::builder()
+            .add_source(ConfigFile::from_str(file, config::FileFormat::Toml));
+        add_env_sources_from(config_builder, vars)
+            .unwrap()
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    #[test]
+    fn env_var_names_join_sections_with_double_underscores() {
+        assert_eq!(
+            env_var_name("chain_source.bitcoinrpc.host"),
+            "CDK_LDK_NODE_CHAIN_SOURCE__BITCOINRPC__HOST"
+        );
+    }
+
+    #[test]
+    fn env_vars_set_nested_keys() {
+        let config = load(
+            "",
+            &[
+                ("CDK_LDK_NODE_CHAIN_SOURCE__SOURCE_TYPE", "bitcoinrpc"),
+                ("CDK_LDK_NODE_CHAIN_SOURCE__BITCOINRPC__HOST", "bitcoind"),
+            ],
+        );
+
+        assert_eq!(
+            config.chain_source.source_type.as_deref(),
+            Some("bitcoinrpc")
+        );
+        assert_eq!(
+            config.chain_source.bitcoinrpc.host.as_deref(),
+            Some("bitcoind")
+        );
+    }
+
+    #[test]
+    fn env_vars_split_lists_on_commas() {
+        let config = load(
+            "",
+            &[(
+                "CDK_LDK_NODE_CHAIN_SOURCE__FALLBACK_ESPLORA_URLS",
+                "https://a.example,https://b.example",
+            )],
+        );
+
+        assert_eq!(
+            config.chain_source.fallback_esplora_urls,
+            Some(vec![
+                "https://a.example".to_string(),
+                "https://b.example".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn env_vars_are_parsed_to_the_field_type() {
+        let config = load(
+            "",
+            &[
+                ("CDK_LDK_NODE_CHAIN_SOURCE__BITCOINRPC__PORT", "18443"),
+                ("CDK_LDK_NODE_CHAIN_SOURCE__FALLBACK_TO_BITCOINRPC", "true"),
+                (
+                    "CDK_LDK_NODE_CHAIN_SOURCE__HEALTH_CHECK_INTERVAL_SECS",
+                    "30",
+                ),
+            ],
+        );
+
+        assert_eq!(config.chain_source.bitcoinrpc.port, Some(18443));
+        assert_eq!(config.chain_source.fallback_to_bitcoinrpc, Some(true));
+        assert_eq!(config.chain_source.health_check_interval_secs, Some(30));
+    }
+
+    #[test]
+    fn env_vars_keep_strings_that_look_like_numbers() {
+        let config = load(
+            "",
+            &[
+                ("CDK_LDK_NODE_SEED__PASSPHRASE", "000123"),
+                ("CDK_LDK_NODE_CHAIN_SOURCE__BITCOINRPC__PASSWORD", "1e3"),
+                ("CDK_LDK_NODE_CHAIN_SOURCE__BITCOINRPC__HOST", "true"),
+            ],
+        );
+
+        assert_eq!(
+            config.seed.passphrase.as_ref().map(Secret::expose),
+            Some("000123")
+        );
+        assert_eq!(
+            config
+                .chain_source
+                .bitcoinrpc
+                .password
+                .as_ref()
+                .map(Secret::expose),
+            Some("1e3")
+        );
+        assert_eq!(config.chain_source.bitcoinrpc.host.as_deref(), Some("true"));
+    }
+
+    #[test]
+    fn env_vars_take_precedence_over_legacy_vars_and_the_file() {
+        let file = r#"
+[chain_source.bitcoinrpc]
+host = "file"
+port = 8332
+"#;
+
+        let config = load(file, &[]);
+        assert_eq!(config.chain_source.bitcoinrpc.host.as_deref(), Some("file"));
+
+        let config = load(
+            file,
+            &[
+                (ENV_BITCOIN_RPC_HOST, "legacy"),
+                (ENV_BITCOIN_RPC_PORT, "18443"),
+            ],
+        );
+        assert_eq!(
+            config.chain_source.bitcoinrpc.host.as_deref(),
+            Some("legacy")
+        );
+        assert_eq!(config.chain_source.bitcoinrpc.port, Some(18443));
+
+        let config = load(
+            file,
+            &[
+                (ENV_BITCOIN_RPC_HOST, "legacy"),
+                ("CDK_LDK_NODE_CHAIN_SOURCE__BITCOINRPC__HOST", "prefixed"),
+            ],
+        );
+        assert_eq!(
+            config.chain_source.bitcoinrpc.host.as_deref(),
+            Some("prefixed")
+        );
+    }
+
+    #[test]
+    fn default_cookie_paths_follow_the_bitcoind_data_dirs() {
+        let cookie = |network| {
+            get_default_bitcoind_cookie_path(network)
+                .unwrap()
+                .iter()
+                .rev()
+                .take(2)
+                .map(|part| part.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(cookie(Network::Testnet), [".cookie", "testnet3"]);
+        assert_eq!(cookie(Network::Testnet4), [".cookie", "testnet4"]);
+        assert_eq!(cookie(Network::Signet), [".cookie", "signet"]);
+        assert_eq!(cookie(Network::Regtest), [".cookie", "regtest"]);
+        assert_eq!(cookie(Network::Bitcoin)[0], ".cookie");
+    }
 }
@@ -0,0 +1,498 @@
+//! Onchain deposits for mint quotes
+//!
+//! Every onchain mint quote gets a fresh address from the node wallet. The
+//! deposit is settled once outputs paying the address have the configured
+//! number of confirmations.
+//!
+//! LDK Node records incoming onchain transactions and their confirmation
+//! status but not the addresses they paid, so the outputs of each incoming
+//! transaction are fetched from the chain source once and cached.
+//!
+//! A watcher tracks the payments to each deposit address and credits them to
+//! the mint once they reach the confirmation depth. Credited payments stay
+//! tracked until they are buried deep enough that a reorg is unlikely, a
+//! credited payment that drops back below the depth is flagged as reorged.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use ldk_node::bitcoin::{Address, BlockHash, FeeRate, ScriptBuf, Txid};
+use ldk_node::payment::{ConfirmationStatus, PaymentDirection, PaymentKind};
+use ldk_node::Node;
+use serde::{Deserialize, Serialize};
+
+use crate::{chain, ChainSource};
+
+/// File deposits are kept in, inside the storage directory
+pub const DEPOSITS_FILE_NAME: &str = "deposits.jsonl";
+
+/// Default confirmations before a deposit is settled
+pub const DEFAULT_MIN_CONFIRMATIONS: u32 = 3;
+
+/// Default time a deposit address is watched for
+pub const DEFAULT_DEPOSIT_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default confirmation target of onchain sends
+pub const DEFAULT_FEE_TARGET_BLOCKS: u16 = 6;
+
+/// Default fee rate of onchain sends when the chain source has no estimate
+pub const DEFAULT_FALLBACK_FEE_RATE_SAT_PER_VB: u64 = 2;
+
+/// Default interval between checks of the watched deposit addresses
+pub const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Confirmations beyond the minimum before a credited payment stops being
+/// watched for reorgs
+pub const REORG_SAFETY_DEPTH: u32 = 6;
+
+/// Default size assumed when quoting the fee of an onchain send
+///
+/// Two P2WPKH inputs and two outputs. Sends spending more inputs pay more
+/// than quoted.
+pub const DEFAULT_SEND_VBYTES: u64 = 210;
+
+/// Settings of onchain mint and melt quotes
+#[derive(Debug, Clone)]
+pub struct OnchainPaymentsConfig {
+    /// Confirmations before a deposit is settled
+    pub min_confirmations: u32,
+    /// Time a deposit address is watched for when the quote sets no expiry
+    pub deposit_expiry: Duration,
+    /// Interval between checks of the watched deposit addresses
+    pub watch_interval: Duration,
+    /// Confirmation target of onchain sends
+    pub fee_target_blocks: u16,
+    /// Fee rate of onchain sends when the chain source has no estimate
+    pub fallback_fee_rate: FeeRate,
+    /// Transaction size assumed when quoting the fee of an onchain send
+    pub send_vbytes: u64,
+}
+
+impl Default for OnchainPaymentsConfig {
+    fn default() -> Self {
+        Self {
+            min_confirmations: DEFAULT_MIN_CONFIRMATIONS,
+            deposit_expiry: DEFAULT_DEPOSIT_EXPIRY,
+            watch_interval: DEFAULT_WATCH_INTERVAL,
+            fee_target_blocks: DEFAULT_FEE_TARGET_BLOCKS,
+            fallback_fee_rate: FeeRate::from_sat_per_vb_unchecked(
+                DEFAULT_FALLBACK_FEE_RATE_SAT_PER_VB,
+            ),
+            send_vbytes: DEFAULT_SEND_VBYTES,
+        }
+    }
+}
+
+impl OnchainPaymentsConfig {
+    /// Fee quoted for an onchain send at `fee_rate`
+    pub fn send_fee_sat(&self, fee_rate: FeeRate) -> Result<u64> {
+        fee_rate
+            .fee_vb(self.send_vbytes)
+            .map(|fee| fee.to_sat())
+            .ok_or(anyhow!("Fee rate overflow"))
+    }
+}
+
+/// Amount of an onchain send plus its fee
+pub fn send_total_sat(amount_sat: u64, fee_sat: u64) -> Result<u64> {
+    amount_sat
+        .checked_add(fee_sat)
+        .ok_or(anyhow!("Amount and fee overflow"))
+}
+
+/// Deposit address issued for a mint quote
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deposit {
+    pub address: String,
+    /// Amount the quote asked for, `None` for any amount
+    pub amount_sat: Option<u64>,
+    /// Unix time the address was issued
+    pub created_at: u64,
+    /// Unix time after which new payments to the address are not watched for
+    pub expires_at: u64,
+    /// Payments to the address seen by the watcher
+    #[serde(default)]
+    pub payments: Vec<TrackedPayment>,
+    /// Expired with every payment credited and buried, no longer watched
+    #[serde(default)]
+    pub settled: bool,
+}
+
+/// Payment to a deposit address seen by the watcher
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackedPayment {
+    pub txid: String,
+    pub amount_sat: u64,
+    /// Reported to the mint as received
+    pub credited: bool,
+    /// Credited but since dropped below the confirmation depth
+    #[serde(default)]
+    pub reorged: bool,
+}
+
+/// Change in a deposit found by [`Deposit::track`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DepositEvent {
+    /// A payment reached the confirmation depth and is to be credited
+    Credited { txid: String, amount_sat: u64 },
+    /// A credited payment dropped below the confirmation depth
+    Reorged { txid: String },
+    /// A reorged payment is back at the confirmation depth
+    Reconfirmed { txid: String },
+    /// A payment that was never credited is no longer seen, it was replaced
+    /// or evicted from the mempool
+    Dropped { txid: String },
+}
+
+impl Deposit {
+    /// Update the tracked payments from the current status of the address
+    ///
+    /// Payments first seen after the deposit expired are ignored. Returns
+    /// what changed, empty if the deposit is unchanged.
+    pub fn track(
+        &mut self,
+        status: &DepositStatus,
+        min_confirmations: u32,
+        now: u64,
+    ) -> Vec<DepositEvent> {
+        let mut events = vec![];
+
+        let confirmations = |txid: &str| {
+            status
+                .payments
+                .iter()
+                .find(|payment| payment.txid.to_string() == txid)
+                .map(|payment| payment.confirmations)
+        };
+
+        if now <= self.expires_at {
+            for payment in &status.payments {
+                let txid = payment.txid.to_string();
+                if !self.payments.iter().any(|tracked| tracked.txid == txid) {
+                    self.payments.push(TrackedPayment {
+                        txid,
+                        amount_sat: payment.amount_sat,
+                        credited: false,
+                        reorged: false,
+                    });
+                }
+            }
+        }
+
+        self.payments.retain(|tracked| {
+            if tracked.credited || confirmations(&tracked.txid).is_some() {
+                return true;
+            }
+            events.push(DepositEvent::Dropped {
+                txid: tracked.txid.clone(),
+            });
+            false
+        });
+
+        for tracked in &mut self.payments {
+            let at_depth = confirmations(&tracked.txid)
+                .is_some_and(|confirmations| confirmations >= min_confirmations);
+
+            match (tracked.credited, tracked.reorged, at_depth) {
+                (false, _, true) => {
+                    tracked.credited = true;
+                    events.push(DepositEvent::Credited {
+                        txid: tracked.txid.clone(),
+                        amount_sat: tracked.amount_sat,
+                    });
+                }
+                (true, false, false) => {
+                    tracked.reorged = true;
+                    events.push(DepositEvent::Reorged {
+                        txid: tracked.txid.clone(),
+                    });
+                }
+                (true, true, true) => {
+                    tracked.reorged = false;
+                    events.push(DepositEvent::Reconfirmed {
+                        txid: tracked.txid.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let buried = self.payments.iter().all(|tracked| {
+            tracked.credited
+                && confirmations(&tracked.txid).is_some_and(|confirmations| {
+                    confirmations >= min_confirmations + REORG_SAFETY_DEPTH
+                })
+        });
+
+        if !self.settled && now > self.expires_at && buried {
+            self.settled = true;
+        }
+
+        events
+    }
+
+    /// Amount credited and still at the confirmation depth
+    pub fn credited_sat(&self) -> u64 {
+        self.payments
+            .iter()
+            .filter(|tracked| tracked.credited && !tracked.reorged)
+            .map(|tracked| tracked.amount_sat)
+            .sum()
+    }
+}
+
+/// Persistent store of issued deposit addresses
+#[derive(Debug)]
+pub struct DepositStore {
+    path: PathBuf,
+    deposits: Mutex<BTreeMap<String, Deposit>>,
+}
+
+impl DepositStore {
+    /// Open the store in the storage directory, starting empty if no deposits exist
+    pub fn new(storage_dir: PathBuf) -> Self {
+        let path = storage_dir.join(DEPOSITS_FILE_NAME);
+
+        let deposits = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .filter_map(|line| match serde_json::from_str::<Deposit>(line) {
+                    Ok(deposit) => Some((deposit.address.clone(), deposit)),
+                    Err(err) => {
+                        tracing::warn!(
+                            "Skipping unreadable deposit in {}: {}",
+                            path.display(),
+                            err
+                        );
+                        None
+                    }
+                })
+                .collect(),
+            Err(_) => BTreeMap::new(),
+        };
+
+        Self {
+            path,
+            deposits: Mutex::new(deposits),
+        }
+    }
+
+    /// Record a deposit, replacing an earlier record of the address
+    ///
+    /// Records are appended, the last record of an address wins when the
+    /// store is opened.
+    pub fn insert(&self, deposit: Deposit) -> Result<()> {
+        let mut deposits = self.deposits.lock().expect("deposits lock poisoned");
+
+        let mut line = serde_json::to_string(&deposit)?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .with_context(|| format!("Could not write deposit to {}", self.path.display()))?;
+
+        deposits.insert(deposit.address.clone(), deposit);
+        Ok(())
+    }
+
+    /// Get the deposit issued for an address
+    pub fn get(&self, address: &str) -> Option<Deposit> {
+        self.deposits
+            .lock()
+            .expect("deposits lock poisoned")
+            .get(address)
+            .cloned()
+    }
+
+    /// Deposits the watcher still checks
+    pub fn watched(&self) -> Vec<Deposit> {
+        self.deposits
+            .lock()
+            .expect("deposits lock poisoned")
+            .values()
+            .filter(|deposit| !deposit.settled)
+            .cloned()
+            .collect()
+    }
+
+    /// All deposits, ordered by address
+    pub fn list(&self) -> Vec<Deposit> {
+        self.deposits
+            .lock()
+            .expect("deposits lock poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+}
+
+/// Outputs of one transaction paying a deposit address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepositPayment {
+    pub txid: Txid,
+    pub amount_sat: u64,
+    /// Confirmations of the transaction, zero while unconfirmed
+    pub confirmations: u32,
+}
+
+/// Payments seen for a deposit address
+#[derive(Debug, Clone)]
+pub struct DepositStatus {
+    pub address: String,
+    pub payments: Vec<DepositPayment>,
+}
+
+impl DepositStatus {
+    /// Amount with at least `min_confirmations`
+    pub fn confirmed_sat(&self, min_confirmations: u32) -> u64 {
+        self.payments
+            .iter()
+            .filter(|payment| payment.confirmations >= min_confirmations)
+            .map(|payment| payment.amount_sat)
+            .sum()
+    }
+
+    /// Amount seen but below `min_confirmations`
+    pub fn pending_sat(&self, min_confirmations: u32) -> u64 {
+        self.payments
+            .iter()
+            .filter(|payment| payment.confirmations < min_confirmations)
+            .map(|payment| payment.amount_sat)
+            .sum()
+    }
+}
+
+/// Cache of the outputs of incoming transactions, fetched from the chain source
+#[derive(Debug, Default)]
+pub struct OutputCache {
+    outputs: Mutex<HashMap<Txid, Vec<(ScriptBuf, u64)>>>,
+}
+
+impl OutputCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn outputs(
+        &self,
+        chain_source: &ChainSource,
+        txid: &Txid,
+        block_hash: Option<&BlockHash>,
+    ) -> Result<Vec<(ScriptBuf, u64)>> {
+        if let Some(outputs) = self
+            .outputs
+            .lock()
+            .expect("output cache lock poisoned")
+            .get(txid)
+        {
+            return Ok(outputs.clone());
+        }
+
+        let tx = chain::fetch_transaction(chain_source, txid, block_hash).await?;
+        let outputs: Vec<(ScriptBuf, u64)> = tx
+            .output
+            .into_iter()
+            .map(|output| (output.script_pubkey, output.value.to_sat()))
+            .collect();
+
+        self.outputs
+            .lock()
+            .expect("output cache lock poisoned")
+            .insert(*txid, outputs.clone());
+
+        Ok(outputs)
+    }
+}
+
+/// Payments to a deposit address among the node's incoming onchain transactions
+///
+/// Confirmations follow LDK Node's view of the chain, so a transaction
+/// reorganized out of the chain drops back to zero confirmations, and to no
+/// payment once LDK Node stops reporting it.
+pub async fn deposit_status(
+    node: &Node,
+    chain_source: &ChainSource,
+    cache: &OutputCache,
+    address: &Address,
+) -> Result<DepositStatus> {
+    let tip_height = node.status().current_best_block.height;
+    let script_pubkey = address.script_pubkey();
+
+    let transactions: Vec<(Txid, ConfirmationStatus)> = node
+        .list_payments_with_filter(|payment| {
+            payment.direction == PaymentDirection::Inbound
+                && matches!(payment.kind, PaymentKind::Onchain { .. })
+        })
+        .into_iter()
+        .filter_map(|payment| match payment.kind {
+            PaymentKind::Onchain { txid, status } => Some((txid, status)),
+            _ => None,
+        })
+        .collect();
+
+    let mut payments = vec![];
+    for (txid, status) in transactions {
+        let (confirmations, block_hash) = match status {
+            ConfirmationStatus::Confirmed {
+                block_hash, height, ..
+            } => (tip_height.saturating_sub(height) + 1, Some(block_hash)),
+            ConfirmationStatus::Unconfirmed => (0, None),
+        };
+
+        let outputs = cache
+            .outputs(chain_source, &txid, block_hash.as_ref())
+            .await?;
+        let amount_sat: u64 = outputs
+            .iter()
+            .filter(|(script, _)| *script == script_pubkey)
+            .map(|(_, value)| value)
+            .sum();
+
+        if amount_sat > 0 {
+            payments.push(DepositPayment {
+                txid,
+                amount_sat,
+                confirmations,
+            });
+        }
+    }
+
+    Ok(DepositStatus {
+        address: address.to_string(),
+        payments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_fee_is_quoted_for_the_configured_size() {
+        let settings = OnchainPaymentsConfig::default();
+
+        assert_eq!(
+            settings
+                .send_fee_sat(FeeRate::from_sat_per_vb_unchecked(2))
+                .unwrap(),
+            2 * DEFAULT_SEND_VBYTES
+        );
+        assert_eq!(settings.send_fee_sat(FeeRate::ZERO).unwrap(), 0);
+        assert!(settings.send_fee_sat(FeeRate::MAX).is_err());
+    }
+
+    #[test]
+    fn send_total_includes_the_fee() {
+        assert_eq!(send_total_sat(100_000, 420).unwrap(), 100_420);
+        assert!(send_total_sat(u64::MAX, 1).is_err());
+    }
+}
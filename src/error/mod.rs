@@ -0,0 +1,66 @@
+//! Errors returned by the node lifecycle API
+
+/// CDK LDK Node error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The node was stopped and cannot be started again
+    #[error("Node has been stopped and cannot be restarted, create a new node instead")]
+    NodeStopped,
+    /// Requested expiry of an incoming payment request is out of range
+    #[error(
+        "Invalid expiry {expiry}: must be between {min_secs} and {max_secs} seconds from now ({now})"
+    )]
+    InvalidExpiry {
+        /// Requested unix expiry
+        expiry: u64,
+        /// Unix time the expiry was checked at
+        now: u64,
+        /// Minimum allowed seconds until expiry
+        min_secs: u32,
+        /// Maximum allowed seconds until expiry
+        max_secs: u32,
+    },
+    /// Onchain spend would dip below the configured reserve
+    #[error(
+        "Spending {amount_sat} sats would dip below the onchain reserve of {reserve_sat} sats, {available_sat} sats are available above it"
+    )]
+    BelowOnchainReserve {
+        /// Amount to spend
+        amount_sat: u64,
+        /// Spendable balance above the reserve
+        available_sat: u64,
+        /// Configured reserve
+        reserve_sat: u64,
+    },
+    /// Payment id or payment hash that cannot be parsed
+    #[error("Invalid payment identifier {id}: {reason}")]
+    InvalidPaymentIdentifier {
+        /// Identifier as received, truncated
+        id: String,
+        /// Why it cannot be parsed
+        reason: String,
+    },
+    /// No usable channel can receive an incoming payment request's amount
+    #[error(
+        "Not enough inbound liquidity to receive {}: {receivable_msat} msat can be received over usable channels",
+        amount_msat.map_or("a payment".to_string(), |amount_msat| format!("{amount_msat} msat"))
+    )]
+    NoInboundLiquidity {
+        /// Requested amount, `None` for variable amount requests
+        amount_msat: Option<u64>,
+        /// Inbound capacity of the usable channels
+        receivable_msat: u64,
+    },
+    /// Amount of an incoming payment request that LDK Node rejected
+    #[error("Invalid amount {amount_msat} msat for a payment request")]
+    InvalidAmount {
+        /// Requested amount
+        amount_msat: u64,
+    },
+    /// LDK Node error
+    #[error(transparent)]
+    Ldk(#[from] ldk_node::NodeError),
+    /// Other error
+    #[error(transparent)]
+    Anyhow(#[from] anyhow::Error),
+}
@@ -0,0 +1,203 @@
+//! Fee reserve strategies for outgoing payment quotes
+//!
+//! The fee returned with a melt quote is the most the mint lets the payment
+//! spend on routing. How it is computed is a policy decision of the mint, so
+//! it sits behind the [`FeeStrategy`] trait. The built-in strategies reserve a
+//! fixed share of the amount, pick the reserve from amount tiers, or probe
+//! the destination before falling back to either.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use cdk_common::common::FeeReserve;
+use cdk_common::{Amount, CurrencyUnit};
+use ldk_node::lightning::offers::offer::Offer;
+use ldk_node::lightning_invoice::Bolt11Invoice;
+use ldk_node::Node;
+
+/// Default minimum fee reserve, in the quote unit
+pub const DEFAULT_MIN_FEE_RESERVE: u64 = 2;
+
+/// Default fee reserve as a share of the payment amount
+pub const DEFAULT_PERCENT_FEE_RESERVE: f32 = 0.02;
+
+/// Destination of an outgoing payment being quoted
+#[derive(Debug, Clone, Copy)]
+pub enum FeeDestination<'a> {
+    Bolt11(&'a Bolt11Invoice),
+    Bolt12(&'a Offer),
+}
+
+/// Outgoing payment a fee reserve is computed for
+#[derive(Debug, Clone, Copy)]
+pub struct FeeRequest<'a> {
+    pub destination: FeeDestination<'a>,
+    /// Amount to pay
+    pub amount_msat: u64,
+    /// Amount to pay in the quote unit
+    pub amount: Amount,
+    /// Unit of the quote, the reserve is returned in it
+    pub unit: &'a CurrencyUnit,
+}
+
+/// Computes the fee reserve of outgoing payment quotes
+///
+/// Implement this to encode a mint's own fee policy and set it with
+/// [`CdkLdkNodeBuilder::set_fee_strategy`](crate::builder::CdkLdkNodeBuilder::set_fee_strategy).
+#[async_trait]
+pub trait FeeStrategy: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    /// Fee reserve for a payment in the quote unit
+    ///
+    /// An error rejects the quote.
+    async fn fee_reserve(&self, node: &Arc<Node>, request: &FeeRequest<'_>) -> Result<Amount>;
+}
+
+/// Larger of the percentage and the minimum of a reserve for an amount
+pub fn apply_reserve(reserve: &FeeReserve, amount: Amount) -> Amount {
+    let relative_fee_reserve = (reserve.percent_fee_reserve * u64::from(amount) as f32) as u64;
+    let absolute_fee_reserve: u64 = reserve.min_fee_reserve.into();
+
+    relative_fee_reserve.max(absolute_fee_reserve).into()
+}
+
+/// Same reserve for every payment
+#[derive(Debug, Clone)]
+pub struct StaticFeeStrategy {
+    pub reserve: FeeReserve,
+}
+
+impl StaticFeeStrategy {
+    pub fn new(reserve: FeeReserve) -> Self {
+        Self { reserve }
+    }
+}
+
+#[async_trait]
+impl FeeStrategy for StaticFeeStrategy {
+    fn name(&self) -> &str {
+        "static"
+    }
+
+    async fn fee_reserve(&self, _node: &Arc<Node>, request: &FeeRequest<'_>) -> Result<Amount> {
+        Ok(apply_reserve(&self.reserve, request.amount))
+    }
+}
+
+/// Reserve applied to payments below an amount
+#[derive(Debug, Clone)]
+pub struct FeeTier {
+    /// The tier applies to payments below this amount
+    pub below_msat: u64,
+    pub reserve: FeeReserve,
+}
+
+/// Reserve picked by payment amount
+///
+/// Small payments usually need a larger share of their amount for routing
+/// than large ones, tiers let the reserve percentage fall as the amount
+/// grows.
+#[derive(Debug, Clone)]
+pub struct TieredFeeStrategy {
+    /// Tiers ordered by `below_msat`
+    tiers: Vec<FeeTier>,
+    /// Reserve for payments above every tier
+    default: FeeReserve,
+}
+
+impl TieredFeeStrategy {
+    pub fn new(mut tiers: Vec<FeeTier>, default: FeeReserve) -> Result<Self> {
+        tiers.sort_by_key(|tier| tier.below_msat);
+
+        if tiers
+            .windows(2)
+            .any(|pair| pair[0].below_msat == pair[1].below_msat)
+        {
+            bail!("Fee tiers must have distinct below_msat amounts");
+        }
+
+        Ok(Self { tiers, default })
+    }
+
+    /// Reserve applying to an amount
+    pub fn reserve_for(&self, amount_msat: u64) -> &FeeReserve {
+        self.tiers
+            .iter()
+            .find(|tier| amount_msat < tier.below_msat)
+            .map_or(&self.default, |tier| &tier.reserve)
+    }
+}
+
+#[async_trait]
+impl FeeStrategy for TieredFeeStrategy {
+    fn name(&self) -> &str {
+        "tiered"
+    }
+
+    async fn fee_reserve(&self, _node: &Arc<Node>, request: &FeeRequest<'_>) -> Result<Amount> {
+        Ok(apply_reserve(
+            self.reserve_for(request.amount_msat),
+            request.amount,
+        ))
+    }
+}
+
+/// Probe BOLT11 destinations before quoting
+///
+/// Sends probes for the payment amount and rejects the quote when no route is
+/// found, so melts that cannot be routed fail at quote time instead of after
+/// the mint has reserved the proofs. LDK Node does not report the fees of
+/// probed routes, so the reserve itself comes from the inner strategy. BOLT12
+/// offers cannot be probed before an invoice is requested and are passed to
+/// the inner strategy directly.
+pub struct ProbeFeeStrategy {
+    inner: Arc<dyn FeeStrategy>,
+}
+
+impl std::fmt::Debug for ProbeFeeStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProbeFeeStrategy")
+            .field("inner", &self.inner.name())
+            .finish()
+    }
+}
+
+impl ProbeFeeStrategy {
+    pub fn new(inner: Arc<dyn FeeStrategy>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl FeeStrategy for ProbeFeeStrategy {
+    fn name(&self) -> &str {
+        "probe"
+    }
+
+    async fn fee_reserve(&self, node: &Arc<Node>, request: &FeeRequest<'_>) -> Result<Amount> {
+        if let FeeDestination::Bolt11(invoice) = request.destination {
+            let node = node.clone();
+            let invoice = invoice.clone();
+            let amount_msat = request.amount_msat;
+
+            // Routing blocks
+            tokio::task::spawn_blocking(move || {
+                let payment = node.bolt11_payment();
+                match invoice.amount_milli_satoshis() {
+                    Some(invoice_amount) if invoice_amount == amount_msat => {
+                        payment.send_probes(&invoice)
+                    }
+                    _ => payment.send_probes_using_amount(&invoice, amount_msat),
+                }
+            })
+            .await
+            .map_err(|err| anyhow!("Probe task failed: {err}"))?
+            .map_err(|err| anyhow!("No route to the payment destination: {err}"))?;
+        }
+
+        self.inner.fee_reserve(node, request).await
+    }
+}
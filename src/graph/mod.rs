@@ -0,0 +1,55 @@
+//! Network graph status
+
+use cdk_common::util::unix_time;
+use ldk_node::Node;
+
+/// Size and freshness of the node's network graph
+#[derive(Debug, Clone)]
+pub struct GraphStatus {
+    /// Number of channels known in the graph
+    pub num_channels: u64,
+    /// Number of nodes known in the graph
+    pub num_nodes: u64,
+    /// Unix timestamp of the most recent channel update in the graph
+    pub latest_update_timestamp: Option<u64>,
+    /// Unix timestamp of the last applied rapid gossip sync snapshot
+    pub latest_rgs_snapshot_timestamp: Option<u64>,
+}
+
+impl GraphStatus {
+    /// Read the current graph status from the node
+    ///
+    /// The graph and scorer are persisted by LDK Node, so after a restart this
+    /// reflects the data loaded from disk until gossip catches up.
+    pub fn from_node(node: &Node) -> Self {
+        let network_graph = node.network_graph();
+        let channels = network_graph.list_channels();
+
+        let latest_update_timestamp = channels
+            .iter()
+            .filter_map(|scid| network_graph.channel(*scid))
+            .flat_map(|channel| [channel.one_to_two, channel.two_to_one])
+            .flatten()
+            .map(|update| update.last_update as u64)
+            .max();
+
+        Self {
+            num_channels: channels.len() as u64,
+            num_nodes: network_graph.list_nodes().len() as u64,
+            latest_update_timestamp,
+            latest_rgs_snapshot_timestamp: node.status().latest_rgs_snapshot_timestamp,
+        }
+    }
+
+    /// Whether the graph has enough data for pathfinding
+    pub fn is_ready(&self) -> bool {
+        self.num_channels > 0
+    }
+
+    /// Seconds since the most recent graph data was received
+    pub fn staleness_secs(&self) -> Option<u64> {
+        self.latest_update_timestamp
+            .max(self.latest_rgs_snapshot_timestamp)
+            .map(|timestamp| unix_time().saturating_sub(timestamp))
+    }
+}
@@ -0,0 +1,173 @@
+//! Hold invoices, whose HTLCs are held until settled or cancelled
+//!
+//! A hold invoice is paid like any BOLT11 invoice, but the node does not claim
+//! the payment when its HTLCs arrive. The payment is accepted and held until
+//! it is settled with the preimage or cancelled, failing the HTLCs back to the
+//! payer. The mint is only told about the payment once it is settled, so it
+//! can decide to settle right before issuing ecash.
+//!
+//! The preimage is either supplied at settlement by the caller who created
+//! the invoice for a payment hash, or generated by the node and kept in the
+//! record. Held HTLCs must be settled before their claim deadline, after which
+//! LDK fails them back on its own.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// File hold invoices are kept in, inside the storage directory
+pub const HOLD_INVOICES_FILE_NAME: &str = "hold_invoices.jsonl";
+
+/// State of a hold invoice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HoldInvoiceState {
+    /// Waiting for the payment
+    Open,
+    /// HTLCs arrived and are held
+    Accepted,
+    /// Payment claimed
+    Settled,
+    /// HTLCs failed back, or the invoice will not be paid
+    Cancelled,
+}
+
+impl HoldInvoiceState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HoldInvoiceState::Open => "open",
+            HoldInvoiceState::Accepted => "accepted",
+            HoldInvoiceState::Settled => "settled",
+            HoldInvoiceState::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Hold invoice record
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HoldInvoice {
+    /// Hex encoded payment hash
+    pub payment_hash: String,
+    /// Hex encoded preimage, set when the node generated it
+    pub preimage: Option<String>,
+    /// Invoice amount, unset for variable amount invoices
+    pub amount_msat: Option<u64>,
+    pub state: HoldInvoiceState,
+    /// Amount held, set once the payment is accepted
+    pub claimable_amount_msat: Option<u64>,
+    /// Block height by which the held HTLCs must be settled
+    pub claim_deadline: Option<u32>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Persistent record of hold invoices
+///
+/// Every change appends the full record, the last line of a payment hash wins.
+#[derive(Debug)]
+pub struct HoldInvoices {
+    path: PathBuf,
+    invoices: Mutex<BTreeMap<String, HoldInvoice>>,
+}
+
+impl HoldInvoices {
+    /// Open the record in the storage directory, starting empty if none exists
+    pub fn new(storage_dir: PathBuf) -> Self {
+        let path = storage_dir.join(HOLD_INVOICES_FILE_NAME);
+
+        let invoices = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .filter_map(|line| match serde_json::from_str::<HoldInvoice>(line) {
+                    Ok(invoice) => Some((invoice.payment_hash.clone(), invoice)),
+                    Err(err) => {
+                        tracing::warn!(
+                            "Skipping unreadable hold invoice in {}: {}",
+                            path.display(),
+                            err
+                        );
+                        None
+                    }
+                })
+                .collect(),
+            Err(_) => BTreeMap::new(),
+        };
+
+        Self {
+            path,
+            invoices: Mutex::new(invoices),
+        }
+    }
+
+    /// Record a new hold invoice
+    pub fn insert(&self, invoice: HoldInvoice) -> Result<()> {
+        let mut invoices = self.invoices.lock().expect("hold invoices lock poisoned");
+        self.append(&invoice)?;
+        invoices.insert(invoice.payment_hash.clone(), invoice);
+        Ok(())
+    }
+
+    /// Hold invoice by hex encoded payment hash
+    pub fn get(&self, payment_hash: &str) -> Option<HoldInvoice> {
+        self.invoices
+            .lock()
+            .expect("hold invoices lock poisoned")
+            .get(payment_hash)
+            .cloned()
+    }
+
+    /// All hold invoices, newest first
+    pub fn list(&self) -> Vec<HoldInvoice> {
+        let mut invoices: Vec<HoldInvoice> = self
+            .invoices
+            .lock()
+            .expect("hold invoices lock poisoned")
+            .values()
+            .cloned()
+            .collect();
+        invoices.sort_by_key(|invoice| std::cmp::Reverse(invoice.created_at));
+        invoices
+    }
+
+    /// Update a hold invoice, returning the updated record
+    ///
+    /// Returns `None` for payment hashes that are not hold invoices.
+    pub fn update(
+        &self,
+        payment_hash: &str,
+        now: u64,
+        update: impl FnOnce(&mut HoldInvoice),
+    ) -> Result<Option<HoldInvoice>> {
+        let mut invoices = self.invoices.lock().expect("hold invoices lock poisoned");
+
+        let Some(invoice) = invoices.get(payment_hash) else {
+            return Ok(None);
+        };
+
+        let mut updated = invoice.clone();
+        update(&mut updated);
+        updated.updated_at = now;
+        self.append(&updated)?;
+
+        invoices.insert(payment_hash.to_string(), updated.clone());
+        Ok(Some(updated))
+    }
+
+    fn append(&self, invoice: &HoldInvoice) -> Result<()> {
+        let mut line = serde_json::to_string(invoice)?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .with_context(|| format!("Could not write hold invoice to {}", self.path.display()))
+    }
+}
@@ -0,0 +1,86 @@
+//! Lifecycle callbacks for applications embedding the node
+//!
+//! Hooks are registered on the [`CdkLdkNodeBuilder`](crate::CdkLdkNodeBuilder)
+//! and called when the node has started, for every received payment queued
+//! for the mint, and once the node has stopped. They run on the task that
+//! reached the moment, the payment event handler for received payments, so
+//! they must return quickly and hand longer work to a task of their own. A
+//! panicking hook is logged and does not affect the node.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+use crate::notifications::PaymentNotification;
+use crate::CdkLdkNode;
+
+/// Called once the node has started
+pub type StartedHook = Arc<dyn Fn(&CdkLdkNode) + Send + Sync>;
+
+/// Called for every received payment queued for the mint
+pub type PaymentReceivedHook = Arc<dyn Fn(&PaymentNotification) + Send + Sync>;
+
+/// Called once the node has stopped
+pub type ShutdownHook = Arc<dyn Fn() + Send + Sync>;
+
+/// Registered lifecycle hooks
+#[derive(Default, Clone)]
+pub struct LifecycleHooks {
+    started: Vec<StartedHook>,
+    payment_received: Vec<PaymentReceivedHook>,
+    shutdown: Vec<ShutdownHook>,
+}
+
+impl std::fmt::Debug for LifecycleHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LifecycleHooks")
+            .field("started", &self.started.len())
+            .field("payment_received", &self.payment_received.len())
+            .field("shutdown", &self.shutdown.len())
+            .finish()
+    }
+}
+
+impl LifecycleHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_started(&mut self, hook: StartedHook) {
+        self.started.push(hook);
+    }
+
+    pub fn add_payment_received(&mut self, hook: PaymentReceivedHook) {
+        self.payment_received.push(hook);
+    }
+
+    pub fn add_shutdown(&mut self, hook: ShutdownHook) {
+        self.shutdown.push(hook);
+    }
+
+    /// Run the hooks of a started node
+    pub fn started(&self, node: &CdkLdkNode) {
+        for hook in &self.started {
+            run("started", || hook(node));
+        }
+    }
+
+    /// Run the hooks of a received payment
+    pub fn payment_received(&self, notification: &PaymentNotification) {
+        for hook in &self.payment_received {
+            run("payment_received", || hook(notification));
+        }
+    }
+
+    /// Run the hooks of a stopped node
+    pub fn shutdown(&self) {
+        for hook in &self.shutdown {
+            run("shutdown", || hook());
+        }
+    }
+}
+
+fn run(moment: &str, hook: impl FnOnce()) {
+    if catch_unwind(AssertUnwindSafe(hook)).is_err() {
+        tracing::error!("A {} hook panicked", moment);
+    }
+}
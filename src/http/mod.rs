@@ -0,0 +1,164 @@
+//! HTTP endpoints served next to the gRPC services
+//!
+//! Prometheus metrics are served on `/metrics`. The optional status page on
+//! `/` renders the node id, balances, channels and recent payments for a
+//! quick look from a browser. Both are read only.
+
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::Html;
+use axum::routing::get;
+use axum::Router;
+use ldk_node::payment::{PaymentDirection, PaymentKind};
+use tokio_util::sync::CancellationToken;
+
+use crate::CdkLdkNode;
+
+/// Payments listed on the status page
+const STATUS_PAGE_PAYMENTS: usize = 20;
+
+/// Seconds between reloads of the status page
+const STATUS_PAGE_REFRESH_SECS: u64 = 30;
+
+/// Serve the HTTP endpoints until the cancel token is triggered
+pub async fn serve(
+    node: Arc<CdkLdkNode>,
+    addr: SocketAddr,
+    status_page: bool,
+    cancel_token: CancellationToken,
+) -> anyhow::Result<()> {
+    let mut app = Router::new().route("/metrics", get(metrics));
+    if status_page {
+        app = app.route("/", get(status));
+    }
+    let app = app.with_state(node);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+
+    tracing::info!("Started HTTP server on {}", addr);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            cancel_token.cancelled().await;
+            tracing::info!("HTTP server received shutdown signal");
+        })
+        .await?;
+
+    Ok(())
+}
+
+async fn metrics(State(node): State<Arc<CdkLdkNode>>) -> Result<String, StatusCode> {
+    node.metrics.encode().map_err(|err| {
+        tracing::error!("Could not encode metrics: {}", err);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn status(State(node): State<Arc<CdkLdkNode>>) -> Html<String> {
+    Html(render_status(&node))
+}
+
+/// Render the status page
+fn render_status(node: &CdkLdkNode) -> String {
+    let inner = &node.inner;
+    let status = inner.status();
+    let balances = inner.list_balances();
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta http-equiv=\"refresh\" content=\"{STATUS_PAGE_REFRESH_SECS}\">\
+         <title>CDK LDK Node</title>\
+         <style>body{{font-family:monospace}}table{{border-collapse:collapse}}\
+         td,th{{border:1px solid #ccc;padding:2px 8px;text-align:left}}</style>\
+         </head><body><h1>CDK LDK Node</h1>"
+    );
+
+    let _ = write!(
+        html,
+        "<table>\
+         <tr><th>Node id</th><td>{}</td></tr>\
+         <tr><th>Network</th><td>{}</td></tr>\
+         <tr><th>State</th><td>{}</td></tr>\
+         <tr><th>Best block</th><td>{}</td></tr>\
+         </table>",
+        inner.node_id(),
+        inner.config().network,
+        node.state(),
+        status.current_best_block.height
+    );
+
+    let _ = write!(
+        html,
+        "<h2>Balances</h2><table>\
+         <tr><th>Onchain</th><td>{} sat</td></tr>\
+         <tr><th>Onchain spendable</th><td>{} sat</td></tr>\
+         <tr><th>Lightning</th><td>{} sat</td></tr>\
+         </table>",
+        balances.total_onchain_balance_sats,
+        balances.spendable_onchain_balance_sats,
+        balances.total_lightning_balance_sats
+    );
+
+    html.push_str(
+        "<h2>Channels</h2><table><tr><th>Channel</th><th>Peer</th><th>Capacity</th>\
+         <th>Outbound</th><th>Inbound</th><th>State</th></tr>",
+    );
+    for channel in inner.list_channels() {
+        let state = match (channel.is_usable, channel.is_channel_ready) {
+            (true, _) => "usable",
+            (false, true) => "ready",
+            (false, false) => "pending",
+        };
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{} sat</td><td>{} sat</td><td>{} sat</td><td>{}</td></tr>",
+            channel.channel_id,
+            channel.counterparty_node_id,
+            channel.channel_value_sats,
+            channel.outbound_capacity_msat / 1000,
+            channel.inbound_capacity_msat / 1000,
+            state
+        );
+    }
+    html.push_str("</table>");
+
+    let mut payments = inner.list_payments();
+    payments.sort_by(|a, b| b.latest_update_timestamp.cmp(&a.latest_update_timestamp));
+
+    html.push_str(
+        "<h2>Recent payments</h2><table><tr><th>Updated</th><th>Direction</th>\
+         <th>Kind</th><th>Amount</th><th>Status</th></tr>",
+    );
+    for payment in payments.iter().take(STATUS_PAGE_PAYMENTS) {
+        let direction = match payment.direction {
+            PaymentDirection::Inbound => "in",
+            PaymentDirection::Outbound => "out",
+        };
+        let kind = match payment.kind {
+            PaymentKind::Onchain { .. } => "onchain",
+            PaymentKind::Bolt11 { .. } => "bolt11",
+            PaymentKind::Bolt11Jit { .. } => "bolt11 jit",
+            PaymentKind::Bolt12Offer { .. } => "bolt12 offer",
+            PaymentKind::Bolt12Refund { .. } => "bolt12 refund",
+            PaymentKind::Spontaneous { .. } => "spontaneous",
+        };
+        let amount = payment
+            .amount_msat
+            .map(|amount_msat| format!("{amount_msat} msat"))
+            .unwrap_or_default();
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:?}</td></tr>",
+            payment.latest_update_timestamp, direction, kind, amount, payment.status
+        );
+    }
+    html.push_str("</table></body></html>");
+
+    html
+}
@@ -0,0 +1,475 @@
+//! Idempotency keys for fund-moving management RPCs
+//!
+//! A client can attach an idempotency key to a request that moves funds. The
+//! result of the first successful request with a key is persisted, and
+//! retries with the same key get the stored result instead of moving funds a
+//! second time.
+//!
+//! A request marks its [`Commitment`] once LDK Node accepted the send or
+//! channel open. Until then a failed request, or one whose client went away,
+//! releases the key so it can be retried. Afterwards the key is never
+//! released: a request that fails, is dropped, or whose payment is still
+//! pending leaves it unresolved, and retries are refused. The client checks
+//! the outcome and retries with a new key if nothing was sent.
+//!
+//! Keys are persisted when claimed, before the request executes. A key still
+//! in progress when the node stopped may or may not have moved funds, so it
+//! is reported as interrupted rather than executed again.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use cdk_common::util::unix_time;
+use serde::{Deserialize, Serialize};
+
+/// File claimed and completed requests are kept in, inside the storage directory
+const IDEMPOTENCY_FILE_NAME: &str = "idempotency_keys.jsonl";
+
+/// Requests older than this are forgotten when the store is opened
+const RETENTION_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Maximum length of a client supplied key
+pub const MAX_KEY_LEN: usize = 128;
+
+/// Persisted request, without a response until it completed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredRequest {
+    /// RPC the key was used with
+    rpc: String,
+    key: String,
+    /// Hash of the request, a retry must match it
+    fingerprint: String,
+    /// Hex encoded response
+    #[serde(default)]
+    response: Option<String>,
+    /// Time the key was claimed, or completed once it has a response
+    #[serde(alias = "completed_at")]
+    updated_at: u64,
+}
+
+#[derive(Debug, Clone)]
+enum Entry {
+    /// Executing in this process
+    InProgress(StoredRequest),
+    /// Was executing when the node stopped
+    Interrupted(StoredRequest),
+    /// Committed funds in this process without a stored result
+    Unresolved(StoredRequest),
+    Completed(StoredRequest),
+}
+
+impl Entry {
+    fn request(&self) -> &StoredRequest {
+        match self {
+            Entry::InProgress(request)
+            | Entry::Interrupted(request)
+            | Entry::Unresolved(request)
+            | Entry::Completed(request) => request,
+        }
+    }
+}
+
+/// Outcome of claiming a key for a request
+#[derive(Debug)]
+pub enum Claim<'a> {
+    /// First use of the key, the request should be executed while holding
+    /// the guard
+    New(ClaimGuard<'a>),
+    /// The key completed before, holds the hex encoded response
+    Completed(String),
+    /// A request with the key is still executing
+    InProgress,
+    /// A request with the key was executing when the node stopped
+    Interrupted,
+    /// A request with the key committed funds but its result is not known
+    Unresolved,
+    /// The key was used for a different request
+    Mismatch,
+}
+
+const UNCOMMITTED: u8 = 0;
+const COMMITTED: u8 = 1;
+const PENDING: u8 = 2;
+
+/// How far a request holding a key got in moving funds
+///
+/// Cloned into the request, which marks it as soon as LDK Node accepted the
+/// send or channel open, before awaiting anything else.
+#[derive(Debug, Clone, Default)]
+pub struct Commitment {
+    state: Arc<AtomicU8>,
+}
+
+impl Commitment {
+    /// Funds were committed, the key is no longer released
+    pub fn commit(&self) {
+        let _ =
+            self.state
+                .compare_exchange(UNCOMMITTED, COMMITTED, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    /// Funds were committed but the result is not final, it is not stored
+    pub fn pending(&self) {
+        self.state.store(PENDING, Ordering::SeqCst);
+    }
+
+    pub fn is_committed(&self) -> bool {
+        self.state.load(Ordering::SeqCst) != UNCOMMITTED
+    }
+
+    /// Whether a response of the request can be stored as its result
+    pub fn is_final(&self) -> bool {
+        self.state.load(Ordering::SeqCst) != PENDING
+    }
+}
+
+/// Key claimed by an executing request
+///
+/// Dropping the guard without completing it releases the key while the
+/// request has not committed funds, so it can be retried. Once it has, the
+/// key is left unresolved instead.
+#[derive(Debug)]
+pub struct ClaimGuard<'a> {
+    store: &'a IdempotencyStore,
+    rpc: String,
+    key: String,
+    fingerprint: String,
+    commitment: Commitment,
+    completed: bool,
+}
+
+impl ClaimGuard<'_> {
+    /// Commitment the request marks
+    pub fn commitment(&self) -> Commitment {
+        self.commitment.clone()
+    }
+
+    /// Store the result of the request
+    pub fn complete(mut self, response: String) -> Result<()> {
+        self.completed = true;
+        self.store
+            .complete(&self.rpc, &self.key, &self.fingerprint, response)
+    }
+}
+
+impl Drop for ClaimGuard<'_> {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+
+        if self.commitment.is_committed() {
+            self.store.unresolve(&self.rpc, &self.key);
+        } else {
+            self.store.release(&self.rpc, &self.key);
+        }
+    }
+}
+
+/// Persistent store of idempotency keys and their results
+#[derive(Debug)]
+pub struct IdempotencyStore {
+    path: PathBuf,
+    entries: Mutex<HashMap<(String, String), Entry>>,
+}
+
+impl IdempotencyStore {
+    /// Open the store in the storage directory
+    pub fn new(storage_dir: PathBuf) -> Self {
+        let path = storage_dir.join(IDEMPOTENCY_FILE_NAME);
+        let cutoff = unix_time().saturating_sub(RETENTION_SECS);
+
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .filter_map(|line| match serde_json::from_str::<StoredRequest>(line) {
+                    Ok(request) => Some(request),
+                    Err(err) => {
+                        tracing::warn!(
+                            "Skipping unreadable idempotency key in {}: {}",
+                            path.display(),
+                            err
+                        );
+                        None
+                    }
+                })
+                .filter(|request| request.updated_at >= cutoff)
+                .map(|request| {
+                    let id = (request.rpc.clone(), request.key.clone());
+                    let entry = match request.response {
+                        Some(_) => Entry::Completed(request),
+                        None => {
+                            tracing::warn!(
+                                "{} with idempotency key {} was interrupted, it may or may not have completed",
+                                request.rpc,
+                                request.key
+                            );
+                            Entry::Interrupted(request)
+                        }
+                    };
+                    (id, entry)
+                })
+                .collect(),
+            Err(_) => HashMap::new(),
+        };
+
+        // Expired keys are dropped from the file on the next write
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Claim a key for a request with the given fingerprint
+    ///
+    /// A new claim is persisted before it is returned, so the request is
+    /// known to be interrupted if the node stops while it executes.
+    pub fn claim(&self, rpc: &str, key: &str, fingerprint: &str) -> Result<Claim<'_>> {
+        let mut entries = self.entries.lock().expect("idempotency lock poisoned");
+        let id = (rpc.to_string(), key.to_string());
+
+        match entries.get(&id) {
+            Some(entry) if entry.request().fingerprint != fingerprint => Ok(Claim::Mismatch),
+            Some(Entry::InProgress(_)) => Ok(Claim::InProgress),
+            Some(Entry::Interrupted(_)) => Ok(Claim::Interrupted),
+            Some(Entry::Unresolved(_)) => Ok(Claim::Unresolved),
+            Some(Entry::Completed(request)) => Ok(Claim::Completed(
+                request.response.clone().unwrap_or_default(),
+            )),
+            None => {
+                entries.insert(
+                    id.clone(),
+                    Entry::InProgress(StoredRequest {
+                        rpc: rpc.to_string(),
+                        key: key.to_string(),
+                        fingerprint: fingerprint.to_string(),
+                        response: None,
+                        updated_at: unix_time(),
+                    }),
+                );
+
+                if let Err(err) = self.persist(&entries) {
+                    entries.remove(&id);
+                    return Err(err);
+                }
+
+                Ok(Claim::New(ClaimGuard {
+                    store: self,
+                    rpc: rpc.to_string(),
+                    key: key.to_string(),
+                    fingerprint: fingerprint.to_string(),
+                    commitment: Commitment::default(),
+                    completed: false,
+                }))
+            }
+        }
+    }
+
+    /// Store the result of a claimed request
+    fn complete(&self, rpc: &str, key: &str, fingerprint: &str, response: String) -> Result<()> {
+        let mut entries = self.entries.lock().expect("idempotency lock poisoned");
+
+        entries.insert(
+            (rpc.to_string(), key.to_string()),
+            Entry::Completed(StoredRequest {
+                rpc: rpc.to_string(),
+                key: key.to_string(),
+                fingerprint: fingerprint.to_string(),
+                response: Some(response),
+                updated_at: unix_time(),
+            }),
+        );
+
+        self.persist(&entries)
+    }
+
+    /// Keep a claimed key whose request committed funds without a result
+    ///
+    /// The persisted entry has no response, so after a restart the key is
+    /// reported as interrupted.
+    fn unresolve(&self, rpc: &str, key: &str) {
+        let mut entries = self.entries.lock().expect("idempotency lock poisoned");
+        let id = (rpc.to_string(), key.to_string());
+
+        if let Some(Entry::InProgress(request)) = entries.get(&id) {
+            tracing::warn!(
+                "{} with idempotency key {} committed funds without a final result",
+                rpc,
+                key
+            );
+            let request = request.clone();
+            entries.insert(id, Entry::Unresolved(request));
+        }
+    }
+
+    /// Release a claimed key after its request failed before committing funds
+    fn release(&self, rpc: &str, key: &str) {
+        let mut entries = self.entries.lock().expect("idempotency lock poisoned");
+        let id = (rpc.to_string(), key.to_string());
+
+        if let Some(Entry::InProgress(_)) = entries.get(&id) {
+            entries.remove(&id);
+
+            if let Err(err) = self.persist(&entries) {
+                tracing::error!(
+                    "Could not release idempotency key {} of {}: {:#}",
+                    key,
+                    rpc,
+                    err
+                );
+            }
+        }
+    }
+
+    fn persist(&self, entries: &HashMap<(String, String), Entry>) -> Result<()> {
+        let mut contents = String::new();
+        for entry in entries.values() {
+            contents.push_str(&serde_json::to_string(entry.request())?);
+            contents.push('\n');
+        }
+
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        std::fs::write(&tmp_path, contents)?;
+        std::fs::rename(&tmp_path, &self.path).with_context(|| {
+            format!(
+                "Could not write idempotency keys to {}",
+                self.path.display()
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    fn guard(claim: Claim<'_>) -> ClaimGuard<'_> {
+        match claim {
+            Claim::New(guard) => guard,
+            other => panic!("expected a new claim, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn completed_keys_return_their_response() {
+        let store = IdempotencyStore::new(temp_dir("idempotency-complete"));
+
+        let claim = guard(store.claim("send", "key", "a").unwrap());
+        assert!(matches!(
+            store.claim("send", "key", "a").unwrap(),
+            Claim::InProgress
+        ));
+        claim.complete("response".to_string()).unwrap();
+
+        assert!(matches!(
+            store.claim("send", "key", "a").unwrap(),
+            Claim::Completed(response) if response == "response"
+        ));
+        assert!(matches!(
+            store.claim("send", "key", "b").unwrap(),
+            Claim::Mismatch
+        ));
+        // Keys are scoped to their RPC
+        guard(store.claim("open", "key", "b").unwrap());
+    }
+
+    #[test]
+    fn dropped_claims_release_their_key() {
+        let store = IdempotencyStore::new(temp_dir("idempotency-release"));
+
+        drop(guard(store.claim("send", "key", "a").unwrap()));
+
+        // Released keys can be used for any request
+        guard(store.claim("send", "key", "b").unwrap());
+    }
+
+    #[test]
+    fn committed_claims_keep_their_key() {
+        let dir = temp_dir("idempotency-committed");
+        let store = IdempotencyStore::new(dir.clone());
+
+        let claim = guard(store.claim("send", "key", "a").unwrap());
+        claim.commitment().commit();
+        // The request failed or its client went away after sending
+        drop(claim);
+
+        assert!(matches!(
+            store.claim("send", "key", "a").unwrap(),
+            Claim::Unresolved
+        ));
+        assert!(matches!(
+            IdempotencyStore::new(dir)
+                .claim("send", "key", "a")
+                .unwrap(),
+            Claim::Interrupted
+        ));
+    }
+
+    #[test]
+    fn commitments_track_whether_a_result_is_final() {
+        let commitment = Commitment::default();
+        assert!(!commitment.is_committed());
+        assert!(commitment.is_final());
+
+        commitment.commit();
+        assert!(commitment.is_committed());
+        assert!(commitment.is_final());
+
+        commitment.pending();
+        commitment.commit();
+        assert!(commitment.is_committed());
+        assert!(!commitment.is_final());
+    }
+
+    #[test]
+    fn keys_are_recovered_after_a_restart() {
+        let dir = temp_dir("idempotency-restart");
+        let store = IdempotencyStore::new(dir.clone());
+
+        guard(store.claim("send", "done", "a").unwrap())
+            .complete("response".to_string())
+            .unwrap();
+        let interrupted = guard(store.claim("send", "interrupted", "a").unwrap());
+
+        // The node stops while the request executes
+        std::mem::forget(interrupted);
+
+        let store = IdempotencyStore::new(dir);
+        assert!(matches!(
+            store.claim("send", "done", "a").unwrap(),
+            Claim::Completed(response) if response == "response"
+        ));
+        assert!(matches!(
+            store.claim("send", "interrupted", "a").unwrap(),
+            Claim::Interrupted
+        ));
+        assert!(matches!(
+            store.claim("send", "interrupted", "b").unwrap(),
+            Claim::Mismatch
+        ));
+    }
+
+    #[test]
+    fn completed_keys_from_older_files_are_loaded() {
+        let dir = temp_dir("idempotency-legacy");
+        let line = serde_json::json!({
+            "rpc": "send",
+            "key": "key",
+            "fingerprint": "a",
+            "response": "response",
+            "completed_at": unix_time(),
+        });
+        std::fs::write(dir.join(IDEMPOTENCY_FILE_NAME), format!("{line}\n")).unwrap();
+
+        let store = IdempotencyStore::new(dir);
+        assert!(matches!(
+            store.claim("send", "key", "a").unwrap(),
+            Claim::Completed(response) if response == "response"
+        ));
+    }
+}
@@ -0,0 +1,136 @@
+//! Parsing of the payment identifiers received from the mint and management clients
+//!
+//! Payment ids and payment hashes are 32 bytes, hex encoded in strings. All
+//! identifiers are parsed here, so a malformed identifier is rejected with
+//! [`Error::InvalidPaymentIdentifier`] wherever it comes from.
+
+use cdk_common::payment::PaymentIdentifier;
+use cdk_common::util::hex;
+use ldk_node::lightning::ln::channelmanager::PaymentId;
+
+use crate::error::Error;
+
+/// Length of a hex encoded payment id or payment hash
+pub const HEX_ID_LEN: usize = 64;
+
+/// Parse a hex encoded 32 byte identifier
+pub fn parse_hex32(id: &str) -> Result<[u8; 32], Error> {
+    let invalid = |reason: &str| Error::InvalidPaymentIdentifier {
+        id: id.chars().take(HEX_ID_LEN).collect(),
+        reason: reason.to_string(),
+    };
+
+    if id.len() != HEX_ID_LEN {
+        return Err(invalid(&format!(
+            "expected {HEX_ID_LEN} hex characters, got {}",
+            id.len()
+        )));
+    }
+
+    hex::decode(id)
+        .map_err(|_| invalid("not hex"))?
+        .try_into()
+        .map_err(|_| invalid("not 32 bytes"))
+}
+
+/// Parse a hex encoded LDK payment id
+pub fn parse_payment_id(id: &str) -> Result<PaymentId, Error> {
+    parse_hex32(id).map(PaymentId)
+}
+
+/// LDK payment id of a payment identified by payment hash or custom id
+///
+/// LDK uses the payment hash as the id of BOLT11 payments, other payments
+/// are identified by their hex encoded payment id.
+pub fn payment_id(identifier: &PaymentIdentifier) -> Result<PaymentId, Error> {
+    match identifier {
+        PaymentIdentifier::PaymentHash(hash) => Ok(PaymentId(*hash)),
+        PaymentIdentifier::CustomId(id) => parse_payment_id(id),
+        other => Err(Error::InvalidPaymentIdentifier {
+            id: format!("{other:?}"),
+            reason: "expected a payment hash or payment id".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const HASH: &str = "0f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0";
+
+    fn is_invalid(result: Result<[u8; 32], Error>) -> bool {
+        matches!(result, Err(Error::InvalidPaymentIdentifier { .. }))
+    }
+
+    #[test]
+    fn parse_hex32_accepts_64_hex_characters() {
+        let bytes = parse_hex32(HASH).unwrap();
+        assert_eq!(hex::encode(bytes), HASH);
+    }
+
+    #[test]
+    fn parse_hex32_accepts_uppercase() {
+        let bytes = parse_hex32(&HASH.to_uppercase()).unwrap();
+        assert_eq!(hex::encode(bytes), HASH);
+    }
+
+    #[test]
+    fn parse_hex32_rejects_other_lengths() {
+        assert!(is_invalid(parse_hex32("")));
+        assert!(is_invalid(parse_hex32(&HASH[..63])));
+        assert!(is_invalid(parse_hex32(&format!("{HASH}0"))));
+    }
+
+    #[test]
+    fn parse_hex32_rejects_non_hex() {
+        let id = format!("{}zz", &HASH[..62]);
+        assert!(is_invalid(parse_hex32(&id)));
+    }
+
+    #[test]
+    fn payment_id_of_every_identifier() {
+        let bytes = parse_hex32(HASH).unwrap();
+
+        assert_eq!(
+            payment_id(&PaymentIdentifier::PaymentHash(bytes)).unwrap(),
+            PaymentId(bytes)
+        );
+        assert_eq!(
+            payment_id(&PaymentIdentifier::CustomId(HASH.to_string())).unwrap(),
+            PaymentId(bytes)
+        );
+        assert!(matches!(
+            payment_id(&PaymentIdentifier::CustomId("not an id".to_string())),
+            Err(Error::InvalidPaymentIdentifier { .. })
+        ));
+        assert!(matches!(
+            payment_id(&PaymentIdentifier::OfferId(HASH.to_string())),
+            Err(Error::InvalidPaymentIdentifier { .. })
+        ));
+        assert!(matches!(
+            payment_id(&PaymentIdentifier::Label(HASH.to_string())),
+            Err(Error::InvalidPaymentIdentifier { .. })
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn parse_hex32_never_panics(id in "\\PC*") {
+            let _ = parse_hex32(&id);
+            let _ = payment_id(&PaymentIdentifier::CustomId(id));
+        }
+
+        #[test]
+        fn parse_hex32_round_trips(bytes in any::<[u8; 32]>()) {
+            let id = hex::encode(bytes);
+            prop_assert_eq!(parse_hex32(&id).unwrap(), bytes);
+            prop_assert_eq!(
+                payment_id(&PaymentIdentifier::CustomId(id)).unwrap(),
+                PaymentId(bytes)
+            );
+        }
+    }
+}
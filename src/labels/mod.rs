@@ -0,0 +1,146 @@
+//! Labels for onchain addresses and transactions
+//!
+//! Labels are kept in the storage directory in the BIP329 JSON lines format,
+//! so they can be exported as is and imported into wallets such as Sparrow.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// File labels are kept in, inside the storage directory
+const LABELS_FILE_NAME: &str = "labels.jsonl";
+
+/// Kind of object a label is attached to, named as in BIP329
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LabelKind {
+    /// Onchain transaction, referenced by txid
+    #[serde(rename = "tx")]
+    Transaction,
+    /// Onchain address
+    #[serde(rename = "addr")]
+    Address,
+}
+
+impl fmt::Display for LabelKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LabelKind::Transaction => write!(f, "tx"),
+            LabelKind::Address => write!(f, "addr"),
+        }
+    }
+}
+
+impl FromStr for LabelKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "tx" => Ok(LabelKind::Transaction),
+            "addr" => Ok(LabelKind::Address),
+            _ => bail!("Unknown label type {s}, expected tx or addr"),
+        }
+    }
+}
+
+/// Label attached to an address or transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Label {
+    /// Kind of the labeled object
+    #[serde(rename = "type")]
+    pub kind: LabelKind,
+    /// Address or txid
+    #[serde(rename = "ref")]
+    pub reference: String,
+    /// Label text
+    pub label: String,
+}
+
+/// Persistent store of address and transaction labels
+#[derive(Debug)]
+pub struct LabelStore {
+    path: PathBuf,
+    labels: Mutex<BTreeMap<(LabelKind, String), Label>>,
+}
+
+impl LabelStore {
+    /// Open the store in the storage directory, starting empty if no labels exist
+    pub fn new(storage_dir: PathBuf) -> Self {
+        let path = storage_dir.join(LABELS_FILE_NAME);
+
+        let labels = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .filter_map(|line| match serde_json::from_str::<Label>(line) {
+                    Ok(label) => Some(((label.kind, label.reference.clone()), label)),
+                    Err(err) => {
+                        tracing::warn!("Skipping unreadable label in {}: {}", path.display(), err);
+                        None
+                    }
+                })
+                .collect(),
+            Err(_) => BTreeMap::new(),
+        };
+
+        Self {
+            path,
+            labels: Mutex::new(labels),
+        }
+    }
+
+    /// Set the label of an address or transaction, an empty label removes it
+    pub fn set(&self, kind: LabelKind, reference: String, label: String) -> Result<()> {
+        let mut labels = self.labels.lock().expect("labels lock poisoned");
+
+        let key = (kind, reference.clone());
+        if label.is_empty() {
+            labels.remove(&key);
+        } else {
+            labels.insert(
+                key,
+                Label {
+                    kind,
+                    reference,
+                    label,
+                },
+            );
+        }
+
+        self.persist(&labels)
+    }
+
+    /// Get the label of an address or transaction
+    pub fn get(&self, kind: LabelKind, reference: &str) -> Option<String> {
+        self.labels
+            .lock()
+            .expect("labels lock poisoned")
+            .get(&(kind, reference.to_string()))
+            .map(|label| label.label.clone())
+    }
+
+    /// All labels, ordered by kind and reference
+    pub fn list(&self) -> Vec<Label> {
+        self.labels
+            .lock()
+            .expect("labels lock poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    fn persist(&self, labels: &BTreeMap<(LabelKind, String), Label>) -> Result<()> {
+        let mut contents = String::new();
+        for label in labels.values() {
+            contents.push_str(&serde_json::to_string(label)?);
+            contents.push('\n');
+        }
+
+        std::fs::write(&self.path, contents)
+            .with_context(|| format!("Could not write labels to {}", self.path.display()))
+    }
+}
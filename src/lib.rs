@@ -1,69 +1,507 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-
-use anyhow::anyhow;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use abandon::{AbandonedPayment, AbandonedPayments};
+use accounting::MonthlyFees;
+use activity::{ActivityLog, ActivityRecord, ChannelReport};
+use alerts::{AlertCondition, AlertEngine, AlertSnapshot};
+use anyhow::{anyhow, bail, Context};
+use approvals::ApprovalQueue;
 use async_trait::async_trait;
+use autoclose::AutoCloseConfig;
+use backup::BackupReport;
 use cdk_common::amount::to_unit;
 use cdk_common::common::FeeReserve;
 use cdk_common::util::{hex, unix_time};
 use cdk_common::{Amount, CurrencyUnit, MeltOptions, MeltQuoteState};
+use chain::{ChainSourceHealth, WalletSyncReport};
+use deposits::{
+    Deposit, DepositEvent, DepositStatus, DepositStore, OnchainPaymentsConfig, OutputCache,
+};
+use error::Error;
+use fees::{FeeDestination, FeeRequest, FeeStrategy};
 use futures::{Stream, StreamExt};
-use ldk_node::bitcoin::hashes::Hash;
-use ldk_node::bitcoin::Network;
+use graph::GraphStatus;
+use hold::{HoldInvoice, HoldInvoiceState, HoldInvoices};
+use hooks::LifecycleHooks;
+use idempotency::IdempotencyStore;
+use labels::{Label, LabelKind, LabelStore};
+use ldk_node::bitcoin::hashes::{sha256, Hash};
+use ldk_node::bitcoin::secp256k1::PublicKey;
+use ldk_node::bitcoin::{Address, FeeRate, Network, Txid};
+use ldk_node::config::ChannelConfig;
 use ldk_node::lightning::ln::channelmanager::PaymentId;
 use ldk_node::lightning::ln::msgs::SocketAddress;
-use ldk_node::lightning_invoice::{Bolt11InvoiceDescription, Description};
-use ldk_node::lightning_types::payment::PaymentHash;
-use ldk_node::payment::{PaymentDirection, PaymentKind, PaymentStatus, SendingParameters};
-use ldk_node::{Builder, Event, Node};
+use ldk_node::lightning::ln::types::ChannelId;
+use ldk_node::lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescription, Description, Sha256};
+use ldk_node::lightning_types::payment::{PaymentHash, PaymentPreimage, PaymentSecret};
+use ldk_node::payment::{
+    ConfirmationStatus, PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus,
+    SendingParameters,
+};
+use ldk_node::{Event, Node, NodeError};
+use limits::{ManagementLimits, PaymentLimits};
+use lock::StorageLock;
+use logs::{LogBuffer, LogEntry};
+use metrics::PaymentMetrics;
+use notifications::sinks::{NodeNotification, NotificationSinks};
+use notifications::{PaymentNotification, PaymentNotifier};
+use offers::pool::OfferPool;
+use offers::InvoiceRequestPolicy;
+use onchain::{AddressIssuer, AddressType};
+use payments::in_flight::InFlightPayments;
+use payments::tracker::PaymentTracker;
+use payments::{settled_spent_msat, spent_in_unit, IndexedPayment, PaymentFilter, PaymentIndex};
+use peers::{CounterpartyChannels, PeerListing, PeerTracker};
 use proto::cdk_ldk_management_server::CdkLdkManagementServer;
 use proto::server::CdkLdkServer;
-use tokio::runtime::Runtime;
-use tokio_stream::wrappers::BroadcastStream;
+use prune::{PrunePolicy, PruneReport};
+use quotes::{QuoteCache, QuoteKey};
+use retry::{FailureReasons, RetryPolicy};
+use routes::RouteHealthConfig;
+use seed::export::{SeedExportAudit, SeedExportToken};
+use seed::NodeSeed;
+use summary::{Summary, SummaryPeriod};
+use tenants::{TenantScoped, TenantTags, Tenants};
+use tls::TlsMaterial;
+use tokio::runtime::{Handle, Runtime};
+use tokio::sync::Notify;
 use tokio_util::sync::CancellationToken;
+use tonic::transport::server::TcpIncoming;
 use tonic::transport::Server;
 use tracing::instrument;
-
+use watchdog::{PaymentWatchdog, StuckPaymentPolicy, WatchdogConfig};
+
+pub mod abandon;
+pub mod accounting;
+pub mod activity;
+pub mod alerts;
+pub mod approvals;
+pub mod autoclose;
+pub mod backup;
+pub mod bench;
+pub mod builder;
+pub mod chain;
 pub mod config;
+pub mod deposits;
+pub mod error;
+pub mod fees;
+pub mod graph;
+pub mod hold;
+pub mod hooks;
+pub mod http;
+pub mod idempotency;
+pub mod identifiers;
+pub mod labels;
+pub mod limits;
+pub mod lock;
+pub mod logs;
+pub mod metrics;
+pub mod notifications;
+pub mod offers;
+pub mod onchain;
+pub mod payments;
+pub mod peers;
+pub mod performance;
 pub mod proto;
+pub mod prune;
+pub mod quotes;
+pub mod retry;
+pub mod routes;
+pub mod seed;
+pub mod store;
+pub mod summary;
+pub mod tenants;
+#[cfg(test)]
+mod test_utils;
+pub mod tls;
 pub mod utils;
+pub mod wallet;
+pub mod watchdog;
+pub use builder::CdkLdkNodeBuilder;
 pub use cdk_common::payment::{self, *};
 
 #[derive(Clone)]
 pub struct CdkLdkNode {
     inner: Arc<Node>,
-    fee_reserve: FeeReserve,
+    fee_strategy: Arc<dyn FeeStrategy>,
     wait_invoice_cancel_token: CancellationToken,
     wait_invoice_is_active: Arc<AtomicBool>,
-    sender: tokio::sync::broadcast::Sender<WaitPaymentResponse>,
-    receiver: Arc<tokio::sync::broadcast::Receiver<WaitPaymentResponse>>,
+    notifier: Arc<PaymentNotifier>,
+    notification_sinks: Arc<NotificationSinks>,
+    invoice_request_policy: Option<Arc<dyn InvoiceRequestPolicy>>,
+    offer_pool: Option<Arc<OfferPool>>,
+    trusted_peers_0conf: Vec<PublicKey>,
     events_cancel_token: CancellationToken,
     management_service_cancel_token: Arc<CancellationToken>,
+    management_server: Arc<Mutex<Option<ManagementServer>>>,
+    management_tls_dir: Option<PathBuf>,
+    management_runtime: Option<Handle>,
+    payment_processor_tls_dir: Option<PathBuf>,
+    tls_rotation: Arc<Notify>,
+    chain_source: ChainSource,
+    chain_source_health: Arc<RwLock<ChainSourceHealth>>,
+    pathfinding: PathfindingConfig,
+    channel_config: ChannelConfig,
+    metrics: Arc<PaymentMetrics>,
+    http_cancel_token: CancellationToken,
+    state: Arc<Mutex<NodeState>>,
+    default_description: Option<String>,
+    storage_dir_path: PathBuf,
+    seed: Option<NodeSeed>,
+    seed_export_token: Option<SeedExportToken>,
+    seed_export_audit: SeedExportAudit,
+    addresses: Arc<AddressIssuer>,
+    address_type: AddressType,
+    reuse_unused_addresses: bool,
+    onchain_reserve_sat: u64,
+    labels: Arc<LabelStore>,
+    onchain_payments: Option<OnchainPaymentsConfig>,
+    deposits: Arc<DepositStore>,
+    deposit_outputs: Arc<OutputCache>,
+    idempotency: Arc<IdempotencyStore>,
+    approvals: Option<Arc<ApprovalQueue>>,
+    /// Largest payments the management API makes without an override
+    management_limits: ManagementLimits,
+    prune_policy: Option<PrunePolicy>,
+    peer_monitor_interval: Duration,
+    peer_tracker: Arc<PeerTracker>,
+    alerts: Option<Arc<AlertEngine>>,
+    activity: Arc<ActivityLog>,
+    payment_index: Arc<PaymentIndex>,
+    /// Leave preimages out of notifications, exports and management responses
+    redact_preimages: bool,
+    /// When failed melt payments are sent again
+    retry_policy: RetryPolicy,
+    /// Failure reasons from payment events, read by melt retries
+    failure_reasons: Arc<FailureReasons>,
+    /// Melts waiting for their payment to complete
+    payment_tracker: Arc<PaymentTracker>,
+    /// Payments melts are sending, so a retried melt does not pay twice
+    in_flight: Arc<InFlightPayments>,
+    /// Outgoing payments whose melts are reported failed
+    abandoned: Arc<AbandonedPayments>,
+    /// Time an outgoing payment must be pending before it can be abandoned
+    melt_cancellation_window: Duration,
+    /// Time a payment is waited for before it is reported pending
+    payment_send_timeout: Duration,
+    hold_invoices: Arc<HoldInvoices>,
+    /// Create the BOLT11 invoices of mint quotes as hold invoices
+    hold_mint_invoices: bool,
+    /// Commit to the descriptions of mint quote invoices by their hash
+    hash_descriptions: bool,
+    /// Put the descriptions wallets request in mint quote invoices
+    request_descriptions: bool,
+    /// Unit of the mint, `sat` or `msat`
+    unit: CurrencyUnit,
+    /// Expiry of mint quote payment requests when the mint does not set one
+    default_incoming_expiry_secs: u32,
+    /// Seconds mint quote payment requests expire before their quote
+    incoming_expiry_margin_secs: u32,
+    /// Tenants sharing the node, see [`tenants`]
+    tenants: Arc<Tenants>,
+    tenant_tags: Arc<TenantTags>,
+    hooks: Arc<LifecycleHooks>,
+    quote_cache: Arc<QuoteCache>,
+    log_buffer: Option<LogBuffer>,
+    /// Lock on the storage directory, held while any clone of the node exists
+    _storage_lock: Arc<StorageLock>,
+    /// Counts the handles held outside the node, `None` in the clones held
+    /// by its background tasks and servers
+    embedder_handle: Option<Arc<()>>,
 }
 
-#[derive(Debug, Clone)]
+/// Management service accepting tenant tokens, see [`CdkLdkNode::management_service`]
+pub type ManagementService = TenantScoped<CdkLdkManagementServer<CdkLdkServer>>;
+
+/// Running management server, replaced when its TLS material is rotated
+#[derive(Debug)]
+struct ManagementServer {
+    addr: SocketAddr,
+    cancel_token: CancellationToken,
+}
+
+/// Servers whose TLS material was reloaded by [`CdkLdkNode::rotate_tls`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TlsRotation {
+    pub management: bool,
+    pub payment_processor: bool,
+}
+
+/// Default interval between samples of the peer list for connection tracking
+pub const DEFAULT_PEER_MONITOR_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Expiry of incoming payment requests when the caller does not set one
+pub const DEFAULT_INCOMING_EXPIRY_SECS: u32 = 36_000;
+
+/// Minimum expiry of incoming payment requests
+pub const MIN_INCOMING_EXPIRY_SECS: u32 = 60;
+
+/// Seconds from `now` until `unix_expiry`, validated for use as a payment request expiry
+pub fn incoming_expiry_secs(unix_expiry: Option<u64>, now: u64) -> Result<u32, Error> {
+    let Some(expiry) = unix_expiry else {
+        return Ok(DEFAULT_INCOMING_EXPIRY_SECS);
+    };
+
+    expiry
+        .checked_sub(now)
+        .and_then(|secs| u32::try_from(secs).ok())
+        .filter(|secs| *secs >= MIN_INCOMING_EXPIRY_SECS)
+        .ok_or(Error::InvalidExpiry {
+            expiry,
+            now,
+            min_secs: MIN_INCOMING_EXPIRY_SECS,
+            max_secs: u32::MAX,
+        })
+}
+
+/// Seconds until a mint quote payment request expires
+///
+/// Without a quote expiry the request expires after `default_secs`.
+/// Otherwise it expires `margin_secs` before the quote, but no sooner than
+/// [`MIN_INCOMING_EXPIRY_SECS`] from now.
+pub fn mint_quote_expiry_secs(
+    unix_expiry: Option<u64>,
+    now: u64,
+    default_secs: u32,
+    margin_secs: u32,
+) -> Result<u32, Error> {
+    if unix_expiry.is_none() {
+        return Ok(default_secs);
+    }
+
+    let secs = incoming_expiry_secs(unix_expiry, now)?;
+    Ok(secs
+        .saturating_sub(margin_secs)
+        .max(MIN_INCOMING_EXPIRY_SECS))
+}
+
+/// Description for an incoming payment request
+///
+/// An empty or missing description falls back to `template`, with
+/// `{amount}` and `{unit}` replaced, or to no description.
+pub fn payment_description(
+    description: Option<String>,
+    template: Option<&str>,
+    amount: Option<Amount>,
+    unit: &CurrencyUnit,
+) -> String {
+    if let Some(description) = description.filter(|d| !d.is_empty()) {
+        return description;
+    }
+
+    match template {
+        Some(template) => template
+            .replace(
+                "{amount}",
+                &amount.map(|a| a.to_string()).unwrap_or_default(),
+            )
+            .replace("{unit}", &unit.to_string()),
+        None => String::new(),
+    }
+}
+
+/// BOLT11 invoice description committing to `description`
+///
+/// The description is committed to by its SHA256 hash when `hash` is set or
+/// when it is too long to fit in an invoice. The payer then needs the full
+/// description from elsewhere to check it against the hash.
+pub fn bolt11_description(description: String, hash: bool) -> Bolt11InvoiceDescription {
+    if !hash {
+        match Description::new(description.clone()) {
+            Ok(description) => return Bolt11InvoiceDescription::Direct(description),
+            Err(_) => tracing::debug!(
+                "Description of {} bytes is too long for an invoice, committing to its hash",
+                description.len()
+            ),
+        }
+    }
+
+    Bolt11InvoiceDescription::Hash(Sha256(sha256::Hash::hash(description.as_bytes())))
+}
+
+/// Time a payment is waited for to complete before it is reported pending,
+/// when no timeout is configured
+pub const DEFAULT_PAYMENT_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of times to re-read a received payment whose preimage is not persisted yet
+const PREIMAGE_RETRIES: usize = 3;
+
+/// Delay between reads of a received payment's preimage
+const PREIMAGE_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Lifecycle state of a [`CdkLdkNode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeState {
+    /// Built but not started yet
+    Created,
+    /// Started and serving payments
+    Running,
+    /// Stop was requested and background tasks are shutting down
+    Stopping,
+    /// Stopped, a stopped node cannot be started again
+    Stopped,
+}
+
+impl fmt::Display for NodeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeState::Created => write!(f, "created"),
+            NodeState::Running => write!(f, "running"),
+            NodeState::Stopping => write!(f, "stopping"),
+            NodeState::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BitcoinRpcConfig {
     pub host: String,
     pub port: u16,
-    pub user: String,
-    pub password: String,
+    pub auth: BitcoinRpcAuth,
 }
 
-#[derive(Debug, Clone)]
+/// Authentication method used for the bitcoind RPC
+#[derive(Clone, PartialEq, Eq)]
+pub enum BitcoinRpcAuth {
+    /// Static rpcuser/rpcpassword credentials
+    UserPass { user: String, password: String },
+    /// Cookie file written by bitcoind on startup
+    CookieFile(PathBuf),
+}
+
+impl fmt::Debug for BitcoinRpcAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitcoinRpcAuth::UserPass { user, .. } => f
+                .debug_struct("UserPass")
+                .field("user", user)
+                .field("password", &"[redacted]")
+                .finish(),
+            BitcoinRpcAuth::CookieFile(path) => f.debug_tuple("CookieFile").field(path).finish(),
+        }
+    }
+}
+
+impl BitcoinRpcAuth {
+    /// Resolve the user and password to authenticate with
+    ///
+    /// The cookie file is read on every call. LDK Node is given the
+    /// credentials once when the node is built, so after bitcoind restarts
+    /// and regenerates its cookie the node has to be restarted as well.
+    pub fn credentials(&self) -> anyhow::Result<(String, String)> {
+        match self {
+            BitcoinRpcAuth::UserPass { user, password } => Ok((user.clone(), password.clone())),
+            BitcoinRpcAuth::CookieFile(path) => {
+                let cookie = std::fs::read_to_string(path).map_err(|e| {
+                    anyhow!(
+                        "Could not read bitcoind cookie file {}: {}",
+                        path.display(),
+                        e
+                    )
+                })?;
+
+                let (user, password) = cookie
+                    .trim()
+                    .split_once(':')
+                    .ok_or(anyhow!("Invalid bitcoind cookie file {}", path.display()))?;
+
+                Ok((user.to_string(), password.to_string()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ChainSource {
     Esplora(String),
     BitcoinRpc(BitcoinRpcConfig),
 }
 
+impl fmt::Display for ChainSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainSource::Esplora(url) => write!(f, "esplora {url}"),
+            ChainSource::BitcoinRpc(BitcoinRpcConfig { host, port, .. }) => {
+                write!(f, "bitcoind rpc {host}:{port}")
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum GossipSource {
     P2P,
     RapidGossipSync(String),
 }
 
+/// Pathfinding and probing parameters passed to LDK Node
+///
+/// Unset values use the LDK Node defaults. LDK Node 0.5 does not expose the
+/// scorer's parameters or a way to turn probing off, so only these are set.
+#[derive(Debug, Clone, Default)]
+pub struct PathfindingConfig {
+    /// Multiplier applied to a channel's outbound capacity to limit what may be probed through it
+    pub probing_liquidity_limit_multiplier: Option<u64>,
+    /// Max share of a channel's capacity used by a single path, as a power of 1/2
+    pub max_channel_saturation_power_of_half: Option<u8>,
+    /// Max number of paths a payment may be split across
+    pub max_path_count: Option<u8>,
+    /// Payments below this amount are sent over a single path
+    pub single_path_below_msat: Option<u64>,
+    /// Max total CLTV expiry delta accepted for outgoing routes
+    pub max_total_cltv_expiry_delta: Option<u32>,
+}
+
+impl PathfindingConfig {
+    /// Sending parameters applied to payments that don't set their own
+    fn sending_parameters(&self) -> SendingParameters {
+        SendingParameters {
+            max_total_routing_fee_msat: None,
+            max_channel_saturation_power_of_half: self.max_channel_saturation_power_of_half,
+            max_total_cltv_expiry_delta: self.max_total_cltv_expiry_delta,
+            max_path_count: self.max_path_count,
+        }
+    }
+
+    /// Sending parameters of a payment of `amount_msat` with an optional
+    /// routing fee limit
+    ///
+    /// Payments below `single_path_below_msat` are sent over one path, unless
+    /// they are larger than `max_payable_single_path_msat`.
+    fn payment_sending_parameters(
+        &self,
+        amount_msat: u64,
+        max_total_routing_fee_msat: Option<u64>,
+        max_payable_single_path_msat: u64,
+    ) -> SendingParameters {
+        let mut params = SendingParameters {
+            max_total_routing_fee_msat: max_total_routing_fee_msat.map(Some),
+            ..self.sending_parameters()
+        };
+
+        // Payments larger than any one channel can carry must be split
+        if self
+            .single_path_below_msat
+            .is_some_and(|threshold| amount_msat < threshold)
+            && amount_msat <= max_payable_single_path_msat
+        {
+            params.max_path_count = Some(1);
+        }
+
+        params
+    }
+}
+
 impl CdkLdkNode {
+    /// Create a node with default optional settings, see [`CdkLdkNodeBuilder`]
     pub fn new(
         network: Network,
         chain_source: ChainSource,
@@ -72,164 +510,2142 @@ impl CdkLdkNode {
         fee_reserve: FeeReserve,
         listening_address: Vec<SocketAddress>,
     ) -> anyhow::Result<Self> {
-        let mut builder = Builder::new();
-        builder.set_network(network);
-        builder.set_storage_dir_path(storage_dir_path);
+        CdkLdkNodeBuilder::new(
+            network,
+            chain_source,
+            gossip_source,
+            storage_dir_path,
+            fee_reserve,
+            listening_address,
+        )
+        .build()
+    }
 
-        match chain_source {
-            ChainSource::Esplora(esplora_url) => {
-                builder.set_chain_source_esplora(esplora_url, None);
-            }
-            ChainSource::BitcoinRpc(BitcoinRpcConfig {
-                host,
-                port,
-                user,
-                password,
-            }) => {
-                builder.set_chain_source_bitcoind_rpc(host, port, user, password);
+    /// Sending parameters for a payment of `amount_msat` with an optional routing fee limit
+    ///
+    /// LDK Node ignores its configured defaults when per-payment parameters are
+    /// given, so the configured pathfinding values are merged in here.
+    fn sending_parameters(
+        &self,
+        amount_msat: u64,
+        max_total_routing_fee_msat: Option<u64>,
+    ) -> SendingParameters {
+        self.pathfinding.payment_sending_parameters(
+            amount_msat,
+            max_total_routing_fee_msat,
+            self.payment_limits().max_payable_single_path_msat,
+        )
+    }
+
+    /// Start the node
+    ///
+    /// Starting a running node is a no-op. A stopped node cannot be started
+    /// again as its background tasks have been cancelled.
+    pub fn start(&self, runtime: Option<Arc<Runtime>>) -> Result<(), Error> {
+        let mut state = self.state.lock().expect("node state lock poisoned");
+
+        match *state {
+            NodeState::Running => {
+                tracing::debug!("Node is already running");
+                return Ok(());
             }
+            NodeState::Stopping | NodeState::Stopped => return Err(Error::NodeStopped),
+            NodeState::Created => (),
+        }
+
+        match runtime {
+            Some(runtime) => self.inner.start_with_runtime(runtime)?,
+            None => self.inner.start()?,
+        };
+        let node_config = self.inner.config();
+
+        tracing::info!("Starting node with network {}", node_config.network);
+
+        tracing::info!("Node status: {:?}", self.inner.status());
+
+        let graph_status = GraphStatus::from_node(&self.inner);
+        tracing::info!(
+            "Loaded network graph with {} channels and {} nodes",
+            graph_status.num_channels,
+            graph_status.num_nodes
+        );
+
+        self.handle_events()?;
+        self.start_peer_monitor();
+        self.start_payment_indexer();
+
+        *state = NodeState::Running;
+        drop(state);
+
+        self.hooks.started(self);
+
+        Ok(())
+    }
+
+    /// Wait up to `timeout` for an outgoing payment to succeed or fail
+    ///
+    /// Completion is signalled by the payment events through the
+    /// [`PaymentTracker`], returning the melt state and the payment as of
+    /// then.
+    async fn wait_for_payment(
+        &self,
+        payment_id: PaymentId,
+        timeout: Duration,
+    ) -> anyhow::Result<(MeltQuoteState, PaymentDetails)> {
+        let completed = self.payment_tracker.register(payment_id);
+
+        let pending = self
+            .inner
+            .payment(&payment_id)
+            .ok_or(anyhow!("Payment not found"))?
+            .status
+            == PaymentStatus::Pending;
+
+        if pending && tokio::time::timeout(timeout, completed).await.is_err() {
+            tracing::warn!(
+                "Payment {} still pending after {:?}, no longer waiting",
+                payment_id,
+                timeout
+            );
         }
+        self.payment_tracker.release(&payment_id);
 
-        match gossip_source {
-            GossipSource::P2P => {
-                builder.set_gossip_source_p2p();
+        let details = self
+            .inner
+            .payment(&payment_id)
+            .ok_or(anyhow!("Payment not found"))?;
+
+        let state = match details.status {
+            PaymentStatus::Succeeded => MeltQuoteState::Paid,
+            PaymentStatus::Failed => {
+                tracing::error!("Payment {} failed", payment_id);
+                MeltQuoteState::Failed
             }
-            GossipSource::RapidGossipSync(rgs_url) => {
-                builder.set_gossip_source_rgs(rgs_url);
+            PaymentStatus::Pending => MeltQuoteState::Pending,
+        };
+
+        Ok((state, details))
+    }
+
+    /// Result of an earlier outgoing payment a retried melt is for, waiting
+    /// for it to complete if it is still pending
+    async fn join_payment(
+        &self,
+        details: PaymentDetails,
+    ) -> anyhow::Result<(MeltQuoteState, PaymentDetails)> {
+        if details.status == PaymentStatus::Pending
+            && self.abandoned.contains(&hex::encode(details.id.0))
+        {
+            return Ok((MeltQuoteState::Failed, details));
+        }
+
+        tracing::info!(
+            "Payment {} was already sent, not sending it again",
+            details.id
+        );
+        self.wait_for_payment(details.id, self.payment_send_timeout)
+            .await
+    }
+
+    /// Classify an LDK Node error creating an incoming payment request
+    ///
+    /// LDK Node does not say why it could not build an invoice or offer.
+    /// When no usable channel can receive the amount that is the likely
+    /// cause, and it is reported as such.
+    fn incoming_request_error(&self, err: NodeError, amount_msat: Option<u64>) -> Error {
+        match (err, amount_msat) {
+            (NodeError::InvalidAmount, Some(amount_msat)) => Error::InvalidAmount { amount_msat },
+            (err @ (NodeError::InvoiceCreationFailed | NodeError::OfferCreationFailed), _) => {
+                let receivable_msat = self
+                    .inner
+                    .list_channels()
+                    .iter()
+                    .filter(|channel| channel.is_usable)
+                    .map(|channel| channel.inbound_capacity_msat)
+                    .sum();
+
+                // Variable amount requests need any inbound capacity at all
+                match amount_msat.unwrap_or(1) > receivable_msat {
+                    true => Error::NoInboundLiquidity {
+                        amount_msat,
+                        receivable_msat,
+                    },
+                    false => Error::Ldk(err),
+                }
             }
+            (err, _) => Error::Ldk(err),
         }
+    }
+
+    /// Seconds until a mint quote payment request expires
+    ///
+    /// Requests expire `incoming_expiry_margin_secs` before the quote, so a
+    /// payment cannot arrive after the mint considers the quote expired.
+    fn mint_quote_expiry_secs(&self, unix_expiry: Option<u64>, now: u64) -> Result<u32, Error> {
+        mint_quote_expiry_secs(
+            unix_expiry,
+            now,
+            self.default_incoming_expiry_secs,
+            self.incoming_expiry_margin_secs,
+        )
+    }
+
+    /// Description for an incoming payment request
+    ///
+    /// An empty or missing description falls back to the configured default
+    /// description template, with `{amount}` and `{unit}` replaced.
+    pub fn payment_description(
+        &self,
+        description: Option<String>,
+        amount: Option<Amount>,
+        unit: &CurrencyUnit,
+    ) -> String {
+        payment_description(
+            description,
+            self.default_description.as_deref(),
+            amount,
+            unit,
+        )
+    }
 
-        builder.set_listening_addresses(listening_address)?;
+    /// Verify a backup of the storage directory loads and belongs to this node
+    ///
+    /// Defaults to verifying the node's own storage directory.
+    pub async fn verify_backup(&self, backup_dir: Option<PathBuf>) -> anyhow::Result<BackupReport> {
+        let backup_dir = backup_dir.unwrap_or_else(|| self.storage_dir_path.clone());
+        let network = self.inner.config().network;
+        let node_id = self.inner.node_id();
+        let passphrase = match &self.seed {
+            Some(NodeSeed::Mnemonic { passphrase, .. }) => passphrase.clone(),
+            _ => None,
+        };
 
-        builder.set_node_alias("cdk-ldk-node".to_string())?;
+        tokio::task::spawn_blocking(move || {
+            backup::verify_backup(&backup_dir, network, node_id, passphrase)
+        })
+        .await?
+    }
 
-        let node = builder.build()?;
+    /// Periodically verify the node's storage directory as a backup
+    pub fn start_backup_verification(&self, interval: Duration) {
+        let node = self.background();
+        let cancel_token = self.events_cancel_token.clone();
 
-        tracing::info!("Creating tokio channel for payment notifications");
-        let (sender, receiver) = tokio::sync::broadcast::channel(8);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
 
-        let id = node.node_id();
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    _ = interval.tick() => {
+                        match node.verify_backup(None).await {
+                            Ok(report) if report.node_id_matches => tracing::info!(
+                                "Backup verified with {} channels",
+                                report.num_channels
+                            ),
+                            Ok(report) => tracing::error!(
+                                "Backup belongs to node {} instead of {}",
+                                report.node_id,
+                                node.inner.node_id()
+                            ),
+                            Err(err) => tracing::error!("Backup verification failed: {}", err),
+                        }
+                    }
+                }
+            }
+        });
+    }
 
-        let adr = node.announcement_addresses();
+    /// Periodically probe the configured destinations and report degraded corridors
+    pub fn start_route_health_monitor(&self, config: RouteHealthConfig) {
+        let node = self.inner.clone();
+        let metrics = self.metrics.clone();
+        let cancel_token = self.events_cancel_token.clone();
 
-        tracing::info!("Created node {} with address {:?}", id, adr);
-        tracing::info!("Initialized message channels for payment notifications");
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(config.interval);
+            let mut failures: HashMap<PublicKey, u32> = HashMap::new();
 
-        Ok(Self {
-            inner: node.into(),
-            fee_reserve,
-            wait_invoice_cancel_token: CancellationToken::new(),
-            wait_invoice_is_active: Arc::new(AtomicBool::new(false)),
-            sender,
-            receiver: Arc::new(receiver),
-            events_cancel_token: CancellationToken::new(),
-            management_service_cancel_token: Arc::new(CancellationToken::new()),
-        })
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        tracing::info!("Route health monitor cancelled");
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        for destination in &config.destinations {
+                            let probe_node = node.clone();
+                            let destination = *destination;
+                            let amount_msat = config.amount_msat;
+
+                            let result = match tokio::task::spawn_blocking(move || {
+                                routes::probe_destination(&probe_node, destination, amount_msat)
+                            })
+                            .await
+                            {
+                                Ok(result) => result,
+                                Err(err) => {
+                                    tracing::error!("Route probe task failed: {}", err);
+                                    continue;
+                                }
+                            };
+
+                            metrics.record_route_probe(&result);
+
+                            let consecutive = failures.entry(destination).or_default();
+                            match &result.error {
+                                None => {
+                                    if *consecutive >= config.failure_threshold {
+                                        tracing::info!("Route to {} recovered", destination);
+                                    }
+                                    *consecutive = 0;
+                                }
+                                Some(err) => {
+                                    *consecutive += 1;
+                                    if *consecutive == config.failure_threshold {
+                                        tracing::error!(
+                                            "Route to {} degraded after {} failed probes of {} msat: {}",
+                                            destination,
+                                            consecutive,
+                                            amount_msat,
+                                            err
+                                        );
+                                    } else {
+                                        tracing::warn!("Probe to {} failed: {}", destination, err);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
     }
 
-    pub fn start(&self, runtime: Option<Arc<Runtime>>) -> anyhow::Result<()> {
-        match runtime {
-            Some(runtime) => self.inner.start_with_runtime(runtime)?,
-            None => self.inner.start()?,
-        };
-        let node_config = self.inner.config();
+    /// Periodically close idle or unusable channels according to the policy
+    pub fn start_auto_close(&self, config: AutoCloseConfig) {
+        let node = self.inner.clone();
+        let activity = self.activity.clone();
+        let cancel_token = self.events_cancel_token.clone();
 
-        tracing::info!("Starting node with network {}", node_config.network);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(config.interval);
+            // Channels already being closed are skipped until they disappear
+            let mut closing: HashSet<String> = HashSet::new();
 
-        tracing::info!("Node status: {:?}", self.inner.status());
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        tracing::info!("Channel auto close cancelled");
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        let channels = node.list_channels();
+                        closing.retain(|channel_id| {
+                            channels
+                                .iter()
+                                .any(|channel| channel.channel_id.to_string() == *channel_id)
+                        });
+
+                        let candidates =
+                            autoclose::close_candidates(&channels, &activity, &config, unix_time());
+
+                        for candidate in candidates {
+                            if closing.contains(&candidate.channel_id) {
+                                continue;
+                            }
 
-        self.handle_events()?;
+                            if config.dry_run {
+                                tracing::info!(
+                                    "Dry run: would close channel {} with {}, {}",
+                                    candidate.channel_id,
+                                    candidate.counterparty_node_id,
+                                    candidate.reason
+                                );
+                                continue;
+                            }
 
-        Ok(())
+                            match node.close_channel(
+                                &candidate.user_channel_id,
+                                candidate.counterparty_node_id,
+                            ) {
+                                Ok(()) => {
+                                    tracing::info!(
+                                        "Closing channel {} with {}, {}",
+                                        candidate.channel_id,
+                                        candidate.counterparty_node_id,
+                                        candidate.reason
+                                    );
+                                    closing.insert(candidate.channel_id);
+                                }
+                                Err(err) => tracing::warn!(
+                                    "Could not close channel {} with {}: {}",
+                                    candidate.channel_id,
+                                    candidate.counterparty_node_id,
+                                    err
+                                ),
+                            }
+                        }
+                    }
+                }
+            }
+        });
     }
 
-    pub fn start_management_service(&self, grpc_addr: SocketAddr) -> anyhow::Result<()> {
-        let management_service = CdkLdkServer::new(Arc::new(self.clone()));
+    /// Largest amounts the node can currently receive and pay
+    pub fn payment_limits(&self) -> PaymentLimits {
+        let mut limits = PaymentLimits::from_channels(&self.inner.list_channels());
+
+        // Payments are not split when pathfinding is limited to a single path
+        if self.pathfinding.max_path_count == Some(1) {
+            limits.max_payable_msat = limits.max_payable_single_path_msat;
+        }
+
+        limits
+    }
+
+    /// Seed the node keys are derived from
+    pub fn seed(&self) -> NodeSeed {
+        self.seed
+            .clone()
+            .unwrap_or_else(|| NodeSeed::SeedFile(self.storage_dir_path.join(seed::SEED_FILE_NAME)))
+    }
 
-        let cancel_token = self.management_service_cancel_token.clone();
+    /// Onchain balance in sats kept back for future force close and anchor fees
+    pub fn onchain_reserve_sat(&self) -> u64 {
+        self.onchain_reserve_sat
+    }
+
+    /// Spendable onchain balance in sats above the reserve
+    pub fn onchain_available_sat(&self) -> u64 {
+        self.inner
+            .list_balances()
+            .spendable_onchain_balance_sats
+            .saturating_sub(self.onchain_reserve_sat)
+    }
 
-        let grpc_server = Server::builder()
-            .add_service(CdkLdkManagementServer::new(management_service))
-            .serve_with_shutdown(grpc_addr, async move {
-                cancel_token.cancelled().await;
-                tracing::info!("Management service received shutdown signal");
+    /// Check spending `amount_sat` onchain leaves the reserve untouched
+    ///
+    /// Transaction fees are not known before the spend, so the fee of a spend
+    /// that exactly fits is taken from the reserve.
+    pub fn check_onchain_reserve(&self, amount_sat: u64) -> Result<(), Error> {
+        let available_sat = self.onchain_available_sat();
+
+        if amount_sat > available_sat {
+            return Err(Error::BelowOnchainReserve {
+                amount_sat,
+                available_sat,
+                reserve_sat: self.onchain_reserve_sat,
             });
+        }
 
-        tokio::spawn(grpc_server);
-        tracing::info!("Started management service on {}", grpc_addr);
         Ok(())
     }
 
-    pub fn stop_management_service(&self) -> anyhow::Result<()> {
-        tracing::info!("Stopping management service");
-        self.management_service_cancel_token.cancel();
-        tracing::info!("Management service shutdown signal sent");
-        Ok(())
+    /// Get an onchain address, falling back to the configured type and mode
+    ///
+    /// Returns the address and whether it was reused.
+    pub fn new_address(
+        &self,
+        address_type: Option<AddressType>,
+        reuse_unused: Option<bool>,
+    ) -> anyhow::Result<(Address, bool)> {
+        self.addresses.new_address(
+            &self.inner,
+            address_type.unwrap_or(self.address_type),
+            reuse_unused.unwrap_or(self.reuse_unused_addresses),
+        )
     }
 
-    pub fn stop(&self) -> anyhow::Result<()> {
-        tracing::info!("Stopping CdkLdkNode");
-        // Cancel all tokio tasks
-        tracing::info!("Cancelling event handler");
-        self.events_cancel_token.cancel();
+    /// Settings of onchain mint and melt quotes, `None` when they are disabled
+    pub fn onchain_payments(&self) -> Option<&OnchainPaymentsConfig> {
+        self.onchain_payments.as_ref()
+    }
 
-        // Stop the management service
-        self.stop_management_service()?;
+    fn require_onchain_payments(&self) -> Result<&OnchainPaymentsConfig, payment::Error> {
+        self.onchain_payments
+            .as_ref()
+            .ok_or_else(|| anyhow!("Onchain payments are not enabled").into())
+    }
 
-        // Cancel any wait_invoice streams
-        if self.is_wait_invoice_active() {
-            tracing::info!("Cancelling wait_invoice stream");
-            self.wait_invoice_cancel_token.cancel();
+    /// Issue a deposit address for an onchain mint quote
+    ///
+    /// Every quote gets a fresh address, which is the request and the lookup
+    /// id of the quote. The deposit settles once payments to the address have
+    /// the configured confirmations, see [`CdkLdkNode::check_onchain_deposit`].
+    pub fn create_incoming_onchain_request(
+        &self,
+        unit: &CurrencyUnit,
+        amount: Option<Amount>,
+        unix_expiry: Option<u64>,
+    ) -> Result<CreateIncomingPaymentResponse, payment::Error> {
+        let settings = self.require_onchain_payments()?;
+
+        let now = unix_time();
+        let expires_at = unix_expiry.unwrap_or(now + settings.deposit_expiry.as_secs());
+        if expires_at <= now {
+            return Err(anyhow!("Deposit expiry {expires_at} is in the past").into());
         }
 
-        // Stop the LDK node
-        tracing::info!("Stopping LDK node");
-        self.inner.stop()?;
-        tracing::info!("CdkLdkNode stopped successfully");
-        Ok(())
+        let amount_sat = amount
+            .map(|amount| to_unit(amount, unit, &CurrencyUnit::Sat))
+            .transpose()?
+            .map(u64::from);
+
+        // Deposits are told apart by address, so they are never reused
+        let (address, _) = self
+            .addresses
+            .new_address(&self.inner, AddressType::Bech32, false)?;
+
+        self.deposits.insert(Deposit {
+            address: address.to_string(),
+            amount_sat,
+            created_at: now,
+            expires_at,
+            payments: vec![],
+            settled: false,
+        })?;
+
+        tracing::info!("Issued deposit address {}", address);
+
+        Ok(CreateIncomingPaymentResponse {
+            request_lookup_id: PaymentIdentifier::CustomId(address.to_string()),
+            request: address.to_string(),
+            expiry: Some(expires_at),
+        })
     }
 
-    /// Handle payment received event
-    async fn handle_payment_received(
-        node: &Arc<Node>,
-        sender: &tokio::sync::broadcast::Sender<WaitPaymentResponse>,
-        payment_id: Option<PaymentId>,
-        payment_hash: PaymentHash,
-        amount_msat: u64,
-    ) {
-        tracing::info!(
-            "Received payment for hash={} of amount={} msat",
-            payment_hash,
-            amount_msat
-        );
+    /// Payments seen for a deposit address issued by the node
+    pub async fn check_onchain_deposit(
+        &self,
+        address: &str,
+    ) -> Result<DepositStatus, payment::Error> {
+        self.require_onchain_payments()?;
 
-        let payment_id = match payment_id {
-            Some(id) => id,
-            None => {
-                tracing::warn!("Received payment without payment_id");
-                return;
-            }
+        if self.deposits.get(address).is_none() {
+            return Err(anyhow!("Unknown deposit address {address}").into());
+        }
+
+        let address = onchain::parse_address(address, self.inner.config().network)?;
+
+        Ok(deposits::deposit_status(
+            &self.inner,
+            &self.chain_source,
+            &self.deposit_outputs,
+            &address,
+        )
+        .await?)
+    }
+
+    /// Check the watched deposit addresses once, crediting payments that
+    /// reached the confirmation depth
+    pub async fn watch_deposits(&self) -> anyhow::Result<()> {
+        let Some(settings) = self.onchain_payments.as_ref() else {
+            return Ok(());
         };
 
-        // Convert to sats for the response
-        let amount_sat = amount_msat / 1000;
-        let payment_id_hex = hex::encode(payment_id.0);
+        let network = self.inner.config().network;
 
-        tracing::info!(
-            "Processing payment notification: id={}, amount={}",
-            payment_id_hex,
-            amount_sat
-        );
+        for mut deposit in self.deposits.watched() {
+            let address = Address::from_str(&deposit.address)?.require_network(network)?;
+            let status = deposits::deposit_status(
+                &self.inner,
+                &self.chain_source,
+                &self.deposit_outputs,
+                &address,
+            )
+            .await?;
 
-        let payment_details = match node.payment(&payment_id) {
-            Some(details) => details,
-            None => {
-                tracing::error!("Could not find payment details for id={}", payment_id_hex);
-                return;
+            let was_settled = deposit.settled;
+            let events = deposit.track(&status, settings.min_confirmations, unix_time());
+
+            if events.is_empty() && deposit.settled == was_settled {
+                continue;
+            }
+
+            // Persist before notifying so a restart does not credit twice
+            self.deposits.insert(deposit.clone())?;
+
+            for event in events {
+                match event {
+                    DepositEvent::Credited { txid, amount_sat } => {
+                        tracing::info!(
+                            "Deposit of {} sats to {} in {} reached {} confirmations",
+                            amount_sat,
+                            deposit.address,
+                            txid,
+                            settings.min_confirmations
+                        );
+
+                        let notification = PaymentNotification {
+                            response: WaitPaymentResponse {
+                                payment_identifier: PaymentIdentifier::CustomId(
+                                    deposit.address.clone(),
+                                ),
+                                payment_amount: to_unit(
+                                    amount_sat,
+                                    &CurrencyUnit::Sat,
+                                    &self.unit,
+                                )?,
+                                unit: self.unit.clone(),
+                                payment_id: txid,
+                            },
+                            preimage: None,
+                            secret: None,
+                        };
+
+                        self.notification_sinks
+                            .dispatch(NodeNotification::payment_received(
+                                &notification,
+                                amount_sat * 1000,
+                                self.tenant_tags.tenant_of(&deposit.address),
+                            ));
+                        self.hooks.payment_received(&notification);
+                        self.notifier.notify(notification);
+                    }
+                    DepositEvent::Reorged { txid } => {
+                        tracing::warn!(
+                            "Credited deposit to {} in {} dropped below {} confirmations after a reorg",
+                            deposit.address,
+                            txid,
+                            settings.min_confirmations
+                        );
+                    }
+                    DepositEvent::Reconfirmed { txid } => {
+                        tracing::info!(
+                            "Reorged deposit to {} in {} is confirmed again",
+                            deposit.address,
+                            txid
+                        );
+                    }
+                    DepositEvent::Dropped { txid } => {
+                        tracing::warn!(
+                            "Unconfirmed deposit to {} in {} is no longer seen",
+                            deposit.address,
+                            txid
+                        );
+                    }
+                }
+            }
+
+            if deposit.settled && !was_settled {
+                tracing::debug!("Deposit address {} settled", deposit.address);
             }
+        }
+
+        Ok(())
+    }
+
+    /// Periodically check the watched deposit addresses
+    pub fn start_deposit_watcher(&self) {
+        let Some(settings) = self.onchain_payments.clone() else {
+            tracing::warn!("Onchain payments are not enabled, not watching deposits");
+            return;
         };
 
-        let (payment_identifier, payment_id) = match payment_details.kind {
+        let node = self.background();
+        let cancel_token = self.events_cancel_token.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(settings.watch_interval);
+
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        tracing::info!("Deposit watcher cancelled");
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        if let Err(err) = node.watch_deposits().await {
+                            tracing::error!("Could not check deposits: {:#}", err);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Offers handed out by the offer pool, oldest first, empty without rotation
+    pub fn pooled_offers(&self) -> Vec<offers::pool::PooledOffer> {
+        self.offer_pool
+            .as_ref()
+            .map(|pool| pool.list())
+            .unwrap_or_default()
+    }
+
+    /// Status of a deposit address as reported to the mint
+    ///
+    /// One response per payment to the address, with the amount once it is
+    /// credited and zero while it is pending or after a reorg, or a single
+    /// zero response if nothing was paid yet. `None` if the address is not a
+    /// deposit address.
+    fn deposit_payment_responses(
+        &self,
+        payment_identifier: &PaymentIdentifier,
+    ) -> Result<Option<Vec<WaitPaymentResponse>>, payment::Error> {
+        let PaymentIdentifier::CustomId(address) = payment_identifier else {
+            return Ok(None);
+        };
+        let Some(deposit) = self.deposits.get(address) else {
+            return Ok(None);
+        };
+
+        if deposit.payments.is_empty() {
+            return Ok(Some(vec![WaitPaymentResponse {
+                payment_identifier: payment_identifier.clone(),
+                payment_amount: Amount::ZERO,
+                unit: self.unit.clone(),
+                payment_id: deposit.address.clone(),
+            }]));
+        }
+
+        deposit
+            .payments
+            .iter()
+            .map(|tracked| {
+                Ok(WaitPaymentResponse {
+                    payment_identifier: payment_identifier.clone(),
+                    payment_amount: match tracked.credited && !tracked.reorged {
+                        true => to_unit(tracked.amount_sat, &CurrencyUnit::Sat, &self.unit)?,
+                        false => Amount::ZERO,
+                    },
+                    unit: self.unit.clone(),
+                    payment_id: tracked.txid.clone(),
+                })
+            })
+            .collect::<Result<_, payment::Error>>()
+            .map(Some)
+    }
+
+    /// Fee rate of onchain sends, from the chain source or the configured fallback
+    async fn onchain_fee_rate(&self, settings: &OnchainPaymentsConfig) -> FeeRate {
+        match chain::estimate_fee_rate(&self.chain_source, settings.fee_target_blocks).await {
+            Ok(Some(fee_rate)) => fee_rate,
+            Ok(None) => settings.fallback_fee_rate,
+            Err(err) => {
+                tracing::warn!("Could not estimate onchain fee rate: {:#}", err);
+                settings.fallback_fee_rate
+            }
+        }
+    }
+
+    /// Quote an onchain melt to an address
+    ///
+    /// The fee assumes a transaction of the configured size at the current
+    /// fee estimate, the actual fee depends on the inputs the wallet selects.
+    pub async fn get_onchain_payment_quote(
+        &self,
+        unit: &CurrencyUnit,
+        address: &str,
+        amount: Amount,
+    ) -> Result<PaymentQuoteResponse, payment::Error> {
+        let settings = self.require_onchain_payments()?;
+
+        let address = onchain::parse_address(address, self.inner.config().network)?;
+
+        let amount_sat = u64::from(to_unit(amount, unit, &CurrencyUnit::Sat)?);
+        let fee_rate = self.onchain_fee_rate(settings).await;
+        let fee_sat = settings.send_fee_sat(fee_rate)?;
+
+        self.check_onchain_reserve(deposits::send_total_sat(amount_sat, fee_sat)?)
+            .map_err(anyhow::Error::from)?;
+
+        Ok(PaymentQuoteResponse {
+            request_lookup_id: PaymentIdentifier::CustomId(address.to_string()),
+            amount,
+            fee: to_unit(fee_sat, &CurrencyUnit::Sat, unit)?,
+            state: MeltQuoteState::Unpaid,
+            options: None,
+        })
+    }
+
+    /// Send an onchain melt to an address
+    ///
+    /// The transaction is broadcast at the current fee estimate, failing if
+    /// the estimated fee exceeds `max_fee`. The txid is the lookup id of the
+    /// payment, which is paid once it has the configured confirmations.
+    pub async fn make_onchain_payment(
+        &self,
+        unit: &CurrencyUnit,
+        address: &str,
+        amount: Amount,
+        max_fee: Option<Amount>,
+    ) -> Result<MakePaymentResponse, payment::Error> {
+        let settings = self.require_onchain_payments()?;
+
+        let address = onchain::parse_address(address, self.inner.config().network)?;
+
+        let amount_sat = u64::from(to_unit(amount, unit, &CurrencyUnit::Sat)?);
+        let fee_rate = self.onchain_fee_rate(settings).await;
+        let estimated_fee_sat = settings.send_fee_sat(fee_rate)?;
+
+        if let Some(max_fee) = max_fee {
+            let max_fee_sat = u64::from(to_unit(max_fee, unit, &CurrencyUnit::Sat)?);
+            if estimated_fee_sat > max_fee_sat {
+                return Err(anyhow!(
+                    "Estimated fee of {estimated_fee_sat} sats exceeds the maximum of {max_fee_sat} sats"
+                )
+                .into());
+            }
+        }
+
+        self.check_onchain_reserve(deposits::send_total_sat(amount_sat, estimated_fee_sat)?)
+            .map_err(anyhow::Error::from)?;
+
+        let txid = self
+            .inner
+            .onchain_payment()
+            .send_to_address(&address, amount_sat, Some(fee_rate))
+            .map_err(|e| anyhow!("Could not send onchain payment: {e}"))?;
+
+        tracing::info!(
+            "Sent {} sats onchain to {} in {}",
+            amount_sat,
+            address,
+            txid
+        );
+
+        let fee_sat = self
+            .onchain_payment_details(&txid)
+            .and_then(|payment| payment.fee_paid_msat)
+            .map(|fee_msat| fee_msat / 1000)
+            .unwrap_or(estimated_fee_sat);
+        let total_spent_sat = deposits::send_total_sat(amount_sat, fee_sat)?;
+
+        Ok(MakePaymentResponse {
+            payment_lookup_id: PaymentIdentifier::CustomId(txid.to_string()),
+            payment_proof: Some(txid.to_string()),
+            status: MeltQuoteState::Pending,
+            total_spent: to_unit(total_spent_sat, &CurrencyUnit::Sat, unit)?,
+            unit: unit.clone(),
+        })
+    }
+
+    /// Outgoing onchain payment recorded for a transaction
+    fn onchain_payment_details(&self, txid: &Txid) -> Option<PaymentDetails> {
+        self.inner
+            .list_payments_with_filter(|payment| {
+                payment.direction == PaymentDirection::Outbound
+                    && matches!(&payment.kind, PaymentKind::Onchain { txid: id, .. } if id == txid)
+            })
+            .into_iter()
+            .next()
+    }
+
+    /// Status of an onchain melt, `None` if the txid is not an outgoing payment
+    fn onchain_payment_status(
+        &self,
+        request_lookup_id: &PaymentIdentifier,
+        txid: &Txid,
+    ) -> Result<Option<MakePaymentResponse>, payment::Error> {
+        let Some(settings) = self.onchain_payments.as_ref() else {
+            return Ok(None);
+        };
+        let Some(payment) = self.onchain_payment_details(txid) else {
+            return Ok(None);
+        };
+
+        let status = match (&payment.status, &payment.kind) {
+            (PaymentStatus::Failed, _) => MeltQuoteState::Failed,
+            (
+                _,
+                PaymentKind::Onchain {
+                    status: ConfirmationStatus::Confirmed { height, .. },
+                    ..
+                },
+            ) => {
+                let tip_height = self.inner.status().current_best_block.height;
+                match tip_height.saturating_sub(*height) + 1 >= settings.min_confirmations {
+                    true => MeltQuoteState::Paid,
+                    false => MeltQuoteState::Pending,
+                }
+            }
+            _ => MeltQuoteState::Pending,
+        };
+
+        let total_spent_msat = payment
+            .amount_msat
+            .unwrap_or_default()
+            .checked_add(payment.fee_paid_msat.unwrap_or_default())
+            .ok_or(anyhow!("Amount and fee overflow"))?;
+
+        Ok(Some(MakePaymentResponse {
+            payment_lookup_id: request_lookup_id.clone(),
+            payment_proof: Some(txid.to_string()),
+            status,
+            total_spent: spent_in_unit(total_spent_msat, &self.unit)?,
+            unit: self.unit.clone(),
+        }))
+    }
+
+    /// Label an onchain address or transaction, an empty label removes it
+    pub fn set_label(&self, kind: LabelKind, reference: &str, label: String) -> anyhow::Result<()> {
+        let reference = match kind {
+            LabelKind::Address => {
+                onchain::parse_address(reference, self.inner.config().network)?.to_string()
+            }
+            LabelKind::Transaction => Txid::from_str(reference)?.to_string(),
+        };
+
+        self.labels.set(kind, reference, label)
+    }
+
+    /// Get the label of an onchain address or transaction
+    pub fn label(&self, kind: LabelKind, reference: &str) -> Option<String> {
+        self.labels.get(kind, reference)
+    }
+
+    /// All onchain labels
+    pub fn list_labels(&self) -> Vec<Label> {
+        self.labels.list()
+    }
+
+    /// Public descriptors of the onchain wallet, for watch-only monitoring
+    pub fn wallet_descriptors(&self) -> anyhow::Result<wallet::WalletDescriptors> {
+        wallet::wallet_descriptors(&self.seed(), self.inner.config().network)
+    }
+
+    /// Token authorizing seed exports, `None` when export is disabled
+    pub fn seed_export_token(&self) -> Option<&SeedExportToken> {
+        self.seed_export_token.as_ref()
+    }
+
+    /// Audit file of seed export attempts
+    pub fn seed_export_audit(&self) -> &SeedExportAudit {
+        &self.seed_export_audit
+    }
+
+    /// Current lifecycle state of the node
+    pub fn state(&self) -> NodeState {
+        *self.state.lock().expect("node state lock poisoned")
+    }
+
+    pub fn start_management_service(&self, grpc_addr: SocketAddr) -> anyhow::Result<()> {
+        let tls = self
+            .management_tls_dir
+            .as_deref()
+            .map(TlsMaterial::load)
+            .transpose()?;
+
+        // Register the listener with, and spawn the server on, the
+        // management runtime when the service has its own
+        let _runtime = self.management_runtime.as_ref().map(Handle::enter);
+
+        let listener = std::net::TcpListener::bind(grpc_addr)?;
+        listener.set_nonblocking(true)?;
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+
+        self.serve_management(grpc_addr, listener, tls)?;
+        tracing::info!("Started management service on {}", grpc_addr);
+        Ok(())
+    }
+
+    /// Management service, for embedders mounting it on their own server
+    ///
+    /// The service accepts tenant tokens, see [`tenants`]. Mount
+    /// [`proto::health_service`] and [`proto::reflection_service`] next to it
+    /// for health checks and reflection. Unlike
+    /// [`start_management_service`](Self::start_management_service) TLS, the
+    /// listener and shutdown are left to the embedder.
+    pub fn management_service(&self) -> ManagementService {
+        Self::scoped_management_service(self.clone())
+    }
+
+    fn scoped_management_service(node: CdkLdkNode) -> ManagementService {
+        let tenants = node.tenants.clone();
+        TenantScoped::new(
+            CdkLdkManagementServer::new(CdkLdkServer::new(Arc::new(node))),
+            tenants,
+        )
+    }
+
+    /// Serve the management service on a bound listener
+    fn serve_management(
+        &self,
+        addr: SocketAddr,
+        listener: tokio::net::TcpListener,
+        tls: Option<TlsMaterial>,
+    ) -> anyhow::Result<()> {
+        let management_service = Self::scoped_management_service(self.background());
+        let reflection_service = proto::reflection_service()?;
+
+        let mut server = Server::builder();
+        if let Some(tls) = &tls {
+            server = server.tls_config(tls.server_tls_config())?;
+        }
+
+        let incoming = TcpIncoming::from_listener(listener, true, None).map_err(|e| anyhow!(e))?;
+
+        // Stopping the management service cancels every generation of the server
+        let cancel_token = self.management_service_cancel_token.child_token();
+        let shutdown = cancel_token.clone();
+
+        *self
+            .management_server
+            .lock()
+            .expect("management server lock poisoned") =
+            Some(ManagementServer { addr, cancel_token });
+
+        tokio::spawn(async move {
+            let grpc_server = server
+                .add_service(proto::health_service().await)
+                .add_service(reflection_service)
+                .add_service(management_service)
+                .serve_with_incoming_shutdown(incoming, async move {
+                    shutdown.cancelled().await;
+                    tracing::info!("Management service received shutdown signal");
+                });
+
+            if let Err(err) = grpc_server.await {
+                tracing::error!("Management service failed: {}", err);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Reload the TLS material of the management service and payment processor
+    ///
+    /// Both TLS directories are read before anything changes, so a missing or
+    /// malformed file leaves the running servers untouched. The management
+    /// service is then restarted on the same address with the new material,
+    /// requests in flight complete on the previous server. The payment
+    /// processor is restarted by the process serving it, see
+    /// [`CdkLdkNode::tls_rotated`].
+    pub fn rotate_tls(&self) -> anyhow::Result<TlsRotation> {
+        let management = self
+            .management_tls_dir
+            .as_deref()
+            .map(TlsMaterial::load)
+            .transpose()
+            .context("Management service TLS")?;
+        let payment_processor = self
+            .payment_processor_tls_dir
+            .as_deref()
+            .map(TlsMaterial::load)
+            .transpose()
+            .context("Payment processor TLS")?;
+
+        let mut rotation = TlsRotation::default();
+
+        if let Some(tls) = management {
+            let previous = self
+                .management_server
+                .lock()
+                .expect("management server lock poisoned")
+                .take();
+
+            if let Some(previous) = previous {
+                let node = self.background();
+                previous.cancel_token.cancel();
+
+                let runtime = self
+                    .management_runtime
+                    .clone()
+                    .unwrap_or_else(Handle::current);
+                runtime.spawn(async move {
+                    let restarted = match tls::bind_with_retry(previous.addr).await {
+                        Ok(listener) => node.serve_management(previous.addr, listener, Some(tls)),
+                        Err(err) => Err(err),
+                    };
+
+                    match restarted {
+                        Ok(()) => tracing::info!(
+                            "Restarted management service on {} with rotated TLS",
+                            previous.addr
+                        ),
+                        Err(err) => tracing::error!(
+                            "Could not restart management service on {}: {:#}",
+                            previous.addr,
+                            err
+                        ),
+                    }
+                });
+
+                rotation.management = true;
+            }
+        }
+
+        if payment_processor.is_some() {
+            self.tls_rotation.notify_one();
+            rotation.payment_processor = true;
+        }
+
+        Ok(rotation)
+    }
+
+    /// Wait until the payment processor TLS material was rotated
+    ///
+    /// The payment processor is served outside the node, whoever runs it
+    /// restarts it with the material from its TLS directory when this returns.
+    pub async fn tls_rotated(&self) {
+        self.tls_rotation.notified().await
+    }
+
+    /// Periodically check the active chain source and compare it against the fallbacks
+    ///
+    /// LDK Node cannot swap its chain source while running, so an unhealthy or
+    /// lagging source is reported in the node status and logged; the failover list
+    /// is applied on the next start.
+    pub fn start_chain_source_monitor(&self, fallbacks: Vec<ChainSource>, interval: Duration) {
+        let network = self.inner.config().network;
+        let chain_source = self.chain_source.clone();
+        let health = self.chain_source_health.clone();
+        let cancel_token = self.events_cancel_token.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        tracing::info!("Chain source monitor cancelled");
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        let status = chain::check_chain_source_health(&chain_source, &fallbacks, network).await;
+
+                        if let Some(err) = &status.last_error {
+                            tracing::warn!("Active chain source {} is unhealthy: {}", chain_source, err);
+                        } else if status.is_lagging() {
+                            tracing::warn!(
+                                "Active chain source {} is lagging behind fallbacks ({:?} vs {:?})",
+                                chain_source,
+                                status.tip_height,
+                                status.fallback_tip_height
+                            );
+                        }
+
+                        match health.write() {
+                            Ok(mut health) => *health = status,
+                            Err(err) => tracing::error!("Chain source health lock poisoned: {}", err),
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Sample the peer and channel lists to track connects, disconnects and channel downtime
+    fn start_peer_monitor(&self) {
+        let node = self.inner.clone();
+        let tracker = self.peer_tracker.clone();
+        let activity = self.activity.clone();
+        let cancel_token = self.events_cancel_token.clone();
+        let peer_monitor_interval = self.peer_monitor_interval;
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(peer_monitor_interval);
+
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        tracing::info!("Peer monitor cancelled");
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        let now = unix_time();
+                        tracker.update(&node.list_peers(), now);
+                        activity.observe_channels(&node.list_channels(), now);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Backfill the payment index, then refresh it with the payments updated
+    /// since the last refresh
+    fn start_payment_indexer(&self) {
+        let node = self.inner.clone();
+        let index = self.payment_index.clone();
+        let cancel_token = self.events_cancel_token.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(payments::PAYMENT_INDEX_REFRESH_INTERVAL);
+            let mut since = None;
+
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        tracing::info!("Payment indexer cancelled");
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        // Payments updated in the same second as the last refresh
+                        // are indexed again, the upsert is idempotent
+                        let payments = match since {
+                            None => node.list_payments(),
+                            Some(since) => node.list_payments_with_filter(|p| {
+                                p.latest_update_timestamp >= since
+                            }),
+                        };
+
+                        let now = unix_time();
+                        match index.upsert(&payments, now) {
+                            Ok(()) => {
+                                if since.is_none() {
+                                    tracing::info!("Indexed {} payments", payments.len());
+                                }
+                                since = payments
+                                    .iter()
+                                    .map(|p| p.latest_update_timestamp)
+                                    .max()
+                                    .or(since)
+                                    .or(Some(now));
+                            }
+                            Err(err) => tracing::warn!("Could not index payments: {:#}", err),
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Payments matching the filter, newest first, from the payment index
+    pub fn list_payments(&self, filter: &PaymentFilter) -> anyhow::Result<Vec<IndexedPayment>> {
+        self.payment_index.list(filter)
+    }
+
+    /// Check the status of many incoming payments in one pass over the payment store
+    ///
+    /// Returns a result per identifier, in the order given. Identifiers that
+    /// are unknown or not incoming payments get an error.
+    pub fn check_incoming_payment_statuses(
+        &self,
+        payment_identifiers: &[PaymentIdentifier],
+    ) -> Vec<Result<WaitPaymentResponse, payment::Error>> {
+        let payment_ids: Vec<Result<PaymentId, payment::Error>> = payment_identifiers
+            .iter()
+            .map(incoming_payment_id)
+            .collect();
+
+        let wanted: HashSet<PaymentId> = payment_ids
+            .iter()
+            .filter_map(|id| id.as_ref().ok().copied())
+            .collect();
+
+        let payments: HashMap<PaymentId, PaymentDetails> = self
+            .inner
+            .list_payments_with_filter(|p| wanted.contains(&p.id))
+            .into_iter()
+            .map(|p| (p.id, p))
+            .collect();
+
+        payment_identifiers
+            .iter()
+            .zip(payment_ids)
+            .map(|(payment_identifier, payment_id)| {
+                let payment_details = payments
+                    .get(&payment_id?)
+                    .ok_or(anyhow!("Payment not found"))?;
+
+                incoming_payment_response(payment_identifier, payment_details)
+            })
+            .collect()
+    }
+
+    /// Most recent log events at or above `min_level`, `None` if logs are not buffered
+    pub fn recent_logs(
+        &self,
+        min_level: tracing::Level,
+        limit: usize,
+        after_seq: Option<u64>,
+    ) -> Option<Vec<LogEntry>> {
+        self.log_buffer
+            .as_ref()
+            .map(|buffer| buffer.recent(min_level, limit, after_seq))
+    }
+
+    /// Utilization of the open channels over the last `period`
+    pub fn channel_report(&self, period: Duration) -> ChannelReport {
+        let now = unix_time();
+        self.activity.report(
+            &self.inner.list_channels(),
+            now.saturating_sub(period.as_secs()),
+            now,
+        )
+    }
+
+    /// Fee income and expense per UTC month between `from` and `to`, see
+    /// [`accounting`]
+    pub fn fee_breakdown(&self, from: u64, to: u64) -> anyhow::Result<Vec<MonthlyFees>> {
+        let fees_paid = self.payment_index.fees_paid_by_month(from, to)?;
+        Ok(accounting::monthly_fees(
+            self.activity.forward_fees(from, to),
+            fees_paid,
+        ))
+    }
+
+    /// Abandon an outgoing payment pending for longer than the melt
+    /// cancellation window, see [`abandon`]
+    ///
+    /// The mint is told the payment failed and releases its melt. LDK keeps
+    /// tracking the payment, it is not cancelled on the network.
+    pub fn abandon_payment(&self, payment_id: &PaymentId) -> anyhow::Result<AbandonedPayment> {
+        let payment = self
+            .inner
+            .payment(payment_id)
+            .ok_or_else(|| anyhow!("Payment not found"))?;
+
+        if payment.direction != PaymentDirection::Outbound {
+            bail!("Only outgoing payments can be abandoned");
+        }
+        if payment.status != PaymentStatus::Pending {
+            bail!("Payment is not pending");
+        }
+
+        let now = unix_time();
+        let pending_for = now.saturating_sub(payment.latest_update_timestamp);
+        if pending_for < self.melt_cancellation_window.as_secs() {
+            bail!(
+                "Payment has been pending for {}s, it can be abandoned after {}s",
+                pending_for,
+                self.melt_cancellation_window.as_secs()
+            );
+        }
+
+        let abandoned = self.abandoned.abandon(AbandonedPayment {
+            payment_id: hex::encode(payment_id.0),
+            amount_msat: payment.amount_msat,
+            abandoned_at: now,
+        })?;
+
+        tracing::warn!(
+            "Abandoned payment {} pending for {}s, its melt is reported failed",
+            abandoned.payment_id,
+            pending_for
+        );
+
+        Ok(abandoned)
+    }
+
+    /// Create a hold invoice, see [`hold`]
+    ///
+    /// The node generates the preimage unless `payment_hash` is given, in
+    /// which case the preimage has to be supplied to settle the payment.
+    pub fn create_hold_invoice(
+        &self,
+        amount_msat: Option<u64>,
+        description: &Bolt11InvoiceDescription,
+        expiry_secs: u32,
+        payment_hash: Option<PaymentHash>,
+    ) -> anyhow::Result<Bolt11Invoice> {
+        let (payment_hash, preimage) = match payment_hash {
+            Some(payment_hash) => (payment_hash, None),
+            None => {
+                let preimage: [u8; 32] = ldk_node::bitcoin::secp256k1::rand::random();
+                let payment_hash = PaymentHash(sha256::Hash::hash(&preimage).to_byte_array());
+                (payment_hash, Some(hex::encode(preimage)))
+            }
+        };
+        let id = hex::encode(payment_hash.0);
+
+        if self.hold_invoices.get(&id).is_some() {
+            bail!("A hold invoice for payment hash {id} exists already");
+        }
+
+        // Recorded first so a payment is never held without its record
+        let now = unix_time();
+        self.hold_invoices.insert(HoldInvoice {
+            payment_hash: id.clone(),
+            preimage,
+            amount_msat,
+            state: HoldInvoiceState::Open,
+            claimable_amount_msat: None,
+            claim_deadline: None,
+            created_at: now,
+            updated_at: now,
+        })?;
+
+        let bolt11_payment = self.inner.bolt11_payment();
+        let invoice = match amount_msat {
+            Some(amount_msat) => {
+                bolt11_payment.receive_for_hash(amount_msat, description, expiry_secs, payment_hash)
+            }
+            None => bolt11_payment.receive_variable_amount_for_hash(
+                description,
+                expiry_secs,
+                payment_hash,
+            ),
+        };
+
+        match invoice {
+            Ok(invoice) => {
+                tracing::info!("Created hold invoice {}", id);
+                Ok(invoice)
+            }
+            Err(err) => {
+                self.hold_invoices.update(&id, unix_time(), |invoice| {
+                    invoice.state = HoldInvoiceState::Cancelled
+                })?;
+                Err(anyhow!("Could not create hold invoice: {err}"))
+            }
+        }
+    }
+
+    /// Settle a held payment, claiming its HTLCs
+    ///
+    /// The preimage is required for hold invoices created for a payment hash,
+    /// the node uses its own otherwise.
+    pub fn settle_hold_invoice(
+        &self,
+        payment_hash: PaymentHash,
+        preimage: Option<PaymentPreimage>,
+    ) -> anyhow::Result<HoldInvoice> {
+        let id = hex::encode(payment_hash.0);
+        let invoice = self.hold_invoice(&id)?;
+
+        if invoice.state != HoldInvoiceState::Accepted {
+            bail!(
+                "Hold invoice {} is {}, only accepted payments can be settled",
+                id,
+                invoice.state.as_str()
+            );
+        }
+
+        let preimage = match (preimage, &invoice.preimage) {
+            (Some(preimage), _) => preimage,
+            (None, Some(preimage)) => PaymentPreimage(identifiers::parse_hex32(preimage)?),
+            (None, None) => {
+                bail!("Hold invoice {id} was created for a payment hash, its preimage is required")
+            }
+        };
+
+        if sha256::Hash::hash(&preimage.0).to_byte_array() != payment_hash.0 {
+            bail!("Preimage does not match payment hash {id}");
+        }
+
+        let claimable_amount_msat = invoice
+            .claimable_amount_msat
+            .ok_or_else(|| anyhow!("Hold invoice {id} has no claimable amount"))?;
+
+        self.inner
+            .bolt11_payment()
+            .claim_for_hash(payment_hash, claimable_amount_msat, preimage)
+            .map_err(|e| anyhow!("Could not settle hold invoice {id}: {e}"))?;
+
+        tracing::info!(
+            "Settled hold invoice {} for {} msat",
+            id,
+            claimable_amount_msat
+        );
+
+        self.hold_invoices
+            .update(&id, unix_time(), |invoice| {
+                invoice.state = HoldInvoiceState::Settled
+            })?
+            .ok_or_else(|| anyhow!("No hold invoice for payment hash {id}"))
+    }
+
+    /// Cancel a hold invoice, failing held HTLCs back to the payer
+    pub fn cancel_hold_invoice(&self, payment_hash: PaymentHash) -> anyhow::Result<HoldInvoice> {
+        let id = hex::encode(payment_hash.0);
+        let invoice = self.hold_invoice(&id)?;
+
+        if !matches!(
+            invoice.state,
+            HoldInvoiceState::Open | HoldInvoiceState::Accepted
+        ) {
+            bail!("Hold invoice {} is {}", id, invoice.state.as_str());
+        }
+
+        self.inner
+            .bolt11_payment()
+            .fail_for_hash(payment_hash)
+            .map_err(|e| anyhow!("Could not cancel hold invoice {id}: {e}"))?;
+
+        tracing::info!("Cancelled hold invoice {}", id);
+
+        self.hold_invoices
+            .update(&id, unix_time(), |invoice| {
+                invoice.state = HoldInvoiceState::Cancelled
+            })?
+            .ok_or_else(|| anyhow!("No hold invoice for payment hash {id}"))
+    }
+
+    /// Hold invoices, newest first
+    ///
+    /// Payments LDK failed back at their claim deadline are reported cancelled.
+    pub fn list_hold_invoices(&self) -> Vec<HoldInvoice> {
+        let mut invoices = self.hold_invoices.list();
+
+        for invoice in invoices.iter_mut().filter(|invoice| {
+            matches!(
+                invoice.state,
+                HoldInvoiceState::Open | HoldInvoiceState::Accepted
+            )
+        }) {
+            let failed = identifiers::parse_hex32(&invoice.payment_hash)
+                .ok()
+                .and_then(|hash| self.inner.payment(&PaymentId(hash)))
+                .is_some_and(|payment| payment.status == PaymentStatus::Failed);
+            if failed {
+                invoice.state = HoldInvoiceState::Cancelled;
+            }
+        }
+
+        invoices
+    }
+
+    fn hold_invoice(&self, payment_hash: &str) -> anyhow::Result<HoldInvoice> {
+        self.hold_invoices
+            .get(payment_hash)
+            .ok_or_else(|| anyhow!("No hold invoice for payment hash {payment_hash}"))
+    }
+
+    /// Whether preimages are left out of notifications, exports and
+    /// management responses
+    pub fn redact_preimages(&self) -> bool {
+        self.redact_preimages
+    }
+
+    /// Index of payment records for listings and ledger exports
+    pub fn payment_index(&self) -> &Arc<PaymentIndex> {
+        &self.payment_index
+    }
+
+    /// Tenants sharing the node
+    pub fn tenants(&self) -> &Arc<Tenants> {
+        &self.tenants
+    }
+
+    /// Tenant tags of invoices, offers, deposit addresses and payments
+    pub fn tenant_tags(&self) -> &Arc<TenantTags> {
+        &self.tenant_tags
+    }
+
+    /// Idempotency keys of fund-moving management requests
+    pub fn idempotency_store(&self) -> &IdempotencyStore {
+        &self.idempotency
+    }
+
+    /// Withdrawals waiting for approval, `None` when approvals are disabled
+    pub fn approval_queue(&self) -> Option<&ApprovalQueue> {
+        self.approvals.as_deref()
+    }
+
+    /// Largest payments the management API makes without an override
+    pub fn management_limits(&self) -> &ManagementLimits {
+        &self.management_limits
+    }
+
+    /// Directory the node keeps its state in
+    pub fn storage_dir_path(&self) -> &Path {
+        &self.storage_dir_path
+    }
+
+    /// Retention policy for payment records and activity history, if configured
+    pub fn prune_policy(&self) -> Option<&PrunePolicy> {
+        self.prune_policy.as_ref()
+    }
+
+    /// Prune payment records and activity history older than the policy's retentions
+    ///
+    /// Pruned payment records are appended to the policy's export directory
+    /// first. A dry run only counts the records that would be pruned.
+    pub fn prune(&self, policy: &PrunePolicy, dry_run: bool) -> anyhow::Result<PruneReport> {
+        let now = unix_time();
+        let mut report = PruneReport {
+            dry_run,
+            ..Default::default()
+        };
+
+        if let Some(retention) = policy.payment_retention {
+            let cutoff = prune::cutoff(now, retention);
+            report.payments_pruned = prune::prune_payments(
+                &self.inner,
+                cutoff,
+                &policy.export_dir,
+                self.redact_preimages,
+                dry_run,
+            )?;
+            report.export_path = Some(policy.export_dir.join(prune::PRUNED_PAYMENTS_FILE_NAME));
+
+            if !dry_run {
+                self.payment_index.remove_final_before(cutoff)?;
+            }
+        }
+
+        if let Some(retention) = policy.activity_retention {
+            report.activity_records_pruned = self
+                .activity
+                .prune(prune::cutoff(now, retention), dry_run)?;
+        }
+
+        if !dry_run {
+            tracing::info!(
+                "Pruned {} payment records and {} activity records",
+                report.payments_pruned,
+                report.activity_records_pruned
+            );
+        }
+
+        Ok(report)
+    }
+
+    /// Periodically prune with the configured retention policy
+    pub fn start_pruning(&self) {
+        let Some(policy) = self.prune_policy.clone() else {
+            tracing::warn!("Scheduled pruning needs a retention policy, not starting");
+            return;
+        };
+
+        let node = self.background();
+        let cancel_token = self.events_cancel_token.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(policy.interval);
+
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        tracing::info!("Scheduled pruning cancelled");
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        if let Err(err) = node.prune(&policy, false) {
+                            tracing::error!("Scheduled pruning failed: {}", err);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Periodically evaluate the alert rules, dispatching raised and resolved
+    /// alerts to the notification sinks
+    pub fn start_alerts(&self) {
+        let Some(engine) = self.alerts.clone() else {
+            tracing::warn!("No alert rules configured, not starting alerts");
+            return;
+        };
+
+        let node = self.inner.clone();
+        let tracker = self.peer_tracker.clone();
+        let sinks = self.notification_sinks.clone();
+        let cancel_token = self.events_cancel_token.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(engine.config().interval);
+
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        tracing::info!("Alerts cancelled");
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        let balances = node.list_balances();
+                        let peers = engine
+                            .config()
+                            .rules
+                            .iter()
+                            .filter_map(|rule| match &rule.condition {
+                                AlertCondition::PeerDisconnected { node_id, .. } => {
+                                    Some((*node_id, tracker.stats(node_id)))
+                                }
+                                _ => None,
+                            })
+                            .collect();
+
+                        let snapshot = AlertSnapshot {
+                            onchain_balance_sat: balances.total_onchain_balance_sats,
+                            lightning_balance_sat: balances.total_lightning_balance_sats,
+                            peers,
+                        };
+
+                        for alert in engine.evaluate(&snapshot, unix_time()) {
+                            sinks.dispatch(alert);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Summary of the node's activity over the last `period`
+    pub fn summary(&self, period: Duration) -> Summary {
+        let now = unix_time();
+        let period_start = now.saturating_sub(period.as_secs());
+        let balances = self.inner.list_balances();
+
+        Summary::new(
+            self.activity.totals(period_start, now),
+            period_start,
+            now,
+            balances.total_onchain_balance_sats,
+            balances.total_lightning_balance_sats,
+        )
+    }
+
+    /// Write a summary to disk and dispatch it to the notification sinks at
+    /// the end of every period
+    pub fn start_summaries(&self, period: SummaryPeriod) {
+        let node = self.background();
+        let cancel_token = self.events_cancel_token.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period.duration());
+            // The first tick completes immediately, there is nothing to summarise yet
+            interval.tick().await;
+
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        tracing::info!("Summaries cancelled");
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        let summary = node.summary(period.duration());
+                        if let Err(err) = summary::write_summary(&node.storage_dir_path, &summary) {
+                            tracing::warn!("Could not write summary: {:#}", err);
+                        }
+                        node.notification_sinks.dispatch(NodeNotification::Summary(summary));
+                    }
+                }
+            }
+        });
+    }
+
+    /// Flag outgoing payments pending for too long and, with the abandon
+    /// policy, abandon them once the melt cancellation window has passed
+    ///
+    /// See [`watchdog`].
+    pub fn start_payment_watchdog(&self, config: WatchdogConfig) {
+        let node = self.background();
+        let cancel_token = self.events_cancel_token.clone();
+        let watchdog = PaymentWatchdog::new(config);
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(watchdog.config().interval);
+
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        tracing::info!("Payment watchdog cancelled");
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        // Abandoned payments stay pending in LDK but no longer hold a melt
+                        let pending = node.inner.list_payments_with_filter(|p| {
+                            p.direction == PaymentDirection::Outbound
+                                && p.status == PaymentStatus::Pending
+                                && !node.abandoned.contains(&hex::encode(p.id.0))
+                        });
+
+                        let now = unix_time();
+                        let stuck =
+                            watchdog::stuck_payments(&pending, watchdog.config().stuck_after, now);
+
+                        for alert in watchdog.check(&stuck, now) {
+                            node.notification_sinks.dispatch(alert);
+                        }
+
+                        if watchdog.config().policy != StuckPaymentPolicy::Abandon {
+                            continue;
+                        }
+
+                        for payment in stuck
+                            .iter()
+                            .filter(|p| p.pending_for >= node.melt_cancellation_window.as_secs())
+                        {
+                            let Some(payment_id) = pending
+                                .iter()
+                                .find(|p| hex::encode(p.id.0) == payment.payment_id)
+                                .map(|p| p.id)
+                            else {
+                                continue;
+                            };
+
+                            if let Err(err) = node.abandon_payment(&payment_id) {
+                                tracing::warn!(
+                                    "Could not abandon stuck payment {}: {:#}",
+                                    payment.payment_id,
+                                    err
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Peers of the node with their channels and connection history
+    pub fn list_peers(&self) -> Vec<PeerListing> {
+        peers::list_peers(&self.inner, &self.peer_tracker)
+    }
+
+    /// Channel capacity, spendable balances and reserves per counterparty
+    pub fn counterparty_channels(&self) -> Vec<CounterpartyChannels> {
+        peers::counterparty_channels(&self.inner.list_channels())
+    }
+
+    /// Sync the onchain and Lightning wallets now instead of waiting for the next interval
+    ///
+    /// LDK Node syncs both wallets in one call, so a single duration is reported.
+    pub async fn sync_wallets(&self) -> Result<WalletSyncReport, Error> {
+        let node = self.inner.clone();
+        let started = std::time::Instant::now();
+
+        tokio::task::spawn_blocking(move || node.sync_wallets())
+            .await
+            .map_err(anyhow::Error::from)??;
+
+        let duration = started.elapsed();
+        let status = self.inner.status();
+
+        tracing::info!(
+            "Synced wallets to height {} in {:?}",
+            status.current_best_block.height,
+            duration
+        );
+
+        Ok(WalletSyncReport {
+            best_block_height: status.current_best_block.height,
+            best_block_hash: status.current_best_block.block_hash,
+            duration,
+            latest_onchain_wallet_sync_timestamp: status.latest_onchain_wallet_sync_timestamp,
+            latest_lightning_wallet_sync_timestamp: status.latest_lightning_wallet_sync_timestamp,
+        })
+    }
+
+    /// Wait until the network graph has data for pathfinding or the timeout elapses
+    pub async fn warm_up_graph(&self, timeout: Duration) -> GraphStatus {
+        let start = std::time::Instant::now();
+
+        loop {
+            let graph_status = GraphStatus::from_node(&self.inner);
+
+            if graph_status.is_ready() || start.elapsed() >= timeout {
+                return graph_status;
+            }
+
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    /// Active chain source
+    pub fn chain_source(&self) -> &ChainSource {
+        &self.chain_source
+    }
+
+    /// Health of the active chain source as of the last check
+    pub fn chain_source_health(&self) -> ChainSourceHealth {
+        self.chain_source_health
+            .read()
+            .map(|health| health.clone())
+            .unwrap_or_default()
+    }
+
+    /// Start the HTTP server exposing the metrics endpoint and optionally the status page
+    pub fn start_http_server(&self, addr: SocketAddr, status_page: bool) -> anyhow::Result<()> {
+        let node = Arc::new(self.background());
+        let cancel_token = self.http_cancel_token.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = http::serve(node, addr, status_page, cancel_token).await {
+                tracing::error!("HTTP server stopped with error: {}", err);
+            }
+        });
+
+        Ok(())
+    }
+
+    pub fn stop_management_service(&self) -> anyhow::Result<()> {
+        tracing::info!("Stopping management service");
+        self.management_service_cancel_token.cancel();
+        tracing::info!("Management service shutdown signal sent");
+        Ok(())
+    }
+
+    /// Stop the node
+    ///
+    /// Stopping a stopped node is a no-op. Stopping a node that was never
+    /// started cancels its background tasks without touching LDK Node.
+    ///
+    /// Stopping LDK Node blocks until its background tasks have finished, use
+    /// [`CdkLdkNode::shutdown`] from async code.
+    pub fn stop(&self) -> Result<(), Error> {
+        let Some(was_running) = self.begin_stop() else {
+            return Ok(());
+        };
+
+        let result = match was_running {
+            true => {
+                tracing::info!("Stopping LDK node");
+                self.inner.stop()
+            }
+            false => Ok(()),
+        };
+
+        self.set_state(NodeState::Stopped);
+        self.hooks.shutdown();
+        result?;
+
+        tracing::info!("CdkLdkNode stopped successfully");
+        Ok(())
+    }
+
+    /// Stop the node without blocking the async runtime
+    ///
+    /// Same as [`CdkLdkNode::stop`] but LDK Node is stopped on a blocking task.
+    pub async fn shutdown(&self) -> Result<(), Error> {
+        let Some(was_running) = self.begin_stop() else {
+            return Ok(());
+        };
+
+        let result = match was_running {
+            true => {
+                tracing::info!("Stopping LDK node");
+                let node = self.inner.clone();
+                tokio::task::spawn_blocking(move || node.stop())
+                    .await
+                    .map_err(|err| anyhow!("LDK node stop task failed: {}", err))?
+            }
+            false => Ok(()),
+        };
+
+        self.set_state(NodeState::Stopped);
+        self.hooks.shutdown();
+        result?;
+
+        tracing::info!("CdkLdkNode stopped successfully");
+        Ok(())
+    }
+
+    /// Move the node to [`NodeState::Stopping`] and signal its background tasks
+    ///
+    /// Returns whether LDK Node was running and needs to be stopped, or `None`
+    /// when the node is already stopping or stopped.
+    fn begin_stop(&self) -> Option<bool> {
+        let mut state = self.state.lock().expect("node state lock poisoned");
+
+        let was_running = match *state {
+            NodeState::Stopping | NodeState::Stopped => {
+                tracing::debug!("Node is already {}", *state);
+                return None;
+            }
+            NodeState::Created => false,
+            NodeState::Running => true,
+        };
+
+        *state = NodeState::Stopping;
+        drop(state);
+
+        tracing::info!("Stopping CdkLdkNode");
+        self.cancel_tasks();
+
+        Some(was_running)
+    }
+
+    /// Clone of the node for a background task, which does not keep the
+    /// node from shutting down when the embedder drops it
+    fn background(&self) -> Self {
+        Self {
+            embedder_handle: None,
+            ..self.clone()
+        }
+    }
+
+    fn set_state(&self, new_state: NodeState) {
+        *self.state.lock().expect("node state lock poisoned") = new_state;
+    }
+
+    /// Signal all background tasks to stop
+    fn cancel_tasks(&self) {
+        tracing::info!("Cancelling event handler");
+        self.events_cancel_token.cancel();
+
+        tracing::info!("Stopping management service");
+        self.management_service_cancel_token.cancel();
+
+        // Stop the HTTP server
+        self.http_cancel_token.cancel();
+
+        // Cancel any wait_invoice streams
+        if self.is_wait_invoice_active() {
+            tracing::info!("Cancelling wait_invoice stream");
+            self.wait_invoice_cancel_token.cancel();
+        }
+    }
+
+    /// Handle payment received event
+    ///
+    /// LDK may not have persisted the preimage yet when the event fires. The
+    /// notification then waits for it in a separate task, so the event loop
+    /// keeps handling events meanwhile. If the node stops before such a
+    /// notification is queued, the mint still finds the payment when it
+    /// checks the quote.
+    #[allow(clippy::too_many_arguments)]
+    fn handle_payment_received(
+        node: &Arc<Node>,
+        notifier: &Arc<PaymentNotifier>,
+        sinks: &Arc<NotificationSinks>,
+        hooks: &Arc<LifecycleHooks>,
+        tenant_tags: &Arc<TenantTags>,
+        unit: &CurrencyUnit,
+        payment_id: Option<PaymentId>,
+        payment_hash: PaymentHash,
+        amount_msat: u64,
+    ) {
+        tracing::info!(
+            "Received payment for hash={} of amount={} msat",
+            payment_hash,
+            amount_msat
+        );
+
+        let payment_id = match payment_id {
+            Some(id) => id,
+            None => {
+                tracing::warn!("Received payment without payment_id");
+                return;
+            }
+        };
+
+        let payment_id_hex = hex::encode(payment_id.0);
+
+        tracing::info!(
+            "Processing payment notification: id={}, amount={} msat",
+            payment_id_hex,
+            amount_msat
+        );
+
+        let payment_details = match node.payment(&payment_id) {
+            Some(details) => details,
+            None => {
+                tracing::error!("Could not find payment details for id={}", payment_id_hex);
+                return;
+            }
+        };
+
+        let (preimage, secret) = Self::payment_secrets(&payment_details.kind);
+
+        let notifier = notifier.clone();
+        let sinks = sinks.clone();
+        let hooks = hooks.clone();
+        let tenant_tags = tenant_tags.clone();
+        let unit = unit.clone();
+        let notify = move |preimage: Option<PaymentPreimage>| {
+            if preimage.is_none() {
+                tracing::debug!(
+                    "Preimage for payment id={} is not available",
+                    payment_id_hex
+                );
+            }
+
+            let Some(notification) = Self::payment_notification(
+                &payment_details.kind,
+                amount_msat,
+                preimage,
+                secret,
+                &unit,
+            ) else {
+                return;
+            };
+
+            let tenant = tenant_tags.tenant_of_payment(&notification.response);
+            sinks.dispatch(NodeNotification::payment_received(
+                &notification,
+                amount_msat,
+                tenant,
+            ));
+            hooks.payment_received(&notification);
+            notifier.notify(notification);
+            tracing::info!("Queued payment notification for consumers");
+        };
+
+        if preimage.is_some() {
+            notify(preimage);
+            return;
+        }
+
+        let node = node.clone();
+        tokio::spawn(async move {
+            let mut preimage = None;
+            for _ in 0..PREIMAGE_RETRIES {
+                tokio::time::sleep(PREIMAGE_RETRY_INTERVAL).await;
+                preimage = node
+                    .payment(&payment_id)
+                    .and_then(|details| Self::payment_secrets(&details.kind).0);
+                if preimage.is_some() {
+                    break;
+                }
+            }
+
+            notify(preimage);
+        });
+    }
+
+    /// Close zero-conf inbound channels from peers that are not trusted LSPs
+    ///
+    /// LDK Node only accepts zero-conf channels from its trusted peers, this
+    /// enforces it again when a channel becomes usable before its funding
+    /// transaction confirmed.
+    fn check_zero_conf_channel(
+        node: &Node,
+        sinks: &NotificationSinks,
+        trusted_peers_0conf: &[PublicKey],
+        channel_id: &ChannelId,
+    ) {
+        let Some(channel) = node
+            .list_channels()
+            .into_iter()
+            .find(|channel| channel.channel_id == *channel_id)
+        else {
+            return;
+        };
+
+        if channel.is_outbound || channel.confirmations_required != Some(0) {
+            return;
+        }
+
+        let counterparty = channel.counterparty_node_id;
+        let accepted = trusted_peers_0conf.contains(&counterparty);
+
+        if accepted {
+            tracing::info!(
+                "Accepted zero-conf channel {} from trusted LSP {}",
+                channel_id,
+                counterparty
+            );
+        } else {
+            tracing::warn!(
+                "Rejecting zero-conf channel {} from untrusted peer {}",
+                channel_id,
+                counterparty
+            );
+            if let Err(err) = node.close_channel(&channel.user_channel_id, counterparty) {
+                tracing::error!(
+                    "Could not close zero-conf channel {} from {}: {}",
+                    channel_id,
+                    counterparty,
+                    err
+                );
+            }
+        }
+
+        sinks.dispatch(NodeNotification::ZeroConfChannel {
+            channel_id: channel_id.to_string(),
+            counterparty_node_id: counterparty.to_string(),
+            accepted,
+            timestamp: unix_time(),
+        });
+    }
+
+    /// Notification for a received payment, `None` for unsupported payment kinds
+    ///
+    /// Every supported payment is reported: LDK has already claimed it, so a
+    /// payment left out would be kept by the node without the mint crediting
+    /// it, see [`offers`].
+    fn payment_notification(
+        kind: &PaymentKind,
+        amount_msat: u64,
+        preimage: Option<PaymentPreimage>,
+        secret: Option<PaymentSecret>,
+        unit: &CurrencyUnit,
+    ) -> Option<PaymentNotification> {
+        let (payment_identifier, payment_id) = match kind {
             PaymentKind::Bolt11 { hash, .. } => {
                 (PaymentIdentifier::PaymentHash(hash.0), hash.to_string())
             }
@@ -240,36 +2656,131 @@ impl CdkLdkNode {
                 ),
                 None => {
                     tracing::error!("Bolt12 payment missing hash");
-                    return;
+                    return None;
                 }
             },
+            // Keysend payments have no invoice, the sender picks the preimage
+            // and LDK identifies the payment by its hash
+            PaymentKind::Spontaneous { hash, .. } => {
+                (PaymentIdentifier::PaymentHash(hash.0), hash.to_string())
+            }
             k => {
                 tracing::warn!("Received payment of kind {:?} which is not supported", k);
-                return;
+                return None;
+            }
+        };
+
+        // Reported in the mint's unit, msat mints keep sub-sat amounts
+        let payment_amount = match to_unit(amount_msat, &CurrencyUnit::Msat, unit) {
+            Ok(amount) => amount,
+            Err(err) => {
+                tracing::error!(
+                    "Could not convert received {} msat to {}: {}",
+                    amount_msat,
+                    unit,
+                    err
+                );
+                return None;
             }
         };
 
         let wait_payment_response = WaitPaymentResponse {
             payment_identifier,
-            payment_amount: amount_sat.into(),
-            unit: CurrencyUnit::Sat,
+            payment_amount,
+            unit: unit.clone(),
             payment_id,
         };
 
-        match sender.send(wait_payment_response) {
-            Ok(_) => tracing::info!("Successfully sent payment notification to stream"),
-            Err(err) => tracing::error!(
-                "Could not send payment received notification on channel: {}",
-                err
-            ),
+        Some(PaymentNotification {
+            response: wait_payment_response,
+            preimage: preimage.map(|p| p.to_string()),
+            secret: secret.map(|s| hex::encode(s.0)),
+        })
+    }
+
+    /// Reconciler rebuilding notifications for payments a lagging consumer missed
+    ///
+    /// Reads every incoming payment that succeeded since the given time from
+    /// the LDK payment store.
+    pub(crate) fn payment_reconciler(
+        node: Arc<Node>,
+        metrics: Arc<PaymentMetrics>,
+        unit: CurrencyUnit,
+    ) -> notifications::Reconciler {
+        Box::new(move |since, missed| {
+            metrics.record_notification_lag(missed);
+
+            node.list_payments_with_filter(|p| {
+                p.direction == PaymentDirection::Inbound
+                    && p.status == PaymentStatus::Succeeded
+                    && p.latest_update_timestamp >= since
+            })
+            .into_iter()
+            .filter_map(|details| {
+                let (preimage, secret) = Self::payment_secrets(&details.kind);
+                Self::payment_notification(
+                    &details.kind,
+                    details.amount_msat.unwrap_or_default(),
+                    preimage,
+                    secret,
+                    &unit,
+                )
+            })
+            .collect()
+        })
+    }
+
+    /// Preimage and secret of a received payment, when LDK knows them
+    fn payment_secrets(kind: &PaymentKind) -> (Option<PaymentPreimage>, Option<PaymentSecret>) {
+        match kind {
+            PaymentKind::Bolt11 {
+                preimage, secret, ..
+            }
+            | PaymentKind::Bolt11Jit {
+                preimage, secret, ..
+            }
+            | PaymentKind::Bolt12Offer {
+                preimage, secret, ..
+            }
+            | PaymentKind::Bolt12Refund {
+                preimage, secret, ..
+            } => (*preimage, *secret),
+            PaymentKind::Spontaneous { preimage, .. } => (*preimage, None),
+            _ => (None, None),
         }
     }
 
+    /// Subscribe to incoming payment notifications including preimages
+    ///
+    /// Unlike `wait_any_incoming_payment` the stream carries the payment
+    /// preimage and secret when LDK has them.
+    pub fn subscribe_payment_notifications(
+        &self,
+    ) -> Pin<Box<dyn Stream<Item = PaymentNotification> + Send>> {
+        self.notifier
+            .subscribe_notifications(self.wait_invoice_cancel_token.clone())
+    }
+
     /// Set up event handling for the node
     pub fn handle_events(&self) -> anyhow::Result<()> {
         let node = self.inner.clone();
-        let sender = self.sender.clone();
+        let notifier = self.notifier.clone();
+        let sinks = self.notification_sinks.clone();
+        let trusted_peers_0conf = self.trusted_peers_0conf.clone();
         let cancel_token = self.events_cancel_token.clone();
+        let metrics = self.metrics.clone();
+        let quote_cache = self.quote_cache.clone();
+        let activity = self.activity.clone();
+        let payment_index = self.payment_index.clone();
+        let abandoned = self.abandoned.clone();
+        let failure_reasons = self.failure_reasons.clone();
+        let payment_tracker = self.payment_tracker.clone();
+        let hold_invoices = self.hold_invoices.clone();
+        let tenant_tags = self.tenant_tags.clone();
+        let hooks = self.hooks.clone();
+        let unit = self.unit.clone();
+
+        sinks.start(cancel_token.clone());
 
         tracing::info!("Starting event handler task");
 
@@ -282,6 +2793,18 @@ impl CdkLdkNode {
                         break;
                     }
                     event = node.next_event_async() => {
+                        let event_start = std::time::Instant::now();
+                        let event_label = metrics::event_label(&event);
+                        let indexed_payment_id = match &event {
+                            Event::PaymentReceived { payment_id, .. }
+                            | Event::PaymentSuccessful { payment_id, .. }
+                            | Event::PaymentFailed { payment_id, .. } => *payment_id,
+                            _ => None,
+                        };
+
+                        // Events are where channel balances change
+                        quote_cache.invalidate();
+
                         match event {
                             Event::PaymentReceived {
                                 payment_id,
@@ -289,24 +2812,202 @@ impl CdkLdkNode {
                                 amount_msat,
                                 custom_records: _
                             } => {
+                                activity.record(ActivityRecord::PaymentReceived {
+                                    timestamp: unix_time(),
+                                    amount_msat,
+                                });
                                 Self::handle_payment_received(
                                     &node,
-                                    &sender,
+                                    &notifier,
+                                    &sinks,
+                                    &hooks,
+                                    &tenant_tags,
+                                    &unit,
                                     payment_id,
                                     payment_hash,
                                     amount_msat
-                                ).await;
+                                );
+                            }
+                            Event::PaymentSuccessful {
+                                payment_id,
+                                payment_hash,
+                                payment_preimage,
+                                fee_paid_msat,
+                                ..
+                            } => {
+                                if let Some(payment_id) = payment_id {
+                                    payment_tracker.complete(&payment_id);
+                                }
+                                let amount_msat = payment_id
+                                    .and_then(|id| node.payment(&id))
+                                    .and_then(|payment| payment.amount_msat)
+                                    .unwrap_or_default();
+                                activity.record(ActivityRecord::PaymentSent {
+                                    timestamp: unix_time(),
+                                    amount_msat,
+                                    fee_msat: fee_paid_msat.unwrap_or_default(),
+                                });
+                                sinks.dispatch(NodeNotification::PaymentSent {
+                                    payment_id: payment_id
+                                        .map(|id| hex::encode(id.0))
+                                        .unwrap_or_default(),
+                                    payment_hash: Some(payment_hash.to_string()),
+                                    amount_msat,
+                                    fee_msat: fee_paid_msat.unwrap_or_default(),
+                                    preimage: payment_preimage.map(|p| p.to_string()),
+                                    timestamp: unix_time(),
+                                });
+                                if let Some(id) = payment_id
+                                    .map(|id| hex::encode(id.0))
+                                    .filter(|id| abandoned.contains(id))
+                                {
+                                    tracing::error!(
+                                        "Abandoned payment {} succeeded after its melt was released",
+                                        id
+                                    );
+                                    sinks.dispatch(NodeNotification::Alert {
+                                        rule: "abandoned_payment".to_string(),
+                                        message: format!(
+                                            "Abandoned payment {id} of {amount_msat} msat succeeded after its melt was released"
+                                        ),
+                                        resolved: false,
+                                        timestamp: unix_time(),
+                                    });
+                                }
+                            }
+                            Event::PaymentFailed {
+                                payment_id,
+                                payment_hash,
+                                reason,
+                            } => {
+                                activity.record(ActivityRecord::PaymentFailed {
+                                    timestamp: unix_time(),
+                                });
+                                if let Some(payment_id) = payment_id {
+                                    failure_reasons.record(payment_id, reason);
+                                    payment_tracker.complete(&payment_id);
+                                }
+                                sinks.dispatch(NodeNotification::PaymentFailed {
+                                    payment_id: payment_id.map(|id| hex::encode(id.0)),
+                                    payment_hash: payment_hash.map(|hash| hash.to_string()),
+                                    reason: reason.map(|reason| format!("{reason:?}")),
+                                    timestamp: unix_time(),
+                                });
+                            }
+                            Event::PaymentClaimable {
+                                payment_hash,
+                                claimable_amount_msat,
+                                claim_deadline,
+                                ..
+                            } => {
+                                let id = hex::encode(payment_hash.0);
+                                match hold_invoices.update(&id, unix_time(), |invoice| {
+                                    invoice.state = HoldInvoiceState::Accepted;
+                                    invoice.claimable_amount_msat = Some(claimable_amount_msat);
+                                    invoice.claim_deadline = claim_deadline;
+                                }) {
+                                    Ok(Some(_)) => {
+                                        tracing::info!(
+                                            "Holding payment of {} msat for hold invoice {}, settle it before block {:?}",
+                                            claimable_amount_msat,
+                                            id,
+                                            claim_deadline
+                                        );
+                                        sinks.dispatch(NodeNotification::HoldInvoiceAccepted {
+                                            payment_hash: id,
+                                            amount_msat: claimable_amount_msat,
+                                            claim_deadline,
+                                            timestamp: unix_time(),
+                                        });
+                                    }
+                                    Ok(None) => {
+                                        tracing::warn!(
+                                            "Claimable payment {} is not a hold invoice",
+                                            id
+                                        );
+                                    }
+                                    Err(err) => {
+                                        tracing::error!(
+                                            "Could not record held payment {}: {:#}",
+                                            id,
+                                            err
+                                        );
+                                    }
+                                }
+                            }
+                            Event::ChannelReady {
+                                channel_id,
+                                counterparty_node_id,
+                                ..
+                            } => {
+                                activity.record(ActivityRecord::ChannelOpened {
+                                    timestamp: unix_time(),
+                                    channel_id: channel_id.to_string(),
+                                });
+                                Self::check_zero_conf_channel(
+                                    &node,
+                                    &sinks,
+                                    &trusted_peers_0conf,
+                                    &channel_id,
+                                );
+                                sinks.dispatch(NodeNotification::ChannelReady {
+                                    channel_id: channel_id.to_string(),
+                                    counterparty_node_id: counterparty_node_id
+                                        .map(|id| id.to_string()),
+                                    timestamp: unix_time(),
+                                });
+                            }
+                            Event::ChannelClosed {
+                                channel_id,
+                                counterparty_node_id,
+                                reason,
+                                ..
+                            } => {
+                                activity.record(ActivityRecord::ChannelClosed {
+                                    timestamp: unix_time(),
+                                    channel_id: channel_id.to_string(),
+                                });
+                                sinks.dispatch(NodeNotification::ChannelClosed {
+                                    channel_id: channel_id.to_string(),
+                                    counterparty_node_id: counterparty_node_id
+                                        .map(|id| id.to_string()),
+                                    reason: reason.map(|reason| reason.to_string()),
+                                    timestamp: unix_time(),
+                                });
+                            }
+                            Event::PaymentForwarded {
+                                prev_channel_id,
+                                next_channel_id,
+                                total_fee_earned_msat,
+                                outbound_amount_forwarded_msat,
+                                ..
+                            } => {
+                                activity.record(ActivityRecord::Forwarded {
+                                    timestamp: unix_time(),
+                                    prev_channel_id: prev_channel_id.to_string(),
+                                    next_channel_id: next_channel_id.to_string(),
+                                    amount_msat: outbound_amount_forwarded_msat.unwrap_or_default(),
+                                    fee_msat: total_fee_earned_msat.unwrap_or_default(),
+                                });
                             }
                             event => {
                                 tracing::debug!("Received other ldk node event: {:?}", event);
                             }
                         }
 
+                        if let Some(payment) = indexed_payment_id.and_then(|id| node.payment(&id)) {
+                            if let Err(err) = payment_index.upsert(&[payment], unix_time()) {
+                                tracing::warn!("Could not index payment: {:#}", err);
+                            }
+                        }
+
                         if let Err(err) = node.event_handled() {
                             tracing::error!("Error handling node event: {}", err);
                         } else {
                             tracing::debug!("Successfully handled node event");
                         }
+
+                        metrics.record_event(event_label, event_start.elapsed());
                     }
                 }
             }
@@ -318,6 +3019,52 @@ impl CdkLdkNode {
     }
 }
 
+/// Id correlating an outgoing payment across the mint and node logs
+///
+/// The payment hash or offer id, which the mint stores as the request lookup
+/// id of its melt quote.
+fn correlation_id(options: &OutgoingPaymentOptions) -> String {
+    match options {
+        OutgoingPaymentOptions::Bolt11(bolt11_options) => {
+            bolt11_options.bolt11.payment_hash().to_string()
+        }
+        OutgoingPaymentOptions::Bolt12(bolt12_options) => bolt12_options.offer.id().to_string(),
+    }
+}
+
+/// Lookup id a melt of an offer is reported under
+///
+/// The offer id is shared by every payment to the offer, the payment id is
+/// specific to the melt and is resolved by `check_outgoing_payment`.
+fn outgoing_offer_lookup_id(payment_id: &PaymentId) -> PaymentIdentifier {
+    PaymentIdentifier::CustomId(hex::encode(payment_id.0))
+}
+
+/// Status of an incoming payment as reported to the mint
+fn incoming_payment_response(
+    payment_identifier: &PaymentIdentifier,
+    payment_details: &PaymentDetails,
+) -> Result<WaitPaymentResponse, payment::Error> {
+    if payment_details.direction == PaymentDirection::Outbound {
+        return Err(anyhow!("Invalid payment direction").into());
+    }
+
+    let amount = if payment_details.status == PaymentStatus::Succeeded {
+        payment_details
+            .amount_msat
+            .ok_or(anyhow!("Could not get payment amount"))?
+    } else {
+        0
+    };
+
+    Ok(WaitPaymentResponse {
+        payment_identifier: payment_identifier.clone(),
+        payment_amount: amount.into(),
+        unit: CurrencyUnit::Msat,
+        payment_id: hex::encode(payment_details.id.0),
+    })
+}
+
 /// Mint payment trait
 #[async_trait]
 impl MintPayment for CdkLdkNode {
@@ -325,13 +3072,34 @@ impl MintPayment for CdkLdkNode {
 
     /// Base Settings
     async fn get_settings(&self) -> Result<serde_json::Value, Self::Err> {
+        // NUT-15 multi-path payments have each mint pay only part of an invoice,
+        // which LDK Node cannot send. The node still splits the melts it pays
+        // in full across channels.
         let settings = Bolt11Settings {
             mpp: false,
-            unit: CurrencyUnit::Sat,
-            invoice_description: true,
-            amountless: false,
+            unit: self.unit.clone(),
+            invoice_description: self.request_descriptions,
+            amountless: true,
         };
-        Ok(serde_json::to_value(settings)?)
+        let mut settings = serde_json::to_value(settings)?;
+
+        // Let the mint warn before quoting amounts the node cannot move
+        if let serde_json::Value::Object(settings) = &mut settings {
+            settings.insert(
+                "payment_limits".to_string(),
+                serde_json::to_value(self.payment_limits())?,
+            );
+            if let Some(onchain) = &self.onchain_payments {
+                settings.insert(
+                    "onchain".to_string(),
+                    serde_json::json!({
+                        "min_confirmations": onchain.min_confirmations,
+                    }),
+                );
+            }
+        }
+
+        Ok(settings)
     }
 
     /// Create a new invoice
@@ -344,33 +3112,37 @@ impl MintPayment for CdkLdkNode {
         match options {
             IncomingPaymentOptions::Bolt11(bolt11_options) => {
                 let amount_msat = to_unit(bolt11_options.amount, unit, &CurrencyUnit::Msat)?;
-                let description = bolt11_options.description.unwrap_or_default();
-                let time = bolt11_options
-                    .unix_expiry
-                    .map(|t| t - unix_time())
-                    .unwrap_or(36000);
-
-                let description = Bolt11InvoiceDescription::Direct(
-                    Description::new(description).map_err(|_| anyhow!("Invalid description"))?,
+                let description = self.payment_description(
+                    bolt11_options
+                        .description
+                        .filter(|_| self.request_descriptions),
+                    Some(bolt11_options.amount),
+                    unit,
                 );
+                let now = unix_time();
+                let time = self
+                    .mint_quote_expiry_secs(bolt11_options.unix_expiry, now)
+                    .map_err(anyhow::Error::from)?;
 
-                let payment = self
-                    .inner
-                    .bolt11_payment()
-                    .receive(amount_msat.into(), &description, time as u32)
-                    .unwrap();
-
-                let payment_hash = payment.payment_hash().to_string();
-                let payment_identifier = PaymentIdentifier::PaymentHash(
-                    hex::decode(&payment_hash)?
-                        .try_into()
-                        .map_err(|_| anyhow!("Invalid payment hash length"))?,
-                );
+                let description = bolt11_description(description, self.hash_descriptions);
+
+                let payment = if self.hold_mint_invoices {
+                    self.create_hold_invoice(Some(amount_msat.into()), &description, time, None)?
+                } else {
+                    self.inner
+                        .bolt11_payment()
+                        .receive(amount_msat.into(), &description, time)
+                        .map_err(|err| self.incoming_request_error(err, Some(amount_msat.into())))
+                        .map_err(anyhow::Error::from)?
+                };
+
+                let payment_identifier =
+                    PaymentIdentifier::PaymentHash(payment.payment_hash().to_byte_array());
 
                 Ok(CreateIncomingPaymentResponse {
                     request_lookup_id: payment_identifier,
                     request: payment.to_string(),
-                    expiry: Some(unix_time() + time),
+                    expiry: Some(now + u64::from(time)),
                 })
             }
             IncomingPaymentOptions::Bolt12(bolt12_options) => {
@@ -381,7 +3153,24 @@ impl MintPayment for CdkLdkNode {
                     unix_expiry,
                 } = *bolt12_options;
 
-                let time = unix_expiry.map(|t| t - unix_time()).unwrap_or(36000);
+                let now = unix_time();
+                let time = self
+                    .mint_quote_expiry_secs(unix_expiry, now)
+                    .map_err(anyhow::Error::from)?;
+
+                if let Some(policy) = &self.invoice_request_policy {
+                    let amount_msat = amount
+                        .map(|amount| to_unit(amount, unit, &CurrencyUnit::Msat))
+                        .transpose()?
+                        .map(u64::from);
+                    policy.check_offer(amount_msat)?;
+                }
+
+                let description = self.payment_description(
+                    description.filter(|_| self.request_descriptions),
+                    amount,
+                    unit,
+                );
 
                 let offer = match amount {
                     Some(amount) => {
@@ -389,29 +3178,38 @@ impl MintPayment for CdkLdkNode {
 
                         self.inner
                             .bolt12_payment()
-                            .receive(
-                                amount_msat.into(),
-                                &description.unwrap_or("".to_string()),
-                                Some(time as u32),
-                                None,
-                            )
-                            .unwrap()
+                            .receive(amount_msat.into(), &description, Some(time), None)
+                            .map_err(|err| {
+                                self.incoming_request_error(err, Some(amount_msat.into()))
+                            })
+                            .map_err(anyhow::Error::from)?
                     }
-                    None => self
-                        .inner
-                        .bolt12_payment()
-                        .receive_variable_amount(
-                            &description.unwrap_or("".to_string()),
-                            Some(time as u32),
-                        )
-                        .unwrap(),
+                    // Pooled offers are shared by quotes, so they carry the
+                    // default description and do not expire with a quote
+                    None => match &self.offer_pool {
+                        Some(pool) => pool.next_offer(now, || {
+                            let description = self.payment_description(None, None, unit);
+                            self.inner
+                                .bolt12_payment()
+                                .receive_variable_amount(&description, None)
+                                .map_err(|err| {
+                                    anyhow::Error::from(self.incoming_request_error(err, None))
+                                })
+                        })?,
+                        None => self
+                            .inner
+                            .bolt12_payment()
+                            .receive_variable_amount(&description, Some(time))
+                            .map_err(|err| self.incoming_request_error(err, None))
+                            .map_err(anyhow::Error::from)?,
+                    },
                 };
                 let payment_identifier = PaymentIdentifier::OfferId(offer.id().to_string());
 
                 Ok(CreateIncomingPaymentResponse {
                     request_lookup_id: payment_identifier,
                     request: offer.to_string(),
-                    expiry: Some(unix_time() + time),
+                    expiry: Some(now + u64::from(time)),
                 })
             }
         }
@@ -419,266 +3217,415 @@ impl MintPayment for CdkLdkNode {
 
     /// Get payment quote
     /// Used to get fee and amount required for a payment request
-    #[instrument(skip_all)]
+    #[instrument(skip_all, fields(correlation_id))]
     async fn get_payment_quote(
         &self,
         unit: &CurrencyUnit,
         options: OutgoingPaymentOptions,
     ) -> Result<PaymentQuoteResponse, Self::Err> {
-        match options {
-            OutgoingPaymentOptions::Bolt11(bolt11_options) => {
-                let bolt11 = bolt11_options.bolt11;
-
-                let amount_msat = match bolt11_options.melt_options {
-                    Some(melt_options) => melt_options.amount_msat(),
-                    None => bolt11
-                        .amount_milli_satoshis()
-                        .ok_or(anyhow!("Unknown invoice amount"))?
-                        .into(),
-                };
-
-                let amount = to_unit(amount_msat, &CurrencyUnit::Msat, unit)?;
-
-                let relative_fee_reserve =
-                    (self.fee_reserve.percent_fee_reserve * u64::from(amount) as f32) as u64;
+        let method = metrics::payment_method_label(&options);
+        let started = std::time::Instant::now();
 
-                let absolute_fee_reserve: u64 = self.fee_reserve.min_fee_reserve.into();
+        let correlation_id = correlation_id(&options);
+        tracing::Span::current().record("correlation_id", correlation_id.as_str());
 
-                let fee = match relative_fee_reserve > absolute_fee_reserve {
-                    true => relative_fee_reserve,
-                    false => absolute_fee_reserve,
-                };
+        let melt_options = match &options {
+            OutgoingPaymentOptions::Bolt11(bolt11_options) => &bolt11_options.melt_options,
+            OutgoingPaymentOptions::Bolt12(bolt12_options) => &bolt12_options.melt_options,
+        };
 
-                let payment_hash = bolt11.payment_hash().to_string();
-                let payment_hash_bytes = hex::decode(&payment_hash)?
-                    .try_into()
-                    .map_err(|_| anyhow!("Invalid payment hash length"))?;
+        // Rejected at quote time, make_payment could not pay part of an invoice
+        if matches!(melt_options, Some(MeltOptions::Mpp { .. })) {
+            return Err(payment::Error::UnsupportedPaymentOption);
+        }
 
-                Ok(PaymentQuoteResponse {
-                    request_lookup_id: PaymentIdentifier::PaymentHash(payment_hash_bytes),
-                    amount,
-                    fee: fee.into(),
-                    state: MeltQuoteState::Unpaid,
-                    options: None,
-                })
-            }
-            OutgoingPaymentOptions::Bolt12(bolt12_options) => {
-                let offer = bolt12_options.offer;
+        let cache_key = QuoteKey {
+            identifier: correlation_id,
+            amount_msat: melt_options
+                .as_ref()
+                .map(|melt_options| u64::from(melt_options.amount_msat())),
+            unit: unit.to_string(),
+        };
 
-                let amount_msat = match bolt12_options.melt_options {
-                    Some(melt_options) => melt_options.amount_msat(),
-                    None => {
-                        let amount = offer.amount().ok_or(payment::Error::AmountMismatch)?;
+        if let Some(quote) = self.quote_cache.get(&cache_key) {
+            tracing::debug!("Serving cached quote for {}", cache_key.identifier);
+            return Ok(quote);
+        }
 
-                        match amount {
-                            ldk_node::lightning::offers::offer::Amount::Bitcoin {
-                                amount_msats,
-                            } => amount_msats.into(),
-                            _ => return Err(payment::Error::AmountMismatch),
+        let result: Result<PaymentQuoteResponse, Self::Err> = async {
+            match options {
+                OutgoingPaymentOptions::Bolt11(bolt11_options) => {
+                    let bolt11 = bolt11_options.bolt11;
+
+                    let amount_msat =
+                        match (bolt11_options.melt_options, bolt11.amount_milli_satoshis()) {
+                            (Some(MeltOptions::Amountless { amountless }), invoice_amount_msat) => {
+                                let amount_msat = u64::from(amountless.amount_msat);
+
+                                if amount_msat == 0 {
+                                    return Err(payment::Error::Custom(
+                                        "Amountless melt amount must be above zero".to_string(),
+                                    ));
+                                }
+
+                                // The amount of an invoice that sets one cannot be changed
+                                if invoice_amount_msat.is_some_and(|invoice| invoice != amount_msat)
+                                {
+                                    return Err(payment::Error::AmountMismatch);
+                                }
+
+                                amount_msat.into()
+                            }
+                            (Some(_), _) => return Err(payment::Error::UnsupportedPaymentOption),
+                            (None, Some(invoice_amount_msat)) => invoice_amount_msat.into(),
+                            (None, None) => {
+                                return Err(payment::Error::Custom(
+                                    "Invoice has no amount, an amountless melt amount is required"
+                                        .to_string(),
+                                ))
+                            }
+                        };
+
+                    let amount = to_unit(amount_msat, &CurrencyUnit::Msat, unit)?;
+
+                    let fee = self
+                        .fee_strategy
+                        .fee_reserve(
+                            &self.inner,
+                            &FeeRequest {
+                                destination: FeeDestination::Bolt11(&bolt11),
+                                amount_msat: u64::from(amount_msat),
+                                amount,
+                                unit,
+                            },
+                        )
+                        .await?;
+
+                    Ok(PaymentQuoteResponse {
+                        request_lookup_id: PaymentIdentifier::PaymentHash(
+                            bolt11.payment_hash().to_byte_array(),
+                        ),
+                        amount,
+                        fee,
+                        state: MeltQuoteState::Unpaid,
+                        options: None,
+                    })
+                }
+                OutgoingPaymentOptions::Bolt12(bolt12_options) => {
+                    let offer = bolt12_options.offer;
+
+                    let amount_msat = match bolt12_options.melt_options {
+                        Some(melt_options) => melt_options.amount_msat(),
+                        None => {
+                            let amount = offer.amount().ok_or(payment::Error::AmountMismatch)?;
+
+                            match amount {
+                                ldk_node::lightning::offers::offer::Amount::Bitcoin {
+                                    amount_msats,
+                                } => amount_msats.into(),
+                                _ => return Err(payment::Error::AmountMismatch),
+                            }
                         }
-                    }
-                };
-                let amount = to_unit(amount_msat, &CurrencyUnit::Msat, unit)?;
-
-                let relative_fee_reserve =
-                    (self.fee_reserve.percent_fee_reserve * u64::from(amount) as f32) as u64;
-
-                let absolute_fee_reserve: u64 = self.fee_reserve.min_fee_reserve.into();
+                    };
+                    let amount = to_unit(amount_msat, &CurrencyUnit::Msat, unit)?;
+
+                    let fee = self
+                        .fee_strategy
+                        .fee_reserve(
+                            &self.inner,
+                            &FeeRequest {
+                                destination: FeeDestination::Bolt12(&offer),
+                                amount_msat: u64::from(amount_msat),
+                                amount,
+                                unit,
+                            },
+                        )
+                        .await?;
+
+                    Ok(PaymentQuoteResponse {
+                        request_lookup_id: PaymentIdentifier::OfferId(offer.id().to_string()),
+                        amount,
+                        fee,
+                        state: MeltQuoteState::Unpaid,
+                        options: None,
+                    })
+                }
+            }
+        }
+        .await;
 
-                let fee = match relative_fee_reserve > absolute_fee_reserve {
-                    true => relative_fee_reserve,
-                    false => absolute_fee_reserve,
-                };
+        self.metrics
+            .record_quote(method, &result, started.elapsed());
 
-                Ok(PaymentQuoteResponse {
-                    request_lookup_id: PaymentIdentifier::OfferId(offer.id().to_string()),
-                    amount,
-                    fee: fee.into(),
-                    state: MeltQuoteState::Unpaid,
-                    options: None,
-                })
-            }
+        if let Ok(quote) = &result {
+            self.quote_cache.insert(cache_key, quote.clone());
         }
+
+        result
     }
 
     /// Pay request
-    #[instrument(skip(self, options))]
+    ///
+    /// `total_spent` includes the routing fee, which is also recorded on the
+    /// span as `fee_paid_msat` since the response has no field for it.
+    #[instrument(skip(self, options), fields(correlation_id, fee_paid_msat))]
     async fn make_payment(
         &self,
         unit: &CurrencyUnit,
         options: OutgoingPaymentOptions,
     ) -> Result<MakePaymentResponse, Self::Err> {
-        match options {
-            OutgoingPaymentOptions::Bolt11(bolt11_options) => {
-                let bolt11 = bolt11_options.bolt11;
-
-                let send_params = match bolt11_options
-                    .max_fee_amount
-                    .map(|f| {
-                        to_unit(f, unit, &CurrencyUnit::Msat).map(|amount_msat| SendingParameters {
-                            max_total_routing_fee_msat: Some(Some(amount_msat.into())),
-                            max_channel_saturation_power_of_half: None,
-                            max_total_cltv_expiry_delta: None,
-                            max_path_count: None,
-                        })
-                    })
-                    .transpose()
-                {
-                    Ok(params) => params,
-                    Err(err) => {
-                        tracing::error!("Failed to convert fee amount: {}", err);
-                        return Err(payment::Error::Custom(format!("Invalid fee amount: {err}")));
-                    }
-                };
-
-                let payment_id = match bolt11_options.melt_options {
-                    Some(MeltOptions::Amountless { amountless }) => self
-                        .inner
-                        .bolt11_payment()
-                        .send_using_amount(&bolt11, amountless.amount_msat.into(), send_params)
-                        .map_err(|err| {
-                            tracing::error!("Could not send send amountless bolt11: {}", err);
-                            anyhow!("Could not send bolt11 without amount")
-                        })?,
-                    None => self
-                        .inner
-                        .bolt11_payment()
-                        .send(&bolt11, send_params)
-                        .map_err(|err| {
-                            tracing::error!("Could not send bolt11 {}", err);
-                            anyhow!("Could not send bolt11")
-                        })?,
-                    _ => return Err(payment::Error::UnsupportedPaymentOption),
-                };
-
-                // Check payment status for up to 10 seconds
-                let start = std::time::Instant::now();
-                let timeout = std::time::Duration::from_secs(10);
-
-                let (status, payment_details) = loop {
-                    let details = self
-                        .inner
-                        .payment(&payment_id)
-                        .ok_or(anyhow!("Payment not found"))?;
-
-                    match details.status {
-                        PaymentStatus::Succeeded => break (MeltQuoteState::Paid, details),
-                        PaymentStatus::Failed => {
-                            tracing::error!("Failed to pay bolt11 payment.");
-                            break (MeltQuoteState::Failed, details);
+        tracing::Span::current().record("correlation_id", correlation_id(&options).as_str());
+
+        let method = metrics::payment_method_label(&options);
+        let started = std::time::Instant::now();
+
+        let result: Result<MakePaymentResponse, Self::Err> = async {
+            match options {
+                OutgoingPaymentOptions::Bolt11(bolt11_options) => {
+                    let bolt11 = bolt11_options.bolt11;
+
+                    let max_fee_msat = match bolt11_options
+                        .max_fee_amount
+                        .map(|f| to_unit(f, unit, &CurrencyUnit::Msat))
+                        .transpose()
+                    {
+                        Ok(max_fee_msat) => max_fee_msat.map(u64::from),
+                        Err(err) => {
+                            tracing::error!("Failed to convert fee amount: {}", err);
+                            return Err(payment::Error::Custom(format!(
+                                "Invalid fee amount: {err}"
+                            )));
                         }
-                        PaymentStatus::Pending => {
-                            tracing::warn!(
-                                "Paying bolt11 exceeded timeout 10 seconds no longer waitning."
-                            );
+                    };
+
+                    let amount_msat: u64 = match &bolt11_options.melt_options {
+                        Some(melt_options) => melt_options.amount_msat().into(),
+                        None => bolt11.amount_milli_satoshis().unwrap_or_default(),
+                    };
+
+                    let payment_hash = bolt11.payment_hash().to_byte_array();
+                    let _in_flight = self.in_flight.lock(hex::encode(payment_hash)).await;
+
+                    // LDK Node keys BOLT11 payments by their payment hash. A
+                    // failed payment may be sent again, anything else means
+                    // the mint is retrying a melt that was already sent.
+                    let existing = self.inner.payment(&PaymentId(payment_hash)).filter(|p| {
+                        p.direction == PaymentDirection::Outbound
+                            && p.status != PaymentStatus::Failed
+                    });
+
+                    let mut attempt = 1;
+
+                    let (status, payment_details) = if let Some(existing) = existing {
+                        self.join_payment(existing).await?
+                    } else {
+                        loop {
+                            let send_params = Some(self.retry_policy.sending_parameters(
+                                attempt,
+                                self.sending_parameters(amount_msat, max_fee_msat),
+                            ));
+
+                            let payment_id = match &bolt11_options.melt_options {
+                                Some(MeltOptions::Amountless { amountless }) => self
+                                    .inner
+                                    .bolt11_payment()
+                                    .send_using_amount(
+                                        &bolt11,
+                                        amountless.amount_msat.into(),
+                                        send_params,
+                                    )
+                                    .map_err(|err| {
+                                        tracing::error!(
+                                            "Could not send send amountless bolt11: {}",
+                                            err
+                                        );
+                                        anyhow!("Could not send bolt11 without amount")
+                                    })?,
+                                None => self
+                                    .inner
+                                    .bolt11_payment()
+                                    .send(&bolt11, send_params)
+                                    .map_err(|err| {
+                                        tracing::error!("Could not send bolt11 {}", err);
+                                        anyhow!("Could not send bolt11")
+                                    })?,
+                                _ => return Err(payment::Error::UnsupportedPaymentOption),
+                            };
+
+                            let (status, payment_details) = self
+                                .wait_for_payment(payment_id, self.payment_send_timeout)
+                                .await?;
+
+                            if status != MeltQuoteState::Failed
+                                || attempt >= self.retry_policy.max_attempts
+                                || bolt11.is_expired()
+                            {
+                                break (status, payment_details);
+                            }
 
-                            if start.elapsed() > timeout {
-                                break (MeltQuoteState::Pending, details);
+                            let reason = self
+                                .failure_reasons
+                                .take(&payment_id, retry::FAILURE_REASON_WAIT)
+                                .await;
+
+                            match self.retry_policy.retry_after(attempt, reason) {
+                                Some(backoff) => {
+                                    tracing::warn!(
+                                        "Bolt11 payment attempt {} failed with {:?}, retrying in {:?}",
+                                        attempt,
+                                        reason,
+                                        backoff
+                                    );
+                                    tokio::time::sleep(backoff).await;
+                                    attempt += 1;
+                                }
+                                None => break (status, payment_details),
                             }
-                            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                            continue;
                         }
+                    };
+
+                    let payment_proof = match payment_details.kind {
+                        PaymentKind::Bolt11 {
+                            hash: _,
+                            preimage,
+                            secret: _,
+                        } => preimage.map(|p| p.to_string()),
+                        _ => return Err(anyhow!("Unexpected payment kind").into()),
+                    };
+
+                    let total_spent = settled_spent_msat(&payment_details)
+                        .ok_or(anyhow!("Could not get amount spent"))?;
+
+                    let total_spent = spent_in_unit(total_spent, unit)?;
+                    if let Some(fee_paid_msat) = payment_details.fee_paid_msat {
+                        tracing::Span::current().record("fee_paid_msat", fee_paid_msat);
                     }
-                };
 
-                let payment_proof = match payment_details.kind {
-                    PaymentKind::Bolt11 {
-                        hash: _,
-                        preimage,
-                        secret: _,
-                    } => preimage.map(|p| p.to_string()),
-                    _ => return Err(anyhow!("Unexpected payment kind").into()),
-                };
-
-                let total_spent = payment_details
-                    .amount_msat
-                    .ok_or(anyhow!("Could not get amount spent"))?;
-
-                let total_spent = to_unit(total_spent, &CurrencyUnit::Msat, unit)?;
-
-                Ok(MakePaymentResponse {
-                    payment_lookup_id: PaymentIdentifier::PaymentHash(
-                        bolt11.payment_hash().to_byte_array(),
-                    ),
-                    payment_proof,
-                    status,
-                    total_spent,
-                    unit: unit.clone(),
-                })
-            }
-            OutgoingPaymentOptions::Bolt12(bolt12_options) => {
-                let offer = bolt12_options.offer;
-
-                let payment_id = match bolt12_options.melt_options {
-                    Some(MeltOptions::Amountless { amountless }) => self
-                        .inner
-                        .bolt12_payment()
-                        .send_using_amount(&offer, amountless.amount_msat.into(), None, None)
-                        .unwrap(),
-                    None => self
-                        .inner
-                        .bolt12_payment()
-                        .send(&offer, None, None)
-                        .unwrap(),
-                    _ => return Err(payment::Error::UnsupportedPaymentOption),
-                };
+                    Ok(MakePaymentResponse {
+                        payment_lookup_id: PaymentIdentifier::PaymentHash(payment_hash),
+                        payment_proof,
+                        status,
+                        total_spent,
+                        unit: unit.clone(),
+                    })
+                }
+                OutgoingPaymentOptions::Bolt12(bolt12_options) => {
+                    let offer = bolt12_options.offer;
+
+                    // Offers can be paid many times and the melt options do
+                    // not say which quote is melted, so an earlier payment to
+                    // the offer is never taken for this melt. Each melt is
+                    // reported under the id of its own payment instead.
+                    let mut attempt = 1;
+
+                    let (status, payment_details) = loop {
+                            // LDK Node 0.5 takes no sending parameters for
+                            // offers, every attempt uses its defaults
+                            let payment_id = match &bolt12_options.melt_options {
+                                Some(MeltOptions::Amountless { amountless }) => self
+                                    .inner
+                                    .bolt12_payment()
+                                    .send_using_amount(
+                                        &offer,
+                                        amountless.amount_msat.into(),
+                                        None,
+                                        None,
+                                    )
+                                    .map_err(|err| {
+                                        tracing::error!(
+                                            "Could not send bolt12 offer with amount: {}",
+                                            err
+                                        );
+                                        anyhow!("Could not send bolt12 offer with amount")
+                                    })?,
+                                None => self
+                                    .inner
+                                    .bolt12_payment()
+                                    .send(&offer, None, None)
+                                    .map_err(|err| {
+                                        tracing::error!("Could not send bolt12 offer {}", err);
+                                        anyhow!("Could not send bolt12 offer")
+                                    })?,
+                                _ => return Err(payment::Error::UnsupportedPaymentOption),
+                            };
+
+                            let (status, payment_details) = self
+                                .wait_for_payment(payment_id, self.payment_send_timeout)
+                                .await?;
+
+                            if status != MeltQuoteState::Failed
+                                || attempt >= self.retry_policy.max_attempts
+                                || offer.is_expired()
+                            {
+                                break (status, payment_details);
+                            }
 
-                // Check payment status for up to 10 seconds
-                let start = std::time::Instant::now();
-                let timeout = std::time::Duration::from_secs(10);
-
-                let (status, payment_details) = loop {
-                    let details = self
-                        .inner
-                        .payment(&payment_id)
-                        .ok_or(anyhow!("Payment not found"))?;
-
-                    match details.status {
-                        PaymentStatus::Succeeded => break (MeltQuoteState::Paid, details),
-                        PaymentStatus::Failed => {
-                            tracing::error!("Payment with id {} failed.", payment_id);
-                            break (MeltQuoteState::Failed, details);
-                        }
-                        PaymentStatus::Pending => {
-                            if start.elapsed() > timeout {
-                                tracing::warn!(
-                                    "Payment has been being for 10 seconds. No longer waiting"
-                                );
-                                break (MeltQuoteState::Pending, details);
+                            let reason = self
+                                .failure_reasons
+                                .take(&payment_id, retry::FAILURE_REASON_WAIT)
+                                .await;
+
+                            match self.retry_policy.retry_after(attempt, reason) {
+                                Some(backoff) => {
+                                    tracing::warn!(
+                                        "Bolt12 payment attempt {} failed with {:?}, retrying in {:?}",
+                                        attempt,
+                                        reason,
+                                        backoff
+                                    );
+                                    tokio::time::sleep(backoff).await;
+                                    attempt += 1;
+                                }
+                                None => break (status, payment_details),
                             }
-                            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                            continue;
-                        }
+                    };
+
+                    let payment_proof = match payment_details.kind {
+                        PaymentKind::Bolt12Offer {
+                            hash: _,
+                            preimage,
+                            secret: _,
+                            offer_id: _,
+                            payer_note: _,
+                            quantity: _,
+                        } => preimage.map(|p| p.to_string()),
+                        _ => return Err(anyhow!("Unexpected payment kind").into()),
+                    };
+
+                    let total_spent = settled_spent_msat(&payment_details)
+                        .ok_or(anyhow!("Could not get amount spent"))?;
+
+                    let total_spent = spent_in_unit(total_spent, unit)?;
+                    if let Some(fee_paid_msat) = payment_details.fee_paid_msat {
+                        tracing::Span::current().record("fee_paid_msat", fee_paid_msat);
                     }
-                };
 
-                let payment_proof = match payment_details.kind {
-                    PaymentKind::Bolt12Offer {
-                        hash: _,
-                        preimage,
-                        secret: _,
-                        offer_id: _,
-                        payer_note: _,
-                        quantity: _,
-                    } => preimage.map(|p| p.to_string()),
-                    _ => return Err(anyhow!("Unexpected payment kind").into()),
-                };
-
-                let total_spent = payment_details
-                    .amount_msat
-                    .ok_or(anyhow!("Could not get amount spent"))?;
-
-                let total_spent = to_unit(total_spent, &CurrencyUnit::Msat, unit)?;
-
-                Ok(MakePaymentResponse {
-                    payment_lookup_id: PaymentIdentifier::OfferId(offer.id().to_string()),
-                    payment_proof,
-                    status,
-                    total_spent,
-                    unit: unit.clone(),
-                })
+                    Ok(MakePaymentResponse {
+                        payment_lookup_id: outgoing_offer_lookup_id(&payment_details.id),
+                        payment_proof,
+                        status,
+                        total_spent,
+                        unit: unit.clone(),
+                    })
+                }
+            }
+        }
+        .await;
+
+        self.metrics
+            .record_payment(method, &result, started.elapsed());
+
+        if let Some(alerts) = &self.alerts {
+            let failed = match &result {
+                Ok(response) => response.status == MeltQuoteState::Failed,
+                Err(_) => true,
+            };
+            if failed {
+                alerts.record_failed_melt(unix_time());
             }
         }
+
+        result
     }
 
     /// Listen for invoices to be paid to the mint
@@ -693,30 +3640,38 @@ impl MintPayment for CdkLdkNode {
         self.wait_invoice_is_active.store(true, Ordering::SeqCst);
         tracing::debug!("wait_invoice_is_active set to true");
 
-        let receiver = self.receiver.clone();
+        // Every stream is a separate consumer with its own cursor so several
+        // payment processors can be attached to this node
+        let stream = self
+            .notifier
+            .subscribe(self.wait_invoice_cancel_token.clone());
 
-        tracing::info!("Receiver obtained successfully, creating response stream");
+        // Payments tagged with a tenant go to the tenant's payment processor
+        let stream = if self.tenants.is_empty() {
+            stream
+        } else {
+            let tags = self.tenant_tags.clone();
+            Box::pin(stream.filter(move |response| {
+                let untagged = tags
+                    .tenant_of_identifier(&response.payment_identifier)
+                    .is_none();
+                async move { untagged }
+            }))
+        };
 
-        // Transform the String stream into a WaitPaymentResponse stream
-        let response_stream = BroadcastStream::new(receiver.resubscribe());
+        // Notifications are acknowledged once the mint asks for the next
+        // one, the rest are replayed after a restart
+        let stream = self.notifier.acknowledge_on_next(stream);
 
-        // Map the stream to handle BroadcastStreamRecvError
-        let response_stream = response_stream.filter_map(|result| async move {
-            match result {
-                Ok(payment) => Some(payment),
-                Err(err) => {
-                    tracing::warn!("Error in broadcast stream: {}", err);
-                    None
-                }
-            }
-        });
+        tracing::info!(
+            "Payment notification stream created, {} consumers attached",
+            self.notifier.consumer_count()
+        );
 
-        // Create a combined stream that also handles cancellation
+        // Clear the active flag once the streams are cancelled
         let cancel_token = self.wait_invoice_cancel_token.clone();
         let is_active = self.wait_invoice_is_active.clone();
 
-        let stream = Box::pin(response_stream);
-
         // Set up a task to clean up when the stream is dropped
         tokio::spawn(async move {
             cancel_token.cancelled().await;
@@ -743,43 +3698,35 @@ impl MintPayment for CdkLdkNode {
         &self,
         payment_identifier: &PaymentIdentifier,
     ) -> Result<Vec<WaitPaymentResponse>, Self::Err> {
-        let payment_id_str = match payment_identifier {
-            PaymentIdentifier::PaymentHash(hash) => hex::encode(hash),
-            PaymentIdentifier::CustomId(id) => id.clone(),
-            _ => return Err(anyhow!("Unsupported payment identifier type").into()),
-        };
+        if let Some(responses) = self.deposit_payment_responses(payment_identifier)? {
+            return Ok(responses);
+        }
 
-        let payment_id = PaymentId(
-            hex::decode(&payment_id_str)?
-                .try_into()
-                .map_err(|_| anyhow!("Invalid payment ID length"))?,
-        );
+        // Offers, including ones rotated out of the pool, can be paid many times
+        if let PaymentIdentifier::OfferId(id) = payment_identifier {
+            return self
+                .inner
+                .list_payments_with_filter(|p| {
+                    p.direction == PaymentDirection::Inbound
+                        && matches!(&p.kind, PaymentKind::Bolt12Offer { offer_id, .. } if offer_id.to_string() == *id)
+                })
+                .iter()
+                .map(|details| incoming_payment_response(payment_identifier, details))
+                .collect();
+        }
+
+        let payment_id =
+            identifiers::payment_id(payment_identifier).map_err(anyhow::Error::from)?;
 
         let payment_details = self
             .inner
             .payment(&payment_id)
             .ok_or(anyhow!("Payment not found"))?;
 
-        if payment_details.direction == PaymentDirection::Outbound {
-            return Err(anyhow!("Invalid payment direction").into());
-        }
-
-        let amount = if payment_details.status == PaymentStatus::Succeeded {
-            payment_details
-                .amount_msat
-                .ok_or(anyhow!("Could not get payment amount"))?
-        } else {
-            0
-        };
-
-        let response = WaitPaymentResponse {
-            payment_identifier: payment_identifier.clone(),
-            payment_amount: amount.into(),
-            unit: CurrencyUnit::Msat,
-            payment_id: payment_id_str,
-        };
-
-        Ok(vec![response])
+        Ok(vec![incoming_payment_response(
+            payment_identifier,
+            &payment_details,
+        )?])
     }
 
     /// Check the status of an outgoing payment
@@ -787,6 +3734,14 @@ impl MintPayment for CdkLdkNode {
         &self,
         request_lookup_id: &PaymentIdentifier,
     ) -> Result<MakePaymentResponse, Self::Err> {
+        if let PaymentIdentifier::CustomId(id) = request_lookup_id {
+            if let Ok(txid) = Txid::from_str(id) {
+                if let Some(response) = self.onchain_payment_status(request_lookup_id, &txid)? {
+                    return Ok(response);
+                }
+            }
+        }
+
         let payment_details = match request_lookup_id {
             PaymentIdentifier::PaymentHash(id_hash) => self
                 .inner
@@ -795,11 +3750,9 @@ impl MintPayment for CdkLdkNode {
                 )
                 .first()
                 .cloned(),
-            PaymentIdentifier::CustomId(id) => self.inner.payment(&PaymentId(
-                hex::decode(id)?
-                    .try_into()
-                    .map_err(|_| payment::Error::Custom("Invalid hex".to_string()))?,
-            )),
+            PaymentIdentifier::CustomId(id) => self
+                .inner
+                .payment(&identifiers::parse_payment_id(id).map_err(anyhow::Error::from)?),
             _ => {
                 return Ok(MakePaymentResponse {
                     payment_lookup_id: request_lookup_id.clone(),
@@ -818,6 +3771,11 @@ impl MintPayment for CdkLdkNode {
         }
 
         let status = match payment_details.status {
+            PaymentStatus::Pending
+                if self.abandoned.contains(&hex::encode(payment_details.id.0)) =>
+            {
+                MeltQuoteState::Failed
+            }
             PaymentStatus::Pending => MeltQuoteState::Pending,
             PaymentStatus::Succeeded => MeltQuoteState::Paid,
             PaymentStatus::Failed => MeltQuoteState::Failed,
@@ -828,13 +3786,13 @@ impl MintPayment for CdkLdkNode {
                 hash: _,
                 preimage,
                 secret: _,
-            } => preimage.map(|p| p.to_string()),
+            }
+            | PaymentKind::Bolt12Offer { preimage, .. } => preimage.map(|p| p.to_string()),
             _ => return Err(anyhow!("Unexpected payment kind").into()),
         };
 
-        let total_spent = payment_details
-            .amount_msat
-            .ok_or(anyhow!("Could not get amount spent"))?;
+        let total_spent =
+            settled_spent_msat(&payment_details).ok_or(anyhow!("Could not get amount spent"))?;
 
         Ok(MakePaymentResponse {
             payment_lookup_id: request_lookup_id.clone(),
@@ -848,14 +3806,232 @@ impl MintPayment for CdkLdkNode {
 
 impl Drop for CdkLdkNode {
     fn drop(&mut self) {
-        tracing::info!("Drop called on CdkLdkNode");
-        self.wait_invoice_cancel_token.cancel();
-        self.management_service_cancel_token.cancel();
-        tracing::debug!("Cancelled wait_invoice token in drop");
-        if let Err(e) = self.stop() {
-            tracing::error!("Error stopping node during drop: {}", e);
-        } else {
-            tracing::info!("Successfully stopped node during drop");
+        // Clones share the node, only the last handle held outside the node
+        // signals shutdown. The clones of background tasks do not count, they
+        // only end once signalled. Drop must not block the runtime, so LDK
+        // Node itself is stopped by its own drop; call `shutdown` or `stop`
+        // for an orderly stop.
+        let Some(handle) = &self.embedder_handle else {
+            return;
+        };
+        if Arc::strong_count(handle) > 1 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if matches!(*state, NodeState::Stopping | NodeState::Stopped) {
+            return;
+        }
+        *state = NodeState::Stopping;
+        drop(state);
+
+        tracing::info!("Last CdkLdkNode handle dropped, signalling background tasks");
+        self.cancel_tasks();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn is_invalid_expiry(result: Result<u32, Error>) -> bool {
+        matches!(result, Err(Error::InvalidExpiry { .. }))
+    }
+
+    #[test]
+    fn incoming_expiry_defaults_without_expiry() {
+        assert_eq!(
+            incoming_expiry_secs(None, NOW).unwrap(),
+            DEFAULT_INCOMING_EXPIRY_SECS
+        );
+    }
+
+    #[test]
+    fn incoming_expiry_rejects_expiry_in_the_past() {
+        assert!(is_invalid_expiry(incoming_expiry_secs(Some(NOW - 1), NOW)));
+        assert!(is_invalid_expiry(incoming_expiry_secs(Some(NOW), NOW)));
+    }
+
+    #[test]
+    fn incoming_expiry_has_a_minimum() {
+        let min = u64::from(MIN_INCOMING_EXPIRY_SECS);
+
+        assert!(is_invalid_expiry(incoming_expiry_secs(
+            Some(NOW + min - 1),
+            NOW
+        )));
+        assert_eq!(
+            incoming_expiry_secs(Some(NOW + min), NOW).unwrap(),
+            MIN_INCOMING_EXPIRY_SECS
+        );
+    }
+
+    #[test]
+    fn incoming_expiry_fits_u32() {
+        let max = u64::from(u32::MAX);
+
+        assert_eq!(
+            incoming_expiry_secs(Some(NOW + max), NOW).unwrap(),
+            u32::MAX
+        );
+        assert!(is_invalid_expiry(incoming_expiry_secs(
+            Some(NOW + max + 1),
+            NOW
+        )));
+    }
+
+    #[test]
+    fn melts_of_the_same_offer_are_reported_under_their_own_payment() {
+        let first = PaymentId([1; 32]);
+        let second = PaymentId([2; 32]);
+
+        let first_lookup_id = outgoing_offer_lookup_id(&first);
+        let second_lookup_id = outgoing_offer_lookup_id(&second);
+
+        assert_ne!(first_lookup_id, second_lookup_id);
+        assert_eq!(identifiers::payment_id(&first_lookup_id).unwrap(), first);
+        assert_eq!(identifiers::payment_id(&second_lookup_id).unwrap(), second);
+    }
+
+    #[test]
+    fn mint_quote_expiry_defaults_without_quote_expiry() {
+        assert_eq!(
+            mint_quote_expiry_secs(None, NOW, 3_600, 120).unwrap(),
+            3_600
+        );
+    }
+
+    #[test]
+    fn mint_quote_expiry_ends_before_the_quote() {
+        assert_eq!(
+            mint_quote_expiry_secs(Some(NOW + 3_600), NOW, 36_000, 120).unwrap(),
+            3_480
+        );
+        assert_eq!(
+            mint_quote_expiry_secs(Some(NOW + 3_600), NOW, 36_000, 0).unwrap(),
+            3_600
+        );
+    }
+
+    #[test]
+    fn mint_quote_expiry_keeps_the_minimum_after_the_margin() {
+        assert_eq!(
+            mint_quote_expiry_secs(Some(NOW + 90), NOW, 36_000, 120).unwrap(),
+            MIN_INCOMING_EXPIRY_SECS
+        );
+        assert!(is_invalid_expiry(mint_quote_expiry_secs(
+            Some(NOW + 30),
+            NOW,
+            36_000,
+            0
+        )));
+    }
+
+    #[test]
+    fn payment_description_prefers_the_request_description() {
+        let template = Some("Mint {amount} {unit}");
+
+        assert_eq!(
+            payment_description(
+                Some("Coffee".to_string()),
+                template,
+                Some(Amount::from(21)),
+                &CurrencyUnit::Sat
+            ),
+            "Coffee"
+        );
+        assert_eq!(
+            payment_description(
+                Some(String::new()),
+                template,
+                Some(Amount::from(21)),
+                &CurrencyUnit::Sat
+            ),
+            "Mint 21 sat"
+        );
+        assert_eq!(
+            payment_description(None, template, None, &CurrencyUnit::Sat),
+            "Mint  sat"
+        );
+        assert_eq!(
+            payment_description(None, None, None, &CurrencyUnit::Sat),
+            ""
+        );
+    }
+
+    #[test]
+    fn bolt11_description_is_hashed_when_asked_or_too_long() {
+        assert!(matches!(
+            bolt11_description("Coffee".to_string(), false),
+            Bolt11InvoiceDescription::Direct(_)
+        ));
+
+        let hashed = bolt11_description("Coffee".to_string(), true);
+        let Bolt11InvoiceDescription::Hash(Sha256(hash)) = hashed else {
+            panic!("description is not hashed");
+        };
+        assert_eq!(hash, sha256::Hash::hash(b"Coffee"));
+
+        assert!(matches!(
+            bolt11_description("a".repeat(1_000), false),
+            Bolt11InvoiceDescription::Hash(_)
+        ));
+    }
+
+    #[test]
+    fn pathfinding_sets_the_default_sending_parameters() {
+        let params = PathfindingConfig {
+            max_channel_saturation_power_of_half: Some(1),
+            max_path_count: Some(3),
+            max_total_cltv_expiry_delta: Some(1_008),
+            ..Default::default()
         }
+        .sending_parameters();
+
+        assert_eq!(params.max_total_routing_fee_msat, None);
+        assert_eq!(params.max_channel_saturation_power_of_half, Some(1));
+        assert_eq!(params.max_path_count, Some(3));
+        assert_eq!(params.max_total_cltv_expiry_delta, Some(1_008));
+
+        let params = PathfindingConfig::default().sending_parameters();
+        assert_eq!(params.max_channel_saturation_power_of_half, None);
+        assert_eq!(params.max_path_count, None);
+        assert_eq!(params.max_total_cltv_expiry_delta, None);
+    }
+
+    #[test]
+    fn payment_sending_parameters_merge_the_fee_limit() {
+        let pathfinding = PathfindingConfig {
+            max_path_count: Some(3),
+            ..Default::default()
+        };
+
+        let params = pathfinding.payment_sending_parameters(10_000, Some(500), u64::MAX);
+        assert_eq!(params.max_total_routing_fee_msat, Some(Some(500)));
+        assert_eq!(params.max_path_count, Some(3));
+
+        let params = pathfinding.payment_sending_parameters(10_000, None, u64::MAX);
+        assert_eq!(params.max_total_routing_fee_msat, None);
+    }
+
+    #[test]
+    fn small_payments_use_a_single_path_when_a_channel_can_carry_them() {
+        let pathfinding = PathfindingConfig {
+            max_path_count: Some(3),
+            single_path_below_msat: Some(100_000),
+            ..Default::default()
+        };
+
+        let params = pathfinding.payment_sending_parameters(99_999, None, 1_000_000);
+        assert_eq!(params.max_path_count, Some(1));
+
+        let params = pathfinding.payment_sending_parameters(100_000, None, 1_000_000);
+        assert_eq!(params.max_path_count, Some(3));
+
+        // No single channel can carry it
+        let params = pathfinding.payment_sending_parameters(99_999, None, 50_000);
+        assert_eq!(params.max_path_count, Some(3));
     }
 }
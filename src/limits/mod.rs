@@ -0,0 +1,62 @@
+//! Amounts the node can currently receive and pay, and the limits on
+//! payments made through the management API
+
+use ldk_node::ChannelDetails;
+use serde::Serialize;
+
+/// Largest amounts the node can currently receive and pay over its usable channels
+///
+/// Channel capacities reported by LDK already exclude the channel reserves
+/// and are capped by the per-HTLC limits of each channel.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct PaymentLimits {
+    /// Largest amount receivable when the payer can split the payment
+    pub max_receivable_msat: u64,
+    /// Largest amount receivable over a single channel
+    pub max_receivable_single_path_msat: u64,
+    /// Largest amount payable when the payment can be split
+    pub max_payable_msat: u64,
+    /// Largest amount payable over a single channel
+    pub max_payable_single_path_msat: u64,
+}
+
+impl PaymentLimits {
+    /// Compute the limits from the node's channels
+    pub fn from_channels(channels: &[ChannelDetails]) -> Self {
+        let usable = channels.iter().filter(|channel| channel.is_usable);
+
+        let mut limits = Self::default();
+
+        for channel in usable {
+            let receivable = channel
+                .inbound_htlc_maximum_msat
+                .map_or(channel.inbound_capacity_msat, |max| {
+                    max.min(channel.inbound_capacity_msat)
+                });
+            let payable = channel.next_outbound_htlc_limit_msat;
+
+            limits.max_receivable_msat = limits.max_receivable_msat.saturating_add(receivable);
+            limits.max_receivable_single_path_msat =
+                limits.max_receivable_single_path_msat.max(receivable);
+            limits.max_payable_msat = limits.max_payable_msat.saturating_add(payable);
+            limits.max_payable_single_path_msat = limits.max_payable_single_path_msat.max(payable);
+        }
+
+        limits
+    }
+}
+
+/// Largest payments the management API makes without an override
+///
+/// A guard against fat-fingered amounts, separate from the mint's melt
+/// limits. Requests above a limit are refused unless they set the override
+/// flag and carry the approver token.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ManagementLimits {
+    /// Largest BOLT11 invoice payment
+    pub max_bolt11_payment_sat: Option<u64>,
+    /// Largest BOLT12 offer payment
+    pub max_bolt12_payment_sat: Option<u64>,
+    /// Largest onchain send
+    pub max_onchain_send_sat: Option<u64>,
+}
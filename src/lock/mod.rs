@@ -0,0 +1,163 @@
+//! Single-instance lock on the storage directory
+//!
+//! Two processes running against the same storage directory corrupt the
+//! persisted channel state. A lock file holding the owner's PID and hostname
+//! is created when the node is built and removed when it is dropped.
+//!
+//! The lock is advisory: it is a plain file, not an OS level lock. A lock left
+//! behind by a crashed process on the same host is taken over once its PID is
+//! no longer running. Locks held by another host, or on platforms where the
+//! PID cannot be checked, are only released with `force_unlock`.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+/// File the lock is kept in, inside the storage directory
+pub const LOCK_FILE_NAME: &str = "cdk-ldk-node.lock";
+
+/// Owner of a storage directory lock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockOwner {
+    pub pid: u32,
+    pub hostname: String,
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            hostname: hostname(),
+        }
+    }
+
+    fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        let pid = lines.next()?.trim().parse().ok()?;
+        let hostname = lines.next()?.trim().to_string();
+
+        Some(Self { pid, hostname })
+    }
+
+    /// Whether the owner is known to no longer be running
+    fn is_stale(&self) -> bool {
+        if self.hostname != hostname() {
+            return false;
+        }
+
+        if cfg!(target_os = "linux") {
+            return !Path::new("/proc").join(self.pid.to_string()).exists();
+        }
+
+        false
+    }
+}
+
+impl std::fmt::Display for LockOwner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PID {} on {}", self.pid, self.hostname)
+    }
+}
+
+/// Lock on a storage directory, released when dropped
+#[derive(Debug)]
+pub struct StorageLock {
+    path: PathBuf,
+}
+
+impl StorageLock {
+    /// Acquire the lock on a storage directory
+    ///
+    /// Fails if another running process holds the lock. With `force_unlock`
+    /// an existing lock is removed first, which must only be used when the
+    /// other process is known not to be running.
+    pub fn acquire(storage_dir: &Path, force_unlock: bool) -> Result<Self> {
+        fs::create_dir_all(storage_dir).with_context(|| {
+            format!(
+                "Could not create storage directory {}",
+                storage_dir.display()
+            )
+        })?;
+
+        let path = storage_dir.join(LOCK_FILE_NAME);
+
+        if path.exists() {
+            let owner = fs::read_to_string(&path)
+                .ok()
+                .and_then(|contents| LockOwner::parse(&contents));
+
+            match owner {
+                _ if force_unlock => {
+                    tracing::warn!(
+                        "Forcibly removing storage lock {} held by {}",
+                        path.display(),
+                        owner.map_or("an unknown process".to_string(), |o| o.to_string())
+                    );
+                }
+                Some(owner) if owner.is_stale() => {
+                    tracing::warn!(
+                        "Removing stale storage lock {} left by {}",
+                        path.display(),
+                        owner
+                    );
+                }
+                Some(owner) => bail!(
+                    "cdk-ldk-node is already running against storage directory {} ({}). \
+                     Stop it first, or start with --force-unlock if it is not running",
+                    storage_dir.display(),
+                    owner
+                ),
+                None => bail!(
+                    "Storage directory {} has an unreadable lock file {}. \
+                     Start with --force-unlock if no other node is running",
+                    storage_dir.display(),
+                    path.display()
+                ),
+            }
+
+            fs::remove_file(&path)
+                .with_context(|| format!("Could not remove lock file {}", path.display()))?;
+        }
+
+        let owner = LockOwner::current();
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| {
+                format!(
+                    "Could not create lock file {}, another node may be starting",
+                    path.display()
+                )
+            })?;
+        writeln!(file, "{}\n{}", owner.pid, owner.hostname)?;
+
+        tracing::info!("Locked storage directory {}", storage_dir.display());
+
+        Ok(Self { path })
+    }
+}
+
+impl Drop for StorageLock {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            tracing::warn!(
+                "Could not remove lock file {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
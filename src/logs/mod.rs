@@ -0,0 +1,165 @@
+//! In-memory buffer of recent log events
+//!
+//! A tracing layer keeps the most recent events so operators without shell
+//! access can fetch diagnostic context through the management service.
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+use cdk_common::util::unix_time;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+/// Default number of log events kept in memory
+pub const DEFAULT_LOG_BUFFER_CAPACITY: usize = 5_000;
+
+/// Log event kept in the buffer
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// Sequence number, increasing by one per event
+    pub seq: u64,
+    /// Unix timestamp of the event
+    pub timestamp: u64,
+    /// Level of the event
+    pub level: Level,
+    /// Module the event was logged from
+    pub target: String,
+    /// Message followed by any other fields
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+struct LogBufferState {
+    next_seq: u64,
+    entries: VecDeque<LogEntry>,
+}
+
+/// Ring buffer of recent log events, cheap to clone
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    capacity: usize,
+    state: Arc<Mutex<LogBufferState>>,
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOG_BUFFER_CAPACITY)
+    }
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Arc::new(Mutex::new(LogBufferState::default())),
+        }
+    }
+
+    /// Tracing layer recording events into this buffer
+    pub fn layer(&self) -> LogBufferLayer {
+        LogBufferLayer {
+            buffer: self.clone(),
+        }
+    }
+
+    fn push(&self, timestamp: u64, level: Level, target: String, message: String) {
+        // Never log from here, the layer would record its own events
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+
+        let seq = state.next_seq;
+        state.next_seq += 1;
+
+        if state.entries.len() >= self.capacity {
+            state.entries.pop_front();
+        }
+
+        state.entries.push_back(LogEntry {
+            seq,
+            timestamp,
+            level,
+            target,
+            message,
+        });
+    }
+
+    /// Most recent events at or above `min_level`, oldest first
+    ///
+    /// Only events with a sequence number of at least `after_seq` are
+    /// returned, so callers can follow the log by passing the next sequence
+    /// number they expect.
+    pub fn recent(&self, min_level: Level, limit: usize, after_seq: Option<u64>) -> Vec<LogEntry> {
+        let Ok(state) = self.state.lock() else {
+            return vec![];
+        };
+
+        let mut entries: Vec<LogEntry> = state
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.level <= min_level)
+            .filter(|entry| after_seq.is_none_or(|seq| entry.seq >= seq))
+            .take(limit)
+            .cloned()
+            .collect();
+
+        entries.reverse();
+        entries
+    }
+}
+
+/// Tracing layer feeding a [`LogBuffer`]
+#[derive(Debug, Clone)]
+pub struct LogBufferLayer {
+    buffer: LogBuffer,
+}
+
+impl<S: Subscriber> Layer<S> for LogBufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        self.buffer.push(
+            unix_time(),
+            *metadata.level(),
+            metadata.target().to_string(),
+            visitor.finish(),
+        );
+    }
+}
+
+/// Formats the message and fields of an event
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn finish(self) -> String {
+        match (self.message.is_empty(), self.fields.is_empty()) {
+            (_, true) => self.message,
+            (true, false) => self.fields,
+            (false, false) => format!("{} {}", self.message, self.fields),
+        }
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            if !self.fields.is_empty() {
+                self.fields.push(' ');
+            }
+            let _ = write!(self.fields, "{}={:?}", field.name(), value);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Prometheus metrics for the mint payment path
+
+use std::time::Duration;
+
+use cdk_common::MeltQuoteState;
+use ldk_node::Event;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+};
+
+use crate::OutgoingPaymentOptions;
+
+/// Latency buckets in seconds, covering fast quotes up to slow multi-hop payments
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+/// Payment path metrics exported on the metrics endpoint
+pub struct PaymentMetrics {
+    registry: Registry,
+    quote_duration: HistogramVec,
+    quotes_total: IntCounterVec,
+    payment_duration: HistogramVec,
+    payments_total: IntCounterVec,
+    event_duration: HistogramVec,
+    events_total: IntCounterVec,
+    notification_lags_total: IntCounter,
+    notifications_missed_total: IntCounter,
+    route_probe_duration: HistogramVec,
+    route_probes_total: IntCounterVec,
+    route_healthy: IntGaugeVec,
+}
+
+impl PaymentMetrics {
+    pub fn new() -> anyhow::Result<Self> {
+        let registry = Registry::new_custom(Some("cdk_ldk_node".to_string()), None)?;
+
+        let quote_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "quote_duration_seconds",
+                "Time taken to quote an outgoing payment",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["method", "result"],
+        )?;
+        let quotes_total = IntCounterVec::new(
+            Opts::new("quotes_total", "Outgoing payment quotes by outcome"),
+            &["method", "result"],
+        )?;
+        let payment_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "payment_duration_seconds",
+                "Time taken to make an outgoing payment",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["method", "result"],
+        )?;
+        let payments_total = IntCounterVec::new(
+            Opts::new("payments_total", "Outgoing payments by outcome"),
+            &["method", "result"],
+        )?;
+        let event_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "event_duration_seconds",
+                "Time taken to handle a node event",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["event"],
+        )?;
+        let events_total = IntCounterVec::new(
+            Opts::new("events_total", "Node events handled by type"),
+            &["event"],
+        )?;
+
+        let notification_lags_total = IntCounter::new(
+            "notification_lags_total",
+            "Times a payment notification consumer lagged and was reconciled",
+        )?;
+        let notifications_missed_total = IntCounter::new(
+            "notifications_missed_total",
+            "Payment notifications dropped before a lagging consumer read them",
+        )?;
+
+        let route_probe_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "route_probe_duration_seconds",
+                "Time taken to route and send a probe to a monitored destination",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["destination"],
+        )?;
+        let route_probes_total = IntCounterVec::new(
+            Opts::new(
+                "route_probes_total",
+                "Probes to monitored destinations by outcome",
+            ),
+            &["destination", "result"],
+        )?;
+        let route_healthy = IntGaugeVec::new(
+            Opts::new(
+                "route_healthy",
+                "Whether the last probe to a monitored destination succeeded",
+            ),
+            &["destination"],
+        )?;
+
+        registry.register(Box::new(quote_duration.clone()))?;
+        registry.register(Box::new(quotes_total.clone()))?;
+        registry.register(Box::new(payment_duration.clone()))?;
+        registry.register(Box::new(payments_total.clone()))?;
+        registry.register(Box::new(event_duration.clone()))?;
+        registry.register(Box::new(events_total.clone()))?;
+        registry.register(Box::new(notification_lags_total.clone()))?;
+        registry.register(Box::new(notifications_missed_total.clone()))?;
+        registry.register(Box::new(route_probe_duration.clone()))?;
+        registry.register(Box::new(route_probes_total.clone()))?;
+        registry.register(Box::new(route_healthy.clone()))?;
+
+        Ok(Self {
+            registry,
+            quote_duration,
+            quotes_total,
+            payment_duration,
+            payments_total,
+            event_duration,
+            events_total,
+            notification_lags_total,
+            notifications_missed_total,
+            route_probe_duration,
+            route_probes_total,
+            route_healthy,
+        })
+    }
+
+    /// Record the outcome of a `get_payment_quote` call
+    pub fn record_quote<T, E>(&self, method: &str, result: &Result<T, E>, elapsed: Duration) {
+        let outcome = match result {
+            Ok(_) => "ok",
+            Err(_) => "error",
+        };
+
+        self.quote_duration
+            .with_label_values(&[method, outcome])
+            .observe(elapsed.as_secs_f64());
+        self.quotes_total
+            .with_label_values(&[method, outcome])
+            .inc();
+    }
+
+    /// Record the outcome of a `make_payment` call
+    pub fn record_payment<E>(
+        &self,
+        method: &str,
+        result: &Result<crate::MakePaymentResponse, E>,
+        elapsed: Duration,
+    ) {
+        let outcome = match result {
+            Ok(response) => match response.status {
+                MeltQuoteState::Paid => "paid",
+                MeltQuoteState::Failed => "failed",
+                MeltQuoteState::Pending => "pending",
+                _ => "unknown",
+            },
+            Err(_) => "error",
+        };
+
+        self.payment_duration
+            .with_label_values(&[method, outcome])
+            .observe(elapsed.as_secs_f64());
+        self.payments_total
+            .with_label_values(&[method, outcome])
+            .inc();
+    }
+
+    /// Record a handled node event
+    pub fn record_event(&self, event: &str, elapsed: Duration) {
+        self.event_duration
+            .with_label_values(&[event])
+            .observe(elapsed.as_secs_f64());
+        self.events_total.with_label_values(&[event]).inc();
+    }
+
+    /// Record a payment notification consumer lagging behind
+    pub fn record_notification_lag(&self, missed: u64) {
+        self.notification_lags_total.inc();
+        self.notifications_missed_total.inc_by(missed);
+    }
+
+    /// Record the outcome of a route health probe
+    pub fn record_route_probe(&self, result: &crate::routes::ProbeResult) {
+        let destination = result.destination.to_string();
+        let outcome = match result.error {
+            None => "ok",
+            Some(_) => "error",
+        };
+
+        self.route_probe_duration
+            .with_label_values(&[&destination])
+            .observe(result.latency.as_secs_f64());
+        self.route_probes_total
+            .with_label_values(&[&destination, outcome])
+            .inc();
+        self.route_healthy
+            .with_label_values(&[&destination])
+            .set(result.error.is_none() as i64);
+    }
+
+    /// Encode all metrics in the Prometheus text format
+    pub fn encode(&self) -> anyhow::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+/// Metric label for the payment method of outgoing payment options
+pub fn payment_method_label(options: &OutgoingPaymentOptions) -> &'static str {
+    match options {
+        OutgoingPaymentOptions::Bolt11(_) => "bolt11",
+        OutgoingPaymentOptions::Bolt12(_) => "bolt12",
+    }
+}
+
+/// Metric label for a node event
+pub fn event_label(event: &Event) -> &'static str {
+    match event {
+        Event::PaymentSuccessful { .. } => "payment_successful",
+        Event::PaymentFailed { .. } => "payment_failed",
+        Event::PaymentReceived { .. } => "payment_received",
+        Event::ChannelPending { .. } => "channel_pending",
+        Event::ChannelReady { .. } => "channel_ready",
+        Event::ChannelClosed { .. } => "channel_closed",
+        _ => "other",
+    }
+}
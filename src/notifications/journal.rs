@@ -0,0 +1,305 @@
+//! Durable journal of incoming payment notifications
+//!
+//! Notifications are only kept in memory until a consumer reads them, so a
+//! restart between a payment arriving and the mint reading its notification
+//! would lose it. Every notification is appended to the journal before it is
+//! queued, and acknowledged once the mint asked a payment processor stream
+//! for the next notification after it. Notifications left unacknowledged by
+//! the previous run are replayed to every consumer that attaches, until one
+//! acknowledges them, so the mint may see a notification twice but never
+//! misses one.
+//!
+//! The journal is compacted to its unacknowledged entries when it is opened.
+
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use cdk_common::payment::WaitPaymentResponse;
+use cdk_common::util::unix_time;
+use serde::{Deserialize, Serialize};
+
+use super::PaymentNotification;
+
+/// File the journal is kept in, inside the storage directory
+pub const NOTIFICATION_JOURNAL_FILE_NAME: &str = "payment_notifications.jsonl";
+
+/// Journaled notification
+///
+/// Every change appends the full entry, the last line of a payment id wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub response: WaitPaymentResponse,
+    pub preimage: Option<String>,
+    pub secret: Option<String>,
+    pub received_at: u64,
+    pub acknowledged: bool,
+}
+
+impl JournalEntry {
+    fn notification(&self) -> PaymentNotification {
+        PaymentNotification {
+            response: self.response.clone(),
+            preimage: self.preimage.clone(),
+            secret: self.secret.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct JournalState {
+    /// Unacknowledged entries by payment id
+    pending: HashMap<String, JournalEntry>,
+    /// Payment ids left unacknowledged by the previous run
+    carried_over: Vec<String>,
+}
+
+/// Durable journal of incoming payment notifications
+#[derive(Debug)]
+pub struct NotificationJournal {
+    path: PathBuf,
+    state: Mutex<JournalState>,
+}
+
+impl NotificationJournal {
+    /// Open the journal in the storage directory, keeping only the
+    /// notifications the previous run left unacknowledged
+    pub fn open(storage_dir: &Path) -> Result<Self> {
+        let path = storage_dir.join(NOTIFICATION_JOURNAL_FILE_NAME);
+
+        let mut pending: HashMap<String, JournalEntry> = HashMap::new();
+        if let Ok(contents) = std::fs::read_to_string(&path) {
+            for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+                match serde_json::from_str::<JournalEntry>(line) {
+                    Ok(entry) if entry.acknowledged => {
+                        pending.remove(&entry.response.payment_id);
+                    }
+                    Ok(entry) => {
+                        pending.insert(entry.response.payment_id.clone(), entry);
+                    }
+                    Err(err) => tracing::warn!(
+                        "Skipping unreadable payment notification in {}: {}",
+                        path.display(),
+                        err
+                    ),
+                }
+            }
+        }
+
+        let mut carried_over: Vec<&JournalEntry> = pending.values().collect();
+        carried_over.sort_by_key(|entry| entry.received_at);
+
+        let mut contents = String::new();
+        for entry in &carried_over {
+            contents.push_str(&serde_json::to_string(entry)?);
+            contents.push('\n');
+        }
+
+        let tmp_path = path.with_extension("jsonl.tmp");
+        std::fs::write(&tmp_path, contents)?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Could not compact {}", path.display()))?;
+
+        if !carried_over.is_empty() {
+            tracing::info!(
+                "{} payment notifications were not acknowledged before the restart",
+                carried_over.len()
+            );
+        }
+
+        let carried_over = carried_over
+            .iter()
+            .map(|entry| entry.response.payment_id.clone())
+            .collect();
+
+        Ok(Self {
+            path,
+            state: Mutex::new(JournalState {
+                pending,
+                carried_over,
+            }),
+        })
+    }
+
+    /// Record a notification before it is queued
+    pub fn record(&self, notification: &PaymentNotification) -> Result<()> {
+        let entry = JournalEntry {
+            response: notification.response.clone(),
+            preimage: notification.preimage.clone(),
+            secret: notification.secret.clone(),
+            received_at: unix_time(),
+            acknowledged: false,
+        };
+
+        let mut state = self.state.lock().expect("journal lock poisoned");
+        self.append(&entry)?;
+        state
+            .pending
+            .insert(entry.response.payment_id.clone(), entry);
+        Ok(())
+    }
+
+    /// Mark a notification as handled by the mint
+    pub fn acknowledge(&self, response: &WaitPaymentResponse) -> Result<()> {
+        let mut state = self.state.lock().expect("journal lock poisoned");
+
+        let Some(mut entry) = state.pending.remove(&response.payment_id) else {
+            return Ok(());
+        };
+        state
+            .carried_over
+            .retain(|payment_id| *payment_id != response.payment_id);
+
+        entry.acknowledged = true;
+        self.append(&entry)
+    }
+
+    /// Notifications the previous run left unacknowledged, oldest first
+    pub fn replay(&self) -> Vec<PaymentNotification> {
+        let state = self.state.lock().expect("journal lock poisoned");
+
+        state
+            .carried_over
+            .iter()
+            .filter_map(|payment_id| state.pending.get(payment_id))
+            .map(JournalEntry::notification)
+            .collect()
+    }
+
+    fn append(&self, entry: &JournalEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| {
+                file.write_all(line.as_bytes())?;
+                file.sync_data()
+            })
+            .with_context(|| {
+                format!(
+                    "Could not write payment notification to {}",
+                    self.path.display()
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cdk_common::payment::PaymentIdentifier;
+    use cdk_common::{Amount, CurrencyUnit};
+
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    fn notification(payment_id: &str) -> PaymentNotification {
+        PaymentNotification {
+            response: WaitPaymentResponse {
+                payment_identifier: PaymentIdentifier::CustomId(payment_id.to_string()),
+                payment_amount: Amount::from(1_000),
+                unit: CurrencyUnit::Sat,
+                payment_id: payment_id.to_string(),
+            },
+            preimage: Some("preimage".to_string()),
+            secret: None,
+        }
+    }
+
+    fn replayed(journal: &NotificationJournal) -> Vec<String> {
+        journal
+            .replay()
+            .into_iter()
+            .map(|notification| notification.response.payment_id)
+            .collect()
+    }
+
+    #[test]
+    fn unacknowledged_notifications_are_replayed_after_a_restart() {
+        let dir = temp_dir("journal-replay");
+        let journal = NotificationJournal::open(&dir).unwrap();
+        assert!(journal.replay().is_empty());
+
+        journal.record(&notification("a")).unwrap();
+        journal.record(&notification("b")).unwrap();
+        journal.record(&notification("c")).unwrap();
+        journal.acknowledge(&notification("b").response).unwrap();
+
+        // Notifications of this run are not replayed by it
+        assert!(journal.replay().is_empty());
+
+        let journal = NotificationJournal::open(&dir).unwrap();
+        let mut carried_over = replayed(&journal);
+        carried_over.sort();
+        assert_eq!(carried_over, ["a", "c"]);
+        assert_eq!(journal.replay()[0].preimage.as_deref(), Some("preimage"));
+
+        // Acknowledged replays are not replayed again
+        journal.acknowledge(&notification("a").response).unwrap();
+        assert_eq!(replayed(&journal), ["c"]);
+        assert_eq!(replayed(&NotificationJournal::open(&dir).unwrap()), ["c"]);
+    }
+
+    #[test]
+    fn replays_are_oldest_first() {
+        let dir = temp_dir("journal-order");
+        let mut contents = String::new();
+        for (payment_id, received_at) in [("newer", 20), ("older", 10)] {
+            let notification = notification(payment_id);
+            let entry = JournalEntry {
+                response: notification.response,
+                preimage: notification.preimage,
+                secret: notification.secret,
+                received_at,
+                acknowledged: false,
+            };
+            contents.push_str(&serde_json::to_string(&entry).unwrap());
+            contents.push('\n');
+        }
+        std::fs::write(dir.join(NOTIFICATION_JOURNAL_FILE_NAME), contents).unwrap();
+
+        let journal = NotificationJournal::open(&dir).unwrap();
+        assert_eq!(replayed(&journal), ["older", "newer"]);
+    }
+
+    #[test]
+    fn the_journal_is_compacted_when_opened() {
+        let dir = temp_dir("journal-compaction");
+        let journal = NotificationJournal::open(&dir).unwrap();
+
+        journal.record(&notification("a")).unwrap();
+        journal.record(&notification("b")).unwrap();
+        journal.acknowledge(&notification("a").response).unwrap();
+
+        let path = dir.join(NOTIFICATION_JOURNAL_FILE_NAME);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        NotificationJournal::open(&dir).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let entries: Vec<JournalEntry> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].response.payment_id, "b");
+        assert!(!entries[0].acknowledged);
+    }
+
+    #[test]
+    fn unreadable_lines_are_skipped() {
+        let dir = temp_dir("journal-unreadable");
+        let journal = NotificationJournal::open(&dir).unwrap();
+        journal.record(&notification("a")).unwrap();
+
+        let path = dir.join(NOTIFICATION_JOURNAL_FILE_NAME);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"not json\n").unwrap();
+
+        assert_eq!(replayed(&NotificationJournal::open(&dir).unwrap()), ["a"]);
+    }
+}
@@ -0,0 +1,535 @@
+//! Incoming payment notifications fanned out to every attached consumer
+//!
+//! Each call to `wait_any_incoming_payment` registers a consumer with its own
+//! cursor into a shared log of notifications. A notification is kept until
+//! every registered consumer has read it, so an active and a standby mint can
+//! both be attached to one node and neither misses a [`WaitPaymentResponse`]
+//! because the other was faster, or because no consumer was connected when
+//! the payment arrived.
+//!
+//! A consumer more than the notifier's capacity behind, by default
+//! [`DEFAULT_NOTIFICATION_CAPACITY`], misses the oldest notifications. The lag is reported to the [`Reconciler`], which
+//! re-reads the payments received since the consumer's last notification so
+//! they are delivered anyway.
+//!
+//! With a [`NotificationJournal`] every notification is also persisted until
+//! a payment processor stream acknowledges it, so notifications pending at a
+//! restart are replayed to the consumers that attach afterwards. A stream
+//! acknowledges a notification when the mint asks for the next one, after it
+//! handled it, so delivery is at least once: a notification the mint was
+//! handling when the node stopped is delivered again.
+
+pub mod journal;
+pub mod sinks;
+
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use cdk_common::payment::WaitPaymentResponse;
+use cdk_common::util::unix_time;
+use futures::{Stream, StreamExt};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+pub use self::journal::NotificationJournal;
+
+/// Incoming payment notification
+#[derive(Debug, Clone)]
+pub struct PaymentNotification {
+    /// Notification passed to the mint
+    pub response: WaitPaymentResponse,
+    /// Hex encoded payment preimage, `None` if LDK has not persisted it yet
+    pub preimage: Option<String>,
+    /// Hex encoded payment secret
+    pub secret: Option<String>,
+}
+
+/// Default maximum number of notifications kept for consumers that have not
+/// read them
+pub const DEFAULT_NOTIFICATION_CAPACITY: usize = 10_000;
+
+/// Rebuilds notifications a lagging consumer missed
+///
+/// Called with the unix time the consumer's last delivered notification was
+/// queued and the number of notifications it missed. Returns the
+/// notifications for every payment received since that time.
+pub type Reconciler = Box<dyn Fn(u64, u64) -> Vec<PaymentNotification> + Send + Sync>;
+
+#[derive(Debug, Clone)]
+struct QueuedNotification {
+    notification: PaymentNotification,
+    /// Unix time the notification was queued
+    queued_at: u64,
+}
+
+#[derive(Debug, Default)]
+struct NotifierState {
+    /// Sequence number of the first entry in `notifications`
+    first_seq: u64,
+    notifications: VecDeque<QueuedNotification>,
+    /// Cursor of every registered consumer, the sequence number it reads next
+    cursors: HashMap<u64, u64>,
+    next_consumer_id: u64,
+}
+
+impl NotifierState {
+    fn next_seq(&self) -> u64 {
+        self.first_seq + self.notifications.len() as u64
+    }
+
+    /// Drop notifications every consumer has read
+    fn prune(&mut self) {
+        let Some(min_cursor) = self.cursors.values().min().copied() else {
+            // Keep notifications for the next consumer to attach
+            return;
+        };
+
+        while self.first_seq < min_cursor && self.notifications.pop_front().is_some() {
+            self.first_seq += 1;
+        }
+    }
+}
+
+/// Fan-out of incoming payment notifications with a cursor per consumer
+pub struct PaymentNotifier {
+    state: Mutex<NotifierState>,
+    notify: Notify,
+    capacity: usize,
+    reconciler: Option<Reconciler>,
+    journal: Option<Arc<NotificationJournal>>,
+}
+
+impl Default for PaymentNotifier {
+    fn default() -> Self {
+        Self {
+            state: Mutex::default(),
+            notify: Notify::new(),
+            capacity: DEFAULT_NOTIFICATION_CAPACITY,
+            reconciler: None,
+            journal: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for PaymentNotifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PaymentNotifier")
+            .field("state", &self.state)
+            .field("capacity", &self.capacity)
+            .field("has_reconciler", &self.reconciler.is_some())
+            .field("journal", &self.journal)
+            .finish()
+    }
+}
+
+impl PaymentNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a notifier that reconciles notifications missed by lagging consumers
+    pub fn with_reconciler(reconciler: Reconciler) -> Self {
+        Self {
+            reconciler: Some(reconciler),
+            ..Self::default()
+        }
+    }
+
+    /// Keep at most `capacity` notifications for consumers that have not read
+    /// them, consumers lagging further behind are reconciled
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Persist notifications in a journal until they are acknowledged
+    pub fn with_journal(mut self, journal: Arc<NotificationJournal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Queue a notification for every consumer
+    pub fn notify(&self, notification: PaymentNotification) {
+        if let Some(journal) = &self.journal {
+            if let Err(err) = journal.record(&notification) {
+                tracing::error!(
+                    "Could not journal notification for payment {}: {}",
+                    notification.response.payment_id,
+                    err
+                );
+            }
+        }
+
+        {
+            let mut state = self.state.lock().expect("notifier lock poisoned");
+
+            if state.notifications.len() >= self.capacity {
+                tracing::warn!(
+                    "Dropping oldest unread payment notification, {} are queued",
+                    state.notifications.len()
+                );
+                state.notifications.pop_front();
+                state.first_seq += 1;
+            }
+
+            state.notifications.push_back(QueuedNotification {
+                notification,
+                queued_at: unix_time(),
+            });
+        }
+
+        self.notify.notify_waiters();
+    }
+
+    /// Mark a notification as handled by the mint so it is not replayed
+    pub fn acknowledge(&self, response: &WaitPaymentResponse) {
+        let Some(journal) = &self.journal else {
+            return;
+        };
+
+        if let Err(err) = journal.acknowledge(response) {
+            tracing::error!(
+                "Could not acknowledge notification for payment {}: {}",
+                response.payment_id,
+                err
+            );
+        }
+    }
+
+    /// Acknowledge each notification of a payment processor stream once the
+    /// consumer asks for the next one
+    ///
+    /// The mint reads the next notification after it handled the previous
+    /// one, so a notification is only acknowledged once handled. The last
+    /// notification read before the stream ends stays unacknowledged and is
+    /// replayed after a restart.
+    pub fn acknowledge_on_next<S>(
+        self: &Arc<Self>,
+        stream: S,
+    ) -> Pin<Box<dyn Stream<Item = WaitPaymentResponse> + Send>>
+    where
+        S: Stream<Item = WaitPaymentResponse> + Send + 'static,
+    {
+        let notifier = self.clone();
+
+        Box::pin(futures::stream::unfold(
+            (Box::pin(stream), None::<WaitPaymentResponse>),
+            move |(mut stream, delivered)| {
+                let notifier = notifier.clone();
+                async move {
+                    if let Some(delivered) = delivered {
+                        notifier.acknowledge(&delivered);
+                    }
+
+                    let response = stream.next().await?;
+                    Some((response.clone(), (stream, Some(response))))
+                }
+            },
+        ))
+    }
+
+    /// Number of consumers currently attached
+    pub fn consumer_count(&self) -> usize {
+        self.state
+            .lock()
+            .expect("notifier lock poisoned")
+            .cursors
+            .len()
+    }
+
+    /// Register a consumer and return the stream of its notifications
+    ///
+    /// The consumer starts at the oldest retained notification, so
+    /// notifications that arrived while no consumer was attached are
+    /// delivered. A notification may be delivered to more than one consumer;
+    /// consumers are expected to handle payments idempotently by payment id.
+    /// Journaled notifications left unacknowledged before a restart are
+    /// delivered first. The consumer is unregistered when the stream is
+    /// dropped.
+    pub fn subscribe(
+        self: &Arc<Self>,
+        cancel_token: CancellationToken,
+    ) -> Pin<Box<dyn Stream<Item = WaitPaymentResponse> + Send>> {
+        Box::pin(
+            self.subscribe_notifications(cancel_token)
+                .map(|notification| notification.response),
+        )
+    }
+
+    /// Register a consumer and return the stream of its full notifications
+    ///
+    /// Same as [`PaymentNotifier::subscribe`] with the preimage and secret.
+    pub fn subscribe_notifications(
+        self: &Arc<Self>,
+        cancel_token: CancellationToken,
+    ) -> Pin<Box<dyn Stream<Item = PaymentNotification> + Send>> {
+        let consumer_id = {
+            let mut state = self.state.lock().expect("notifier lock poisoned");
+            let consumer_id = state.next_consumer_id;
+            state.next_consumer_id += 1;
+            let cursor = state.first_seq;
+            state.cursors.insert(consumer_id, cursor);
+            consumer_id
+        };
+
+        tracing::info!("Payment notification consumer {} attached", consumer_id);
+
+        let consumer = Consumer {
+            notifier: self.clone(),
+            consumer_id,
+            last_queued_at: unix_time(),
+            reconciled: self
+                .journal
+                .as_ref()
+                .map(|journal| journal.replay().into())
+                .unwrap_or_default(),
+        };
+
+        Box::pin(futures::stream::unfold(
+            (consumer, cancel_token),
+            |(mut consumer, cancel_token)| async move {
+                let notification = tokio::select! {
+                    _ = cancel_token.cancelled() => None,
+                    notification = consumer.next() => Some(notification),
+                }?;
+
+                Some((notification, (consumer, cancel_token)))
+            },
+        ))
+    }
+}
+
+/// Registered consumer, unregistered on drop
+struct Consumer {
+    notifier: Arc<PaymentNotifier>,
+    consumer_id: u64,
+    /// Unix time the last delivered notification was queued, or the attach time
+    last_queued_at: u64,
+    /// Notifications replayed from the journal or rebuilt after the consumer
+    /// lagged, delivered first
+    reconciled: VecDeque<PaymentNotification>,
+}
+
+impl Consumer {
+    /// Wait for the next notification at this consumer's cursor
+    async fn next(&mut self) -> PaymentNotification {
+        loop {
+            if let Some(notification) = self.reconciled.pop_front() {
+                return notification;
+            }
+
+            // Register for wakeups before checking so a notification sent in
+            // between is not missed
+            let notified = self.notifier.notify.notified();
+
+            match self.try_next() {
+                Next::Notification(queued) => {
+                    self.last_queued_at = queued.queued_at;
+                    return queued.notification;
+                }
+                Next::Lagged(missed) => self.reconcile(missed),
+                Next::Empty => notified.await,
+            }
+        }
+    }
+
+    fn try_next(&self) -> Next {
+        let mut state = self.notifier.state.lock().expect("notifier lock poisoned");
+
+        let first_seq = state.first_seq;
+        let next_seq = state.next_seq();
+        let Some(cursor) = state.cursors.get_mut(&self.consumer_id) else {
+            return Next::Empty;
+        };
+
+        if *cursor < first_seq {
+            let missed = first_seq - *cursor;
+            *cursor = first_seq;
+            return Next::Lagged(missed);
+        }
+
+        if *cursor >= next_seq {
+            return Next::Empty;
+        }
+
+        let index = (*cursor - first_seq) as usize;
+        *cursor += 1;
+
+        let notification = state.notifications.get(index).cloned();
+        state.prune();
+
+        match notification {
+            Some(notification) => Next::Notification(notification),
+            None => Next::Empty,
+        }
+    }
+
+    /// Rebuild the notifications missed while lagging
+    fn reconcile(&mut self, missed: u64) {
+        tracing::warn!(
+            "Payment notification consumer {} lagged and missed {} notifications",
+            self.consumer_id,
+            missed
+        );
+
+        let Some(reconciler) = &self.notifier.reconciler else {
+            return;
+        };
+
+        let notifications = reconciler(self.last_queued_at, missed);
+        tracing::info!(
+            "Reconciled {} payments for notification consumer {} since {}",
+            notifications.len(),
+            self.consumer_id,
+            self.last_queued_at
+        );
+        self.reconciled.extend(notifications);
+    }
+}
+
+/// Outcome of reading at a consumer's cursor
+enum Next {
+    Notification(QueuedNotification),
+    Lagged(u64),
+    Empty,
+}
+
+impl Drop for Consumer {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.notifier.state.lock() {
+            state.cursors.remove(&self.consumer_id);
+            state.prune();
+        }
+
+        tracing::info!(
+            "Payment notification consumer {} detached",
+            self.consumer_id
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cdk_common::payment::PaymentIdentifier;
+    use cdk_common::{Amount, CurrencyUnit};
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    fn notification(payment_id: &str) -> PaymentNotification {
+        PaymentNotification {
+            response: WaitPaymentResponse {
+                payment_identifier: PaymentIdentifier::CustomId(payment_id.to_string()),
+                payment_amount: Amount::from(1_000),
+                unit: CurrencyUnit::Sat,
+                payment_id: payment_id.to_string(),
+            },
+            preimage: None,
+            secret: None,
+        }
+    }
+
+    fn replayed(journal: &NotificationJournal) -> Vec<String> {
+        journal
+            .replay()
+            .into_iter()
+            .map(|notification| notification.response.payment_id)
+            .collect()
+    }
+
+    #[test]
+    fn notifications_are_acknowledged_when_the_next_is_read() {
+        let dir = temp_dir("notifier-acknowledge");
+        let journal = Arc::new(NotificationJournal::open(&dir).unwrap());
+        let notifier = Arc::new(PaymentNotifier::new().with_journal(journal));
+        let mut stream = notifier.acknowledge_on_next(notifier.subscribe(CancellationToken::new()));
+
+        notifier.notify(notification("a"));
+        notifier.notify(notification("b"));
+
+        assert_eq!(block_on(stream.next()).unwrap().payment_id, "a");
+        // Read but not handled yet, a restart replays it
+        assert_eq!(
+            replayed(&NotificationJournal::open(&dir).unwrap()),
+            ["a", "b"]
+        );
+
+        assert_eq!(block_on(stream.next()).unwrap().payment_id, "b");
+        assert_eq!(replayed(&NotificationJournal::open(&dir).unwrap()), ["b"]);
+    }
+
+    fn payment_ids(
+        stream: &mut Pin<Box<dyn Stream<Item = WaitPaymentResponse> + Send>>,
+        count: usize,
+    ) -> Vec<String> {
+        (0..count)
+            .map(|_| block_on(stream.next()).unwrap().payment_id)
+            .collect()
+    }
+
+    #[test]
+    fn every_consumer_reads_every_notification() {
+        let notifier = Arc::new(PaymentNotifier::new());
+        let mut first = notifier.subscribe(CancellationToken::new());
+        let mut second = notifier.subscribe(CancellationToken::new());
+
+        notifier.notify(notification("a"));
+        notifier.notify(notification("b"));
+
+        assert_eq!(payment_ids(&mut first, 2), ["a", "b"]);
+        notifier.notify(notification("c"));
+        assert_eq!(payment_ids(&mut second, 3), ["a", "b", "c"]);
+        assert_eq!(payment_ids(&mut first, 1), ["c"]);
+    }
+
+    #[test]
+    fn notifications_are_kept_until_every_consumer_read_them() {
+        let notifier = Arc::new(PaymentNotifier::new());
+
+        // Queued while no consumer is attached
+        notifier.notify(notification("a"));
+        let mut first = notifier.subscribe(CancellationToken::new());
+        assert_eq!(payment_ids(&mut first, 1), ["a"]);
+
+        let mut second = notifier.subscribe(CancellationToken::new());
+        notifier.notify(notification("b"));
+        assert_eq!(payment_ids(&mut first, 1), ["b"]);
+        assert_eq!(notifier.consumer_count(), 2);
+
+        // Read by one consumer, the other still gets it
+        assert_eq!(payment_ids(&mut second, 1), ["b"]);
+        assert!(notifier.state.lock().unwrap().notifications.is_empty());
+
+        drop(second);
+        assert_eq!(notifier.consumer_count(), 1);
+    }
+
+    #[test]
+    fn lagging_consumers_are_reconciled() {
+        let reconciler: Reconciler = Box::new(|_, missed| {
+            (0..missed)
+                .map(|i| notification(&format!("reconciled-{i}")))
+                .collect()
+        });
+        let notifier = Arc::new(PaymentNotifier::with_reconciler(reconciler).with_capacity(2));
+        let mut stream = notifier.subscribe(CancellationToken::new());
+
+        notifier.notify(notification("a"));
+        notifier.notify(notification("b"));
+        notifier.notify(notification("c"));
+
+        assert_eq!(payment_ids(&mut stream, 3), ["reconciled-0", "b", "c"]);
+    }
+
+    #[test]
+    fn cancelled_streams_end() {
+        let notifier = Arc::new(PaymentNotifier::new());
+        let cancel_token = CancellationToken::new();
+        let mut stream = notifier.subscribe(cancel_token.clone());
+
+        cancel_token.cancel();
+        assert!(block_on(stream.next()).is_none());
+    }
+}
@@ -0,0 +1,931 @@
+//! Pluggable sinks for payment and channel notifications
+//!
+//! Every [`NodeNotification`] produced by the event handler is passed to each
+//! registered [`NotificationSink`]. Sinks run on their own task with their own
+//! queue, so a slow webhook neither delays the event handler nor the other
+//! sinks, and each sink sees notifications in the order they were produced.
+//!
+//! Sinks are best effort: a notification is dropped when the sink's queue is
+//! full or delivery fails. The mint's incoming payment stream does not go
+//! through sinks, see [`PaymentNotifier`](super::PaymentNotifier).
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use cdk_common::payment::PaymentIdentifier;
+use cdk_common::util::unix_time;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use nostr_sdk::prelude::{nip04, Client, EventBuilder, Keys, Kind, PublicKey, Tag};
+use serde_json::{json, Value};
+use tokio::sync::{broadcast, mpsc, OnceCell};
+use tokio_util::sync::CancellationToken;
+
+use super::PaymentNotification;
+use crate::summary::Summary;
+
+/// Notifications queued per sink before new ones are dropped
+pub const SINK_QUEUE_SIZE: usize = 1024;
+
+/// Default timeout of a webhook request
+pub const DEFAULT_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default timeout of an SMTP connection
+pub const DEFAULT_SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Event kind of NIP-47 wallet notifications
+const NWC_NOTIFICATION_KIND: u16 = 23196;
+
+/// Payment or channel event passed to notification sinks
+#[derive(Debug, Clone)]
+pub enum NodeNotification {
+    PaymentReceived {
+        /// Payment hash for BOLT11 and BOLT12 payments
+        payment_id: String,
+        /// Offer paid, for BOLT12 payments
+        offer_id: Option<String>,
+        amount_msat: u64,
+        preimage: Option<String>,
+        /// Tenant the invoice or offer was created for, see [`tenants`](crate::tenants)
+        tenant: Option<String>,
+        timestamp: u64,
+    },
+    PaymentSent {
+        payment_id: String,
+        payment_hash: Option<String>,
+        amount_msat: u64,
+        fee_msat: u64,
+        preimage: Option<String>,
+        timestamp: u64,
+    },
+    PaymentFailed {
+        payment_id: Option<String>,
+        payment_hash: Option<String>,
+        reason: Option<String>,
+        timestamp: u64,
+    },
+    ChannelReady {
+        channel_id: String,
+        counterparty_node_id: Option<String>,
+        timestamp: u64,
+    },
+    ChannelClosed {
+        channel_id: String,
+        counterparty_node_id: Option<String>,
+        reason: Option<String>,
+        timestamp: u64,
+    },
+    /// Decision on a zero-conf inbound channel, only trusted LSPs are accepted
+    ZeroConfChannel {
+        channel_id: String,
+        counterparty_node_id: String,
+        accepted: bool,
+        timestamp: u64,
+    },
+    /// Payment to a hold invoice held until it is settled or cancelled,
+    /// see [`hold`](crate::hold)
+    HoldInvoiceAccepted {
+        payment_hash: String,
+        amount_msat: u64,
+        /// Block height by which the payment must be settled
+        claim_deadline: Option<u32>,
+        timestamp: u64,
+    },
+    /// Alert rule raised or resolved, see [`alerts`](crate::alerts)
+    Alert {
+        rule: String,
+        message: String,
+        resolved: bool,
+        timestamp: u64,
+    },
+    /// Scheduled summary of the node's activity, see [`summary`](crate::summary)
+    Summary(Summary),
+}
+
+impl NodeNotification {
+    /// Notification for a received payment queued for the mint
+    pub fn payment_received(
+        notification: &PaymentNotification,
+        amount_msat: u64,
+        tenant: Option<String>,
+    ) -> Self {
+        let offer_id = match &notification.response.payment_identifier {
+            PaymentIdentifier::OfferId(offer_id) => Some(offer_id.clone()),
+            _ => None,
+        };
+
+        NodeNotification::PaymentReceived {
+            payment_id: notification.response.payment_id.clone(),
+            offer_id,
+            amount_msat,
+            preimage: notification.preimage.clone(),
+            tenant,
+            timestamp: unix_time(),
+        }
+    }
+
+    /// Drop the preimage, a proof of payment the mint may not want to disclose
+    pub fn redact_preimage(&mut self) {
+        match self {
+            NodeNotification::PaymentReceived { preimage, .. }
+            | NodeNotification::PaymentSent { preimage, .. } => *preimage = None,
+            _ => (),
+        }
+    }
+
+    /// Type name used in the JSON representation
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NodeNotification::PaymentReceived { .. } => "payment_received",
+            NodeNotification::PaymentSent { .. } => "payment_sent",
+            NodeNotification::PaymentFailed { .. } => "payment_failed",
+            NodeNotification::ChannelReady { .. } => "channel_ready",
+            NodeNotification::ChannelClosed { .. } => "channel_closed",
+            NodeNotification::ZeroConfChannel { .. } => "zero_conf_channel",
+            NodeNotification::HoldInvoiceAccepted { .. } => "hold_invoice_accepted",
+            NodeNotification::Alert { .. } => "alert",
+            NodeNotification::Summary(_) => "summary",
+        }
+    }
+
+    /// JSON representation, as posted to webhooks and logged
+    pub fn to_json(&self) -> Value {
+        let mut value = match self {
+            NodeNotification::PaymentReceived {
+                payment_id,
+                offer_id,
+                amount_msat,
+                preimage,
+                tenant,
+                timestamp,
+            } => json!({
+                "payment_id": payment_id,
+                "offer_id": offer_id,
+                "amount_msat": amount_msat,
+                "preimage": preimage,
+                "tenant": tenant,
+                "timestamp": timestamp,
+            }),
+            NodeNotification::PaymentSent {
+                payment_id,
+                payment_hash,
+                amount_msat,
+                fee_msat,
+                preimage,
+                timestamp,
+            } => json!({
+                "payment_id": payment_id,
+                "payment_hash": payment_hash,
+                "amount_msat": amount_msat,
+                "fee_msat": fee_msat,
+                "preimage": preimage,
+                "timestamp": timestamp,
+            }),
+            NodeNotification::PaymentFailed {
+                payment_id,
+                payment_hash,
+                reason,
+                timestamp,
+            } => json!({
+                "payment_id": payment_id,
+                "payment_hash": payment_hash,
+                "reason": reason,
+                "timestamp": timestamp,
+            }),
+            NodeNotification::ChannelReady {
+                channel_id,
+                counterparty_node_id,
+                timestamp,
+            } => json!({
+                "channel_id": channel_id,
+                "counterparty_node_id": counterparty_node_id,
+                "timestamp": timestamp,
+            }),
+            NodeNotification::ChannelClosed {
+                channel_id,
+                counterparty_node_id,
+                reason,
+                timestamp,
+            } => json!({
+                "channel_id": channel_id,
+                "counterparty_node_id": counterparty_node_id,
+                "reason": reason,
+                "timestamp": timestamp,
+            }),
+            NodeNotification::ZeroConfChannel {
+                channel_id,
+                counterparty_node_id,
+                accepted,
+                timestamp,
+            } => json!({
+                "channel_id": channel_id,
+                "counterparty_node_id": counterparty_node_id,
+                "accepted": accepted,
+                "timestamp": timestamp,
+            }),
+            NodeNotification::HoldInvoiceAccepted {
+                payment_hash,
+                amount_msat,
+                claim_deadline,
+                timestamp,
+            } => json!({
+                "payment_hash": payment_hash,
+                "amount_msat": amount_msat,
+                "claim_deadline": claim_deadline,
+                "timestamp": timestamp,
+            }),
+            NodeNotification::Alert {
+                rule,
+                message,
+                resolved,
+                timestamp,
+            } => json!({
+                "rule": rule,
+                "message": message,
+                "resolved": resolved,
+                "timestamp": timestamp,
+            }),
+            NodeNotification::Summary(summary) => {
+                let mut value = serde_json::to_value(summary).unwrap_or_default();
+                value["failure_rate"] = json!(summary.failure_rate());
+                value
+            }
+        };
+
+        value["type"] = json!(self.kind());
+        value
+    }
+}
+
+/// Text of the notifications sent to chat sinks, `None` for other notifications
+///
+/// Chats only get alerts and summaries, every payment would drown them out.
+fn chat_message(notification: &NodeNotification) -> Option<String> {
+    match notification {
+        NodeNotification::Alert {
+            rule,
+            resolved: true,
+            ..
+        } => Some(format!("Alert {rule} resolved")),
+        NodeNotification::Alert {
+            rule,
+            message,
+            resolved: false,
+            ..
+        } => Some(format!("Alert {rule}: {message}")),
+        NodeNotification::Summary(summary) => Some(format!(
+            "Summary of the last {}h\n\
+             Received: {} payments, {} sat\n\
+             Sent: {} payments, {} sat, {} failed ({:.1}%)\n\
+             Fees paid: {} sat, earned: {} sat\n\
+             Channels opened: {}, closed: {}\n\
+             Lightning balance: {} sat\n\
+             Onchain balance: {} sat",
+            (summary.period_end - summary.period_start) / 3600,
+            summary.payments_received,
+            summary.received_msat / 1000,
+            summary.payments_sent,
+            summary.sent_msat / 1000,
+            summary.payments_failed,
+            summary.failure_rate().unwrap_or_default() * 100.0,
+            summary.fees_paid_msat / 1000,
+            summary.fees_earned_msat / 1000,
+            summary.channels_opened,
+            summary.channels_closed,
+            summary.lightning_balance_sat,
+            summary.onchain_balance_sat
+        )),
+        _ => None,
+    }
+}
+
+/// Destination for node notifications
+///
+/// Implement this to route notifications into another system and register it
+/// with [`CdkLdkNodeBuilder::add_notification_sink`](crate::builder::CdkLdkNodeBuilder::add_notification_sink).
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    /// Deliver a notification, errors are logged and the notification dropped
+    async fn send(&self, notification: &NodeNotification) -> Result<()>;
+}
+
+/// Registered sinks and the queues feeding them
+#[derive(Default)]
+pub struct NotificationSinks {
+    sinks: Vec<Arc<dyn NotificationSink>>,
+    /// Strip preimages before notifications reach the sinks
+    redact_preimages: bool,
+    queues: Mutex<Vec<(String, mpsc::Sender<NodeNotification>)>>,
+}
+
+impl std::fmt::Debug for NotificationSinks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NotificationSinks")
+            .field(
+                "sinks",
+                &self
+                    .sinks
+                    .iter()
+                    .map(|sink| sink.name())
+                    .collect::<Vec<_>>(),
+            )
+            .field("redact_preimages", &self.redact_preimages)
+            .finish()
+    }
+}
+
+impl NotificationSinks {
+    pub fn new(sinks: Vec<Arc<dyn NotificationSink>>, redact_preimages: bool) -> Self {
+        Self {
+            sinks,
+            redact_preimages,
+            queues: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Spawn a delivery task per sink, stopped by the cancel token
+    ///
+    /// Notifications dispatched before the sinks are started are dropped.
+    pub fn start(&self, cancel_token: CancellationToken) {
+        let mut queues = self.queues.lock().expect("sinks lock poisoned");
+        if !queues.is_empty() {
+            return;
+        }
+
+        for sink in &self.sinks {
+            let (sender, mut receiver) = mpsc::channel::<NodeNotification>(SINK_QUEUE_SIZE);
+            queues.push((sink.name().to_string(), sender));
+
+            let sink = sink.clone();
+            let cancel_token = cancel_token.clone();
+            tokio::spawn(async move {
+                loop {
+                    let notification = tokio::select! {
+                        _ = cancel_token.cancelled() => break,
+                        notification = receiver.recv() => match notification {
+                            Some(notification) => notification,
+                            None => break,
+                        },
+                    };
+
+                    if let Err(err) = sink.send(&notification).await {
+                        tracing::warn!(
+                            "Notification sink {} could not deliver {}: {:#}",
+                            sink.name(),
+                            notification.kind(),
+                            err
+                        );
+                    }
+                }
+                tracing::debug!("Notification sink {} stopped", sink.name());
+            });
+
+            tracing::info!("Started notification sink {}", sink.name());
+        }
+    }
+
+    /// Queue a notification for every sink
+    pub fn dispatch(&self, mut notification: NodeNotification) {
+        if self.redact_preimages {
+            notification.redact_preimage();
+        }
+
+        let queues = self.queues.lock().expect("sinks lock poisoned");
+
+        for (name, sender) in queues.iter() {
+            if let Err(err) = sender.try_send(notification.clone()) {
+                tracing::warn!(
+                    "Dropping {} notification for sink {}: {}",
+                    notification.kind(),
+                    name,
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Sink republishing notifications on a broadcast channel
+///
+/// Keep a clone of the sink to [`subscribe`](BroadcastSink::subscribe).
+/// Receivers that fall more than the channel capacity behind miss the oldest
+/// notifications.
+#[derive(Debug, Clone)]
+pub struct BroadcastSink {
+    sender: broadcast::Sender<NodeNotification>,
+}
+
+impl BroadcastSink {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<NodeNotification> {
+        self.sender.subscribe()
+    }
+}
+
+#[async_trait]
+impl NotificationSink for BroadcastSink {
+    fn name(&self) -> &str {
+        "broadcast"
+    }
+
+    async fn send(&self, notification: &NodeNotification) -> Result<()> {
+        // No receivers is not an error
+        let _ = self.sender.send(notification.clone());
+        Ok(())
+    }
+}
+
+/// Sink posting notifications as JSON to a URL
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    name: String,
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(url: String, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+
+        Ok(Self {
+            name: format!("webhook {url}"),
+            url,
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationSink for WebhookSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send(&self, notification: &NodeNotification) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(&notification.to_json())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Sink publishing NIP-47 Nostr Wallet Connect notifications
+///
+/// Only `payment_received` and `payment_sent` exist in NIP-47, alerts are
+/// sent as NIP-04 direct messages and other notifications are skipped.
+/// Notifications are NIP-04 encrypted to the wallet connection's client key
+/// and published to its relay.
+pub struct NwcSink {
+    relay_url: String,
+    keys: Keys,
+    client_pubkey: PublicKey,
+    client: Client,
+    connected: OnceCell<()>,
+}
+
+impl std::fmt::Debug for NwcSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NwcSink")
+            .field("relay_url", &self.relay_url)
+            .field("wallet_pubkey", &self.keys.public_key())
+            .field("client_pubkey", &self.client_pubkey)
+            .finish()
+    }
+}
+
+impl NwcSink {
+    /// Create a sink for a wallet connection
+    ///
+    /// `secret_key` is the wallet service key, `client_pubkey` the key of the
+    /// connected client, both hex or bech32.
+    pub fn new(relay_url: String, secret_key: &str, client_pubkey: &str) -> Result<Self> {
+        let keys = Keys::parse(secret_key).context("Invalid NWC secret key")?;
+        let client_pubkey = PublicKey::parse(client_pubkey).context("Invalid NWC client pubkey")?;
+
+        Ok(Self {
+            relay_url,
+            client: Client::new(keys.clone()),
+            keys,
+            client_pubkey,
+            connected: OnceCell::new(),
+        })
+    }
+
+    async fn connect(&self) -> Result<()> {
+        self.connected
+            .get_or_try_init(|| async {
+                self.client.add_relay(&self.relay_url).await?;
+                self.client.connect().await;
+                Ok::<_, anyhow::Error>(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// NIP-47 notification type and transaction, `None` if NIP-47 has no equivalent
+    fn nip47_notification(notification: &NodeNotification) -> Option<(&'static str, Value)> {
+        match notification {
+            NodeNotification::PaymentReceived {
+                payment_id,
+                amount_msat,
+                preimage,
+                timestamp,
+                ..
+            } => Some((
+                "payment_received",
+                json!({
+                    "type": "incoming",
+                    "payment_hash": payment_id,
+                    "preimage": preimage,
+                    "amount": amount_msat,
+                    "fees_paid": 0,
+                    "settled_at": timestamp,
+                }),
+            )),
+            NodeNotification::PaymentSent {
+                payment_hash,
+                amount_msat,
+                fee_msat,
+                preimage,
+                timestamp,
+                ..
+            } => Some((
+                "payment_sent",
+                json!({
+                    "type": "outgoing",
+                    "payment_hash": payment_hash,
+                    "preimage": preimage,
+                    "amount": amount_msat,
+                    "fees_paid": fee_msat,
+                    "settled_at": timestamp,
+                }),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for NwcSink {
+    fn name(&self) -> &str {
+        "nwc"
+    }
+
+    async fn send(&self, notification: &NodeNotification) -> Result<()> {
+        // NIP-47 has no alerts, they are sent to the client as direct messages
+        if let NodeNotification::Alert {
+            rule,
+            message,
+            resolved,
+            ..
+        } = notification
+        {
+            self.connect().await?;
+
+            let text = match resolved {
+                true => format!("Alert {rule} resolved"),
+                false => format!("Alert {rule}: {message}"),
+            };
+            let encrypted = nip04::encrypt(self.keys.secret_key(), &self.client_pubkey, text)
+                .map_err(|err| anyhow!("Could not encrypt alert: {err}"))?;
+
+            let event = EventBuilder::new(Kind::EncryptedDirectMessage, encrypted)
+                .tag(Tag::public_key(self.client_pubkey));
+            self.client.send_event_builder(event).await?;
+
+            return Ok(());
+        }
+
+        let Some((notification_type, transaction)) = Self::nip47_notification(notification) else {
+            return Ok(());
+        };
+
+        self.connect().await?;
+
+        let content = json!({
+            "notification_type": notification_type,
+            "notification": transaction,
+        })
+        .to_string();
+        let encrypted = nip04::encrypt(self.keys.secret_key(), &self.client_pubkey, content)
+            .map_err(|err| anyhow!("Could not encrypt NWC notification: {err}"))?;
+
+        let event = EventBuilder::new(Kind::Custom(NWC_NOTIFICATION_KIND), encrypted)
+            .tag(Tag::public_key(self.client_pubkey));
+        self.client.send_event_builder(event).await?;
+
+        Ok(())
+    }
+}
+
+/// Sink posting alerts and summaries to a Telegram chat through a bot
+pub struct TelegramSink {
+    url: String,
+    chat_id: String,
+    client: reqwest::Client,
+}
+
+impl std::fmt::Debug for TelegramSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The URL holds the bot token
+        f.debug_struct("TelegramSink")
+            .field("chat_id", &self.chat_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TelegramSink {
+    pub fn new(bot_token: &str, chat_id: String, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+
+        Ok(Self {
+            url: format!("https://api.telegram.org/bot{bot_token}/sendMessage"),
+            chat_id,
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationSink for TelegramSink {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    async fn send(&self, notification: &NodeNotification) -> Result<()> {
+        let Some(text) = chat_message(notification) else {
+            return Ok(());
+        };
+
+        self.client
+            .post(&self.url)
+            .json(&json!({
+                "chat_id": self.chat_id,
+                "text": text,
+            }))
+            .send()
+            .await
+            // Errors include the URL and with it the bot token
+            .map_err(|err| anyhow!("Could not reach Telegram: {}", err.without_url()))?
+            .error_for_status()
+            .map_err(|err| anyhow!("Telegram rejected the message: {}", err.without_url()))?;
+        Ok(())
+    }
+}
+
+/// Sink posting alerts and summaries to a Matrix room
+pub struct MatrixSink {
+    homeserver_url: String,
+    room_id: String,
+    access_token: String,
+    client: reqwest::Client,
+}
+
+impl std::fmt::Debug for MatrixSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MatrixSink")
+            .field("homeserver_url", &self.homeserver_url)
+            .field("room_id", &self.room_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MatrixSink {
+    pub fn new(
+        homeserver_url: String,
+        room_id: String,
+        access_token: String,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+
+        Ok(Self {
+            homeserver_url: homeserver_url.trim_end_matches('/').to_string(),
+            room_id,
+            access_token,
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationSink for MatrixSink {
+    fn name(&self) -> &str {
+        "matrix"
+    }
+
+    async fn send(&self, notification: &NodeNotification) -> Result<()> {
+        let Some(text) = chat_message(notification) else {
+            return Ok(());
+        };
+
+        // The transaction id makes retried requests idempotent, it only has
+        // to be unique per access token
+        let txn_id = format!("cdk-ldk-node-{}", unix_time_nanos());
+        let mut url = reqwest::Url::parse(&self.homeserver_url)?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Invalid Matrix homeserver URL"))?
+            .extend([
+                "_matrix",
+                "client",
+                "v3",
+                "rooms",
+                &self.room_id,
+                "send",
+                "m.room.message",
+                &txn_id,
+            ]);
+
+        self.client
+            .put(url)
+            .bearer_auth(&self.access_token)
+            .json(&json!({
+                "msgtype": "m.text",
+                "body": text,
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Nanoseconds since the unix epoch
+fn unix_time_nanos() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default()
+}
+
+/// How the SMTP connection is secured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// Upgrade a plain connection with STARTTLS, port 587 by default
+    #[default]
+    StartTls,
+    /// TLS from the start of the connection, port 465 by default
+    Tls,
+    /// Unencrypted, only for a relay on the same host, port 25 by default
+    None,
+}
+
+/// SMTP server and mailboxes of an [`SmtpSink`]
+#[derive(Debug, Clone)]
+pub struct SmtpSinkConfig {
+    pub server: String,
+    /// Port, the default of the TLS mode when `None`
+    pub port: Option<u16>,
+    pub tls: SmtpTls,
+    /// Username and password, `None` for servers without authentication
+    pub credentials: Option<(String, String)>,
+    pub from: String,
+    pub recipients: Vec<String>,
+    pub timeout: Duration,
+}
+
+/// Sink emailing alerts and channel closures
+///
+/// Meant for operators without a webhook receiver, so only notifications that
+/// need attention are sent: alerts and closed channels. Other notifications
+/// are skipped.
+pub struct SmtpSink {
+    name: String,
+    from: Mailbox,
+    recipients: Vec<Mailbox>,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl std::fmt::Debug for SmtpSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmtpSink")
+            .field("name", &self.name)
+            .field("from", &self.from)
+            .field("recipients", &self.recipients)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SmtpSink {
+    pub fn new(config: SmtpSinkConfig) -> Result<Self> {
+        let from: Mailbox = config
+            .from
+            .parse()
+            .with_context(|| format!("Invalid SMTP sender {}", config.from))?;
+        let recipients = config
+            .recipients
+            .iter()
+            .map(|recipient| {
+                recipient
+                    .parse::<Mailbox>()
+                    .with_context(|| format!("Invalid SMTP recipient {recipient}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if recipients.is_empty() {
+            bail!("SMTP notifications need at least one recipient");
+        }
+
+        let mut builder = match config.tls {
+            SmtpTls::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.server)?
+            }
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.server)?,
+            SmtpTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.server)
+            }
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let Some((username, password)) = config.credentials {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(Self {
+            name: format!("smtp {}", config.server),
+            from,
+            recipients,
+            transport: builder.timeout(Some(config.timeout)).build(),
+        })
+    }
+
+    /// Subject and body of the email, `None` if the notification is not emailed
+    fn email(notification: &NodeNotification) -> Option<(String, String)> {
+        let subject = match notification {
+            NodeNotification::Alert {
+                rule,
+                resolved: false,
+                ..
+            } => format!("Alert {rule} raised"),
+            NodeNotification::Alert {
+                rule,
+                resolved: true,
+                ..
+            } => format!("Alert {rule} resolved"),
+            NodeNotification::ChannelClosed { channel_id, .. } => {
+                format!("Channel {channel_id} closed")
+            }
+            _ => return None,
+        };
+
+        let body = serde_json::to_string_pretty(&notification.to_json()).ok()?;
+
+        Some((format!("[cdk-ldk-node] {subject}"), body))
+    }
+}
+
+#[async_trait]
+impl NotificationSink for SmtpSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send(&self, notification: &NodeNotification) -> Result<()> {
+        let Some((subject, body)) = Self::email(notification) else {
+            return Ok(());
+        };
+
+        let mut message = Message::builder().from(self.from.clone()).subject(subject);
+        for recipient in &self.recipients {
+            message = message.to(recipient.clone());
+        }
+        let message = message.body(body)?;
+
+        self.transport.send(message).await?;
+
+        Ok(())
+    }
+}
+
+/// Sink writing notifications to the node log
+#[derive(Debug, Clone, Default)]
+pub struct LogSink;
+
+#[async_trait]
+impl NotificationSink for LogSink {
+    fn name(&self) -> &str {
+        "log"
+    }
+
+    async fn send(&self, notification: &NodeNotification) -> Result<()> {
+        tracing::info!("Notification: {}", notification.to_json());
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+//! Policies for BOLT12 offers
+//!
+//! A mint offer can be paid any number of times, so it is open to dust and
+//! spam payments. An [`InvoiceRequestPolicy`] checks offers before they are
+//! created, while nothing has been paid yet.
+//!
+//! LDK Node answers invoice requests itself and claims the payments to its
+//! offers, with no hook to reject an invoice request before the invoice is
+//! issued or to fail a payment back. A payment that reached the node is
+//! therefore always credited to the mint: rejecting it then would leave the
+//! payer without ecash and the node with the funds. Limits on what a payer
+//! chooses, the amount paid to a variable amount offer or the payer note,
+//! cannot be enforced. Offers created by the node do not support quantities,
+//! so LDK rejects invoice requests for more than one item.
+
+pub mod pool;
+
+use anyhow::{bail, Result};
+
+/// Decides which BOLT12 offers the mint creates
+///
+/// Implement this for a mint's own rules and set it with
+/// [`CdkLdkNodeBuilder::set_invoice_request_policy`](crate::builder::CdkLdkNodeBuilder::set_invoice_request_policy).
+pub trait InvoiceRequestPolicy: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    /// Check an offer before it is created, `None` for variable amount offers
+    ///
+    /// An error rejects the incoming payment request.
+    fn check_offer(&self, amount_msat: Option<u64>) -> Result<()>;
+}
+
+/// Limits on the offers created for mint quotes
+#[derive(Debug, Clone, Default)]
+pub struct Bolt12Limits {
+    /// Smallest amount of a fixed amount offer
+    pub min_amount_msat: Option<u64>,
+}
+
+impl InvoiceRequestPolicy for Bolt12Limits {
+    fn name(&self) -> &str {
+        "limits"
+    }
+
+    fn check_offer(&self, amount_msat: Option<u64>) -> Result<()> {
+        if let (Some(amount_msat), Some(min_amount_msat)) = (amount_msat, self.min_amount_msat) {
+            if amount_msat < min_amount_msat {
+                bail!("Offer amount of {amount_msat} msat is below the minimum of {min_amount_msat} msat");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_amount_offers_below_the_minimum_are_refused() {
+        let limits = Bolt12Limits {
+            min_amount_msat: Some(1_000),
+        };
+
+        assert!(limits.check_offer(Some(999)).is_err());
+        assert!(limits.check_offer(Some(1_000)).is_ok());
+    }
+
+    #[test]
+    fn variable_amount_offers_are_not_limited() {
+        let limits = Bolt12Limits {
+            min_amount_msat: Some(1_000),
+        };
+
+        assert!(limits.check_offer(None).is_ok());
+        assert!(Bolt12Limits::default().check_offer(Some(1)).is_ok());
+    }
+}
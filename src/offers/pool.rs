@@ -0,0 +1,143 @@
+//! Pool of rotating offers for variable amount mint quotes
+//!
+//! Instead of a new offer per quote, variable amount mint quotes share the
+//! current offer of the pool, which is replaced after a number of quotes or
+//! once it reaches a maximum age. Every offer handed out is kept, as LDK Node
+//! keeps accepting payments to retired offers and their payments are looked
+//! up by offer id.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use ldk_node::lightning::offers::offer::Offer;
+use serde::{Deserialize, Serialize};
+
+/// File pooled offers are kept in, inside the storage directory
+pub const OFFERS_FILE_NAME: &str = "offers.jsonl";
+
+/// When the current offer of the pool is replaced, both limits apply when set
+#[derive(Debug, Clone, Default)]
+pub struct OfferRotation {
+    /// Quotes an offer is handed out to before it is replaced
+    pub max_uses: Option<u32>,
+    /// Age after which an offer is replaced
+    pub max_age: Option<Duration>,
+}
+
+/// Offer handed out by the pool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PooledOffer {
+    pub offer_id: String,
+    pub offer: String,
+    /// Unix time the offer was created
+    pub created_at: u64,
+    /// Quotes the offer was handed out to
+    pub uses: u32,
+}
+
+impl PooledOffer {
+    fn is_due(&self, rotation: &OfferRotation, now: u64) -> bool {
+        let used_up = rotation
+            .max_uses
+            .is_some_and(|max_uses| self.uses >= max_uses);
+        let expired = rotation
+            .max_age
+            .is_some_and(|max_age| now >= self.created_at + max_age.as_secs());
+
+        used_up || expired
+    }
+}
+
+/// Persistent pool of offers, the last one is current
+#[derive(Debug)]
+pub struct OfferPool {
+    path: PathBuf,
+    rotation: OfferRotation,
+    offers: Mutex<Vec<PooledOffer>>,
+}
+
+impl OfferPool {
+    /// Open the pool in the storage directory, starting empty if no offers exist
+    pub fn new(storage_dir: PathBuf, rotation: OfferRotation) -> Self {
+        let path = storage_dir.join(OFFERS_FILE_NAME);
+
+        let mut offers: Vec<PooledOffer> = vec![];
+        if let Ok(contents) = std::fs::read_to_string(&path) {
+            for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+                match serde_json::from_str::<PooledOffer>(line) {
+                    // Records are appended on every use, the last one of an offer wins
+                    Ok(offer) => match offers.iter_mut().find(|o| o.offer_id == offer.offer_id) {
+                        Some(existing) => *existing = offer,
+                        None => offers.push(offer),
+                    },
+                    Err(err) => {
+                        tracing::warn!("Skipping unreadable offer in {}: {}", path.display(), err);
+                    }
+                }
+            }
+        }
+
+        Self {
+            path,
+            rotation,
+            offers: Mutex::new(offers),
+        }
+    }
+
+    /// Hand out the current offer for a quote
+    ///
+    /// A new offer is created with `create` when the pool is empty or the
+    /// current offer is due for rotation.
+    pub fn next_offer(&self, now: u64, create: impl FnOnce() -> Result<Offer>) -> Result<Offer> {
+        let mut offers = self.offers.lock().expect("offer pool lock poisoned");
+
+        let mut current = match offers.last() {
+            Some(current) if !current.is_due(&self.rotation, now) => current.clone(),
+            _ => {
+                let offer = create()?;
+                tracing::info!("Rotated mint quote offer to {}", offer.id());
+
+                PooledOffer {
+                    offer_id: offer.id().to_string(),
+                    offer: offer.to_string(),
+                    created_at: now,
+                    uses: 0,
+                }
+            }
+        };
+        current.uses += 1;
+
+        let offer = Offer::from_str(&current.offer)
+            .map_err(|err| anyhow!("Could not parse pooled offer: {err:?}"))?;
+
+        let mut line = serde_json::to_string(&current)?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .with_context(|| format!("Could not write offer to {}", self.path.display()))?;
+
+        match offers.last_mut() {
+            Some(last) if last.offer_id == current.offer_id => *last = current,
+            _ => offers.push(current),
+        }
+
+        Ok(offer)
+    }
+
+    /// All offers handed out, oldest first
+    pub fn list(&self) -> Vec<PooledOffer> {
+        self.offers
+            .lock()
+            .expect("offer pool lock poisoned")
+            .clone()
+    }
+}
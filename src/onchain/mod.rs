@@ -0,0 +1,171 @@
+//! Onchain address issuing
+//!
+//! LDK Node reveals a fresh address on every call, which quickly runs past
+//! the gap limit of external watch-only wallets when addresses are requested
+//! but never paid. In reuse mode the last issued address is handed out again
+//! until the wallet has seen an onchain transaction since it was issued.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use anyhow::{bail, Context, Result};
+use cdk_common::util::unix_time;
+use ldk_node::bitcoin::{Address, Network};
+use ldk_node::payment::PaymentKind;
+use ldk_node::Node;
+use serde::{Deserialize, Serialize};
+
+/// File the last issued address is kept in, inside the storage directory
+const LAST_ADDRESS_FILE_NAME: &str = "last_address.json";
+
+/// Parse an address, which must be for `network`
+///
+/// Testnet and signet addresses share their encoding, so the error names
+/// every network the address is valid on.
+pub fn parse_address(address: &str, network: Network) -> Result<Address> {
+    let unchecked =
+        Address::from_str(address).with_context(|| format!("{address} is not an address"))?;
+
+    if unchecked.is_valid_for_network(network) {
+        return Ok(unchecked.assume_checked());
+    }
+
+    let networks: Vec<String> = [
+        Network::Bitcoin,
+        Network::Testnet,
+        Network::Signet,
+        Network::Regtest,
+    ]
+    .into_iter()
+    .filter(|other| unchecked.is_valid_for_network(*other))
+    .map(|other| other.to_string())
+    .collect();
+
+    bail!(
+        "{address} is a {} address, the node runs on {network}",
+        networks.join(" or ")
+    )
+}
+
+/// Onchain address type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressType {
+    /// Native segwit v0 (P2WPKH), encoded as bech32
+    #[default]
+    Bech32,
+    /// Taproot (P2TR), encoded as bech32m
+    Bech32m,
+}
+
+impl fmt::Display for AddressType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressType::Bech32 => write!(f, "bech32"),
+            AddressType::Bech32m => write!(f, "bech32m"),
+        }
+    }
+}
+
+impl FromStr for AddressType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "bech32" | "p2wpkh" => Ok(AddressType::Bech32),
+            "bech32m" | "p2tr" | "taproot" => Ok(AddressType::Bech32m),
+            _ => bail!("Unknown address type {s}, expected bech32 or bech32m"),
+        }
+    }
+}
+
+/// Address handed out by the node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IssuedAddress {
+    address: String,
+    issued_at: u64,
+}
+
+/// Issues onchain addresses, optionally reusing the last unused one
+#[derive(Debug)]
+pub struct AddressIssuer {
+    path: PathBuf,
+    last: Mutex<Option<IssuedAddress>>,
+}
+
+impl AddressIssuer {
+    /// Create an issuer keeping its state in the storage directory
+    pub fn new(storage_dir: PathBuf) -> Self {
+        let path = storage_dir.join(LAST_ADDRESS_FILE_NAME);
+
+        let last = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+
+        Self {
+            path,
+            last: Mutex::new(last),
+        }
+    }
+
+    /// Get an address of the given type
+    ///
+    /// Returns the address and whether it was reused. With `reuse_unused` the
+    /// last issued address is returned again unless an onchain transaction was
+    /// recorded since it was issued. Any onchain transaction counts, so an
+    /// address may be replaced earlier than needed but is never reused after
+    /// it was paid.
+    pub fn new_address(
+        &self,
+        node: &Node,
+        address_type: AddressType,
+        reuse_unused: bool,
+    ) -> Result<(Address, bool)> {
+        if address_type != AddressType::Bech32 {
+            bail!(
+                "Address type {address_type} is not supported, LDK Node's onchain wallet only derives bech32 (P2WPKH) addresses"
+            );
+        }
+
+        let mut last = self.last.lock().expect("address lock poisoned");
+
+        if reuse_unused {
+            if let Some(issued) = last.as_ref() {
+                if !has_onchain_activity_since(node, issued.issued_at) {
+                    let address = Address::from_str(&issued.address)?
+                        .require_network(node.config().network)?;
+                    return Ok((address, true));
+                }
+            }
+        }
+
+        let address = node.onchain_payment().new_address()?;
+
+        let issued = IssuedAddress {
+            address: address.to_string(),
+            issued_at: unix_time(),
+        };
+        if let Err(err) = self.persist(&issued) {
+            tracing::warn!("Could not persist last issued address: {:#}", err);
+        }
+        *last = Some(issued);
+
+        Ok((address, false))
+    }
+
+    fn persist(&self, issued: &IssuedAddress) -> Result<()> {
+        let bytes = serde_json::to_vec(issued)?;
+        std::fs::write(&self.path, bytes)
+            .with_context(|| format!("Could not write {}", self.path.display()))
+    }
+}
+
+fn has_onchain_activity_since(node: &Node, since: u64) -> bool {
+    !node
+        .list_payments_with_filter(|payment| {
+            matches!(payment.kind, PaymentKind::Onchain { .. })
+                && payment.latest_update_timestamp >= since
+        })
+        .is_empty()
+}
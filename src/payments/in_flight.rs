@@ -0,0 +1,68 @@
+//! Melts sending a payment, keyed by what they pay
+//!
+//! A mint retrying a melt calls `make_payment` again for the same invoice.
+//! The melt holds the key of its payment, the payment hash of a BOLT11
+//! invoice, from before it looks up earlier payments until it has its
+//! result. A retry arriving while the first melt is still
+//! sending waits for it, then finds its payment instead of sending another.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Keys of the payments melts are sending
+#[derive(Debug, Default)]
+pub struct InFlightPayments {
+    locks: Mutex<HashMap<String, Arc<AsyncMutex<()>>>>,
+}
+
+impl InFlightPayments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait until no other melt holds `key`, then hold it until the guard is
+    /// dropped
+    pub async fn lock(&self, key: String) -> InFlightGuard<'_> {
+        let lock = self
+            .locks
+            .lock()
+            .expect("in-flight payments lock poisoned")
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        InFlightGuard {
+            payments: self,
+            key,
+            _guard: lock.lock_owned().await,
+        }
+    }
+}
+
+/// Key of a payment held by a melt
+#[derive(Debug)]
+pub struct InFlightGuard<'a> {
+    payments: &'a InFlightPayments,
+    key: String,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        let mut locks = self
+            .payments
+            .locks
+            .lock()
+            .expect("in-flight payments lock poisoned");
+
+        // Held by the map and this guard only, no melt is waiting for the key
+        if locks
+            .get(&self.key)
+            .is_some_and(|lock| Arc::strong_count(lock) <= 2)
+        {
+            locks.remove(&self.key);
+        }
+    }
+}
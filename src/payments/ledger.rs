@@ -0,0 +1,58 @@
+//! CSV ledger of indexed payments
+//!
+//! The ledger is produced in batches read from the payment index with a
+//! cursor, oldest payment first, so a history of any size is exported
+//! without holding it in memory.
+
+use super::IndexedPayment;
+
+/// Payments per batch when the request does not set a size
+pub const DEFAULT_LEDGER_BATCH_SIZE: u32 = 1000;
+
+/// Largest number of payments per batch
+pub const MAX_LEDGER_BATCH_SIZE: u32 = 10_000;
+
+/// Batches buffered for a client before reading more from the index waits
+pub const LEDGER_STREAM_BUFFER: usize = 4;
+
+/// Header line of the ledger
+pub const LEDGER_HEADER: &str = "payment_id,kind,direction,status,amount_msat,fee_paid_msat,payment_hash,created_at,updated_at,settled_at\n";
+
+/// Position in the ledger, the last payment of the previous batch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerCursor {
+    pub created_at: u64,
+    pub id: String,
+}
+
+impl From<&IndexedPayment> for LedgerCursor {
+    fn from(payment: &IndexedPayment) -> Self {
+        Self {
+            created_at: payment.created_at,
+            id: payment.id.clone(),
+        }
+    }
+}
+
+/// CSV lines of a batch of payments
+pub fn csv_rows(payments: &[IndexedPayment]) -> String {
+    let optional = |value: Option<u64>| value.map(|value| value.to_string()).unwrap_or_default();
+
+    let mut rows = String::new();
+    for payment in payments {
+        rows.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{}\n",
+            payment.id,
+            payment.kind,
+            payment.direction,
+            payment.status,
+            optional(payment.amount_msat),
+            optional(payment.fee_paid_msat),
+            payment.payment_hash.as_deref().unwrap_or_default(),
+            payment.created_at,
+            payment.updated_at,
+            optional(payment.settled_at),
+        ));
+    }
+    rows
+}
@@ -0,0 +1,485 @@
+//! SQLite index of payment records for filtered listings
+//!
+//! LDK Node can only list payments by scanning its whole payment store, which
+//! gets slow on large histories. The index keeps the fields listings filter
+//! on in a SQLite database in the storage directory, with indexes on the
+//! creation and settlement times and the amount.
+//!
+//! The index is derived from LDK Node's store: it is backfilled on start,
+//! updated on payment events and periodically refreshed with the payments
+//! that changed since, which picks up onchain payments and outgoing payments
+//! that are still pending. LDK Node does not record when a payment was
+//! created, so the creation time is the payment's update time when the index
+//! first saw it.
+
+pub mod in_flight;
+pub mod ledger;
+pub mod tracker;
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use cdk_common::amount::{self, to_unit};
+use cdk_common::util::hex;
+use cdk_common::{Amount, CurrencyUnit};
+use ldk_node::payment::{PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use self::ledger::LedgerCursor;
+use crate::accounting::FeesPaid;
+
+/// Index database, inside the storage directory
+pub const PAYMENT_INDEX_FILE_NAME: &str = "payment_index.sqlite";
+
+/// Interval between refreshes of the index from LDK Node's payment store
+pub const PAYMENT_INDEX_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Payments returned by a listing when no limit is given
+pub const DEFAULT_LIST_LIMIT: u32 = 100;
+
+/// Largest number of payments returned by one listing
+pub const MAX_LIST_LIMIT: u32 = 1000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS payments (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    direction TEXT NOT NULL,
+    status TEXT NOT NULL,
+    amount_msat INTEGER,
+    fee_paid_msat INTEGER,
+    payment_hash TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    settled_at INTEGER
+);
+CREATE INDEX IF NOT EXISTS payments_created_at ON payments (created_at);
+CREATE INDEX IF NOT EXISTS payments_settled_at ON payments (settled_at);
+CREATE INDEX IF NOT EXISTS payments_amount_msat ON payments (amount_msat);
+CREATE INDEX IF NOT EXISTS payments_updated_at ON payments (updated_at);
+";
+
+/// Filters of a payment listing, every filter set must match
+#[derive(Debug, Clone, Default)]
+pub struct PaymentFilter {
+    /// Created at or after this unix time
+    pub created_from: Option<u64>,
+    /// Created before this unix time
+    pub created_to: Option<u64>,
+    /// Settled at or after this unix time, excludes unsettled payments
+    pub settled_from: Option<u64>,
+    /// Settled before this unix time, excludes unsettled payments
+    pub settled_to: Option<u64>,
+    pub min_amount_msat: Option<u64>,
+    pub max_amount_msat: Option<u64>,
+    /// `inbound` or `outbound`
+    pub direction: Option<String>,
+    /// `pending`, `succeeded` or `failed`
+    pub status: Option<String>,
+    /// Payments returned, [`DEFAULT_LIST_LIMIT`] when unset
+    pub limit: Option<u32>,
+    /// Payments skipped, for paging
+    pub offset: Option<u32>,
+    /// Only payments with one of these payment hashes, for tenant listings
+    pub payment_hashes: Option<Vec<String>>,
+}
+
+/// Payment as recorded in the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedPayment {
+    pub id: String,
+    pub kind: String,
+    pub direction: String,
+    pub status: String,
+    pub amount_msat: Option<u64>,
+    pub fee_paid_msat: Option<u64>,
+    pub payment_hash: Option<String>,
+    /// Update time of the payment when the index first saw it
+    pub created_at: u64,
+    /// Unix time of the payment's latest update
+    pub updated_at: u64,
+    /// Unix time the payment succeeded, `None` unless it did
+    pub settled_at: Option<u64>,
+}
+
+/// Amount an outgoing payment spent including routing fees, zero unless it
+/// succeeded, `None` if a succeeded payment has no amount
+///
+/// Parts of a multi-path payment can fail and be retried over other paths
+/// while it is pending, and a failed payment releases all its parts, so only
+/// a settled payment has spent anything. `MakePaymentResponse` has no fee
+/// field, so the fee is reported to the mint as part of the amount spent.
+pub fn settled_spent_msat(payment: &PaymentDetails) -> Option<u64> {
+    match payment.status {
+        PaymentStatus::Succeeded => payment
+            .amount_msat
+            .map(|amount_msat| amount_msat + payment.fee_paid_msat.unwrap_or_default()),
+        PaymentStatus::Pending | PaymentStatus::Failed => Some(0),
+    }
+}
+
+/// Amount spent in msat converted to the mint's unit, rounded up
+///
+/// A routing fee that is a fraction of a sat still counts against the melt's
+/// fee reserve, so sat amounts are rounded up rather than truncated.
+pub fn spent_in_unit(spent_msat: u64, unit: &CurrencyUnit) -> Result<Amount, amount::Error> {
+    let spent_msat = match unit {
+        CurrencyUnit::Sat => spent_msat.div_ceil(1000).saturating_mul(1000),
+        _ => spent_msat,
+    };
+    to_unit(spent_msat, &CurrencyUnit::Msat, unit)
+}
+
+/// Name of a payment kind as stored in the index
+pub fn kind_name(kind: &PaymentKind) -> &'static str {
+    match kind {
+        PaymentKind::Onchain { .. } => "onchain",
+        PaymentKind::Bolt11 { .. } => "bolt11",
+        PaymentKind::Bolt11Jit { .. } => "bolt11_jit",
+        PaymentKind::Bolt12Offer { .. } => "bolt12_offer",
+        PaymentKind::Bolt12Refund { .. } => "bolt12_refund",
+        PaymentKind::Spontaneous { .. } => "spontaneous",
+    }
+}
+
+fn direction_name(direction: PaymentDirection) -> &'static str {
+    match direction {
+        PaymentDirection::Inbound => "inbound",
+        PaymentDirection::Outbound => "outbound",
+    }
+}
+
+fn status_name(status: PaymentStatus) -> &'static str {
+    match status {
+        PaymentStatus::Pending => "pending",
+        PaymentStatus::Succeeded => "succeeded",
+        PaymentStatus::Failed => "failed",
+    }
+}
+
+fn payment_hash(kind: &PaymentKind) -> Option<String> {
+    match kind {
+        PaymentKind::Onchain { .. } => None,
+        PaymentKind::Bolt11 { hash, .. }
+        | PaymentKind::Bolt11Jit { hash, .. }
+        | PaymentKind::Spontaneous { hash, .. } => Some(hash.to_string()),
+        PaymentKind::Bolt12Offer { hash, .. } | PaymentKind::Bolt12Refund { hash, .. } => {
+            hash.map(|hash| hash.to_string())
+        }
+    }
+}
+
+/// SQLite index of payment records
+#[derive(Debug)]
+pub struct PaymentIndex {
+    connection: Mutex<Connection>,
+}
+
+impl PaymentIndex {
+    /// Open the index in the storage directory, creating it if needed
+    pub fn open(storage_dir: &Path) -> Result<Self> {
+        let path = storage_dir.join(PAYMENT_INDEX_FILE_NAME);
+        let connection = Connection::open(&path)
+            .with_context(|| format!("Could not open payment index {}", path.display()))?;
+        connection
+            .execute_batch(SCHEMA)
+            .with_context(|| format!("Could not create payment index {}", path.display()))?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Insert or update payments, in one transaction
+    ///
+    /// The creation time of a payment already in the index is kept.
+    pub fn upsert(&self, payments: &[PaymentDetails], now: u64) -> Result<()> {
+        let mut connection = self.connection.lock().expect("payment index lock poisoned");
+        let transaction = connection.transaction()?;
+
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT INTO payments (id, kind, direction, status, amount_msat, fee_paid_msat,
+                     payment_hash, created_at, updated_at, settled_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                 ON CONFLICT (id) DO UPDATE SET
+                     status = excluded.status,
+                     amount_msat = excluded.amount_msat,
+                     fee_paid_msat = excluded.fee_paid_msat,
+                     payment_hash = excluded.payment_hash,
+                     updated_at = excluded.updated_at,
+                     settled_at = CASE WHEN excluded.status = 'succeeded'
+                         THEN COALESCE(payments.settled_at, excluded.settled_at) END",
+            )?;
+
+            for payment in payments {
+                let settled_at = (payment.status == PaymentStatus::Succeeded)
+                    .then_some(payment.latest_update_timestamp);
+
+                statement.execute(params![
+                    hex::encode(payment.id.0),
+                    kind_name(&payment.kind),
+                    direction_name(payment.direction),
+                    status_name(payment.status),
+                    payment.amount_msat,
+                    payment.fee_paid_msat,
+                    payment_hash(&payment.kind),
+                    payment.latest_update_timestamp.min(now),
+                    payment.latest_update_timestamp,
+                    settled_at,
+                ])?;
+            }
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Latest update time of the indexed payments, `None` if the index is empty
+    pub fn last_updated_at(&self) -> Result<Option<u64>> {
+        let connection = self.connection.lock().expect("payment index lock poisoned");
+        let last = connection
+            .query_row("SELECT MAX(updated_at) FROM payments", [], |row| {
+                row.get::<_, Option<u64>>(0)
+            })
+            .optional()?
+            .flatten();
+        Ok(last)
+    }
+
+    /// Remove payments in a final state last updated before `cutoff`, as
+    /// pruned from LDK Node's store
+    pub fn remove_final_before(&self, cutoff: u64) -> Result<usize> {
+        let connection = self.connection.lock().expect("payment index lock poisoned");
+        let removed = connection.execute(
+            "DELETE FROM payments WHERE status != 'pending' AND updated_at < ?1",
+            params![cutoff],
+        )?;
+        Ok(removed)
+    }
+
+    /// Payments matching the filter, newest first
+    pub fn list(&self, filter: &PaymentFilter) -> Result<Vec<IndexedPayment>> {
+        let limit = filter
+            .limit
+            .unwrap_or(DEFAULT_LIST_LIMIT)
+            .min(MAX_LIST_LIMIT);
+
+        let connection = self.connection.lock().expect("payment index lock poisoned");
+        let mut statement = connection.prepare_cached(
+            "SELECT id, kind, direction, status, amount_msat, fee_paid_msat, payment_hash,
+                 created_at, updated_at, settled_at
+             FROM payments
+             WHERE (?1 IS NULL OR created_at >= ?1)
+                 AND (?2 IS NULL OR created_at < ?2)
+                 AND (?3 IS NULL OR settled_at >= ?3)
+                 AND (?4 IS NULL OR settled_at < ?4)
+                 AND (?5 IS NULL OR amount_msat >= ?5)
+                 AND (?6 IS NULL OR amount_msat <= ?6)
+                 AND (?7 IS NULL OR direction = ?7)
+                 AND (?8 IS NULL OR status = ?8)
+                 AND (?11 IS NULL OR payment_hash IN (SELECT value FROM json_each(?11)))
+             ORDER BY created_at DESC, id
+             LIMIT ?9 OFFSET ?10",
+        )?;
+
+        let payment_hashes = filter
+            .payment_hashes
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+
+        let payments = statement
+            .query_map(
+                params![
+                    filter.created_from,
+                    filter.created_to,
+                    filter.settled_from,
+                    filter.settled_to,
+                    filter.min_amount_msat,
+                    filter.max_amount_msat,
+                    filter.direction,
+                    filter.status,
+                    limit,
+                    filter.offset.unwrap_or_default(),
+                    payment_hashes,
+                ],
+                indexed_payment,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(payments)
+    }
+
+    /// Next batch of the ledger, payments created between `from` and `to`
+    /// after the cursor, oldest first
+    pub fn ledger_batch(
+        &self,
+        after: Option<&LedgerCursor>,
+        from: Option<u64>,
+        to: Option<u64>,
+        limit: u32,
+    ) -> Result<Vec<IndexedPayment>> {
+        let connection = self.connection.lock().expect("payment index lock poisoned");
+        let mut statement = connection.prepare_cached(
+            "SELECT id, kind, direction, status, amount_msat, fee_paid_msat, payment_hash,
+                 created_at, updated_at, settled_at
+             FROM payments
+             WHERE (?1 IS NULL OR created_at > ?1 OR (created_at = ?1 AND id > ?2))
+                 AND (?3 IS NULL OR created_at >= ?3)
+                 AND (?4 IS NULL OR created_at < ?4)
+             ORDER BY created_at, id
+             LIMIT ?5",
+        )?;
+
+        let payments = statement
+            .query_map(
+                params![
+                    after.map(|cursor| cursor.created_at),
+                    after.map(|cursor| cursor.id.as_str()),
+                    from,
+                    to,
+                    limit,
+                ],
+                indexed_payment,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(payments)
+    }
+
+    /// Fees of outgoing payments settled between `from` and `to`, per UTC month
+    pub fn fees_paid_by_month(&self, from: u64, to: u64) -> Result<Vec<FeesPaid>> {
+        let connection = self.connection.lock().expect("payment index lock poisoned");
+        let mut statement = connection.prepare_cached(
+            "SELECT strftime('%Y-%m', settled_at, 'unixepoch') AS month,
+                 SUM(CASE WHEN kind != 'onchain' THEN COALESCE(fee_paid_msat, 0) ELSE 0 END),
+                 SUM(CASE WHEN kind = 'onchain' THEN COALESCE(fee_paid_msat, 0) ELSE 0 END)
+             FROM payments
+             WHERE direction = 'outbound'
+                 AND status = 'succeeded'
+                 AND settled_at >= ?1
+                 AND settled_at < ?2
+             GROUP BY month
+             ORDER BY month",
+        )?;
+
+        let fees = statement
+            .query_map(params![from, to], |row| {
+                Ok(FeesPaid {
+                    month: row.get(0)?,
+                    lightning_msat: row.get(1)?,
+                    onchain_msat: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(fees)
+    }
+}
+
+/// Read a payment selected in index column order
+fn indexed_payment(row: &Row<'_>) -> rusqlite::Result<IndexedPayment> {
+    Ok(IndexedPayment {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        direction: row.get(2)?,
+        status: row.get(3)?,
+        amount_msat: row.get(4)?,
+        fee_paid_msat: row.get(5)?,
+        payment_hash: row.get(6)?,
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
+        settled_at: row.get(9)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ldk_node::lightning::ln::channelmanager::PaymentId;
+    use ldk_node::lightning_types::payment::PaymentHash;
+
+    use super::*;
+
+    fn payment(
+        status: PaymentStatus,
+        amount_msat: Option<u64>,
+        fee_msat: Option<u64>,
+    ) -> PaymentDetails {
+        PaymentDetails {
+            id: PaymentId([1; 32]),
+            kind: PaymentKind::Bolt11 {
+                hash: PaymentHash([1; 32]),
+                preimage: None,
+                secret: None,
+            },
+            amount_msat,
+            fee_paid_msat: fee_msat,
+            direction: PaymentDirection::Outbound,
+            status,
+            latest_update_timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn settled_payments_spend_their_amount_and_fee() {
+        assert_eq!(
+            settled_spent_msat(&payment(
+                PaymentStatus::Succeeded,
+                Some(10_000),
+                Some(1_500)
+            )),
+            Some(11_500)
+        );
+        assert_eq!(
+            settled_spent_msat(&payment(PaymentStatus::Succeeded, Some(10_000), None)),
+            Some(10_000)
+        );
+        assert_eq!(
+            settled_spent_msat(&payment(PaymentStatus::Succeeded, None, Some(1_500))),
+            None
+        );
+    }
+
+    #[test]
+    fn unsettled_payments_spend_nothing() {
+        assert_eq!(
+            settled_spent_msat(&payment(PaymentStatus::Pending, Some(10_000), Some(1_500))),
+            Some(0)
+        );
+        assert_eq!(
+            settled_spent_msat(&payment(PaymentStatus::Failed, Some(10_000), Some(1_500))),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn spent_sats_are_rounded_up() {
+        assert_eq!(
+            spent_in_unit(0, &CurrencyUnit::Sat).unwrap(),
+            Amount::from(0)
+        );
+        assert_eq!(
+            spent_in_unit(1, &CurrencyUnit::Sat).unwrap(),
+            Amount::from(1)
+        );
+        assert_eq!(
+            spent_in_unit(1_000, &CurrencyUnit::Sat).unwrap(),
+            Amount::from(1)
+        );
+        assert_eq!(
+            spent_in_unit(11_500, &CurrencyUnit::Sat).unwrap(),
+            Amount::from(12)
+        );
+    }
+
+    #[test]
+    fn spent_msats_are_exact() {
+        assert_eq!(
+            spent_in_unit(11_500, &CurrencyUnit::Msat).unwrap(),
+            Amount::from(11_500)
+        );
+    }
+}
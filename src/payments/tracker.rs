@@ -0,0 +1,66 @@
+//! Completion of outgoing payments, signalled by payment events
+//!
+//! Melts wait for their payment to succeed or fail before answering the
+//! mint. Instead of polling the payment store, a melt registers the payment
+//! here and the event handler completes it on `PaymentSuccessful` or
+//! `PaymentFailed`. LDK Node updates its payment store before it emits the
+//! event, so the store holds the final status once the wait returns.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use ldk_node::lightning::ln::channelmanager::PaymentId;
+use tokio::sync::oneshot;
+
+/// Registry of melts waiting for their payment to complete
+#[derive(Debug, Default)]
+pub struct PaymentTracker {
+    waiters: Mutex<HashMap<PaymentId, Vec<oneshot::Sender<()>>>>,
+}
+
+impl PaymentTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a wait for a payment, resolved when it completes
+    ///
+    /// Register before reading the payment's status, so a payment completing
+    /// in between is not missed.
+    pub fn register(&self, payment_id: PaymentId) -> oneshot::Receiver<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.waiters
+            .lock()
+            .expect("payment tracker lock poisoned")
+            .entry(payment_id)
+            .or_default()
+            .push(sender);
+        receiver
+    }
+
+    /// Resolve every wait for a payment that succeeded or failed
+    pub fn complete(&self, payment_id: &PaymentId) {
+        let waiters = self
+            .waiters
+            .lock()
+            .expect("payment tracker lock poisoned")
+            .remove(payment_id);
+
+        for waiter in waiters.into_iter().flatten() {
+            // The melt may have stopped waiting already
+            let _ = waiter.send(());
+        }
+    }
+
+    /// Drop the waits for a payment that are no longer awaited
+    pub fn release(&self, payment_id: &PaymentId) {
+        let mut waiters = self.waiters.lock().expect("payment tracker lock poisoned");
+
+        if let Some(senders) = waiters.get_mut(payment_id) {
+            senders.retain(|sender| !sender.is_closed());
+            if senders.is_empty() {
+                waiters.remove(payment_id);
+            }
+        }
+    }
+}
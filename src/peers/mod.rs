@@ -0,0 +1,335 @@
+//! Peer connection diagnostics, channel balances per peer and the node's own
+//! connectivity
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ldk_node::bitcoin::secp256k1::PublicKey;
+use ldk_node::lightning::ln::msgs::SocketAddress;
+use ldk_node::lightning::routing::gossip::NodeId;
+use ldk_node::{ChannelDetails, Node, PeerDetails};
+
+/// Timeout for the plain TCP connection attempt
+const TCP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Result of diagnosing the connection to a peer
+#[derive(Debug, Clone, Default)]
+pub struct PeerDiagnosis {
+    /// Address the connection was attempted on
+    pub address: Option<SocketAddress>,
+    /// Addresses announced by the peer in gossip
+    pub gossip_addresses: Vec<SocketAddress>,
+    /// Alias announced by the peer in gossip
+    pub alias: Option<String>,
+    /// Features announced by the peer in gossip
+    pub announced_features: Option<String>,
+    /// Whether a TCP connection to the address could be opened
+    pub tcp_reachable: bool,
+    /// Time taken to open the TCP connection
+    pub tcp_latency: Option<Duration>,
+    /// Whether the Lightning handshake with the peer succeeded
+    pub handshake_succeeded: bool,
+    /// Time taken to connect and complete the handshake
+    pub handshake_latency: Option<Duration>,
+    /// Whether the peer was already connected before the diagnosis
+    pub was_connected: bool,
+    /// First error encountered
+    pub error: Option<String>,
+}
+
+/// Connection history of a peer, observed by sampling the peer list
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PeerConnectionStats {
+    /// Unix time the current connection was first observed
+    pub connected_since: Option<u64>,
+    /// Unix time the peer was last observed disconnecting
+    pub last_disconnected_at: Option<u64>,
+    /// Unix time the peer was last observed reconnecting after a disconnect
+    pub last_reconnected_at: Option<u64>,
+    /// Number of observed reconnects
+    pub num_reconnects: u64,
+}
+
+/// Tracks peer connection changes between samples of the peer list
+///
+/// LDK Node reconnects persisted peers on its own but does not report the
+/// attempts, so connects and disconnects are inferred from changes in the
+/// peer list. Flaps shorter than the sampling interval are not seen.
+#[derive(Debug, Default)]
+pub struct PeerTracker {
+    peers: Mutex<HashMap<PublicKey, PeerConnectionStats>>,
+}
+
+impl PeerTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the current connection state of the node's peers
+    pub fn update(&self, peers: &[PeerDetails], now: u64) {
+        let mut tracked = self.peers.lock().expect("peer tracker lock poisoned");
+
+        for peer in peers {
+            let stats = tracked.entry(peer.node_id).or_default();
+            let was_connected = stats.connected_since.is_some();
+
+            match (was_connected, peer.is_connected) {
+                (false, true) => {
+                    stats.connected_since = Some(now);
+                    if stats.last_disconnected_at.is_some() {
+                        stats.last_reconnected_at = Some(now);
+                        stats.num_reconnects += 1;
+                    }
+                }
+                (true, false) => {
+                    stats.connected_since = None;
+                    stats.last_disconnected_at = Some(now);
+                }
+                _ => (),
+            }
+        }
+
+        tracked.retain(|node_id, _| peers.iter().any(|peer| peer.node_id == *node_id));
+    }
+
+    /// Connection history of a peer
+    pub fn stats(&self, node_id: &PublicKey) -> PeerConnectionStats {
+        self.peers
+            .lock()
+            .expect("peer tracker lock poisoned")
+            .get(node_id)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Peer of the node with its channels and connection history
+#[derive(Debug, Clone)]
+pub struct PeerListing {
+    /// Peer details from LDK Node
+    pub details: PeerDetails,
+    /// Number of channels with the peer
+    pub num_channels: usize,
+    /// Features announced by the peer in gossip
+    pub announced_features: Option<String>,
+    /// Connection history
+    pub stats: PeerConnectionStats,
+}
+
+/// List the node's peers with channel counts and connection history
+pub fn list_peers(node: &Node, tracker: &PeerTracker) -> Vec<PeerListing> {
+    let channels = node.list_channels();
+    let graph = node.network_graph();
+
+    node.list_peers()
+        .into_iter()
+        .map(|details| PeerListing {
+            num_channels: channels
+                .iter()
+                .filter(|channel| channel.counterparty_node_id == details.node_id)
+                .count(),
+            announced_features: graph
+                .node(&NodeId::from_pubkey(&details.node_id))
+                .and_then(|info| info.announcement_info)
+                .map(|announcement| announcement.features().to_string()),
+            stats: tracker.stats(&details.node_id),
+            details,
+        })
+        .collect()
+}
+
+/// Channels with one counterparty, summed
+#[derive(Debug, Clone)]
+pub struct CounterpartyChannels {
+    pub counterparty_node_id: PublicKey,
+    pub num_channels: usize,
+    pub num_usable_channels: usize,
+    /// Total value of the channels
+    pub capacity_sat: u64,
+    /// Sendable over usable channels, reserves excluded
+    pub outbound_msat: u64,
+    /// Receivable over usable channels, reserves excluded
+    pub inbound_msat: u64,
+    /// Balance the counterparty requires the node to keep in its channels
+    pub reserve_sat: u64,
+    /// Balance the node requires the counterparty to keep in its channels
+    pub counterparty_reserve_sat: u64,
+}
+
+/// Sum channels per counterparty, ordered by counterparty node id
+///
+/// Only usable channels count towards what can be sent and received, the
+/// reserves and capacity cover every channel.
+pub fn counterparty_channels(channels: &[ChannelDetails]) -> Vec<CounterpartyChannels> {
+    let mut counterparties: BTreeMap<PublicKey, CounterpartyChannels> = BTreeMap::new();
+
+    for channel in channels {
+        let counterparty = counterparties
+            .entry(channel.counterparty_node_id)
+            .or_insert_with(|| CounterpartyChannels {
+                counterparty_node_id: channel.counterparty_node_id,
+                num_channels: 0,
+                num_usable_channels: 0,
+                capacity_sat: 0,
+                outbound_msat: 0,
+                inbound_msat: 0,
+                reserve_sat: 0,
+                counterparty_reserve_sat: 0,
+            });
+
+        counterparty.num_channels += 1;
+        counterparty.capacity_sat += channel.channel_value_sats;
+        counterparty.reserve_sat += channel.unspendable_punishment_reserve.unwrap_or_default();
+        counterparty.counterparty_reserve_sat +=
+            channel.counterparty_unspendable_punishment_reserve;
+
+        if channel.is_usable {
+            counterparty.num_usable_channels += 1;
+            counterparty.outbound_msat += channel.outbound_capacity_msat;
+            counterparty.inbound_msat += channel.inbound_capacity_msat;
+        }
+    }
+
+    counterparties.into_values().collect()
+}
+
+/// Address the node is configured to listen on
+#[derive(Debug, Clone)]
+pub struct ListeningAddress {
+    /// Configured listening address
+    pub address: SocketAddress,
+    /// Local sockets the address resolves to and the node binds
+    pub bound_sockets: Vec<SocketAddr>,
+    /// Whether the address is announced in the node's own gossip announcement
+    pub announced_in_gossip: bool,
+}
+
+/// Configured listening addresses of the node
+///
+/// An address announced in the node's gossip announcement is considered
+/// publicly reachable, other peers learn about it and can connect.
+pub fn listening_addresses(node: &Node) -> Vec<ListeningAddress> {
+    let gossip_addresses = node
+        .network_graph()
+        .node(&NodeId::from_pubkey(&node.node_id()))
+        .and_then(|info| info.announcement_info)
+        .map(|announcement| announcement.addresses().to_vec())
+        .unwrap_or_default();
+
+    node.listening_addresses()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|address| {
+            let bound_sockets = match address.to_socket_addrs() {
+                Ok(sockets) => sockets.collect(),
+                Err(err) => {
+                    tracing::debug!("Could not resolve listening address {}: {}", address, err);
+                    vec![]
+                }
+            };
+
+            ListeningAddress {
+                announced_in_gossip: gossip_addresses.contains(&address),
+                bound_sockets,
+                address,
+            }
+        })
+        .collect()
+}
+
+/// Diagnose the connection to a peer
+///
+/// Looks the peer up in gossip, checks the address is reachable over TCP and
+/// attempts a Lightning handshake. When `address` is not given the first
+/// address announced in gossip is used. A peer that was not connected before
+/// is disconnected again afterwards.
+///
+/// LDK Node does not expose the features negotiated in the handshake, so the
+/// features announced in gossip are reported instead.
+pub async fn diagnose_peer(
+    node: &Arc<Node>,
+    node_id: PublicKey,
+    address: Option<SocketAddress>,
+) -> PeerDiagnosis {
+    let mut diagnosis = PeerDiagnosis {
+        was_connected: node
+            .list_peers()
+            .iter()
+            .any(|peer| peer.node_id == node_id && peer.is_connected),
+        ..Default::default()
+    };
+
+    if let Some(announcement) = node
+        .network_graph()
+        .node(&NodeId::from_pubkey(&node_id))
+        .and_then(|info| info.announcement_info)
+    {
+        diagnosis.gossip_addresses = announcement.addresses().to_vec();
+        diagnosis.alias = Some(announcement.alias().to_string());
+        diagnosis.announced_features = Some(announcement.features().to_string());
+    }
+
+    let Some(address) = address.or_else(|| diagnosis.gossip_addresses.first().cloned()) else {
+        diagnosis.error = Some("No address given and none found in gossip".to_string());
+        return diagnosis;
+    };
+    diagnosis.address = Some(address.clone());
+
+    match address {
+        SocketAddress::OnionV2(_) | SocketAddress::OnionV3 { .. } => {
+            tracing::debug!("Skipping TCP check for onion address {}", address);
+        }
+        _ => {
+            let started = Instant::now();
+            match tokio::time::timeout(
+                TCP_TIMEOUT,
+                tokio::net::TcpStream::connect(address.to_string()),
+            )
+            .await
+            {
+                Ok(Ok(_)) => {
+                    diagnosis.tcp_reachable = true;
+                    diagnosis.tcp_latency = Some(started.elapsed());
+                }
+                Ok(Err(err)) => {
+                    diagnosis.error = Some(format!("TCP connection to {address} failed: {err}"));
+                    return diagnosis;
+                }
+                Err(_) => {
+                    diagnosis.error = Some(format!("TCP connection to {address} timed out"));
+                    return diagnosis;
+                }
+            }
+        }
+    }
+
+    let started = Instant::now();
+    let connect_node = node.clone();
+    let connect_address = address.clone();
+    let result =
+        tokio::task::spawn_blocking(move || connect_node.connect(node_id, connect_address, false))
+            .await;
+
+    match result {
+        Ok(Ok(())) => {
+            diagnosis.handshake_succeeded = true;
+            diagnosis.handshake_latency = Some(started.elapsed());
+        }
+        Ok(Err(err)) => {
+            diagnosis.error = Some(format!("Handshake with {node_id}@{address} failed: {err}"));
+        }
+        Err(err) => {
+            diagnosis.error = Some(format!("Connection task failed: {err}"));
+        }
+    }
+
+    if diagnosis.handshake_succeeded && !diagnosis.was_connected {
+        if let Err(err) = node.disconnect(node_id) {
+            tracing::warn!("Could not disconnect diagnosed peer {}: {}", node_id, err);
+        }
+    }
+
+    diagnosis
+}
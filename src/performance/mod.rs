@@ -0,0 +1,106 @@
+//! Performance profiles
+//!
+//! The low profile trades freshness for a smaller footprint on Raspberry Pi
+//! class hardware: fewer runtime worker threads, longer wallet sync and
+//! monitoring intervals, and Rapid Gossip Sync instead of processing P2P
+//! gossip. Settings given explicitly in the config take precedence over the
+//! profile.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use ldk_node::bitcoin::Network;
+
+/// Resource usage profile
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PerformanceProfile {
+    #[default]
+    Default,
+    /// Reduced memory and CPU usage for low power hardware
+    Low,
+}
+
+impl FromStr for PerformanceProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "default" => Ok(PerformanceProfile::Default),
+            "low" => Ok(PerformanceProfile::Low),
+            other => Err(anyhow!(
+                "Unknown performance profile {other}, expected default or low"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for PerformanceProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PerformanceProfile::Default => write!(f, "default"),
+            PerformanceProfile::Low => write!(f, "low"),
+        }
+    }
+}
+
+/// Wallet sync intervals used with an Esplora chain source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncIntervals {
+    pub onchain_wallet_secs: u64,
+    pub lightning_wallet_secs: u64,
+    pub fee_rate_cache_secs: u64,
+}
+
+impl PerformanceProfile {
+    /// Tokio worker threads, `None` for one per CPU core
+    pub fn worker_threads(&self) -> Option<usize> {
+        match self {
+            PerformanceProfile::Default => None,
+            PerformanceProfile::Low => Some(2),
+        }
+    }
+
+    /// Wallet sync intervals, `None` for the LDK Node defaults
+    pub fn sync_intervals(&self) -> Option<SyncIntervals> {
+        match self {
+            PerformanceProfile::Default => None,
+            PerformanceProfile::Low => Some(SyncIntervals {
+                onchain_wallet_secs: 300,
+                lightning_wallet_secs: 120,
+                fee_rate_cache_secs: 1800,
+            }),
+        }
+    }
+
+    /// Interval between chain source health checks
+    pub fn chain_source_health_check_interval(&self) -> Duration {
+        match self {
+            PerformanceProfile::Default => Duration::from_secs(60),
+            PerformanceProfile::Low => Duration::from_secs(300),
+        }
+    }
+
+    /// Interval between samples of the peer and channel lists
+    pub fn peer_monitor_interval(&self) -> Duration {
+        match self {
+            PerformanceProfile::Default => Duration::from_secs(5),
+            PerformanceProfile::Low => Duration::from_secs(30),
+        }
+    }
+
+    /// Rapid Gossip Sync server used when no gossip source is configured
+    ///
+    /// `None` keeps P2P gossip, also for networks without a public RGS server.
+    pub fn default_rgs_url(&self, network: Network) -> Option<&'static str> {
+        match (self, network) {
+            (PerformanceProfile::Low, Network::Bitcoin) => {
+                Some("https://rapidsync.lightningdevkit.org/snapshot")
+            }
+            (PerformanceProfile::Low, Network::Testnet) => {
+                Some("https://rapidsync.lightningdevkit.org/testnet/snapshot")
+            }
+            _ => None,
+        }
+    }
+}
@@ -1,25 +1,55 @@
+use std::cmp::Ordering;
 use std::path::PathBuf;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
 use tonic::transport::Channel;
+use tonic::{Code, Status};
 
 use super::cdk_ldk_management_client::CdkLdkManagementClient;
 use super::*;
+use crate::tenants::TENANT_TOKEN_HEADER;
 
+/// Adds the tenant token, if any, to every request
+#[derive(Clone, Default)]
+struct TenantToken(Option<MetadataValue<Ascii>>);
+
+impl Interceptor for TenantToken {
+    fn call(&mut self, mut request: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
+        if let Some(token) = &self.0 {
+            request
+                .metadata_mut()
+                .insert(TENANT_TOKEN_HEADER, token.clone());
+        }
+        Ok(request)
+    }
+}
+
+#[derive(Clone)]
 pub struct CdkLdkClient {
-    client: CdkLdkManagementClient<Channel>,
+    client: CdkLdkManagementClient<InterceptedService<Channel, TenantToken>>,
 }
 
 impl CdkLdkClient {
     pub fn new(channel: Channel) -> Self {
         Self {
-            client: CdkLdkManagementClient::new(channel),
+            client: CdkLdkManagementClient::with_interceptor(channel, TenantToken::default()),
         }
     }
 
+    /// Create a client scoped to a tenant, see [`crate::tenants`]
+    pub fn with_tenant_token(channel: Channel, token: &str) -> Result<Self> {
+        let token = TenantToken(Some(MetadataValue::try_from(token)?));
+        Ok(Self {
+            client: CdkLdkManagementClient::with_interceptor(channel, token),
+        })
+    }
+
     pub async fn connect(addr: String) -> Result<Self> {
-        let client = CdkLdkManagementClient::connect(addr).await?;
-        Ok(Self { client })
+        let channel = Channel::from_shared(addr)?.connect().await?;
+        Ok(Self::new(channel))
     }
 
     /// Create a client with TLS configuration based on the work_dir
@@ -28,16 +58,165 @@ impl CdkLdkClient {
         Ok(Self::new(channel))
     }
 
+    /// Create a tenant scoped client with TLS configuration based on the work_dir
+    pub async fn create_with_tenant_token(
+        address: String,
+        work_dir: PathBuf,
+        token: &str,
+    ) -> Result<Self> {
+        let channel = crate::utils::create_channel(address, work_dir).await?;
+        Self::with_tenant_token(channel, token)
+    }
+
+    pub async fn get_api_version(&mut self) -> Result<GetApiVersionResponse> {
+        let request = GetApiVersionRequest {};
+        let response = self.client.get_api_version(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// Check that the server speaks a compatible API version
+    ///
+    /// Returns `None` for servers that predate API versioning.
+    pub async fn check_api_version(&mut self) -> Result<Option<GetApiVersionResponse>> {
+        let version = match self.get_api_version().await {
+            Ok(version) => version,
+            Err(err) => match err.downcast_ref::<Status>() {
+                Some(status) if status.code() == Code::Unimplemented => return Ok(None),
+                _ => return Err(err),
+            },
+        };
+
+        match version.api_version.cmp(&API_VERSION) {
+            Ordering::Less => bail!(
+                "The server API version {} ({}) is older than this client's ({} {}). \
+                 Upgrade cdk-ldk-node on the server",
+                version.api_version,
+                version.server_version,
+                API_VERSION,
+                SERVER_VERSION
+            ),
+            Ordering::Greater => bail!(
+                "The server API version {} ({}) is newer than this client's ({} {}). \
+                 Upgrade the client",
+                version.api_version,
+                version.server_version,
+                API_VERSION,
+                SERVER_VERSION
+            ),
+            Ordering::Equal => Ok(Some(version)),
+        }
+    }
+
     pub async fn get_info(&mut self) -> Result<GetInfoResponse> {
         let request = GetInfoRequest {};
         let response = self.client.get_info(request).await?;
         Ok(response.into_inner())
     }
 
-    pub async fn get_new_address(&mut self) -> Result<String> {
-        let request = GetNewAddressRequest {};
+    pub async fn get_node_status(&mut self) -> Result<GetNodeStatusResponse> {
+        let request = GetNodeStatusRequest {};
+        let response = self.client.get_node_status(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn get_payment_limits(&mut self) -> Result<GetPaymentLimitsResponse> {
+        let request = GetPaymentLimitsRequest {};
+        let response = self.client.get_payment_limits(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn verify_backup(
+        &mut self,
+        backup_dir: Option<String>,
+    ) -> Result<VerifyBackupResponse> {
+        let request = VerifyBackupRequest { backup_dir };
+        let response = self.client.verify_backup(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// Export the node seed, authorized with the export token
+    pub async fn export_seed(&mut self, export_token: &str) -> Result<ExportSeedResponse> {
+        let mut request = tonic::Request::new(ExportSeedRequest {});
+        request.metadata_mut().insert(
+            server::SEED_EXPORT_TOKEN_HEADER,
+            MetadataValue::try_from(export_token)?,
+        );
+        let response = self.client.export_seed(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn get_wallet_descriptors(&mut self) -> Result<GetWalletDescriptorsResponse> {
+        let request = GetWalletDescriptorsRequest {};
+        let response = self.client.get_wallet_descriptors(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn warmup_graph(
+        &mut self,
+        timeout_seconds: Option<u32>,
+    ) -> Result<WarmupGraphResponse> {
+        let request = WarmupGraphRequest { timeout_seconds };
+        let response = self.client.warmup_graph(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn sync_wallets(&mut self) -> Result<SyncWalletsResponse> {
+        let request = SyncWalletsRequest {};
+        let response = self.client.sync_wallets(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn get_recent_logs(
+        &mut self,
+        level: Option<String>,
+        limit: Option<u32>,
+        after_seq: Option<u64>,
+    ) -> Result<GetRecentLogsResponse> {
+        let request = GetRecentLogsRequest {
+            level,
+            limit,
+            after_seq,
+        };
+        let response = self.client.get_recent_logs(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn get_new_address(
+        &mut self,
+        address_type: Option<String>,
+        reuse_unused: Option<bool>,
+        label: Option<String>,
+    ) -> Result<GetNewAddressResponse> {
+        let request = GetNewAddressRequest {
+            address_type,
+            reuse_unused,
+            label,
+        };
         let response = self.client.get_new_address(request).await?;
-        Ok(response.into_inner().address)
+        Ok(response.into_inner())
+    }
+
+    pub async fn set_label(
+        &mut self,
+        kind: String,
+        reference: String,
+        label: String,
+    ) -> Result<()> {
+        let request = SetLabelRequest {
+            label: Some(Label {
+                r#type: kind,
+                r#ref: reference,
+                label,
+            }),
+        };
+        self.client.set_label(request).await?;
+        Ok(())
+    }
+
+    pub async fn list_labels(&mut self) -> Result<Vec<Label>> {
+        let request = ListLabelsRequest {};
+        let response = self.client.list_labels(request).await?;
+        Ok(response.into_inner().labels)
     }
 
     pub async fn open_channel(
@@ -45,29 +224,111 @@ impl CdkLdkClient {
         node_id: String,
         address: String,
         port: u32,
-        amount_msats: u64,
-        push_to_counter_party_msats: Option<u64>,
+        amount_sat: u64,
+        push_to_counter_party_msat: Option<u64>,
+        idempotency_key: Option<String>,
     ) -> Result<String> {
         let request = OpenChannelRequest {
             node_id,
             address,
             port,
-            amount_msats,
-            push_to_counter_party_msats,
+            amount_sat,
+            push_to_counter_party_msat,
+            dry_run: false,
+            idempotency_key,
         };
         let response = self.client.open_channel(request).await?;
         Ok(response.into_inner().channel_id)
     }
 
+    pub async fn batch_open_channels(
+        &mut self,
+        channels: Vec<BatchChannel>,
+        idempotency_key: Option<String>,
+    ) -> Result<BatchOpenChannelsResponse> {
+        let request = BatchOpenChannelsRequest {
+            channels,
+            idempotency_key,
+        };
+        let response = self.client.batch_open_channels(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// Validate a channel open and return its plan without opening
+    pub async fn plan_open_channel(
+        &mut self,
+        node_id: String,
+        address: String,
+        port: u32,
+        amount_sat: u64,
+        push_to_counter_party_msat: Option<u64>,
+    ) -> Result<DryRunPlan> {
+        let request = OpenChannelRequest {
+            node_id,
+            address,
+            port,
+            amount_sat,
+            push_to_counter_party_msat,
+            dry_run: true,
+            idempotency_key: None,
+        };
+        let response = self.client.open_channel(request).await?;
+        response
+            .into_inner()
+            .plan
+            .ok_or_else(|| anyhow!("Server did not return a plan"))
+    }
+
+    /// Open a channel and stream its progress until it is ready
+    pub async fn open_channel_stream(
+        &mut self,
+        node_id: String,
+        address: String,
+        port: u32,
+        amount_sat: u64,
+        push_to_counter_party_msat: Option<u64>,
+    ) -> Result<tonic::Streaming<OpenChannelProgress>> {
+        let request = OpenChannelRequest {
+            node_id,
+            address,
+            port,
+            amount_sat,
+            push_to_counter_party_msat,
+            dry_run: false,
+            idempotency_key: None,
+        };
+        let response = self.client.open_channel_stream(request).await?;
+        Ok(response.into_inner())
+    }
+
     pub async fn close_channel(&mut self, channel_id: String, node_pubkey: String) -> Result<()> {
         let request = CloseChannelRequest {
             channel_id,
             node_pubkey,
+            dry_run: false,
         };
         self.client.close_channel(request).await?;
         Ok(())
     }
 
+    /// Validate a channel close and return its plan without closing
+    pub async fn plan_close_channel(
+        &mut self,
+        channel_id: String,
+        node_pubkey: String,
+    ) -> Result<DryRunPlan> {
+        let request = CloseChannelRequest {
+            channel_id,
+            node_pubkey,
+            dry_run: true,
+        };
+        let response = self.client.close_channel(request).await?;
+        response
+            .into_inner()
+            .plan
+            .ok_or_else(|| anyhow!("Server did not return a plan"))
+    }
+
     pub async fn list_balance(&mut self) -> Result<ListBalanceResponse> {
         let request = ListBalanceRequest {};
         let response = self.client.list_balance(request).await?;
@@ -80,24 +341,91 @@ impl CdkLdkClient {
         Ok(response.into_inner())
     }
 
-    pub async fn send_onchain(&mut self, amount_sat: u64, address: String) -> Result<String> {
+    pub async fn list_counterparty_balances(&mut self) -> Result<ListCounterpartyBalancesResponse> {
+        let request = ListCounterpartyBalancesRequest {};
+        let response = self.client.list_counterparty_balances(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn channel_report(
+        &mut self,
+        period_secs: Option<u64>,
+    ) -> Result<ChannelReportResponse> {
+        let request = ChannelReportRequest { period_secs };
+        let response = self.client.channel_report(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn get_fee_breakdown(
+        &mut self,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> Result<GetFeeBreakdownResponse> {
+        let request = GetFeeBreakdownRequest { from, to };
+        let response = self.client.get_fee_breakdown(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn send_onchain(
+        &mut self,
+        amount_sat: u64,
+        address: String,
+        label: Option<String>,
+        idempotency_key: Option<String>,
+        override_token: Option<&str>,
+    ) -> Result<SendOnchainResponse> {
+        let request = SendOnchainRequest {
+            amount_sat,
+            address,
+            label,
+            dry_run: false,
+            idempotency_key,
+            override_limit: override_token.is_some(),
+        };
+        let request = with_override_token(request, override_token)?;
+        let response = self.client.send_onchain(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// Validate an onchain send and return its plan without sending
+    pub async fn plan_send_onchain(
+        &mut self,
+        amount_sat: u64,
+        address: String,
+    ) -> Result<DryRunPlan> {
         let request = SendOnchainRequest {
             amount_sat,
             address,
+            label: None,
+            dry_run: true,
+            idempotency_key: None,
+            override_limit: false,
         };
         let response = self.client.send_onchain(request).await?;
-        Ok(response.into_inner().txid)
+        response
+            .into_inner()
+            .plan
+            .ok_or_else(|| anyhow!("Server did not return a plan"))
     }
 
     pub async fn pay_bolt11_invoice(
         &mut self,
         invoice: String,
-        amount_msats: Option<u64>,
+        amount_msat: Option<u64>,
+        correlation_id: Option<String>,
+        idempotency_key: Option<String>,
+        override_token: Option<&str>,
+        timeout_secs: Option<u64>,
     ) -> Result<PaymentResponse> {
         let request = PayBolt11InvoiceRequest {
             invoice,
-            amount_msats,
+            amount_msat,
+            correlation_id,
+            idempotency_key,
+            override_limit: override_token.is_some(),
+            timeout_secs,
         };
+        let request = with_override_token(request, override_token)?;
         let response = self.client.pay_bolt11_invoice(request).await?;
         Ok(response.into_inner())
     }
@@ -105,43 +433,237 @@ impl CdkLdkClient {
     pub async fn pay_bolt12_offer(
         &mut self,
         offer: String,
-        amount_msats: u64,
+        amount_msat: Option<u64>,
+        correlation_id: Option<String>,
+        idempotency_key: Option<String>,
+        override_token: Option<&str>,
+        timeout_secs: Option<u64>,
     ) -> Result<PaymentResponse> {
         let request = PayBolt12OfferRequest {
             offer,
-            amount_msats,
+            amount_msat,
+            correlation_id,
+            idempotency_key,
+            override_limit: override_token.is_some(),
+            timeout_secs,
         };
+        let request = with_override_token(request, override_token)?;
         let response = self.client.pay_bolt12_offer(request).await?;
         Ok(response.into_inner())
     }
 
     pub async fn create_bolt11_invoice(
         &mut self,
-        amount_msats: u64,
+        amount_msat: u64,
+        description: String,
+        expiry_seconds: Option<u32>,
+        description_hash: bool,
+    ) -> Result<CreateInvoiceResponse> {
+        let request = CreateBolt11InvoiceRequest {
+            amount_msat,
+            description,
+            expiry_seconds,
+            hold: false,
+            payment_hash: None,
+            description_hash,
+        };
+        let response = self.client.create_bolt11_invoice(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// Create a hold invoice, for `payment_hash` when the caller keeps the preimage
+    pub async fn create_hold_invoice(
+        &mut self,
+        amount_msat: u64,
         description: String,
         expiry_seconds: Option<u32>,
+        payment_hash: Option<String>,
+        description_hash: bool,
     ) -> Result<CreateInvoiceResponse> {
         let request = CreateBolt11InvoiceRequest {
-            amount_msats,
+            amount_msat,
             description,
             expiry_seconds,
+            hold: true,
+            payment_hash,
+            description_hash,
         };
         let response = self.client.create_bolt11_invoice(request).await?;
         Ok(response.into_inner())
     }
 
+    pub async fn settle_hold_invoice(
+        &mut self,
+        payment_hash: String,
+        preimage: Option<String>,
+    ) -> Result<HoldInvoice> {
+        let request = SettleHoldInvoiceRequest {
+            payment_hash,
+            preimage,
+        };
+        let response = self.client.settle_hold_invoice(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn cancel_hold_invoice(&mut self, payment_hash: String) -> Result<HoldInvoice> {
+        let request = CancelHoldInvoiceRequest { payment_hash };
+        let response = self.client.cancel_hold_invoice(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn list_hold_invoices(&mut self) -> Result<Vec<HoldInvoice>> {
+        let request = ListHoldInvoicesRequest {};
+        let response = self.client.list_hold_invoices(request).await?;
+        Ok(response.into_inner().invoices)
+    }
+
     pub async fn create_bolt12_offer(
         &mut self,
-        amount_msats: Option<u64>,
+        amount_msat: Option<u64>,
         description: String,
         expiry_seconds: Option<u32>,
+        absolute_expiry: Option<u64>,
+        quantity: Option<u64>,
     ) -> Result<CreateOfferResponse> {
         let request = CreateBolt12OfferRequest {
-            amount_msats,
+            amount_msat,
             description,
             expiry_seconds,
+            absolute_expiry,
+            quantity,
         };
         let response = self.client.create_bolt12_offer(request).await?;
         Ok(response.into_inner())
     }
+
+    pub async fn decode_offer(&mut self, offer: String) -> Result<DecodeOfferResponse> {
+        let request = DecodeOfferRequest { offer };
+        let response = self.client.decode_offer(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn check_incoming_payments(
+        &mut self,
+        payment_ids: Vec<String>,
+    ) -> Result<Vec<IncomingPaymentStatus>> {
+        let request = CheckIncomingPaymentsRequest { payment_ids };
+        let response = self.client.check_incoming_payments(request).await?;
+        Ok(response.into_inner().payments)
+    }
+
+    pub async fn list_peers(&mut self) -> Result<Vec<PeerInfo>> {
+        let request = ListPeersRequest {};
+        let response = self.client.list_peers(request).await?;
+        Ok(response.into_inner().peers)
+    }
+
+    pub async fn list_pending_operations(&mut self) -> Result<Vec<PendingOperation>> {
+        let request = ListPendingOperationsRequest {};
+        let response = self.client.list_pending_operations(request).await?;
+        Ok(response.into_inner().operations)
+    }
+
+    /// Approve a pending withdrawal, executing it
+    pub async fn approve_operation(
+        &mut self,
+        id: String,
+        approver_token: &str,
+    ) -> Result<ApproveOperationResponse> {
+        let mut request = tonic::Request::new(ApproveOperationRequest { id });
+        request.metadata_mut().insert(
+            server::APPROVER_TOKEN_HEADER,
+            MetadataValue::try_from(approver_token)?,
+        );
+        let response = self.client.approve_operation(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// Reject a pending withdrawal
+    pub async fn reject_operation(&mut self, id: String, approver_token: &str) -> Result<()> {
+        let mut request = tonic::Request::new(RejectOperationRequest { id });
+        request.metadata_mut().insert(
+            server::APPROVER_TOKEN_HEADER,
+            MetadataValue::try_from(approver_token)?,
+        );
+        self.client.reject_operation(request).await?;
+        Ok(())
+    }
+
+    /// Prune payment records and activity history, unset retentions use the node's policy
+    pub async fn prune(
+        &mut self,
+        payment_retention_days: Option<u64>,
+        activity_retention_days: Option<u64>,
+        dry_run: bool,
+    ) -> Result<PruneResponse> {
+        let request = PruneRequest {
+            payment_retention_days,
+            activity_retention_days,
+            dry_run,
+        };
+        let response = self.client.prune(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn rotate_tls(&mut self) -> Result<RotateTlsResponse> {
+        let response = self.client.rotate_tls(RotateTlsRequest {}).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn get_summary(&mut self, period_secs: Option<u64>) -> Result<GetSummaryResponse> {
+        let request = GetSummaryRequest { period_secs };
+        let response = self.client.get_summary(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn list_payments(
+        &mut self,
+        request: ListPaymentsRequest,
+    ) -> Result<ListPaymentsResponse> {
+        let response = self.client.list_payments(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn export_ledger(
+        &mut self,
+        created_from: Option<u64>,
+        created_to: Option<u64>,
+        batch_size: Option<u32>,
+    ) -> Result<tonic::Streaming<LedgerBatch>> {
+        let request = ExportLedgerRequest {
+            created_from,
+            created_to,
+            batch_size,
+        };
+        let response = self.client.export_ledger(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn abandon_payment(&mut self, payment_id: String) -> Result<AbandonPaymentResponse> {
+        let request = AbandonPaymentRequest { payment_id };
+        let response = self.client.abandon_payment(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn diagnose_peer(
+        &mut self,
+        node_id: String,
+        address: Option<String>,
+    ) -> Result<DiagnosePeerResponse> {
+        let request = DiagnosePeerRequest { node_id, address };
+        let response = self.client.diagnose_peer(request).await?;
+        Ok(response.into_inner())
+    }
+}
+
+/// Request carrying the approver token that overrides the management limits
+fn with_override_token<T>(message: T, override_token: Option<&str>) -> Result<tonic::Request<T>> {
+    let mut request = tonic::Request::new(message);
+    if let Some(token) = override_token {
+        request.metadata_mut().insert(
+            server::APPROVER_TOKEN_HEADER,
+            MetadataValue::try_from(token)?,
+        );
+    }
+    Ok(request)
 }
@@ -1,4 +1,85 @@
 tonic::include_proto!("cdk_ldk_management");
 
+use cdk_ldk_management_server::CdkLdkManagementServer;
+use tonic_health::pb::health_server::{Health, HealthServer};
+use tonic_reflection::pb::v1::server_reflection_server::{
+    ServerReflection, ServerReflectionServer,
+};
+
+/// Encoded descriptors of the management API, served by [`reflection_service`]
+pub const FILE_DESCRIPTOR_SET: &[u8] =
+    tonic::include_file_descriptor_set!("cdk_ldk_management_descriptor");
+
+/// Version of the management API
+///
+/// Only bumped on breaking changes, additive RPCs are advertised through [`CAPABILITIES`].
+pub const API_VERSION: u32 = 1;
+
+/// Version of the crate serving or consuming the management API
+pub const SERVER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Optional RPCs supported by this version of the management API
+pub const CAPABILITIES: &[&str] = &[
+    "get_node_status",
+    "warmup_graph",
+    "decode_offer",
+    "diagnose_peer",
+    "open_channel_stream",
+    "get_payment_limits",
+    "verify_backup",
+    "export_seed",
+    "get_wallet_descriptors",
+    "labels",
+    "list_peers",
+    "sync_wallets",
+    "check_incoming_payments",
+    "get_recent_logs",
+    "channel_report",
+    "dry_run",
+    "idempotency_keys",
+    "approvals",
+    "prune",
+    "rotate_tls",
+    "get_summary",
+    "list_payments",
+    "export_ledger",
+    "abandon_payment",
+    "hold_invoices",
+    "tenants",
+    "list_counterparty_balances",
+    "fee_breakdown",
+    "batch_open_channels",
+];
+
+/// Convert millisatoshis to satoshis, rounding down
+pub fn msat_to_sat(amount_msat: u64) -> u64 {
+    amount_msat / 1000
+}
+
+/// Convert satoshis to millisatoshis, `None` on overflow
+pub fn sat_to_msat(amount_sat: u64) -> Option<u64> {
+    amount_sat.checked_mul(1000)
+}
+
+/// gRPC health service reporting the management service as serving
+///
+/// Mount it next to [`CdkLdkNode::management_service`](crate::CdkLdkNode::management_service)
+/// on an embedder's server.
+pub async fn health_service() -> HealthServer<impl Health> {
+    let (mut reporter, service) = tonic_health::server::health_reporter();
+    reporter
+        .set_serving::<CdkLdkManagementServer<server::CdkLdkServer>>()
+        .await;
+    service
+}
+
+/// gRPC reflection service describing the management API
+pub fn reflection_service() -> anyhow::Result<ServerReflectionServer<impl ServerReflection>> {
+    Ok(tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .build_v1()?)
+}
+
 pub mod client;
 pub mod server;
+pub mod validation;
@@ -1,16 +1,45 @@
+use std::future::Future;
+use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
+use cdk_common::payment::PaymentIdentifier;
+use cdk_common::util::{hex, unix_time};
+use cdk_common::{CurrencyUnit, MeltQuoteState};
+use futures::Stream;
+use ldk_node::bitcoin::hashes::{sha256, Hash};
 use ldk_node::bitcoin::secp256k1::PublicKey;
 use ldk_node::bitcoin::Address;
 use ldk_node::lightning::ln::msgs::SocketAddress;
-use ldk_node::payment::{PaymentKind, PaymentStatus};
+use ldk_node::lightning::offers::offer::{Amount, Offer, Quantity};
+use ldk_node::lightning_types::payment::{PaymentHash, PaymentPreimage};
+use ldk_node::payment::PaymentKind;
 use ldk_node::UserChannelId;
+use prost::Message;
+use tokio::sync::mpsc;
 use tonic::{Request, Response, Status};
+use tracing::Instrument;
 
 use super::cdk_ldk_management_server::CdkLdkManagement;
+use super::open_channel_progress::Stage;
+use super::validation::{self, invalid_field};
 use super::*;
-use crate::CdkLdkNode;
+use crate::accounting::DEFAULT_FEE_BREAKDOWN_PERIOD_SECS;
+use crate::approvals::ApprovalError;
+use crate::idempotency::{Claim, Commitment, MAX_KEY_LEN};
+use crate::labels::LabelKind;
+use crate::onchain::AddressType;
+use crate::payments::ledger::{
+    csv_rows, LedgerCursor, DEFAULT_LEDGER_BATCH_SIZE, LEDGER_HEADER, LEDGER_STREAM_BUFFER,
+    MAX_LEDGER_BATCH_SIZE,
+};
+use crate::payments::PaymentFilter;
+use crate::prune::{self, PrunePolicy, DEFAULT_PRUNE_INTERVAL};
+use crate::seed::export::{SeedExportOutcome, SeedExportRecord};
+use crate::seed::NodeSeed;
+use crate::tenants::tenant_scope;
+use crate::{identifiers, CdkLdkNode};
 
 pub struct CdkLdkServer {
     node: Arc<CdkLdkNode>,
@@ -22,289 +51,150 @@ impl CdkLdkServer {
     }
 }
 
-#[tonic::async_trait]
-impl CdkLdkManagement for CdkLdkServer {
-    async fn get_info(
-        &self,
-        _request: Request<GetInfoRequest>,
-    ) -> Result<Response<GetInfoResponse>, Status> {
-        let node = self.node.inner.as_ref();
-
-        let node_id = node.node_id();
-        let alias = node
-            .node_alias()
-            .map(|a| a.to_string())
-            .unwrap_or("".to_string());
-
-        let config = self.node.inner.config();
-
-        let announcement_addresses = config
-            .announcement_addresses
-            .as_ref()
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|a| a.to_string())
-            .collect();
-
-        let listening_addresses = config
-            .announcement_addresses
-            .unwrap_or_default()
-            .iter()
-            .map(|a| a.to_string())
-            .collect();
-
-        let (num_peers, num_connected_peers) =
-            node.list_peers()
-                .iter()
-                .fold((0, 0), |(mut peers, mut connected), p| {
-                    if p.is_connected {
-                        connected += 1;
-                    }
-                    peers += 1;
-
-                    (peers, connected)
-                });
-
-        let (num_active_channels, num_inactive_channels) =
-            node.list_channels()
-                .iter()
-                .fold((0, 0), |(mut active, mut inactive), c| {
-                    if c.is_usable {
-                        active += 1;
-                    } else {
-                        inactive += 1;
-                    }
-                    (active, inactive)
-                });
-
-        Ok(Response::new(GetInfoResponse {
-            node_id: node_id.to_string(),
-            alias,
-            announcement_addresses,
-            listening_addresses,
-            num_peers,
-            num_connected_peers,
-            num_active_channels,
-            num_inactive_channels,
-        }))
-    }
-
-    async fn get_new_address(
-        &self,
-        _request: Request<GetNewAddressRequest>,
-    ) -> Result<Response<GetNewAddressResponse>, Status> {
-        let address = self
-            .node
-            .inner
-            .onchain_payment()
-            .new_address()
-            .map_err(|e| Status::internal(e.to_string()))?;
-
-        Ok(Response::new(GetNewAddressResponse {
-            address: address.to_string(),
-        }))
-    }
-
-    async fn open_channel(
-        &self,
-        request: Request<OpenChannelRequest>,
-    ) -> Result<Response<OpenChannelResponse>, Status> {
-        let req = request.into_inner();
-
-        let socket_addr = SocketAddress::from_str(&format!("{}:{}", req.address, req.port))
-            .map_err(|e| Status::internal(e.to_string()))?;
-
-        let pubkey =
-            PublicKey::from_str(&req.node_id).map_err(|e| Status::internal(e.to_string()))?;
-
-        self.node
-            .inner
-            .connect(pubkey, socket_addr.clone(), true)
-            .map_err(|e| Status::internal(e.to_string()))?;
-
-        let channel = self
-            .node
-            .inner
-            .open_announced_channel(
-                pubkey,
-                socket_addr,
-                req.amount_msats,
-                req.push_to_counter_party_msats,
-                None,
-            )
-            .map_err(|e| Status::internal(e.to_string()))?;
-
-        Ok(Response::new(OpenChannelResponse {
-            channel_id: channel.0.to_string(),
-        }))
-    }
-
-    async fn close_channel(
-        &self,
-        request: Request<CloseChannelRequest>,
-    ) -> Result<Response<CloseChannelResponse>, Status> {
-        let req = request.into_inner();
-
-        let node_pubkey = req
-            .node_pubkey
-            .parse()
-            .map_err(|e| Status::invalid_argument(format!("Invalid node pubkey: {e}")))?;
-
-        let channel_id: u128 = req
-            .channel_id
-            .parse()
-            .map_err(|e| Status::invalid_argument(format!("Invalid channel id: {e}")))?;
-
-        let channel_id = UserChannelId(channel_id);
+/// Interval between channel state checks while streaming channel open progress
+const OPEN_CHANNEL_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
-        self.node
-            .inner
-            .close_channel(&channel_id, node_pubkey)
-            .map_err(|e| Status::internal(e.to_string()))?;
+/// Metadata key a caller can pass a correlation id in
+const CORRELATION_ID_HEADER: &str = "x-correlation-id";
 
-        Ok(Response::new(CloseChannelResponse {}))
-    }
+/// Correlation id passed in the request metadata
+fn correlation_id_header<T>(request: &Request<T>) -> Option<String> {
+    request
+        .metadata()
+        .get(CORRELATION_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
 
-    async fn list_balance(
-        &self,
-        _request: Request<ListBalanceRequest>,
-    ) -> Result<Response<ListBalanceResponse>, Status> {
-        let node_balance = self.node.inner.list_balances();
+/// Metadata key the approver token is passed in
+pub const APPROVER_TOKEN_HEADER: &str = "x-approver-token";
 
-        Ok(Response::new(ListBalanceResponse {
-            total_onchain_balance_sats: node_balance.total_onchain_balance_sats,
-            spendable_onchain_balance_sats: node_balance.spendable_onchain_balance_sats,
-            total_lightning_balance_sats: node_balance.total_lightning_balance_sats,
-        }))
-    }
+/// Metadata key the seed export token is passed in
+pub const SEED_EXPORT_TOKEN_HEADER: &str = "x-seed-export-token";
 
-    async fn list_channels(
-        &self,
-        _request: Request<ListChannelsRequest>,
-    ) -> Result<Response<ListChannelsResponse>, Status> {
-        let channels = self.node.inner.list_channels();
+/// Number of log entries returned when the request does not set a limit
+const DEFAULT_LOG_LIMIT: u32 = 100;
 
-        let channel_infos = channels
-            .iter()
-            .map(|channel| ChannelInfo {
-                channel_id: channel.channel_id.to_string(),
-                counterparty_node_id: channel.counterparty_node_id.to_string(),
-                // For balance, we'll calculate based on outbound capacity
-                balance_msat: channel.outbound_capacity_msat,
-                outbound_capacity_msat: channel.outbound_capacity_msat,
-                inbound_capacity_msat: channel.inbound_capacity_msat,
-                is_usable: channel.is_usable,
-                is_public: channel.is_channel_ready, // Using is_channel_ready as is_public
-                short_channel_id: channel
-                    .short_channel_id
-                    .map(|scid| scid.to_string())
-                    .unwrap_or_default(),
-            })
-            .collect();
+/// Period covered by a channel report when the request does not set one
+const DEFAULT_CHANNEL_REPORT_PERIOD: Duration = Duration::from_secs(30 * 24 * 60 * 60);
 
-        Ok(Response::new(ListChannelsResponse {
-            channels: channel_infos,
-        }))
-    }
+/// Most channels opened by one batch
+const MAX_BATCH_CHANNELS: usize = 20;
 
-    async fn send_onchain(
-        &self,
-        request: Request<SendOnchainRequest>,
-    ) -> Result<Response<SendOnchainResponse>, Status> {
-        let req = request.into_inner();
+/// Longest time a payment request may wait for its payment
+const MAX_SEND_TIMEOUT_SECS: u64 = 60 * 60;
 
-        let address =
-            Address::from_str(&req.address).map_err(|e| Status::invalid_argument(e.to_string()))?;
+/// Period of on-demand summaries when the request does not set one
+const DEFAULT_SUMMARY_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
 
-        let txid = self
-            .node
-            .inner
-            .onchain_payment()
-            .send_to_address(address.assume_checked_ref(), req.amount_sat, None)
-            .map_err(|e| Status::internal(e.to_string()))?;
+/// Stream of channel open progress updates
+type OpenChannelProgressStream =
+    Pin<Box<dyn Stream<Item = Result<OpenChannelProgress, Status>> + Send>>;
 
-        Ok(Response::new(SendOnchainResponse {
-            txid: txid.to_string(),
-        }))
-    }
+/// Stream of ledger batches
+type LedgerBatchStream = Pin<Box<dyn Stream<Item = Result<LedgerBatch, Status>> + Send>>;
 
-    async fn pay_bolt11_invoice(
+impl CdkLdkServer {
+    /// Pay a BOLT11 invoice, waiting up to the request's or the node's send timeout
+    ///
+    /// Payments above the management limit are refused and payments above
+    /// the approval threshold are held unless `approved`.
+    async fn pay_bolt11_invoice_inner(
         &self,
-        request: Request<PayBolt11InvoiceRequest>,
+        req: PayBolt11InvoiceRequest,
+        approved: bool,
+        commitment: Commitment,
     ) -> Result<Response<PaymentResponse>, Status> {
-        let req = request.into_inner();
-
         // Parse the BOLT11 invoice
         let bolt11 = ldk_node::lightning_invoice::Bolt11Invoice::from_str(&req.invoice)
-            .map_err(|e| Status::invalid_argument(format!("Invalid BOLT11 invoice: {e}")))?;
+            .map_err(|e| invalid_field("invoice", e))?;
+
+        if !approved {
+            let amount_msat = req
+                .amount_msat
+                .or(bolt11.amount_milli_satoshis())
+                .unwrap_or_default();
+            check_amount_limit(
+                "BOLT11 payment",
+                amount_msat,
+                self.node.management_limits().max_bolt11_payment_sat,
+            )?;
+            let description = format!(
+                "Pay {} msats to BOLT11 invoice {}",
+                amount_msat,
+                bolt11.payment_hash()
+            );
+
+            if let Some(id) =
+                self.hold_for_approval("pay_bolt11_invoice", &req, amount_msat, description)
+            {
+                return Ok(Response::new(pending_payment(
+                    bolt11.payment_hash().to_string(),
+                    id,
+                )));
+            }
+        }
+
+        let timeout = send_timeout(req.timeout_secs, self.node.payment_send_timeout)?;
 
         // Determine sending parameters
         let send_params = None; // Use default parameters
 
         // Send the payment
-        let payment_id = if let Some(amount_msats) = req.amount_msats {
+        let payment_id = if let Some(amount_msats) = req.amount_msat {
             // Send with a specific amount (amountless invoice or override amount)
             self.node
                 .inner
                 .bolt11_payment()
                 .send_using_amount(&bolt11, amount_msats, send_params)
-                .map_err(|e| Status::internal(format!("Failed to pay invoice: {e}")))?
+                .map_err(|e| validation::node_error("Failed to pay invoice", e))?
         } else {
             // Send with the amount specified in the invoice
             self.node
                 .inner
                 .bolt11_payment()
                 .send(&bolt11, send_params)
-                .map_err(|e| Status::internal(format!("Failed to pay invoice: {e}")))?
+                .map_err(|e| validation::node_error("Failed to pay invoice", e))?
         };
+        commitment.commit();
 
-        // Check payment status for up to 10 seconds
-        let start = std::time::Instant::now();
-        let timeout = std::time::Duration::from_secs(10);
-
-        let payment_details = loop {
-            let details = self
-                .node
-                .inner
-                .payment(&payment_id)
-                .ok_or_else(|| Status::internal("Payment not found"))?;
-
-            match details.status {
-                PaymentStatus::Succeeded => break details,
-                PaymentStatus::Failed => {
-                    return Ok(Response::new(PaymentResponse {
-                        payment_hash: bolt11.payment_hash().to_string(),
-                        payment_preimage: String::new(),
-                        fee_msats: 0,
-                        success: false,
-                        failure_reason: Some("Payment failed".to_string()),
-                    }));
-                }
-                PaymentStatus::Pending => {
-                    if start.elapsed() > timeout {
-                        // Return pending status after timeout
-                        return Ok(Response::new(PaymentResponse {
-                            payment_hash: bolt11.payment_hash().to_string(),
-                            payment_preimage: String::new(),
-                            fee_msats: 0,
-                            success: false,
-                            failure_reason: Some("Payment is still pending".to_string()),
-                        }));
-                    }
-                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                    continue;
-                }
-            }
+        let (state, payment_details) = self
+            .node
+            .wait_for_payment(payment_id, timeout)
+            .await
+            .map_err(|e| Status::internal(format!("{e:#}")))?;
+
+        if state == MeltQuoteState::Pending {
+            commitment.pending();
+        }
+
+        let failure_reason = match state {
+            MeltQuoteState::Failed => Some("Payment failed"),
+            MeltQuoteState::Pending => Some("Payment is still pending"),
+            _ => None,
         };
+        if let Some(failure_reason) = failure_reason {
+            return Ok(Response::new(PaymentResponse {
+                payment_hash: bolt11.payment_hash().to_string(),
+                payment_preimage: String::new(),
+                fee_msat: 0,
+                success: false,
+                failure_reason: Some(failure_reason.to_string()),
+                pending_approval_id: None,
+            }));
+        }
 
         // Extract payment details
-        let (preimage, fee_msats) = match payment_details.kind {
+        let (preimage, fee_msat) = match payment_details.kind {
             PaymentKind::Bolt11 {
                 hash: _,
                 preimage,
                 secret: _,
             } => (
-                preimage.map(|p| p.to_string()).unwrap_or_default(),
+                preimage
+                    .filter(|_| !self.node.redact_preimages())
+                    .map(|p| p.to_string())
+                    .unwrap_or_default(),
                 payment_details.fee_paid_msat.unwrap_or(0),
             ),
             _ => (String::new(), 0),
@@ -313,71 +203,110 @@ impl CdkLdkManagement for CdkLdkServer {
         Ok(Response::new(PaymentResponse {
             payment_hash: bolt11.payment_hash().to_string(),
             payment_preimage: preimage,
-            fee_msats,
+            fee_msat,
             success: true,
             failure_reason: None,
+            pending_approval_id: None,
         }))
     }
 
-    async fn pay_bolt12_offer(
+    /// Pay a BOLT12 offer, waiting up to the request's or the node's send timeout
+    ///
+    /// Payments above the management limit are refused and payments above
+    /// the approval threshold are held unless `approved`.
+    async fn pay_bolt12_offer_inner(
         &self,
-        request: Request<PayBolt12OfferRequest>,
+        req: PayBolt12OfferRequest,
+        approved: bool,
+        commitment: Commitment,
     ) -> Result<Response<PaymentResponse>, Status> {
-        let req = request.into_inner();
-
         // Parse the BOLT12 offer
-        let offer = ldk_node::lightning::offers::offer::Offer::from_str(&req.offer)
-            .map_err(|e| Status::invalid_argument(format!("Invalid BOLT12 offer: {e:?}")))?;
+        let offer =
+            Offer::from_str(&req.offer).map_err(|e| invalid_field("offer", format!("{e:?}")))?;
+
+        // Use the offer amount unless the caller overrides it
+        let amount_msats = match (offer.amount(), req.amount_msat) {
+            (Some(Amount::Bitcoin { amount_msats }), Some(requested))
+                if requested < amount_msats =>
+            {
+                return Err(Status::invalid_argument(format!(
+                    "Amount {requested} msats is below the offer amount of {amount_msats} msats"
+                )));
+            }
+            (Some(Amount::Bitcoin { .. }), None) => None,
+            (Some(Amount::Currency { .. }), None) => {
+                return Err(Status::invalid_argument(
+                    "Offer amount is not denominated in bitcoin, an amount is required",
+                ));
+            }
+            (None, None) => {
+                return Err(Status::invalid_argument(
+                    "Offer has no amount, an amount is required",
+                ));
+            }
+            (_, Some(requested)) => Some(requested),
+        };
 
-        // Send the payment with the specified amount
-        let payment_id = self
-            .node
-            .inner
-            .bolt12_payment()
-            .send_using_amount(&offer, req.amount_msats, None, None)
-            .map_err(|e| Status::internal(format!("Failed to pay offer: {e}")))?;
+        if !approved {
+            let amount_msat = match (amount_msats, offer.amount()) {
+                (Some(amount_msats), _) => amount_msats,
+                (None, Some(Amount::Bitcoin { amount_msats })) => amount_msats,
+                _ => 0,
+            };
+            check_amount_limit(
+                "BOLT12 payment",
+                amount_msat,
+                self.node.management_limits().max_bolt12_payment_sat,
+            )?;
+            let description = format!("Pay {} msats to BOLT12 offer {}", amount_msat, offer.id());
+
+            if let Some(id) =
+                self.hold_for_approval("pay_bolt12_offer", &req, amount_msat, description)
+            {
+                return Ok(Response::new(pending_payment(String::new(), id)));
+            }
+        }
 
-        // Check payment status for up to 10 seconds
-        let start = std::time::Instant::now();
-        let timeout = std::time::Duration::from_secs(10);
+        let timeout = send_timeout(req.timeout_secs, self.node.payment_send_timeout)?;
 
-        let payment_details = loop {
-            let details = self
-                .node
-                .inner
-                .payment(&payment_id)
-                .ok_or_else(|| Status::internal("Payment not found"))?;
-
-            match details.status {
-                PaymentStatus::Succeeded => break details,
-                PaymentStatus::Failed => {
-                    return Ok(Response::new(PaymentResponse {
-                        payment_hash: String::new(), // Will be filled with actual hash if available
-                        payment_preimage: String::new(),
-                        fee_msats: 0,
-                        success: false,
-                        failure_reason: Some("Payment failed".to_string()),
-                    }));
-                }
-                PaymentStatus::Pending => {
-                    if start.elapsed() > timeout {
-                        // Return pending status after timeout
-                        return Ok(Response::new(PaymentResponse {
-                            payment_hash: String::new(),
-                            payment_preimage: String::new(),
-                            fee_msats: 0,
-                            success: false,
-                            failure_reason: Some("Payment is still pending".to_string()),
-                        }));
-                    }
-                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                    continue;
-                }
+        let bolt12_payment = self.node.inner.bolt12_payment();
+        let payment_id = match amount_msats {
+            Some(amount_msats) => {
+                bolt12_payment.send_using_amount(&offer, amount_msats, None, None)
             }
+            None => bolt12_payment.send(&offer, None, None),
+        }
+        .map_err(|e| validation::node_error("Failed to pay offer", e))?;
+        commitment.commit();
+
+        let (state, payment_details) = self
+            .node
+            .wait_for_payment(payment_id, timeout)
+            .await
+            .map_err(|e| Status::internal(format!("{e:#}")))?;
+
+        if state == MeltQuoteState::Pending {
+            commitment.pending();
+        }
+
+        let failure_reason = match state {
+            MeltQuoteState::Failed => Some("Payment failed"),
+            MeltQuoteState::Pending => Some("Payment is still pending"),
+            _ => None,
         };
+        if let Some(failure_reason) = failure_reason {
+            return Ok(Response::new(PaymentResponse {
+                payment_hash: String::new(),
+                payment_preimage: String::new(),
+                fee_msat: 0,
+                success: false,
+                failure_reason: Some(failure_reason.to_string()),
+                pending_approval_id: None,
+            }));
+        }
 
         // Extract payment details
-        let (payment_hash, preimage, fee_msats) = match payment_details.kind {
+        let (payment_hash, preimage, fee_msat) = match payment_details.kind {
             PaymentKind::Bolt12Offer {
                 hash,
                 preimage,
@@ -387,7 +316,10 @@ impl CdkLdkManagement for CdkLdkServer {
                 quantity: _,
             } => (
                 hash.map(|h| h.to_string()).unwrap_or_default(),
-                preimage.map(|p| p.to_string()).unwrap_or_default(),
+                preimage
+                    .filter(|_| !self.node.redact_preimages())
+                    .map(|p| p.to_string())
+                    .unwrap_or_default(),
                 payment_details.fee_paid_msat.unwrap_or(0),
             ),
             _ => (String::new(), String::new(), 0),
@@ -396,80 +328,1582 @@ impl CdkLdkManagement for CdkLdkServer {
         Ok(Response::new(PaymentResponse {
             payment_hash,
             payment_preimage: preimage,
-            fee_msats,
+            fee_msat,
             success: true,
             failure_reason: None,
+            pending_approval_id: None,
         }))
     }
 
-    async fn create_bolt11_invoice(
-        &self,
-        request: Request<CreateBolt11InvoiceRequest>,
-    ) -> Result<Response<CreateInvoiceResponse>, Status> {
-        let req = request.into_inner();
+    /// Connect to the peer and open the channel, sending progress until it is ready
+    async fn stream_open_channel(
+        node: Arc<CdkLdkNode>,
+        req: OpenChannelRequest,
+        tx: mpsc::Sender<Result<OpenChannelProgress, Status>>,
+    ) -> Result<(), Status> {
+        let socket_addr =
+            validation::parse_socket_address("address", &format!("{}:{}", req.address, req.port))?;
+
+        let pubkey = validation::parse_public_key("node_id", &req.node_id)?;
+
+        let send = |progress: OpenChannelProgress| {
+            let tx = tx.clone();
+            async move {
+                tx.send(Ok(progress))
+                    .await
+                    .map_err(|_| Status::cancelled("Client disconnected"))
+            }
+        };
 
-        // Set up the description
-        let description = ldk_node::lightning_invoice::Bolt11InvoiceDescription::Direct(
-            ldk_node::lightning_invoice::Description::new(req.description)
-                .map_err(|_| Status::invalid_argument("Invalid description"))?,
-        );
+        node.check_onchain_reserve(req.amount_sat)
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
 
-        // Get expiry time (default to 1 hour if not specified)
-        let expiry_seconds = req.expiry_seconds.unwrap_or(3600);
+        send(progress(Stage::Connecting)).await?;
 
-        // Create the invoice
-        let invoice = self
-            .node
-            .inner
-            .bolt11_payment()
-            .receive(req.amount_msats, &description, expiry_seconds)
-            .map_err(|e| Status::internal(format!("Failed to create invoice: {e}")))?;
+        let ldk_node = node.inner.clone();
+        let channel_config = node.channel_config;
+        let user_channel_id = tokio::task::spawn_blocking(move || {
+            ldk_node.connect(pubkey, socket_addr.clone(), true)?;
+            ldk_node.open_announced_channel(
+                pubkey,
+                socket_addr,
+                req.amount_sat,
+                req.push_to_counter_party_msat,
+                Some(channel_config),
+            )
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map_err(|e| validation::node_error("Could not open channel", e))?;
 
-        // Get current time for expiry calculation
-        let current_time = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        let channel_id = user_channel_id.0.to_string();
 
-        Ok(Response::new(CreateInvoiceResponse {
-            payment_hash: invoice.payment_hash().to_string(),
-            invoice: invoice.to_string(),
-            expiry_time: current_time + expiry_seconds as u64,
-        }))
-    }
+        send(OpenChannelProgress {
+            channel_id: Some(channel_id.clone()),
+            ..progress(Stage::FundingCreated)
+        })
+        .await?;
 
-    async fn create_bolt12_offer(
-        &self,
-        request: Request<CreateBolt12OfferRequest>,
-    ) -> Result<Response<CreateOfferResponse>, Status> {
-        let req = request.into_inner();
+        let mut funding_txid = None;
+        let mut confirmations = None;
+        let mut interval = tokio::time::interval(OPEN_CHANNEL_POLL_INTERVAL);
 
-        // Get expiry time (default to 1 hour if not specified)
-        let expiry_seconds = req.expiry_seconds.unwrap_or(3600);
+        loop {
+            interval.tick().await;
 
-        // Create the offer based on whether an amount was specified
-        let offer = if let Some(amount_msats) = req.amount_msats {
-            self.node
-                .inner
-                .bolt12_payment()
-                .receive(amount_msats, &req.description, Some(expiry_seconds), None)
-                .map_err(|e| Status::internal(format!("Failed to create offer: {e}")))?
-        } else {
-            // Create a variable amount offer
-            self.node
+            let Some(channel) = node
                 .inner
-                .bolt12_payment()
-                .receive_variable_amount(&req.description, Some(expiry_seconds))
-                .map_err(|e| {
-                    Status::internal(format!("Failed to create variable amount offer: {e}"))
-                })?
-        };
+                .list_channels()
+                .into_iter()
+                .find(|c| c.user_channel_id == user_channel_id)
+            else {
+                send(OpenChannelProgress {
+                    channel_id: Some(channel_id),
+                    failure_reason: Some("Channel was closed before it became ready".to_string()),
+                    ..progress(Stage::Failed)
+                })
+                .await?;
+                return Ok(());
+            };
+
+            let txid = channel.funding_txo.map(|txo| txo.txid.to_string());
+
+            if funding_txid.is_none() && txid.is_some() {
+                funding_txid = txid.clone();
+                send(OpenChannelProgress {
+                    channel_id: Some(channel_id.clone()),
+                    funding_txid: txid.clone(),
+                    ..progress(Stage::FundingBroadcast)
+                })
+                .await?;
+            }
 
-        // Get current time for expiry calculation
-        let current_time = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+            if channel.is_channel_ready {
+                send(OpenChannelProgress {
+                    channel_id: Some(channel_id),
+                    funding_txid: txid,
+                    confirmations: channel.confirmations,
+                    confirmations_required: channel.confirmations_required,
+                    ..progress(Stage::Ready)
+                })
+                .await?;
+                return Ok(());
+            }
+
+            if funding_txid.is_some() && channel.confirmations != confirmations {
+                confirmations = channel.confirmations;
+                send(OpenChannelProgress {
+                    channel_id: Some(channel_id.clone()),
+                    funding_txid: txid,
+                    confirmations: channel.confirmations,
+                    confirmations_required: channel.confirmations_required,
+                    ..progress(Stage::Pending)
+                })
+                .await?;
+            }
+        }
+    }
+}
+
+impl CdkLdkServer {
+    /// Run a fund-moving request at most once per idempotency key
+    ///
+    /// Without a key the request is always executed. With a key, the final
+    /// result of the first successful request is stored and returned on
+    /// retries. `execute` marks the commitment it is given once it committed
+    /// funds.
+    async fn idempotent<Req, Resp, Fut>(
+        &self,
+        rpc: &str,
+        key: Option<String>,
+        request: &Req,
+        execute: impl FnOnce(Commitment) -> Fut,
+    ) -> Result<Response<Resp>, Status>
+    where
+        Req: Message,
+        Resp: Message + Default,
+        Fut: Future<Output = Result<Response<Resp>, Status>>,
+    {
+        let Some(key) = key else {
+            return execute(Commitment::default()).await;
+        };
+
+        if key.is_empty() || key.len() > MAX_KEY_LEN {
+            return Err(Status::invalid_argument(format!(
+                "Idempotency key must be 1 to {MAX_KEY_LEN} characters"
+            )));
+        }
+
+        let fingerprint = sha256::Hash::hash(&request.encode_to_vec()).to_string();
+        let store = self.node.idempotency_store();
+
+        let claim = store.claim(rpc, &key, &fingerprint).map_err(|err| {
+            tracing::error!("Could not claim idempotency key {}: {:#}", key, err);
+            Status::internal("Could not store the idempotency key")
+        })?;
+
+        let guard = match claim {
+            Claim::New(guard) => guard,
+            Claim::Completed(response) => {
+                tracing::info!(
+                    "Returning stored {} result for idempotency key {}",
+                    rpc,
+                    key
+                );
+                let bytes = hex::decode(&response).map_err(|e| Status::internal(e.to_string()))?;
+                return Resp::decode(bytes.as_slice())
+                    .map(Response::new)
+                    .map_err(|e| Status::internal(e.to_string()));
+            }
+            Claim::InProgress => {
+                return Err(Status::aborted(
+                    "A request with this idempotency key is still in progress",
+                ));
+            }
+            Claim::Interrupted => {
+                return Err(Status::failed_precondition(
+                    "A request with this idempotency key was interrupted by a restart, check whether it completed before retrying with a new key",
+                ));
+            }
+            Claim::Unresolved => {
+                return Err(Status::failed_precondition(
+                    "A request with this idempotency key sent funds but did not finish, check whether it completed before retrying with a new key",
+                ));
+            }
+            Claim::Mismatch => {
+                return Err(Status::invalid_argument(
+                    "Idempotency key was already used for a different request",
+                ));
+            }
+        };
+
+        // If the request fails, or this future is dropped because the client
+        // went away, the guard releases the key unless funds were committed
+        let commitment = guard.commitment();
+        let response = execute(commitment.clone()).await?;
+
+        // A pending payment may still complete or fail, retries are refused
+        // rather than answered with it
+        if !commitment.is_final() {
+            return Ok(response);
+        }
+
+        let encoded = hex::encode(response.get_ref().encode_to_vec());
+        if let Err(err) = guard.complete(encoded) {
+            tracing::error!(
+                "Could not store {} result for idempotency key {}: {:#}",
+                rpc,
+                key,
+                err
+            );
+        }
+        Ok(response)
+    }
+
+    /// Open a channel, or plan it for a dry run
+    async fn open_channel_inner(
+        &self,
+        req: OpenChannelRequest,
+        commitment: Commitment,
+    ) -> Result<Response<OpenChannelResponse>, Status> {
+        let socket_addr =
+            validation::parse_socket_address("address", &format!("{}:{}", req.address, req.port))?;
+
+        let pubkey = validation::parse_public_key("node_id", &req.node_id)?;
+
+        if req.dry_run {
+            let plan = self.plan_open_channel(
+                pubkey,
+                &socket_addr,
+                req.amount_sat,
+                req.push_to_counter_party_msat,
+            )?;
+
+            return Ok(Response::new(OpenChannelResponse {
+                channel_id: String::new(),
+                plan: Some(plan),
+            }));
+        }
+
+        self.node
+            .check_onchain_reserve(req.amount_sat)
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
+
+        self.node
+            .inner
+            .connect(pubkey, socket_addr.clone(), true)
+            .map_err(|e| validation::node_error("Could not connect to peer", e))?;
+
+        let channel = self
+            .node
+            .inner
+            .open_announced_channel(
+                pubkey,
+                socket_addr,
+                req.amount_sat,
+                req.push_to_counter_party_msat,
+                Some(self.node.channel_config),
+            )
+            .map_err(|e| validation::node_error("Could not open channel", e))?;
+        commitment.commit();
+
+        Ok(Response::new(OpenChannelResponse {
+            channel_id: channel.0.to_string(),
+            plan: None,
+        }))
+    }
+
+    /// Open several channels, checked together before any is opened
+    ///
+    /// LDK Node cannot fund several channels from one transaction, so every
+    /// channel gets its own funding transaction. Channels failing to open
+    /// once others were opened are reported in their result.
+    async fn batch_open_channels_inner(
+        &self,
+        req: BatchOpenChannelsRequest,
+        commitment: Commitment,
+    ) -> Result<Response<BatchOpenChannelsResponse>, Status> {
+        if req.channels.is_empty() {
+            return Err(invalid_field("channels", "must not be empty"));
+        }
+        if req.channels.len() > MAX_BATCH_CHANNELS {
+            return Err(invalid_field(
+                "channels",
+                format!("must not have more than {MAX_BATCH_CHANNELS} entries"),
+            ));
+        }
+
+        let mut peers: Vec<(PublicKey, SocketAddress)> = Vec::with_capacity(req.channels.len());
+        let mut total_amount: u64 = 0;
+
+        for (index, channel) in req.channels.iter().enumerate() {
+            let socket_addr = validation::parse_socket_address(
+                &format!("channels[{index}].address"),
+                &format!("{}:{}", channel.address, channel.port),
+            )?;
+            let pubkey = validation::parse_public_key(
+                &format!("channels[{index}].node_id"),
+                &channel.node_id,
+            )?;
+
+            if peers.iter().any(|(peer, _)| *peer == pubkey) {
+                return Err(invalid_field(
+                    &format!("channels[{index}].node_id"),
+                    "has another channel in the batch",
+                ));
+            }
+
+            total_amount = total_amount.saturating_add(channel.amount_sat);
+            peers.push((pubkey, socket_addr));
+        }
+
+        self.node
+            .check_onchain_reserve(total_amount)
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
+
+        for (pubkey, socket_addr) in &peers {
+            self.node
+                .inner
+                .connect(*pubkey, socket_addr.clone(), true)
+                .map_err(|e| validation::node_error("Could not connect to peer", e))?;
+        }
+
+        let channels = req
+            .channels
+            .into_iter()
+            .zip(peers)
+            .map(|(channel, (pubkey, socket_addr))| {
+                match self.node.inner.open_announced_channel(
+                    pubkey,
+                    socket_addr,
+                    channel.amount_sat,
+                    channel.push_to_counter_party_msat,
+                    Some(self.node.channel_config),
+                ) {
+                    Ok(user_channel_id) => {
+                        commitment.commit();
+                        BatchChannelResult {
+                            node_id: channel.node_id,
+                            channel_id: Some(user_channel_id.0.to_string()),
+                            error: None,
+                        }
+                    }
+                    Err(err) => {
+                        tracing::warn!("Could not open channel to {}: {}", pubkey, err);
+                        BatchChannelResult {
+                            node_id: channel.node_id,
+                            channel_id: None,
+                            error: Some(err.to_string()),
+                        }
+                    }
+                }
+            })
+            .collect();
+
+        Ok(Response::new(BatchOpenChannelsResponse {
+            channels,
+            single_transaction: false,
+        }))
+    }
+
+    /// Send onchain, or plan it for a dry run
+    ///
+    /// Sends above the management limit are refused and sends above the
+    /// approval threshold are held unless `approved`.
+    async fn send_onchain_inner(
+        &self,
+        req: SendOnchainRequest,
+        approved: bool,
+        commitment: Commitment,
+    ) -> Result<Response<SendOnchainResponse>, Status> {
+        let address =
+            validation::parse_address("address", &req.address, self.node.inner.config().network)?;
+
+        if req.dry_run {
+            let plan = self.plan_send_onchain(&address, req.amount_sat)?;
+
+            return Ok(Response::new(SendOnchainResponse {
+                txid: String::new(),
+                plan: Some(plan),
+                pending_approval_id: None,
+            }));
+        }
+
+        self.node
+            .check_onchain_reserve(req.amount_sat)
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
+
+        if !approved {
+            let amount_msat = sat_to_msat(req.amount_sat).unwrap_or(u64::MAX);
+            check_amount_limit(
+                "Onchain send",
+                amount_msat,
+                self.node.management_limits().max_onchain_send_sat,
+            )?;
+            let description = format!("Send {} sats to {}", req.amount_sat, req.address);
+
+            if let Some(id) = self.hold_for_approval("send_onchain", &req, amount_msat, description)
+            {
+                return Ok(Response::new(SendOnchainResponse {
+                    txid: String::new(),
+                    plan: None,
+                    pending_approval_id: Some(id),
+                }));
+            }
+        }
+
+        let txid = self
+            .node
+            .inner
+            .onchain_payment()
+            .send_to_address(&address, req.amount_sat, None)
+            .map_err(|e| validation::node_error("Could not send onchain", e))?;
+        commitment.commit();
+
+        if let Some(label) = req.label {
+            if let Err(err) = self
+                .node
+                .set_label(LabelKind::Transaction, &txid.to_string(), label)
+            {
+                tracing::warn!("Could not label transaction {}: {:#}", txid, err);
+            }
+        }
+
+        Ok(Response::new(SendOnchainResponse {
+            txid: txid.to_string(),
+            plan: None,
+            pending_approval_id: None,
+        }))
+    }
+
+    /// Check the approver token of a request overriding the management limits
+    ///
+    /// A request carrying the approver token is approved by it, so it skips
+    /// the approval queue as well.
+    fn authorize_override(&self, token: Option<String>) -> Result<(), Status> {
+        let queue = self.node.approval_queue().ok_or_else(|| {
+            Status::failed_precondition(
+                "Overriding the management limits requires approvals to be enabled",
+            )
+        })?;
+
+        queue
+            .verify_token(&token.unwrap_or_default())
+            .map_err(|err| {
+                tracing::warn!("Rejected management limit override with an invalid token");
+                approval_status(err)
+            })
+    }
+
+    /// Hold a withdrawal for approval if the policy requires it
+    ///
+    /// Returns the id of the pending operation, `None` if the withdrawal can
+    /// be executed right away.
+    fn hold_for_approval(
+        &self,
+        rpc: &str,
+        request: &impl Message,
+        amount_msat: u64,
+        description: String,
+    ) -> Option<String> {
+        let queue = self.node.approval_queue()?;
+        // Round up so amounts just above the threshold are not let through
+        let amount_sat = amount_msat.div_ceil(1000);
+
+        if !queue.requires_approval(amount_sat) {
+            return None;
+        }
+
+        let operation = queue.submit(rpc, request.encode_to_vec(), amount_sat, description);
+        Some(operation.id)
+    }
+
+    /// Execute an approved operation
+    async fn execute_approved(
+        &self,
+        rpc: &str,
+        request: &[u8],
+    ) -> Result<approve_operation_response::Outcome, Status> {
+        let decode_error = |e: prost::DecodeError| Status::internal(e.to_string());
+
+        let outcome = match rpc {
+            "send_onchain" => {
+                let req = SendOnchainRequest::decode(request).map_err(decode_error)?;
+                approve_operation_response::Outcome::SendOnchain(
+                    self.send_onchain_inner(req, true, Commitment::default())
+                        .await?
+                        .into_inner(),
+                )
+            }
+            "pay_bolt11_invoice" => {
+                let req = PayBolt11InvoiceRequest::decode(request).map_err(decode_error)?;
+                approve_operation_response::Outcome::Payment(
+                    self.pay_bolt11_invoice_inner(req, true, Commitment::default())
+                        .await?
+                        .into_inner(),
+                )
+            }
+            "pay_bolt12_offer" => {
+                let req = PayBolt12OfferRequest::decode(request).map_err(decode_error)?;
+                approve_operation_response::Outcome::Payment(
+                    self.pay_bolt12_offer_inner(req, true, Commitment::default())
+                        .await?
+                        .into_inner(),
+                )
+            }
+            other => return Err(Status::internal(format!("Unknown operation type {other}"))),
+        };
+
+        Ok(outcome)
+    }
+
+    /// Plan opening a channel without opening it
+    fn plan_open_channel(
+        &self,
+        pubkey: PublicKey,
+        socket_addr: &SocketAddress,
+        amount_sat: u64,
+        push_msat: Option<u64>,
+    ) -> Result<DryRunPlan, Status> {
+        self.node
+            .check_onchain_reserve(amount_sat)
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
+
+        let balances = self.node.inner.list_balances();
+        let push_sat = push_msat.map(msat_to_sat).unwrap_or_default();
+
+        if push_sat > amount_sat {
+            return Err(Status::invalid_argument(
+                "Push amount exceeds the channel amount",
+            ));
+        }
+
+        let mut warnings = vec![];
+        if !self
+            .node
+            .inner
+            .list_peers()
+            .iter()
+            .any(|peer| peer.node_id == pubkey && peer.is_connected)
+        {
+            warnings.push(format!(
+                "Not connected to {pubkey}, the node connects to {socket_addr} first"
+            ));
+        }
+
+        Ok(DryRunPlan {
+            action: format!("Open a {amount_sat} sat channel to {pubkey}@{socket_addr}"),
+            spendable_onchain_balance_after_sat: balances
+                .spendable_onchain_balance_sats
+                .saturating_sub(amount_sat),
+            total_lightning_balance_after_sat: balances.total_lightning_balance_sats + amount_sat
+                - push_sat,
+            onchain_reserve_sat: self.node.onchain_reserve_sat(),
+            warnings,
+        })
+    }
+
+    /// Plan cooperatively closing a channel without closing it
+    fn plan_close_channel(
+        &self,
+        user_channel_id: UserChannelId,
+        counterparty_node_id: PublicKey,
+    ) -> Result<DryRunPlan, Status> {
+        let channel = self
+            .node
+            .inner
+            .list_channels()
+            .into_iter()
+            .find(|channel| {
+                channel.user_channel_id == user_channel_id
+                    && channel.counterparty_node_id == counterparty_node_id
+            })
+            .ok_or_else(|| Status::not_found("Channel not found"))?;
+
+        let balances = self.node.inner.list_balances();
+        let outbound_sat = msat_to_sat(channel.outbound_capacity_msat);
+
+        let mut warnings = vec![];
+        if !channel.is_usable {
+            warnings.push(
+                "Channel is not usable, a cooperative close needs the peer online".to_string(),
+            );
+        }
+        if channel.channel_value_sats > 0 && outbound_sat == 0 {
+            warnings.push("Channel holds no balance for this node".to_string());
+        }
+
+        Ok(DryRunPlan {
+            action: format!(
+                "Close channel {} with {}, returning about {} sats onchain once confirmed",
+                channel.channel_id, counterparty_node_id, outbound_sat
+            ),
+            spendable_onchain_balance_after_sat: balances.spendable_onchain_balance_sats
+                + outbound_sat,
+            total_lightning_balance_after_sat: balances
+                .total_lightning_balance_sats
+                .saturating_sub(outbound_sat),
+            onchain_reserve_sat: self.node.onchain_reserve_sat(),
+            warnings,
+        })
+    }
+
+    /// Plan an onchain send without sending
+    fn plan_send_onchain(&self, address: &Address, amount_sat: u64) -> Result<DryRunPlan, Status> {
+        self.node
+            .check_onchain_reserve(amount_sat)
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
+
+        let balances = self.node.inner.list_balances();
+
+        Ok(DryRunPlan {
+            action: format!("Send {amount_sat} sats to {address}"),
+            spendable_onchain_balance_after_sat: balances
+                .spendable_onchain_balance_sats
+                .saturating_sub(amount_sat),
+            total_lightning_balance_after_sat: balances.total_lightning_balance_sats,
+            onchain_reserve_sat: self.node.onchain_reserve_sat(),
+            warnings: vec![],
+        })
+    }
+}
+
+/// Response for a payment held for approval
+fn pending_payment(payment_hash: String, pending_approval_id: String) -> PaymentResponse {
+    PaymentResponse {
+        payment_hash,
+        payment_preimage: String::new(),
+        fee_msat: 0,
+        success: false,
+        failure_reason: Some(format!(
+            "Payment is pending approval as operation {pending_approval_id}"
+        )),
+        pending_approval_id: Some(pending_approval_id),
+    }
+}
+
+/// Management response of a hold invoice
+fn hold_invoice_response(invoice: crate::hold::HoldInvoice) -> HoldInvoice {
+    HoldInvoice {
+        payment_hash: invoice.payment_hash,
+        amount_msat: invoice.amount_msat,
+        state: invoice.state.as_str().to_string(),
+        claimable_amount_msat: invoice.claimable_amount_msat,
+        claim_deadline: invoice.claim_deadline,
+        created_at: invoice.created_at,
+        updated_at: invoice.updated_at,
+    }
+}
+
+/// Map an approval queue error to a gRPC status
+fn approval_status(err: ApprovalError) -> Status {
+    match err {
+        ApprovalError::Unauthorized => Status::unauthenticated(err.to_string()),
+        ApprovalError::NotFound(_) => Status::not_found(err.to_string()),
+    }
+}
+
+/// Refuse a payment above a management limit
+fn check_amount_limit(rpc: &str, amount_msat: u64, max_sat: Option<u64>) -> Result<(), Status> {
+    let Some(max_sat) = max_sat else {
+        return Ok(());
+    };
+    // Round up so amounts just above the limit are not let through
+    let amount_sat = amount_msat.div_ceil(1000);
+
+    if amount_sat > max_sat {
+        return Err(Status::failed_precondition(format!(
+            "{rpc} of {amount_sat} sats is above the limit of {max_sat} sats, override it with the approver token"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Time to wait for a payment, the request's timeout or the node's default
+fn send_timeout(timeout_secs: Option<u64>, default: Duration) -> Result<Duration, Status> {
+    match timeout_secs {
+        None => Ok(default),
+        Some(0) => Err(invalid_field("timeout_secs", "must be positive")),
+        Some(secs) if secs > MAX_SEND_TIMEOUT_SECS => Err(invalid_field(
+            "timeout_secs",
+            format!("must not exceed {MAX_SEND_TIMEOUT_SECS}"),
+        )),
+        Some(secs) => Ok(Duration::from_secs(secs)),
+    }
+}
+
+/// Approver token passed in the request metadata
+fn approver_token_header<T>(request: &Request<T>) -> Option<String> {
+    request
+        .metadata()
+        .get(APPROVER_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Progress update at the given stage with no details
+fn progress(stage: Stage) -> OpenChannelProgress {
+    OpenChannelProgress {
+        stage: stage.into(),
+        ..Default::default()
+    }
+}
+
+#[tonic::async_trait]
+impl CdkLdkManagement for CdkLdkServer {
+    type OpenChannelStreamStream = OpenChannelProgressStream;
+    type ExportLedgerStream = LedgerBatchStream;
+
+    async fn get_api_version(
+        &self,
+        _request: Request<GetApiVersionRequest>,
+    ) -> Result<Response<GetApiVersionResponse>, Status> {
+        Ok(Response::new(GetApiVersionResponse {
+            api_version: API_VERSION,
+            server_version: SERVER_VERSION.to_string(),
+            capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+        }))
+    }
+
+    async fn get_info(
+        &self,
+        _request: Request<GetInfoRequest>,
+    ) -> Result<Response<GetInfoResponse>, Status> {
+        let node = self.node.inner.as_ref();
+
+        let node_id = node.node_id();
+        let alias = node
+            .node_alias()
+            .map(|a| a.to_string())
+            .unwrap_or("".to_string());
+
+        let config = self.node.inner.config();
+
+        let announcement_addresses = config
+            .announcement_addresses
+            .as_ref()
+            .unwrap_or(&vec![])
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+
+        let listening_address_details: Vec<ListeningAddress> =
+            crate::peers::listening_addresses(node)
+                .into_iter()
+                .map(|listening| ListeningAddress {
+                    address: listening.address.to_string(),
+                    bound_sockets: listening
+                        .bound_sockets
+                        .iter()
+                        .map(|socket| socket.to_string())
+                        .collect(),
+                    announced_in_gossip: listening.announced_in_gossip,
+                })
+                .collect();
+
+        let listening_addresses = listening_address_details
+            .iter()
+            .map(|listening| listening.address.clone())
+            .collect();
+
+        let (num_peers, num_connected_peers) =
+            node.list_peers()
+                .iter()
+                .fold((0, 0), |(mut peers, mut connected), p| {
+                    if p.is_connected {
+                        connected += 1;
+                    }
+                    peers += 1;
+
+                    (peers, connected)
+                });
+
+        let (num_active_channels, num_inactive_channels) =
+            node.list_channels()
+                .iter()
+                .fold((0, 0), |(mut active, mut inactive), c| {
+                    if c.is_usable {
+                        active += 1;
+                    } else {
+                        inactive += 1;
+                    }
+                    (active, inactive)
+                });
+
+        Ok(Response::new(GetInfoResponse {
+            node_id: node_id.to_string(),
+            alias,
+            announcement_addresses,
+            listening_addresses,
+            num_peers,
+            num_connected_peers,
+            num_active_channels,
+            num_inactive_channels,
+            api_version: API_VERSION,
+            server_version: SERVER_VERSION.to_string(),
+            listening_address_details,
+            is_listening: node.status().is_listening,
+        }))
+    }
+
+    async fn get_node_status(
+        &self,
+        _request: Request<GetNodeStatusRequest>,
+    ) -> Result<Response<GetNodeStatusResponse>, Status> {
+        let status = self.node.inner.status();
+        let chain_source_health = self.node.chain_source_health();
+
+        Ok(Response::new(GetNodeStatusResponse {
+            is_running: status.is_running,
+            state: self.node.state().to_string(),
+            is_listening: status.is_listening,
+            best_block_height: status.current_best_block.height,
+            best_block_hash: status.current_best_block.block_hash.to_string(),
+            latest_lightning_wallet_sync_timestamp: status.latest_lightning_wallet_sync_timestamp,
+            latest_onchain_wallet_sync_timestamp: status.latest_onchain_wallet_sync_timestamp,
+            latest_fee_rate_cache_update_timestamp: status.latest_fee_rate_cache_update_timestamp,
+            latest_rgs_snapshot_timestamp: status.latest_rgs_snapshot_timestamp,
+            chain_source: Some(ChainSourceStatus {
+                active: self.node.chain_source().to_string(),
+                healthy: chain_source_health.is_healthy(),
+                fallbacks: chain_source_health.fallbacks,
+                tip_height: chain_source_health.tip_height,
+                fallback_tip_height: chain_source_health.fallback_tip_height,
+                last_error: chain_source_health.last_error,
+                last_checked: chain_source_health.last_checked,
+            }),
+        }))
+    }
+
+    async fn get_payment_limits(
+        &self,
+        _request: Request<GetPaymentLimitsRequest>,
+    ) -> Result<Response<GetPaymentLimitsResponse>, Status> {
+        let limits = self.node.payment_limits();
+
+        Ok(Response::new(GetPaymentLimitsResponse {
+            max_receivable_msat: limits.max_receivable_msat,
+            max_receivable_single_path_msat: limits.max_receivable_single_path_msat,
+            max_payable_msat: limits.max_payable_msat,
+            max_payable_single_path_msat: limits.max_payable_single_path_msat,
+        }))
+    }
+
+    async fn verify_backup(
+        &self,
+        request: Request<VerifyBackupRequest>,
+    ) -> Result<Response<VerifyBackupResponse>, Status> {
+        let req = request.into_inner();
+
+        let report = self
+            .node
+            .verify_backup(req.backup_dir.map(Into::into))
+            .await
+            .map_err(|e| {
+                Status::failed_precondition(format!("Backup verification failed: {e:#}"))
+            })?;
+
+        Ok(Response::new(VerifyBackupResponse {
+            backup_dir: report.backup_dir.display().to_string(),
+            node_id: report.node_id.to_string(),
+            node_id_matches: report.node_id_matches,
+            num_channels: report.num_channels as u64,
+            verified_at: report.verified_at,
+        }))
+    }
+
+    async fn export_seed(
+        &self,
+        request: Request<ExportSeedRequest>,
+    ) -> Result<Response<ExportSeedResponse>, Status> {
+        let remote_addr = request.remote_addr().map(|addr| addr.to_string());
+        let client_certificate = request
+            .peer_certs()
+            .and_then(|certs| certs.first().map(|cert| cert.as_ref().to_vec()));
+        let token = request
+            .metadata()
+            .get(SEED_EXPORT_TOKEN_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        let outcome = match self.node.seed_export_token() {
+            None => SeedExportOutcome::Disabled,
+            Some(export_token) if !export_token.verify(token) => SeedExportOutcome::Unauthorized,
+            Some(_) => SeedExportOutcome::Exported,
+        };
+
+        // The export is only returned once it is on record
+        let record =
+            SeedExportRecord::new(remote_addr.clone(), client_certificate.as_deref(), outcome);
+        if let Err(err) = self.node.seed_export_audit().record(&record) {
+            tracing::error!("Could not record seed export attempt: {:#}", err);
+            if outcome == SeedExportOutcome::Exported {
+                return Err(Status::internal(
+                    "Could not record the seed export, refusing to export",
+                ));
+            }
+        }
+
+        let remote_addr = remote_addr.unwrap_or_else(|| "unknown".to_string());
+        match outcome {
+            SeedExportOutcome::Disabled => {
+                tracing::warn!(
+                    "Refused seed export requested from {}, export is disabled",
+                    remote_addr
+                );
+                return Err(Status::permission_denied(
+                    "Seed export is disabled, set allow_export in the [seed] config section",
+                ));
+            }
+            SeedExportOutcome::Unauthorized => {
+                tracing::warn!(
+                    "Refused seed export requested from {}, invalid export token",
+                    remote_addr
+                );
+                return Err(Status::unauthenticated("Invalid seed export token"));
+            }
+            SeedExportOutcome::Exported => {
+                tracing::warn!("Exporting node seed, requested from {}", remote_addr);
+            }
+        }
+
+        let response = match self.node.seed() {
+            NodeSeed::Mnemonic {
+                mnemonic,
+                passphrase,
+            } => ExportSeedResponse {
+                seed: Some(export_seed_response::Seed::Mnemonic(mnemonic.to_string())),
+                has_passphrase: passphrase.is_some(),
+            },
+            NodeSeed::SeedFile(path) => {
+                let raw_seed = std::fs::read(&path).map_err(|e| {
+                    Status::internal(format!("Could not read seed file {}: {e}", path.display()))
+                })?;
+                ExportSeedResponse {
+                    seed: Some(export_seed_response::Seed::RawSeedHex(hex::encode(
+                        raw_seed,
+                    ))),
+                    has_passphrase: false,
+                }
+            }
+        };
+
+        Ok(Response::new(response))
+    }
+
+    async fn get_wallet_descriptors(
+        &self,
+        _request: Request<GetWalletDescriptorsRequest>,
+    ) -> Result<Response<GetWalletDescriptorsResponse>, Status> {
+        let descriptors = self
+            .node
+            .wallet_descriptors()
+            .map_err(|e| Status::internal(format!("Could not derive wallet descriptors: {e:#}")))?;
+
+        Ok(Response::new(GetWalletDescriptorsResponse {
+            external_descriptor: descriptors.external,
+            internal_descriptor: descriptors.internal,
+        }))
+    }
+
+    async fn warmup_graph(
+        &self,
+        request: Request<WarmupGraphRequest>,
+    ) -> Result<Response<WarmupGraphResponse>, Status> {
+        let req = request.into_inner();
+
+        let timeout = Duration::from_secs(req.timeout_seconds.unwrap_or(0) as u64);
+        let graph_status = self.node.warm_up_graph(timeout).await;
+
+        Ok(Response::new(WarmupGraphResponse {
+            num_channels: graph_status.num_channels,
+            num_nodes: graph_status.num_nodes,
+            is_ready: graph_status.is_ready(),
+            latest_update_timestamp: graph_status.latest_update_timestamp,
+            latest_rgs_snapshot_timestamp: graph_status.latest_rgs_snapshot_timestamp,
+            staleness_seconds: graph_status.staleness_secs(),
+        }))
+    }
+
+    async fn sync_wallets(
+        &self,
+        _request: Request<SyncWalletsRequest>,
+    ) -> Result<Response<SyncWalletsResponse>, Status> {
+        let report = self
+            .node
+            .sync_wallets()
+            .await
+            .map_err(|e| Status::unavailable(format!("Wallet sync failed: {e}")))?;
+
+        Ok(Response::new(SyncWalletsResponse {
+            best_block_height: report.best_block_height,
+            best_block_hash: report.best_block_hash.to_string(),
+            duration_ms: report.duration.as_millis() as u64,
+            latest_onchain_wallet_sync_timestamp: report.latest_onchain_wallet_sync_timestamp,
+            latest_lightning_wallet_sync_timestamp: report.latest_lightning_wallet_sync_timestamp,
+        }))
+    }
+
+    async fn get_recent_logs(
+        &self,
+        request: Request<GetRecentLogsRequest>,
+    ) -> Result<Response<GetRecentLogsResponse>, Status> {
+        let req = request.into_inner();
+
+        let level = match req.level {
+            Some(level) => {
+                tracing::Level::from_str(&level).map_err(|_| invalid_field("level", &level))?
+            }
+            None => tracing::Level::INFO,
+        };
+        let limit = req.limit.unwrap_or(DEFAULT_LOG_LIMIT) as usize;
+
+        let entries = self
+            .node
+            .recent_logs(level, limit, req.after_seq)
+            .ok_or_else(|| Status::unavailable("Log buffering is not enabled on this node"))?;
+
+        let next_seq = entries
+            .last()
+            .map(|entry| entry.seq + 1)
+            .or(req.after_seq)
+            .unwrap_or_default();
+
+        let entries = entries
+            .into_iter()
+            .map(|entry| LogEntry {
+                seq: entry.seq,
+                timestamp: entry.timestamp,
+                level: entry.level.to_string(),
+                target: entry.target,
+                message: entry.message,
+            })
+            .collect();
+
+        Ok(Response::new(GetRecentLogsResponse { entries, next_seq }))
+    }
+
+    async fn get_new_address(
+        &self,
+        request: Request<GetNewAddressRequest>,
+    ) -> Result<Response<GetNewAddressResponse>, Status> {
+        let req = request.into_inner();
+
+        let address_type = req
+            .address_type
+            .as_deref()
+            .map(AddressType::from_str)
+            .transpose()
+            .map_err(|e| invalid_field("address_type", e))?;
+
+        let (address, reused) = self
+            .node
+            .new_address(address_type, req.reuse_unused)
+            .map_err(|e| Status::failed_precondition(format!("{e:#}")))?;
+
+        if let Some(label) = req.label {
+            self.node
+                .set_label(LabelKind::Address, &address.to_string(), label)
+                .map_err(|e| Status::internal(format!("Could not label address: {e:#}")))?;
+        }
+
+        Ok(Response::new(GetNewAddressResponse {
+            address: address.to_string(),
+            reused,
+        }))
+    }
+
+    async fn set_label(
+        &self,
+        request: Request<SetLabelRequest>,
+    ) -> Result<Response<SetLabelResponse>, Status> {
+        let label = request
+            .into_inner()
+            .label
+            .ok_or_else(|| Status::invalid_argument("Label is required"))?;
+
+        let kind =
+            LabelKind::from_str(&label.r#type).map_err(|e| invalid_field("label type", e))?;
+
+        self.node
+            .set_label(kind, &label.r#ref, label.label)
+            .map_err(|e| Status::invalid_argument(format!("{e:#}")))?;
+
+        Ok(Response::new(SetLabelResponse {}))
+    }
+
+    async fn list_labels(
+        &self,
+        _request: Request<ListLabelsRequest>,
+    ) -> Result<Response<ListLabelsResponse>, Status> {
+        let labels = self
+            .node
+            .list_labels()
+            .into_iter()
+            .map(|label| Label {
+                r#type: label.kind.to_string(),
+                r#ref: label.reference,
+                label: label.label,
+            })
+            .collect();
+
+        Ok(Response::new(ListLabelsResponse { labels }))
+    }
+
+    async fn open_channel(
+        &self,
+        request: Request<OpenChannelRequest>,
+    ) -> Result<Response<OpenChannelResponse>, Status> {
+        let req = request.into_inner();
+        let idempotency_key = req.idempotency_key.clone().filter(|_| !req.dry_run);
+
+        self.idempotent(
+            "open_channel",
+            idempotency_key,
+            &req.clone(),
+            |commitment| self.open_channel_inner(req, commitment),
+        )
+        .await
+    }
+
+    async fn open_channel_stream(
+        &self,
+        request: Request<OpenChannelRequest>,
+    ) -> Result<Response<Self::OpenChannelStreamStream>, Status> {
+        let req = request.into_inner();
+        let node = self.node.clone();
+
+        if req.dry_run || req.idempotency_key.is_some() {
+            return Err(Status::invalid_argument(
+                "Dry runs and idempotency keys are not supported when streaming, use OpenChannel",
+            ));
+        }
+
+        let (tx, rx) = mpsc::channel(16);
+
+        tokio::spawn(async move {
+            if let Err(status) = Self::stream_open_channel(node, req, tx.clone()).await {
+                if status.code() != tonic::Code::Cancelled {
+                    tracing::warn!("Channel open failed: {}", status.message());
+                    let _ = tx.send(Err(status)).await;
+                }
+            }
+        });
+
+        let stream = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|item| (item, rx))
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn batch_open_channels(
+        &self,
+        request: Request<BatchOpenChannelsRequest>,
+    ) -> Result<Response<BatchOpenChannelsResponse>, Status> {
+        let req = request.into_inner();
+        let idempotency_key = req.idempotency_key.clone();
+
+        self.idempotent(
+            "batch_open_channels",
+            idempotency_key,
+            &req.clone(),
+            |commitment| self.batch_open_channels_inner(req, commitment),
+        )
+        .await
+    }
+
+    async fn close_channel(
+        &self,
+        request: Request<CloseChannelRequest>,
+    ) -> Result<Response<CloseChannelResponse>, Status> {
+        let req = request.into_inner();
+
+        let node_pubkey = validation::parse_public_key("node_pubkey", &req.node_pubkey)?;
+
+        let channel_id: u128 = req
+            .channel_id
+            .parse()
+            .map_err(|e| invalid_field("channel_id", e))?;
+
+        let channel_id = UserChannelId(channel_id);
+
+        if req.dry_run {
+            let plan = self.plan_close_channel(channel_id, node_pubkey)?;
+            return Ok(Response::new(CloseChannelResponse { plan: Some(plan) }));
+        }
+
+        self.node
+            .inner
+            .close_channel(&channel_id, node_pubkey)
+            .map_err(|e| validation::node_error("Could not close channel", e))?;
+
+        Ok(Response::new(CloseChannelResponse { plan: None }))
+    }
+
+    async fn list_balance(
+        &self,
+        _request: Request<ListBalanceRequest>,
+    ) -> Result<Response<ListBalanceResponse>, Status> {
+        let node_balance = self.node.inner.list_balances();
+        let onchain_reserve_sat = self.node.onchain_reserve_sat();
+
+        Ok(Response::new(ListBalanceResponse {
+            total_onchain_balance_sat: node_balance.total_onchain_balance_sats,
+            spendable_onchain_balance_sat: node_balance.spendable_onchain_balance_sats,
+            total_lightning_balance_sat: node_balance.total_lightning_balance_sats,
+            onchain_reserve_sat,
+            available_onchain_balance_sat: node_balance
+                .spendable_onchain_balance_sats
+                .saturating_sub(onchain_reserve_sat),
+        }))
+    }
+
+    async fn list_channels(
+        &self,
+        _request: Request<ListChannelsRequest>,
+    ) -> Result<Response<ListChannelsResponse>, Status> {
+        let channels = self.node.inner.list_channels();
+
+        // balance_msat is deprecated but still filled for older clients
+        #[allow(deprecated)]
+        let channel_infos = channels
+            .iter()
+            .map(|channel| ChannelInfo {
+                channel_id: channel.channel_id.to_string(),
+                counterparty_node_id: channel.counterparty_node_id.to_string(),
+                balance_msat: channel.outbound_capacity_msat,
+                outbound_capacity_msat: channel.outbound_capacity_msat,
+                inbound_capacity_msat: channel.inbound_capacity_msat,
+                is_usable: channel.is_usable,
+                is_public: channel.is_channel_ready, // Using is_channel_ready as is_public
+                short_channel_id: channel
+                    .short_channel_id
+                    .map(|scid| scid.to_string())
+                    .unwrap_or_default(),
+                channel_value_sat: channel.channel_value_sats,
+            })
+            .collect();
+
+        Ok(Response::new(ListChannelsResponse {
+            channels: channel_infos,
+        }))
+    }
+
+    async fn list_counterparty_balances(
+        &self,
+        _request: Request<ListCounterpartyBalancesRequest>,
+    ) -> Result<Response<ListCounterpartyBalancesResponse>, Status> {
+        let counterparties = self
+            .node
+            .counterparty_channels()
+            .into_iter()
+            .map(|counterparty| CounterpartyBalance {
+                counterparty_node_id: counterparty.counterparty_node_id.to_string(),
+                num_channels: counterparty.num_channels as u64,
+                num_usable_channels: counterparty.num_usable_channels as u64,
+                capacity_sat: counterparty.capacity_sat,
+                spendable_outbound_msat: counterparty.outbound_msat,
+                receivable_inbound_msat: counterparty.inbound_msat,
+                reserve_sat: counterparty.reserve_sat,
+                counterparty_reserve_sat: counterparty.counterparty_reserve_sat,
+            })
+            .collect();
+
+        Ok(Response::new(ListCounterpartyBalancesResponse {
+            counterparties,
+        }))
+    }
+
+    async fn channel_report(
+        &self,
+        request: Request<ChannelReportRequest>,
+    ) -> Result<Response<ChannelReportResponse>, Status> {
+        let req = request.into_inner();
+
+        let period = req
+            .period_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_CHANNEL_REPORT_PERIOD);
+
+        let report = self.node.channel_report(period);
+
+        let channels = report
+            .channels
+            .into_iter()
+            .map(|channel| ChannelUsage {
+                channel_id: channel.channel_id,
+                counterparty_node_id: channel.counterparty_node_id,
+                channel_value_sat: channel.channel_value_sat,
+                is_usable: channel.is_usable,
+                forwarded_in_msat: channel.forwarded_in_msat,
+                forwarded_out_msat: channel.forwarded_out_msat,
+                num_forwards: channel.num_forwards,
+                fees_earned_msat: channel.fees_earned_msat,
+                downtime_secs: channel.downtime_secs,
+            })
+            .collect();
+
+        Ok(Response::new(ChannelReportResponse {
+            period_start: report.period_start,
+            period_end: report.period_end,
+            channels,
+            payments_sent_msat: report.payments_sent_msat,
+            fees_paid_msat: report.fees_paid_msat,
+            payments_received_msat: report.payments_received_msat,
+        }))
+    }
+
+    async fn get_fee_breakdown(
+        &self,
+        request: Request<GetFeeBreakdownRequest>,
+    ) -> Result<Response<GetFeeBreakdownResponse>, Status> {
+        let req = request.into_inner();
+
+        let to = req.to.unwrap_or_else(unix_time);
+        let from = req
+            .from
+            .unwrap_or_else(|| to.saturating_sub(DEFAULT_FEE_BREAKDOWN_PERIOD_SECS));
+        if from >= to {
+            return Err(invalid_field("from", "must be before to"));
+        }
+
+        let months = self
+            .node
+            .fee_breakdown(from, to)
+            .map_err(|e| Status::internal(format!("Could not compute fee breakdown: {e:#}")))?
+            .into_iter()
+            .map(|month| MonthlyFees {
+                net_msat: month.net_msat(),
+                month: month.month,
+                routing_income_msat: month.routing_income_msat,
+                lightning_fees_paid_msat: month.lightning_fees_paid_msat,
+                onchain_fees_paid_msat: month.onchain_fees_paid_msat,
+            })
+            .collect();
+
+        Ok(Response::new(GetFeeBreakdownResponse { from, to, months }))
+    }
+
+    async fn send_onchain(
+        &self,
+        request: Request<SendOnchainRequest>,
+    ) -> Result<Response<SendOnchainResponse>, Status> {
+        let token = approver_token_header(&request);
+        let req = request.into_inner();
+        let idempotency_key = req.idempotency_key.clone().filter(|_| !req.dry_run);
+
+        let approved = req.override_limit;
+        if approved {
+            self.authorize_override(token)?;
+        }
+
+        self.idempotent(
+            "send_onchain",
+            idempotency_key,
+            &req.clone(),
+            |commitment| self.send_onchain_inner(req, approved, commitment),
+        )
+        .await
+    }
+
+    async fn pay_bolt11_invoice(
+        &self,
+        request: Request<PayBolt11InvoiceRequest>,
+    ) -> Result<Response<PaymentResponse>, Status> {
+        let header_correlation_id = correlation_id_header(&request);
+        let token = approver_token_header(&request);
+        let req = request.into_inner();
+
+        let approved = req.override_limit;
+        if approved {
+            self.authorize_override(token)?;
+        }
+        let correlation_id = req.correlation_id.clone().or(header_correlation_id);
+
+        let span = tracing::info_span!(
+            "pay_bolt11_invoice",
+            correlation_id = correlation_id.as_deref()
+        );
+        // Retries may carry a different correlation id
+        let fingerprint = PayBolt11InvoiceRequest {
+            correlation_id: None,
+            ..req.clone()
+        };
+        let idempotency_key = req.idempotency_key.clone();
+
+        self.idempotent(
+            "pay_bolt11_invoice",
+            idempotency_key,
+            &fingerprint,
+            |commitment| self.pay_bolt11_invoice_inner(req, approved, commitment),
+        )
+        .instrument(span)
+        .await
+    }
+
+    async fn pay_bolt12_offer(
+        &self,
+        request: Request<PayBolt12OfferRequest>,
+    ) -> Result<Response<PaymentResponse>, Status> {
+        let header_correlation_id = correlation_id_header(&request);
+        let token = approver_token_header(&request);
+        let req = request.into_inner();
+
+        let approved = req.override_limit;
+        if approved {
+            self.authorize_override(token)?;
+        }
+        let correlation_id = req.correlation_id.clone().or(header_correlation_id);
+
+        let span = tracing::info_span!(
+            "pay_bolt12_offer",
+            correlation_id = correlation_id.as_deref()
+        );
+        // Retries may carry a different correlation id
+        let fingerprint = PayBolt12OfferRequest {
+            correlation_id: None,
+            ..req.clone()
+        };
+        let idempotency_key = req.idempotency_key.clone();
+
+        self.idempotent(
+            "pay_bolt12_offer",
+            idempotency_key,
+            &fingerprint,
+            |commitment| self.pay_bolt12_offer_inner(req, approved, commitment),
+        )
+        .instrument(span)
+        .await
+    }
+
+    async fn create_bolt11_invoice(
+        &self,
+        request: Request<CreateBolt11InvoiceRequest>,
+    ) -> Result<Response<CreateInvoiceResponse>, Status> {
+        let tenant = tenant_scope(&request);
+        let req = request.into_inner();
+
+        // Set up the description
+        let description = self.node.payment_description(
+            Some(req.description),
+            Some(req.amount_msat.into()),
+            &CurrencyUnit::Msat,
+        );
+        let description = crate::bolt11_description(description, req.description_hash);
+
+        // Get expiry time (default to 1 hour if not specified)
+        let expiry_seconds = req.expiry_seconds.unwrap_or(3600);
+
+        let payment_hash = req
+            .payment_hash
+            .as_deref()
+            .map(|hash| {
+                identifiers::parse_hex32(hash)
+                    .map(PaymentHash)
+                    .map_err(|e| invalid_field("payment_hash", e))
+            })
+            .transpose()?;
+
+        // A tenant choosing the payment hash could hold and capture a payment
+        // to another tenant's invoice
+        if tenant.is_some() && payment_hash.is_some() {
+            return Err(Status::permission_denied(
+                "Tenant tokens cannot create invoices for a payment hash",
+            ));
+        }
+
+        // Create the invoice
+        let invoice = if req.hold {
+            self.node
+                .create_hold_invoice(
+                    Some(req.amount_msat),
+                    &description,
+                    expiry_seconds,
+                    payment_hash,
+                )
+                .map_err(|e| Status::failed_precondition(format!("{e:#}")))?
+        } else {
+            if payment_hash.is_some() {
+                return Err(invalid_field(
+                    "payment_hash",
+                    "only hold invoices can be created for a payment hash",
+                ));
+            }
+
+            self.node
+                .inner
+                .bolt11_payment()
+                .receive(req.amount_msat, &description, expiry_seconds)
+                .map_err(|e| validation::node_error("Failed to create invoice", e))?
+        };
+
+        if let Some(tenant) = &tenant {
+            self.node
+                .tenant_tags()
+                .tag(invoice.payment_hash().to_string(), tenant)
+                .map_err(|e| Status::internal(format!("Could not tag invoice: {e:#}")))?;
+        }
+
+        // Get current time for expiry calculation
+        let current_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        Ok(Response::new(CreateInvoiceResponse {
+            payment_hash: invoice.payment_hash().to_string(),
+            invoice: invoice.to_string(),
+            expiry_time: current_time + expiry_seconds as u64,
+        }))
+    }
+
+    async fn create_bolt12_offer(
+        &self,
+        request: Request<CreateBolt12OfferRequest>,
+    ) -> Result<Response<CreateOfferResponse>, Status> {
+        let tenant = tenant_scope(&request);
+        let req = request.into_inner();
+
+        // Get expiry time (default to 1 hour if not specified)
+        let expiry_seconds = match req.absolute_expiry {
+            Some(absolute_expiry) => {
+                crate::incoming_expiry_secs(Some(absolute_expiry), unix_time())
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?
+            }
+            None => req.expiry_seconds.unwrap_or(3600),
+        };
+
+        if req.quantity == Some(0) {
+            return Err(Status::invalid_argument("Quantity must be at least 1"));
+        }
+
+        let description = self.node.payment_description(
+            Some(req.description),
+            req.amount_msat.map(Into::into),
+            &CurrencyUnit::Msat,
+        );
+
+        // Create the offer based on whether an amount was specified
+        let offer = if let Some(amount_msats) = req.amount_msat {
+            self.node
+                .inner
+                .bolt12_payment()
+                .receive(
+                    amount_msats,
+                    &description,
+                    Some(expiry_seconds),
+                    req.quantity,
+                )
+                .map_err(|e| validation::node_error("Failed to create offer", e))?
+        } else {
+            if req.quantity.is_some() {
+                return Err(Status::invalid_argument(
+                    "Quantity is only supported for fixed-amount offers",
+                ));
+            }
+
+            // Create a variable amount offer
+            self.node
+                .inner
+                .bolt12_payment()
+                .receive_variable_amount(&description, Some(expiry_seconds))
+                .map_err(|e| validation::node_error("Failed to create variable amount offer", e))?
+        };
+
+        if let Some(tenant) = &tenant {
+            self.node
+                .tenant_tags()
+                .tag(offer.id().to_string(), tenant)
+                .map_err(|e| Status::internal(format!("Could not tag offer: {e:#}")))?;
+        }
+
+        // Get current time for expiry calculation
+        let current_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
 
         Ok(Response::new(CreateOfferResponse {
             offer_id: offer.id().to_string(),
@@ -477,4 +1911,591 @@ impl CdkLdkManagement for CdkLdkServer {
             expiry_time: current_time + expiry_seconds as u64,
         }))
     }
+
+    async fn decode_offer(
+        &self,
+        request: Request<DecodeOfferRequest>,
+    ) -> Result<Response<DecodeOfferResponse>, Status> {
+        let req = request.into_inner();
+
+        let offer =
+            Offer::from_str(&req.offer).map_err(|e| invalid_field("offer", format!("{e:?}")))?;
+
+        let (amount_msats, currency_amount) = match offer.amount() {
+            Some(Amount::Bitcoin { amount_msats }) => (Some(amount_msats), None),
+            Some(Amount::Currency {
+                iso4217_code,
+                amount,
+            }) => (
+                None,
+                Some(format!(
+                    "{amount} {}",
+                    String::from_utf8_lossy(&iso4217_code)
+                )),
+            ),
+            None => (None, None),
+        };
+
+        let max_quantity = match offer.supported_quantity() {
+            Quantity::One => Some(1),
+            Quantity::Bounded(max) => Some(max.get()),
+            Quantity::Unbounded => None,
+        };
+
+        Ok(Response::new(DecodeOfferResponse {
+            offer_id: offer.id().to_string(),
+            description: offer.description().map(|d| d.to_string()),
+            issuer: offer.issuer().map(|i| i.to_string()),
+            amount_msat: amount_msats,
+            currency_amount,
+            absolute_expiry: offer.absolute_expiry().map(|e| e.as_secs()),
+            is_expired: offer.is_expired(),
+            max_quantity,
+            issuer_signing_pubkey: offer.issuer_signing_pubkey().map(|k| k.to_string()),
+            num_blinded_paths: offer.paths().len() as u32,
+        }))
+    }
+
+    async fn check_incoming_payments(
+        &self,
+        request: Request<CheckIncomingPaymentsRequest>,
+    ) -> Result<Response<CheckIncomingPaymentsResponse>, Status> {
+        let tenant = tenant_scope(&request);
+        let req = request.into_inner();
+
+        let identifiers = req
+            .payment_ids
+            .iter()
+            .map(|id| {
+                identifiers::parse_hex32(id)
+                    .map(PaymentIdentifier::PaymentHash)
+                    .map_err(|e| Status::invalid_argument(e.to_string()))
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+
+        let payments = req
+            .payment_ids
+            .into_iter()
+            .zip(self.node.check_incoming_payment_statuses(&identifiers))
+            .map(|(payment_id, result)| {
+                // Payments of other tenants are reported as unknown
+                let foreign = tenant.as_ref().is_some_and(|tenant| {
+                    self.node.tenant_tags().tenant_of(&payment_id).as_ref() != Some(tenant)
+                });
+                if foreign {
+                    return IncomingPaymentStatus {
+                        payment_id,
+                        amount_msat: 0,
+                        error: Some("Payment not found".to_string()),
+                    };
+                }
+
+                match result {
+                    Ok(response) => IncomingPaymentStatus {
+                        payment_id,
+                        amount_msat: u64::from(response.payment_amount),
+                        error: None,
+                    },
+                    Err(err) => IncomingPaymentStatus {
+                        payment_id,
+                        amount_msat: 0,
+                        error: Some(err.to_string()),
+                    },
+                }
+            })
+            .collect();
+
+        Ok(Response::new(CheckIncomingPaymentsResponse { payments }))
+    }
+
+    async fn list_peers(
+        &self,
+        _request: Request<ListPeersRequest>,
+    ) -> Result<Response<ListPeersResponse>, Status> {
+        let now = unix_time();
+
+        let peers = self
+            .node
+            .list_peers()
+            .into_iter()
+            .map(|peer| PeerInfo {
+                node_id: peer.details.node_id.to_string(),
+                address: peer.details.address.to_string(),
+                is_persisted: peer.details.is_persisted,
+                is_connected: peer.details.is_connected,
+                connected_since: peer.stats.connected_since,
+                connected_duration_secs: peer
+                    .stats
+                    .connected_since
+                    .map(|since| now.saturating_sub(since)),
+                num_channels: peer.num_channels as u64,
+                last_disconnected_at: peer.stats.last_disconnected_at,
+                last_reconnected_at: peer.stats.last_reconnected_at,
+                num_reconnects: peer.stats.num_reconnects,
+                announced_features: peer.announced_features,
+            })
+            .collect();
+
+        Ok(Response::new(ListPeersResponse { peers }))
+    }
+
+    async fn list_pending_operations(
+        &self,
+        _request: Request<ListPendingOperationsRequest>,
+    ) -> Result<Response<ListPendingOperationsResponse>, Status> {
+        let operations = self
+            .node
+            .approval_queue()
+            .map(|queue| queue.list())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|operation| PendingOperation {
+                id: operation.id,
+                rpc: operation.rpc,
+                amount_sat: operation.amount_sat,
+                description: operation.description,
+                created_at: operation.created_at,
+                expires_at: operation.expires_at,
+            })
+            .collect();
+
+        Ok(Response::new(ListPendingOperationsResponse { operations }))
+    }
+
+    async fn approve_operation(
+        &self,
+        request: Request<ApproveOperationRequest>,
+    ) -> Result<Response<ApproveOperationResponse>, Status> {
+        let token = approver_token_header(&request).unwrap_or_default();
+        let req = request.into_inner();
+
+        let queue = self
+            .node
+            .approval_queue()
+            .ok_or_else(|| Status::failed_precondition("Approvals are not enabled"))?;
+
+        let operation = queue.approve(&req.id, &token).map_err(approval_status)?;
+        let outcome = self
+            .execute_approved(&operation.rpc, &operation.request)
+            .await?;
+
+        Ok(Response::new(ApproveOperationResponse {
+            outcome: Some(outcome),
+        }))
+    }
+
+    async fn reject_operation(
+        &self,
+        request: Request<RejectOperationRequest>,
+    ) -> Result<Response<RejectOperationResponse>, Status> {
+        let token = approver_token_header(&request).unwrap_or_default();
+        let req = request.into_inner();
+
+        let queue = self
+            .node
+            .approval_queue()
+            .ok_or_else(|| Status::failed_precondition("Approvals are not enabled"))?;
+
+        queue.reject(&req.id, &token).map_err(approval_status)?;
+
+        Ok(Response::new(RejectOperationResponse {}))
+    }
+
+    async fn prune(
+        &self,
+        request: Request<PruneRequest>,
+    ) -> Result<Response<PruneResponse>, Status> {
+        let req = request.into_inner();
+
+        let payment_retention = req
+            .payment_retention_days
+            .map(prune::payment_retention)
+            .transpose()
+            .map_err(|e| validation::invalid_field("payment_retention_days", e))?;
+        let activity_retention = req
+            .activity_retention_days
+            .map(prune::activity_retention)
+            .transpose()
+            .map_err(|e| validation::invalid_field("activity_retention_days", e))?;
+
+        let configured = self.node.prune_policy();
+        let policy = PrunePolicy {
+            payment_retention: payment_retention
+                .or_else(|| configured.and_then(|policy| policy.payment_retention)),
+            activity_retention: activity_retention
+                .or_else(|| configured.and_then(|policy| policy.activity_retention)),
+            export_dir: configured
+                .map(|policy| policy.export_dir.clone())
+                .unwrap_or_else(|| self.node.storage_dir_path().to_path_buf()),
+            interval: DEFAULT_PRUNE_INTERVAL,
+        };
+
+        if policy.payment_retention.is_none() && policy.activity_retention.is_none() {
+            return Err(Status::invalid_argument(
+                "No retention given and none configured",
+            ));
+        }
+
+        let report = self
+            .node
+            .prune(&policy, req.dry_run)
+            .map_err(|e| Status::internal(format!("Prune failed: {e}")))?;
+
+        Ok(Response::new(PruneResponse {
+            payments_pruned: report.payments_pruned as u64,
+            activity_records_pruned: report.activity_records_pruned as u64,
+            export_path: report.export_path.map(|path| path.display().to_string()),
+            dry_run: report.dry_run,
+        }))
+    }
+
+    async fn rotate_tls(
+        &self,
+        _request: Request<RotateTlsRequest>,
+    ) -> Result<Response<RotateTlsResponse>, Status> {
+        let rotation = self
+            .node
+            .rotate_tls()
+            .map_err(|e| Status::failed_precondition(format!("Could not rotate TLS: {e:#}")))?;
+
+        Ok(Response::new(RotateTlsResponse {
+            management_reloaded: rotation.management,
+            payment_processor_reloaded: rotation.payment_processor,
+        }))
+    }
+
+    async fn get_summary(
+        &self,
+        request: Request<GetSummaryRequest>,
+    ) -> Result<Response<GetSummaryResponse>, Status> {
+        let req = request.into_inner();
+
+        let period = req
+            .period_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_SUMMARY_PERIOD);
+
+        let summary = self.node.summary(period);
+
+        Ok(Response::new(GetSummaryResponse {
+            period_start: summary.period_start,
+            period_end: summary.period_end,
+            payments_received: summary.payments_received,
+            received_msat: summary.received_msat,
+            payments_sent: summary.payments_sent,
+            sent_msat: summary.sent_msat,
+            payments_failed: summary.payments_failed,
+            failure_rate: summary.failure_rate(),
+            fees_paid_msat: summary.fees_paid_msat,
+            forwards: summary.forwards,
+            fees_earned_msat: summary.fees_earned_msat,
+            channels_opened: summary.channels_opened,
+            channels_closed: summary.channels_closed,
+            onchain_balance_sat: summary.onchain_balance_sat,
+            lightning_balance_sat: summary.lightning_balance_sat,
+        }))
+    }
+
+    async fn list_payments(
+        &self,
+        request: Request<ListPaymentsRequest>,
+    ) -> Result<Response<ListPaymentsResponse>, Status> {
+        let tenant = tenant_scope(&request);
+        let req = request.into_inner();
+
+        if let Some(direction) = &req.direction {
+            if !["inbound", "outbound"].contains(&direction.as_str()) {
+                return Err(Status::invalid_argument(format!(
+                    "Unknown direction {direction}, expected inbound or outbound"
+                )));
+            }
+        }
+        if let Some(status) = &req.status {
+            if !["pending", "succeeded", "failed"].contains(&status.as_str()) {
+                return Err(Status::invalid_argument(format!(
+                    "Unknown status {status}, expected pending, succeeded or failed"
+                )));
+            }
+        }
+
+        let filter = PaymentFilter {
+            created_from: req.created_from,
+            created_to: req.created_to,
+            settled_from: req.settled_from,
+            settled_to: req.settled_to,
+            min_amount_msat: req.min_amount_msat,
+            max_amount_msat: req.max_amount_msat,
+            direction: req.direction,
+            status: req.status,
+            limit: req.limit,
+            offset: req.offset,
+            payment_hashes: tenant.map(|tenant| self.node.tenant_tags().references(&tenant)),
+        };
+
+        let payments = self
+            .node
+            .list_payments(&filter)
+            .map_err(|e| Status::internal(format!("Could not list payments: {e:#}")))?
+            .into_iter()
+            .map(|payment| PaymentInfo {
+                payment_id: payment.id,
+                kind: payment.kind,
+                direction: payment.direction,
+                status: payment.status,
+                amount_msat: payment.amount_msat,
+                fee_paid_msat: payment.fee_paid_msat,
+                payment_hash: payment.payment_hash,
+                created_at: payment.created_at,
+                updated_at: payment.updated_at,
+                settled_at: payment.settled_at,
+            })
+            .collect();
+
+        Ok(Response::new(ListPaymentsResponse { payments }))
+    }
+
+    async fn export_ledger(
+        &self,
+        request: Request<ExportLedgerRequest>,
+    ) -> Result<Response<Self::ExportLedgerStream>, Status> {
+        let req = request.into_inner();
+        let index = self.node.payment_index().clone();
+        let batch_size = req
+            .batch_size
+            .unwrap_or(DEFAULT_LEDGER_BATCH_SIZE)
+            .clamp(1, MAX_LEDGER_BATCH_SIZE);
+
+        // The bounded channel is the backpressure: the next batch is only read
+        // from the index once the client has taken one from the buffer
+        let (tx, rx) = mpsc::channel(LEDGER_STREAM_BUFFER);
+
+        tokio::spawn(async move {
+            let mut cursor: Option<LedgerCursor> = None;
+            let mut exported = 0;
+
+            loop {
+                let index = index.clone();
+                let after = cursor.clone();
+                let batch = tokio::task::spawn_blocking(move || {
+                    index.ledger_batch(after.as_ref(), req.created_from, req.created_to, batch_size)
+                })
+                .await;
+
+                let payments = match batch {
+                    Ok(Ok(payments)) => payments,
+                    Ok(Err(err)) => {
+                        let _ = tx
+                            .send(Err(Status::internal(format!(
+                                "Could not read payment index: {err:#}"
+                            ))))
+                            .await;
+                        break;
+                    }
+                    Err(err) => {
+                        let _ = tx
+                            .send(Err(Status::internal(format!(
+                                "Ledger export failed: {err}"
+                            ))))
+                            .await;
+                        break;
+                    }
+                };
+
+                let mut csv = match cursor {
+                    None => LEDGER_HEADER.to_string(),
+                    Some(_) => String::new(),
+                };
+                csv.push_str(&csv_rows(&payments));
+
+                let rows = payments.len();
+                let complete = rows < batch_size as usize;
+                cursor = payments.last().map(LedgerCursor::from).or(cursor);
+
+                if tx
+                    .send(Ok(LedgerBatch {
+                        csv,
+                        rows: rows as u64,
+                    }))
+                    .await
+                    .is_err()
+                {
+                    tracing::debug!("Ledger export abandoned after {} payments", exported);
+                    break;
+                }
+                exported += rows;
+
+                if complete {
+                    tracing::debug!("Exported ledger of {} payments", exported);
+                    break;
+                }
+            }
+        });
+
+        let stream = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|item| (item, rx))
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn abandon_payment(
+        &self,
+        request: Request<AbandonPaymentRequest>,
+    ) -> Result<Response<AbandonPaymentResponse>, Status> {
+        let req = request.into_inner();
+
+        let payment_id = identifiers::parse_payment_id(&req.payment_id)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let abandoned = self
+            .node
+            .abandon_payment(&payment_id)
+            .map_err(|e| Status::failed_precondition(format!("{e:#}")))?;
+
+        Ok(Response::new(AbandonPaymentResponse {
+            payment_id: abandoned.payment_id,
+            amount_msat: abandoned.amount_msat,
+            abandoned_at: abandoned.abandoned_at,
+        }))
+    }
+
+    async fn settle_hold_invoice(
+        &self,
+        request: Request<SettleHoldInvoiceRequest>,
+    ) -> Result<Response<HoldInvoice>, Status> {
+        let req = request.into_inner();
+
+        let payment_hash = identifiers::parse_hex32(&req.payment_hash)
+            .map(PaymentHash)
+            .map_err(|e| invalid_field("payment_hash", e))?;
+        let preimage = req
+            .preimage
+            .as_deref()
+            .map(|preimage| {
+                identifiers::parse_hex32(preimage)
+                    .map(PaymentPreimage)
+                    .map_err(|e| invalid_field("preimage", e))
+            })
+            .transpose()?;
+
+        let invoice = self
+            .node
+            .settle_hold_invoice(payment_hash, preimage)
+            .map_err(|e| Status::failed_precondition(format!("{e:#}")))?;
+
+        Ok(Response::new(hold_invoice_response(invoice)))
+    }
+
+    async fn cancel_hold_invoice(
+        &self,
+        request: Request<CancelHoldInvoiceRequest>,
+    ) -> Result<Response<HoldInvoice>, Status> {
+        let req = request.into_inner();
+
+        let payment_hash = identifiers::parse_hex32(&req.payment_hash)
+            .map(PaymentHash)
+            .map_err(|e| invalid_field("payment_hash", e))?;
+
+        let invoice = self
+            .node
+            .cancel_hold_invoice(payment_hash)
+            .map_err(|e| Status::failed_precondition(format!("{e:#}")))?;
+
+        Ok(Response::new(hold_invoice_response(invoice)))
+    }
+
+    async fn list_hold_invoices(
+        &self,
+        _request: Request<ListHoldInvoicesRequest>,
+    ) -> Result<Response<ListHoldInvoicesResponse>, Status> {
+        let invoices = self
+            .node
+            .list_hold_invoices()
+            .into_iter()
+            .map(hold_invoice_response)
+            .collect();
+
+        Ok(Response::new(ListHoldInvoicesResponse { invoices }))
+    }
+
+    async fn diagnose_peer(
+        &self,
+        request: Request<DiagnosePeerRequest>,
+    ) -> Result<Response<DiagnosePeerResponse>, Status> {
+        let req = request.into_inner();
+
+        let node_id = validation::parse_public_key("node_id", &req.node_id)?;
+
+        let address = req
+            .address
+            .map(|address| validation::parse_socket_address("address", &address))
+            .transpose()?;
+
+        let diagnosis = crate::peers::diagnose_peer(&self.node.inner, node_id, address).await;
+
+        Ok(Response::new(DiagnosePeerResponse {
+            node_id: node_id.to_string(),
+            address: diagnosis.address.map(|a| a.to_string()),
+            gossip_addresses: diagnosis
+                .gossip_addresses
+                .iter()
+                .map(|a| a.to_string())
+                .collect(),
+            alias: diagnosis.alias,
+            announced_features: diagnosis.announced_features,
+            tcp_reachable: diagnosis.tcp_reachable,
+            tcp_latency_ms: diagnosis.tcp_latency.map(|l| l.as_millis() as u64),
+            handshake_succeeded: diagnosis.handshake_succeeded,
+            handshake_latency_ms: diagnosis.handshake_latency.map(|l| l.as_millis() as u64),
+            was_connected: diagnosis.was_connected,
+            error: diagnosis.error,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+
+    use super::*;
+
+    #[test]
+    fn amounts_up_to_the_limit_are_allowed() {
+        assert!(check_amount_limit("Payment", 5_000_000, None).is_ok());
+        assert!(check_amount_limit("Payment", 1_000_000, Some(1_000)).is_ok());
+        assert!(check_amount_limit("Payment", 999_999, Some(1_000)).is_ok());
+    }
+
+    #[test]
+    fn amounts_above_the_limit_are_refused() {
+        let status = check_amount_limit("Payment", 1_000_001, Some(1_000)).unwrap_err();
+
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert!(status.message().contains("1001 sats"));
+    }
+
+    #[test]
+    fn send_timeout_defaults_to_the_node_setting() {
+        let default = Duration::from_secs(10);
+
+        assert_eq!(send_timeout(None, default).unwrap(), default);
+        assert_eq!(
+            send_timeout(Some(30), default).unwrap(),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            send_timeout(Some(MAX_SEND_TIMEOUT_SECS), default).unwrap(),
+            Duration::from_secs(MAX_SEND_TIMEOUT_SECS)
+        );
+    }
+
+    #[test]
+    fn send_timeouts_out_of_range_are_rejected() {
+        let default = Duration::from_secs(10);
+
+        for timeout_secs in [0, MAX_SEND_TIMEOUT_SECS + 1] {
+            let status = send_timeout(Some(timeout_secs), default).unwrap_err();
+            assert_eq!(status.code(), Code::InvalidArgument);
+        }
+    }
 }
@@ -0,0 +1,77 @@
+//! Validation of management RPC inputs and mapping of LDK errors to gRPC codes
+//!
+//! Malformed fields are rejected with `invalid_argument` naming the field, so
+//! the CLI can tell a typo from a node failure. LDK Node errors are mapped to
+//! the code that matches their cause instead of `internal`.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use ldk_node::bitcoin::secp256k1::PublicKey;
+use ldk_node::bitcoin::{Address, Network};
+use ldk_node::lightning::ln::msgs::SocketAddress;
+use ldk_node::NodeError;
+use tonic::Status;
+
+use crate::onchain;
+
+/// `invalid_argument` status naming the field that was rejected
+pub fn invalid_field(field: &str, reason: impl Display) -> Status {
+    Status::invalid_argument(format!("Invalid {field}: {reason}"))
+}
+
+/// Parse a node id
+pub fn parse_public_key(field: &str, value: &str) -> Result<PublicKey, Status> {
+    PublicKey::from_str(value).map_err(|e| invalid_field(field, e))
+}
+
+/// Parse a `host:port` socket address
+pub fn parse_socket_address(field: &str, value: &str) -> Result<SocketAddress, Status> {
+    SocketAddress::from_str(value).map_err(|e| invalid_field(field, format!("{e:?}")))
+}
+
+/// Parse a bitcoin address, which must be for the node's network
+pub fn parse_address(field: &str, value: &str, network: Network) -> Result<Address, Status> {
+    onchain::parse_address(value, network).map_err(|e| invalid_field(field, format!("{e:#}")))
+}
+
+/// Status of an LDK Node error, prefixed with what was being done
+pub fn node_error(context: &str, err: NodeError) -> Status {
+    let message = format!("{context}: {err}");
+
+    match err {
+        NodeError::InvalidAddress
+        | NodeError::InvalidSocketAddress
+        | NodeError::InvalidPublicKey
+        | NodeError::InvalidSecretKey
+        | NodeError::InvalidOfferId
+        | NodeError::InvalidNodeId
+        | NodeError::InvalidPaymentId
+        | NodeError::InvalidPaymentHash
+        | NodeError::InvalidPaymentPreimage
+        | NodeError::InvalidPaymentSecret
+        | NodeError::InvalidAmount
+        | NodeError::InvalidInvoice
+        | NodeError::InvalidOffer
+        | NodeError::InvalidRefund
+        | NodeError::InvalidChannelId
+        | NodeError::InvalidNetwork
+        | NodeError::InvalidUri
+        | NodeError::InvalidQuantity
+        | NodeError::InvalidNodeAlias
+        | NodeError::InvalidFeeRate
+        | NodeError::InvalidCustomTlvs => Status::invalid_argument(message),
+        NodeError::DuplicatePayment => Status::already_exists(message),
+        NodeError::NotRunning
+        | NodeError::AlreadyRunning
+        | NodeError::InsufficientFunds
+        | NodeError::UnsupportedCurrency => Status::failed_precondition(message),
+        NodeError::ConnectionFailed
+        | NodeError::LiquiditySourceUnavailable
+        | NodeError::WalletOperationTimeout
+        | NodeError::TxSyncTimeout
+        | NodeError::GossipUpdateTimeout
+        | NodeError::FeerateEstimationUpdateTimeout => Status::unavailable(message),
+        _ => Status::internal(message),
+    }
+}
@@ -0,0 +1,272 @@
+//! Pruning of old payment records and activity history
+//!
+//! LDK Node keeps every payment record forever, and the activity log grows
+//! with every forward and channel change. Pruning removes payment records that
+//! reached a final state longer ago than the retention, after appending them
+//! to an export file, and drops old activity records.
+//!
+//! Pending payments are never pruned. A mint asking for the status of a
+//! pruned payment gets an unknown payment error, so the payment retention is
+//! at least [`MIN_PAYMENT_RETENTION_DAYS`], well above the mint's quote
+//! expiry.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use cdk_common::util::hex;
+use ldk_node::payment::{PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus};
+use ldk_node::Node;
+use serde::Serialize;
+
+/// File pruned payment records are appended to, inside the export directory
+pub const PRUNED_PAYMENTS_FILE_NAME: &str = "pruned_payments.jsonl";
+
+/// Default interval between scheduled prunes
+pub const DEFAULT_PRUNE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Shortest payment retention, in days
+pub const MIN_PAYMENT_RETENTION_DAYS: u64 = 7;
+
+/// Payment retention of `days`, rejecting retentions below the minimum
+pub fn payment_retention(days: u64) -> Result<Duration> {
+    if days < MIN_PAYMENT_RETENTION_DAYS {
+        bail!(
+            "Payment retention must be at least {MIN_PAYMENT_RETENTION_DAYS} days so the mint can check its quotes, got {days}"
+        );
+    }
+
+    Ok(days_to_duration(days))
+}
+
+/// Activity retention of `days`, which must be at least a day
+pub fn activity_retention(days: u64) -> Result<Duration> {
+    if days == 0 {
+        bail!("Activity retention must be at least 1 day");
+    }
+
+    Ok(days_to_duration(days))
+}
+
+fn days_to_duration(days: u64) -> Duration {
+    Duration::from_secs(days.saturating_mul(24 * 60 * 60))
+}
+
+/// Unix time before which records are pruned
+pub fn cutoff(now: u64, retention: Duration) -> u64 {
+    now.saturating_sub(retention.as_secs())
+}
+
+/// Whether a payment record is pruned with the given cutoff
+///
+/// Only payments that reached a final state before the cutoff are pruned.
+pub fn is_prunable(payment: &PaymentDetails, cutoff: u64) -> bool {
+    payment.status != PaymentStatus::Pending && payment.latest_update_timestamp < cutoff
+}
+
+/// Retention policy for payment records and activity history
+#[derive(Debug, Clone)]
+pub struct PrunePolicy {
+    /// Prune final payment records older than this, `None` keeps them
+    pub payment_retention: Option<Duration>,
+    /// Prune activity records older than this, `None` keeps them
+    pub activity_retention: Option<Duration>,
+    /// Directory pruned payment records are exported to
+    pub export_dir: PathBuf,
+    /// Interval between scheduled prunes
+    pub interval: Duration,
+}
+
+/// Result of a prune
+#[derive(Debug, Clone, Default)]
+pub struct PruneReport {
+    /// Payment records removed, or that would be removed in a dry run
+    pub payments_pruned: usize,
+    /// Activity records removed, or that would be removed in a dry run
+    pub activity_records_pruned: usize,
+    /// File the pruned payment records were appended to
+    pub export_path: Option<PathBuf>,
+    pub dry_run: bool,
+}
+
+/// Payment record as written to the export file
+#[derive(Debug, Serialize)]
+struct ExportedPayment {
+    id: String,
+    kind: &'static str,
+    direction: &'static str,
+    status: &'static str,
+    amount_msat: Option<u64>,
+    fee_paid_msat: Option<u64>,
+    payment_hash: Option<String>,
+    preimage: Option<String>,
+    txid: Option<String>,
+    latest_update_timestamp: u64,
+}
+
+impl From<&PaymentDetails> for ExportedPayment {
+    fn from(payment: &PaymentDetails) -> Self {
+        let (kind, hash, preimage, txid) = match &payment.kind {
+            PaymentKind::Onchain { txid, .. } => ("onchain", None, None, Some(txid.to_string())),
+            PaymentKind::Bolt11 { hash, preimage, .. } => ("bolt11", Some(hash), *preimage, None),
+            PaymentKind::Bolt11Jit { hash, preimage, .. } => {
+                ("bolt11_jit", Some(hash), *preimage, None)
+            }
+            PaymentKind::Bolt12Offer { hash, preimage, .. } => {
+                ("bolt12_offer", hash.as_ref(), *preimage, None)
+            }
+            PaymentKind::Bolt12Refund { hash, preimage, .. } => {
+                ("bolt12_refund", hash.as_ref(), *preimage, None)
+            }
+            PaymentKind::Spontaneous { hash, preimage } => {
+                ("spontaneous", Some(hash), *preimage, None)
+            }
+        };
+
+        Self {
+            id: hex::encode(payment.id.0),
+            kind,
+            direction: match payment.direction {
+                PaymentDirection::Inbound => "inbound",
+                PaymentDirection::Outbound => "outbound",
+            },
+            status: match payment.status {
+                PaymentStatus::Pending => "pending",
+                PaymentStatus::Succeeded => "succeeded",
+                PaymentStatus::Failed => "failed",
+            },
+            amount_msat: payment.amount_msat,
+            fee_paid_msat: payment.fee_paid_msat,
+            payment_hash: hash.map(|hash| hash.to_string()),
+            preimage: preimage.map(|preimage| preimage.to_string()),
+            txid,
+            latest_update_timestamp: payment.latest_update_timestamp,
+        }
+    }
+}
+
+/// Export and remove payment records that reached a final state before `cutoff`
+///
+/// Records are appended to the export file before any is removed, so a
+/// failed export removes nothing. Preimages are left out of the export when
+/// `redact_preimages` is set. Returns the number of pruned records.
+pub fn prune_payments(
+    node: &Node,
+    cutoff: u64,
+    export_dir: &Path,
+    redact_preimages: bool,
+    dry_run: bool,
+) -> Result<usize> {
+    let payments = node.list_payments_with_filter(|payment| is_prunable(payment, cutoff));
+
+    if payments.is_empty() || dry_run {
+        return Ok(payments.len());
+    }
+
+    std::fs::create_dir_all(export_dir)
+        .with_context(|| format!("Could not create export directory {}", export_dir.display()))?;
+
+    let export_path = export_dir.join(PRUNED_PAYMENTS_FILE_NAME);
+    let mut contents = String::new();
+    for payment in &payments {
+        let mut exported = ExportedPayment::from(payment);
+        if redact_preimages {
+            exported.preimage = None;
+        }
+        contents.push_str(&serde_json::to_string(&exported)?);
+        contents.push('\n');
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&export_path)
+        .with_context(|| format!("Could not open {}", export_path.display()))?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+
+    let mut pruned = 0;
+    for payment in &payments {
+        match node.remove_payment(&payment.id) {
+            Ok(()) => pruned += 1,
+            Err(err) => tracing::warn!(
+                "Could not remove payment {}: {}",
+                hex::encode(payment.id.0),
+                err
+            ),
+        }
+    }
+
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use ldk_node::lightning::ln::channelmanager::PaymentId;
+    use ldk_node::lightning_types::payment::PaymentHash;
+
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn payment(status: PaymentStatus, latest_update_timestamp: u64) -> PaymentDetails {
+        PaymentDetails {
+            id: PaymentId([1; 32]),
+            kind: PaymentKind::Bolt11 {
+                hash: PaymentHash([1; 32]),
+                preimage: None,
+                secret: None,
+            },
+            amount_msat: Some(1_000),
+            fee_paid_msat: None,
+            direction: PaymentDirection::Outbound,
+            status,
+            latest_update_timestamp,
+        }
+    }
+
+    #[test]
+    fn payment_retention_has_a_minimum() {
+        assert!(payment_retention(0).is_err());
+        assert!(payment_retention(MIN_PAYMENT_RETENTION_DAYS - 1).is_err());
+        assert_eq!(
+            payment_retention(MIN_PAYMENT_RETENTION_DAYS).unwrap(),
+            Duration::from_secs(MIN_PAYMENT_RETENTION_DAYS * DAY)
+        );
+        assert!(payment_retention(u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn activity_retention_is_at_least_a_day() {
+        assert!(activity_retention(0).is_err());
+        assert_eq!(activity_retention(1).unwrap(), Duration::from_secs(DAY));
+    }
+
+    #[test]
+    fn cutoff_is_the_retention_before_now() {
+        assert_eq!(cutoff(NOW, Duration::from_secs(DAY)), NOW - DAY);
+        assert_eq!(cutoff(NOW, payment_retention(u64::MAX).unwrap()), 0);
+    }
+
+    #[test]
+    fn only_final_payments_before_the_cutoff_are_pruned() {
+        let cutoff = cutoff(NOW, Duration::from_secs(7 * DAY));
+
+        assert!(is_prunable(
+            &payment(PaymentStatus::Succeeded, cutoff - 1),
+            cutoff
+        ));
+        assert!(is_prunable(
+            &payment(PaymentStatus::Failed, cutoff - 1),
+            cutoff
+        ));
+        assert!(!is_prunable(
+            &payment(PaymentStatus::Succeeded, cutoff),
+            cutoff
+        ));
+        assert!(!is_prunable(&payment(PaymentStatus::Pending, 0), cutoff));
+    }
+}
@@ -0,0 +1,106 @@
+//! Short lived cache of outgoing payment quotes
+//!
+//! Mints under load quote the same request repeatedly. Quotes are cached per
+//! payment identifier, amount and unit for a short TTL. Every LDK Node event
+//! can move channel balances, so any event invalidates all cached quotes.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use cdk_common::payment::PaymentQuoteResponse;
+
+/// Default time a quote is served from the cache
+pub const DEFAULT_QUOTE_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Maximum number of cached quotes, expired entries are dropped beyond it
+const MAX_CACHED_QUOTES: usize = 10_000;
+
+/// Inputs a quote is computed from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QuoteKey {
+    /// Payment hash or offer id
+    pub identifier: String,
+    /// Amount requested by the melt options, `None` for the request amount
+    pub amount_msat: Option<u64>,
+    /// Unit the quote is expressed in
+    pub unit: String,
+}
+
+#[derive(Debug)]
+struct CachedQuote {
+    quote: PaymentQuoteResponse,
+    created: Instant,
+    generation: u64,
+}
+
+/// Cache of payment quotes invalidated by node events
+#[derive(Debug)]
+pub struct QuoteCache {
+    ttl: Duration,
+    generation: AtomicU64,
+    quotes: Mutex<HashMap<QuoteKey, CachedQuote>>,
+}
+
+impl QuoteCache {
+    /// Create a cache, a zero TTL disables caching
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            generation: AtomicU64::new(0),
+            quotes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Cached quote for the key, if still fresh
+    pub fn get(&self, key: &QuoteKey) -> Option<PaymentQuoteResponse> {
+        if self.ttl.is_zero() {
+            return None;
+        }
+
+        let generation = self.generation.load(Ordering::Acquire);
+        let quotes = self.quotes.lock().expect("quote cache lock poisoned");
+
+        quotes
+            .get(key)
+            .filter(|cached| cached.generation == generation && cached.created.elapsed() < self.ttl)
+            .map(|cached| cached.quote.clone())
+    }
+
+    /// Cache a freshly computed quote
+    pub fn insert(&self, key: QuoteKey, quote: PaymentQuoteResponse) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        let generation = self.generation.load(Ordering::Acquire);
+        let mut quotes = self.quotes.lock().expect("quote cache lock poisoned");
+
+        if quotes.len() >= MAX_CACHED_QUOTES {
+            quotes.retain(|_, cached| {
+                cached.generation == generation && cached.created.elapsed() < self.ttl
+            });
+        }
+
+        if quotes.len() < MAX_CACHED_QUOTES {
+            quotes.insert(
+                key,
+                CachedQuote {
+                    quote,
+                    created: Instant::now(),
+                    generation,
+                },
+            );
+        }
+    }
+
+    /// Invalidate every cached quote
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.quotes
+            .lock()
+            .expect("quote cache lock poisoned")
+            .clear();
+    }
+}
@@ -0,0 +1,213 @@
+//! Retries of failed melt payments
+//!
+//! LDK retries a payment over other paths until its own retry budget runs
+//! out, after which the melt would be reported failed. The retry policy sends
+//! the payment again, with backoff between attempts, when it failed for a
+//! reason another attempt may overcome: no route was found or LDK ran out of
+//! retries. Retries may use looser route constraints, such as more paths or
+//! less of each channel's capacity per path.
+//!
+//! Melts are retried while their invoice or offer has not expired. LDK Node
+//! allows paying a payment hash again once the earlier attempt failed, and
+//! every payment to an offer gets a new payment id. The sending parameters
+//! only apply to BOLT11 melts, LDK Node 0.5 takes none for offers.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use ldk_node::lightning::events::PaymentFailureReason;
+use ldk_node::lightning::ln::channelmanager::PaymentId;
+use ldk_node::payment::SendingParameters;
+
+/// Default delay before the first retry, doubled on every further retry
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Default longest delay between retries
+pub const DEFAULT_MAX_RETRY_BACKOFF: Duration = Duration::from_secs(10);
+
+/// How long a failed payment waits for its failure reason from the event handler
+pub const FAILURE_REASON_WAIT: Duration = Duration::from_secs(2);
+
+/// Failure reasons kept for payments nobody asked about yet
+const MAX_FAILURE_REASONS: usize = 256;
+
+/// When and how failed melt payments are sent again
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts per melt including the first, 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub backoff: Duration,
+    /// Longest delay between retries
+    pub max_backoff: Duration,
+    /// Max number of paths retries may be split across
+    pub max_path_count: Option<u8>,
+    /// Max share of a channel's capacity used by a path of a retry, as a power of 1/2
+    pub max_channel_saturation_power_of_half: Option<u8>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff: DEFAULT_RETRY_BACKOFF,
+            max_backoff: DEFAULT_MAX_RETRY_BACKOFF,
+            max_path_count: None,
+            max_channel_saturation_power_of_half: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether another attempt may succeed where one failed for `reason`
+    pub fn is_retryable(reason: Option<PaymentFailureReason>) -> bool {
+        matches!(
+            reason,
+            Some(PaymentFailureReason::RouteNotFound | PaymentFailureReason::RetriesExhausted)
+        )
+    }
+
+    /// Delay before retrying after `attempt` failed for `reason`, `None` if
+    /// the payment should not be retried
+    pub fn retry_after(
+        &self,
+        attempt: u32,
+        reason: Option<PaymentFailureReason>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts || !Self::is_retryable(reason) {
+            return None;
+        }
+
+        let backoff = self
+            .backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        Some(backoff.min(self.max_backoff))
+    }
+
+    /// Sending parameters of an attempt, retries use the policy's route constraints
+    pub fn sending_parameters(&self, attempt: u32, params: SendingParameters) -> SendingParameters {
+        if attempt <= 1 {
+            return params;
+        }
+
+        SendingParameters {
+            max_path_count: self.max_path_count.or(params.max_path_count),
+            max_channel_saturation_power_of_half: self
+                .max_channel_saturation_power_of_half
+                .or(params.max_channel_saturation_power_of_half),
+            ..params
+        }
+    }
+}
+
+/// Failure reasons of outgoing payments, recorded by the event handler
+#[derive(Debug, Default)]
+pub struct FailureReasons {
+    reasons: Mutex<VecDeque<(PaymentId, Option<PaymentFailureReason>)>>,
+}
+
+impl FailureReasons {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record why a payment failed
+    pub fn record(&self, payment_id: PaymentId, reason: Option<PaymentFailureReason>) {
+        let mut reasons = self.reasons.lock().expect("failure reasons lock poisoned");
+        reasons.retain(|(id, _)| *id != payment_id);
+        reasons.push_back((payment_id, reason));
+        while reasons.len() > MAX_FAILURE_REASONS {
+            reasons.pop_front();
+        }
+    }
+
+    /// Take the failure reason of a payment, waiting up to `timeout` for the
+    /// event handler to record it
+    pub async fn take(
+        &self,
+        payment_id: &PaymentId,
+        timeout: Duration,
+    ) -> Option<PaymentFailureReason> {
+        let start = std::time::Instant::now();
+
+        loop {
+            {
+                let mut reasons = self.reasons.lock().expect("failure reasons lock poisoned");
+                if let Some(position) = reasons.iter().position(|(id, _)| id == payment_id) {
+                    return reasons.remove(position).and_then(|(_, reason)| reason);
+                }
+            }
+
+            if start.elapsed() > timeout {
+                return None;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 4,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(3),
+            max_path_count: Some(8),
+            max_channel_saturation_power_of_half: Some(1),
+        }
+    }
+
+    #[test]
+    fn retries_back_off_up_to_the_limit() {
+        let policy = policy();
+        let reason = Some(PaymentFailureReason::RouteNotFound);
+
+        assert_eq!(policy.retry_after(1, reason), Some(Duration::from_secs(1)));
+        assert_eq!(policy.retry_after(2, reason), Some(Duration::from_secs(2)));
+        assert_eq!(policy.retry_after(3, reason), Some(Duration::from_secs(3)));
+        assert_eq!(policy.retry_after(4, reason), None);
+    }
+
+    #[test]
+    fn only_routing_failures_are_retried() {
+        let policy = policy();
+
+        assert!(policy
+            .retry_after(1, Some(PaymentFailureReason::RetriesExhausted))
+            .is_some());
+        assert!(policy
+            .retry_after(1, Some(PaymentFailureReason::RecipientRejected))
+            .is_none());
+        assert!(policy.retry_after(1, None).is_none());
+    }
+
+    #[test]
+    fn retries_use_the_policy_route_constraints() {
+        let policy = policy();
+        let params = SendingParameters {
+            max_total_routing_fee_msat: Some(Some(500)),
+            max_total_cltv_expiry_delta: Some(1_008),
+            max_path_count: Some(1),
+            max_channel_saturation_power_of_half: Some(2),
+        };
+
+        let first = policy.sending_parameters(1, params);
+        assert_eq!(first.max_path_count, Some(1));
+        assert_eq!(first.max_channel_saturation_power_of_half, Some(2));
+
+        let retry = policy.sending_parameters(2, params);
+        assert_eq!(retry.max_path_count, Some(8));
+        assert_eq!(retry.max_channel_saturation_power_of_half, Some(1));
+        assert_eq!(retry.max_total_routing_fee_msat, Some(Some(500)));
+        assert_eq!(retry.max_total_cltv_expiry_delta, Some(1_008));
+
+        // Unset policy constraints keep the payment's own
+        let retry = RetryPolicy::default().sending_parameters(2, params);
+        assert_eq!(retry.max_path_count, Some(1));
+        assert_eq!(retry.max_channel_saturation_power_of_half, Some(2));
+    }
+}
@@ -0,0 +1,71 @@
+//! Route health monitoring
+//!
+//! Periodically probes configured destinations, such as LSPs or frequent
+//! melt targets, so degraded payment corridors show up before melts fail.
+//!
+//! LDK Node does not report probe outcomes, only whether probes could be
+//! routed and dispatched. A probe counts as successful when a route was found
+//! and the probe HTLCs were sent; LDK's scorer learns from the actual outcome
+//! and later probes fail to find a route once the corridor is exhausted.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ldk_node::bitcoin::secp256k1::PublicKey;
+use ldk_node::Node;
+
+/// Default amount probed towards each destination
+pub const DEFAULT_PROBE_AMOUNT_MSAT: u64 = 100_000_000;
+
+/// Default interval between probe rounds
+pub const DEFAULT_PROBE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Default number of consecutive failures before a corridor is reported degraded
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+/// Route health monitor settings
+#[derive(Debug, Clone)]
+pub struct RouteHealthConfig {
+    /// Destinations to probe
+    pub destinations: Vec<PublicKey>,
+    /// Amount probed towards each destination
+    pub amount_msat: u64,
+    /// Interval between probe rounds
+    pub interval: Duration,
+    /// Consecutive failures before a destination is reported degraded
+    pub failure_threshold: u32,
+}
+
+/// Outcome of probing a destination
+#[derive(Debug, Clone)]
+pub struct ProbeResult {
+    /// Probed destination
+    pub destination: PublicKey,
+    /// Time taken to route and dispatch the probe
+    pub latency: Duration,
+    /// Error if no route was found or the probe could not be sent
+    pub error: Option<String>,
+}
+
+/// Probe a destination with the given amount
+///
+/// Must not be called from an async context, routing blocks.
+pub fn probe_destination(
+    node: &Arc<Node>,
+    destination: PublicKey,
+    amount_msat: u64,
+) -> ProbeResult {
+    let started = Instant::now();
+
+    let error = node
+        .spontaneous_payment()
+        .send_probes(amount_msat, destination)
+        .err()
+        .map(|err| err.to_string());
+
+    ProbeResult {
+        destination,
+        latency: started.elapsed(),
+        error,
+    }
+}
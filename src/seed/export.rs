@@ -0,0 +1,169 @@
+//! Authorization and audit of seed exports
+//!
+//! Exporting the seed needs a dedicated export token on top of the management
+//! client certificate, so a leaked management client cannot take the node's
+//! funds on its own. Every export attempt is appended to an audit file in the
+//! storage directory with its time, remote address, client certificate and
+//! outcome. An export is only returned once its audit record is on disk.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use cdk_common::util::unix_time;
+use ldk_node::bitcoin::hashes::{sha256, Hash};
+use serde::{Deserialize, Serialize};
+
+/// File seed export attempts are appended to, inside the storage directory
+pub const SEED_EXPORT_AUDIT_FILE_NAME: &str = "seed_exports.jsonl";
+
+/// Outcome of a seed export attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedExportOutcome {
+    Exported,
+    /// Export is disabled in the config
+    Disabled,
+    /// The export token was missing or wrong
+    Unauthorized,
+}
+
+/// Audit record of a seed export attempt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeedExportRecord {
+    pub timestamp: u64,
+    /// Address the request came from
+    pub remote_addr: Option<String>,
+    /// SHA256 fingerprint of the client certificate
+    pub client_certificate: Option<String>,
+    pub outcome: SeedExportOutcome,
+}
+
+impl SeedExportRecord {
+    pub fn new(
+        remote_addr: Option<String>,
+        client_certificate: Option<&[u8]>,
+        outcome: SeedExportOutcome,
+    ) -> Self {
+        Self {
+            timestamp: unix_time(),
+            remote_addr,
+            client_certificate: client_certificate.map(|der| sha256::Hash::hash(der).to_string()),
+            outcome,
+        }
+    }
+}
+
+/// Append-only audit file of seed export attempts
+#[derive(Debug, Clone)]
+pub struct SeedExportAudit {
+    path: PathBuf,
+}
+
+impl SeedExportAudit {
+    /// Audit file in the storage directory
+    pub fn new(storage_dir: &Path) -> Self {
+        Self {
+            path: storage_dir.join(SEED_EXPORT_AUDIT_FILE_NAME),
+        }
+    }
+
+    /// Append a record and sync it to disk
+    pub fn record(&self, record: &SeedExportRecord) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Could not open {}", self.path.display()))?;
+        file.write_all(line.as_bytes())?;
+        file.sync_all()
+            .with_context(|| format!("Could not sync {}", self.path.display()))
+    }
+
+    /// Records in the order they were written
+    pub fn records(&self) -> Result<Vec<SeedExportRecord>> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Could not read {}", self.path.display()))
+            }
+        };
+
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(Into::into))
+            .collect()
+    }
+}
+
+/// Token authorizing seed exports
+#[derive(Clone)]
+pub struct SeedExportToken {
+    hash: sha256::Hash,
+}
+
+impl std::fmt::Debug for SeedExportToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SeedExportToken").finish_non_exhaustive()
+    }
+}
+
+impl SeedExportToken {
+    pub fn new(token: &str) -> Self {
+        Self {
+            hash: sha256::Hash::hash(token.as_bytes()),
+        }
+    }
+
+    /// Check a token given with an export request
+    pub fn verify(&self, token: &str) -> bool {
+        // Compare hashes so the comparison time does not reveal the token
+        sha256::Hash::hash(token.as_bytes()) == self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    #[test]
+    fn only_the_export_token_is_accepted() {
+        let token = SeedExportToken::new("secret");
+
+        assert!(token.verify("secret"));
+        assert!(!token.verify("other"));
+        assert!(!token.verify(""));
+    }
+
+    #[test]
+    fn records_are_appended() {
+        let audit = SeedExportAudit::new(&temp_dir("seed-export-audit"));
+        assert!(audit.records().unwrap().is_empty());
+
+        let refused = SeedExportRecord::new(
+            Some("127.0.0.1:5000".to_string()),
+            None,
+            SeedExportOutcome::Unauthorized,
+        );
+        let exported = SeedExportRecord::new(
+            Some("127.0.0.1:5001".to_string()),
+            Some(b"certificate"),
+            SeedExportOutcome::Exported,
+        );
+        audit.record(&refused).unwrap();
+        audit.record(&exported).unwrap();
+
+        assert_eq!(audit.records().unwrap(), vec![refused, exported.clone()]);
+        assert_eq!(
+            exported.client_certificate,
+            Some(sha256::Hash::hash(b"certificate").to_string())
+        );
+    }
+}
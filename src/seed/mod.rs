@@ -0,0 +1,173 @@
+//! Node seed management
+//!
+//! New nodes are created from a BIP39 mnemonic stored in the storage
+//! directory so the seed can be exported and imported on another machine.
+//! Nodes created before mnemonic support keep using the raw seed file LDK
+//! Node generated for them.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use bip39::Mnemonic;
+
+pub mod export;
+
+/// File the BIP39 mnemonic is stored in, inside the storage directory
+pub const MNEMONIC_FILE_NAME: &str = "seed_mnemonic";
+
+/// File LDK Node stores a raw seed in, inside the storage directory
+pub const SEED_FILE_NAME: &str = "keys_seed";
+
+/// Seed the node keys are derived from
+#[derive(Clone)]
+pub enum NodeSeed {
+    /// BIP39 mnemonic with an optional passphrase
+    Mnemonic {
+        mnemonic: Mnemonic,
+        passphrase: Option<String>,
+    },
+    /// Raw seed file created by LDK Node
+    SeedFile(PathBuf),
+}
+
+impl std::fmt::Debug for NodeSeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeSeed::Mnemonic { passphrase, .. } => f
+                .debug_struct("Mnemonic")
+                .field("has_passphrase", &passphrase.is_some())
+                .finish_non_exhaustive(),
+            NodeSeed::SeedFile(path) => f.debug_tuple("SeedFile").field(path).finish(),
+        }
+    }
+}
+
+impl NodeSeed {
+    /// Load the node seed from the storage directory, importing or creating one if needed
+    ///
+    /// `import` is a mnemonic to create the node from. It is only accepted for
+    /// a storage directory without a seed, or one holding the same mnemonic,
+    /// so an existing node is never silently replaced.
+    pub fn load_or_create(
+        storage_dir: &Path,
+        import: Option<Mnemonic>,
+        passphrase: Option<String>,
+    ) -> Result<Self> {
+        let mnemonic_path = storage_dir.join(MNEMONIC_FILE_NAME);
+        let seed_path = storage_dir.join(SEED_FILE_NAME);
+
+        let stored = match mnemonic_path.is_file() {
+            true => Some(read_mnemonic(&mnemonic_path)?),
+            false => None,
+        };
+
+        let mnemonic = match (stored, import) {
+            (Some(stored), Some(import)) if stored != import => bail!(
+                "Storage directory {} already holds a different mnemonic, refusing to import",
+                storage_dir.display()
+            ),
+            (Some(stored), _) => stored,
+            (None, Some(_)) if seed_path.is_file() => bail!(
+                "Storage directory {} already holds a node seed, import into an empty storage directory",
+                storage_dir.display()
+            ),
+            (None, Some(import)) => {
+                write_mnemonic(&mnemonic_path, &import)?;
+                tracing::info!("Imported node seed from mnemonic");
+                import
+            }
+            (None, None) if seed_path.is_file() => {
+                if passphrase.is_some() {
+                    tracing::warn!("Seed passphrase is ignored for nodes using a raw seed file");
+                }
+                return Ok(NodeSeed::SeedFile(seed_path));
+            }
+            (None, None) => {
+                let mnemonic = Mnemonic::generate(24)?;
+                write_mnemonic(&mnemonic_path, &mnemonic)?;
+                tracing::info!("Generated new node seed mnemonic");
+                mnemonic
+            }
+        };
+
+        Ok(NodeSeed::Mnemonic {
+            mnemonic,
+            passphrase,
+        })
+    }
+
+    /// Raw seed bytes the node and wallet keys are derived from
+    pub fn seed_bytes(&self) -> Result<[u8; SEED_LEN]> {
+        match self {
+            NodeSeed::Mnemonic {
+                mnemonic,
+                passphrase,
+            } => Ok(mnemonic.to_seed(passphrase.as_deref().unwrap_or(""))),
+            NodeSeed::SeedFile(path) => {
+                let bytes = fs::read(path)
+                    .with_context(|| format!("Could not read seed file {}", path.display()))?;
+                bytes.try_into().map_err(|bytes: Vec<u8>| {
+                    anyhow!(
+                        "Seed file {} holds {} bytes, expected {}",
+                        path.display(),
+                        bytes.len(),
+                        SEED_LEN
+                    )
+                })
+            }
+        }
+    }
+
+    /// Apply the seed to an LDK Node builder
+    pub fn apply(&self, builder: &mut ldk_node::Builder) {
+        match self {
+            NodeSeed::Mnemonic {
+                mnemonic,
+                passphrase,
+            } => {
+                builder.set_entropy_bip39_mnemonic(mnemonic.clone(), passphrase.clone());
+            }
+            NodeSeed::SeedFile(path) => {
+                builder.set_entropy_seed_path(path.to_string_lossy().to_string());
+            }
+        }
+    }
+}
+
+/// Length of the raw seed LDK Node derives its keys from
+pub const SEED_LEN: usize = 64;
+
+/// Parse a mnemonic given as words or read from a file
+pub fn parse_mnemonic(words: &str) -> Result<Mnemonic> {
+    Mnemonic::parse(words.trim()).context("Invalid BIP39 mnemonic")
+}
+
+fn read_mnemonic(path: &Path) -> Result<Mnemonic> {
+    let words = fs::read_to_string(path)
+        .with_context(|| format!("Could not read mnemonic from {}", path.display()))?;
+    parse_mnemonic(&words)
+}
+
+fn write_mnemonic(path: &Path, mnemonic: &Mnemonic) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Could not write mnemonic to {}", path.display()))?;
+    writeln!(file, "{mnemonic}")?;
+
+    Ok(())
+}
@@ -0,0 +1,375 @@
+//! Storage directory integrity checks and repair
+//!
+//! Checks a stopped node's storage directory: the seed is present and
+//! readable, the LDK Node SQLite database passes an integrity check, the
+//! channel manager and monitors load, and the node's own state files are
+//! readable. Files partially written during a crash can be moved into a
+//! quarantine directory, keeping the readable records of line based stores.
+//!
+//! The LDK Node database and the seed are never modified, issues with them are
+//! only reported.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use cdk_common::util::unix_time;
+use ldk_node::bitcoin::Network;
+
+use crate::backup;
+use crate::seed::{self, MNEMONIC_FILE_NAME, SEED_FILE_NAME, SEED_LEN};
+
+/// SQLite database LDK Node keeps its state in, inside the storage directory
+pub const SQLITE_FILE_NAME: &str = "ldk_node_data.sqlite";
+
+/// Directory unreadable files are moved to, inside the storage directory
+pub const QUARANTINE_DIR_NAME: &str = "quarantine";
+
+/// Line based stores of the node, one JSON record per line
+const JSONL_FILE_NAMES: &[&str] = &[
+    "channel_activity.jsonl",
+    "labels.jsonl",
+    "idempotency_keys.jsonl",
+    "deposits.jsonl",
+    "offers.jsonl",
+    "summaries.jsonl",
+    "abandoned_payments.jsonl",
+    "hold_invoices.jsonl",
+    "tenant_tags.jsonl",
+    "payment_notifications.jsonl",
+];
+
+/// JSON state files of the node
+const JSON_FILE_NAMES: &[&str] = &["last_address.json"];
+
+/// Severity of a storage issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The node works but some state is lost or ignored
+    Warning,
+    /// The node will not start or will lose funds related state
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Repair that can be applied for an issue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Repair {
+    /// Move the file into quarantine
+    Quarantine,
+    /// Move the file into quarantine and write back only its readable lines
+    KeepReadableLines,
+}
+
+/// Problem found in the storage directory
+#[derive(Debug, Clone)]
+pub struct StoreIssue {
+    pub severity: Severity,
+    /// File or directory the issue is about
+    pub path: PathBuf,
+    pub problem: String,
+    /// Repair applied by [`repair`], `None` if it needs an operator
+    pub repair: Option<Repair>,
+}
+
+/// Result of checking a storage directory
+#[derive(Debug, Clone, Default)]
+pub struct StoreReport {
+    pub storage_dir: PathBuf,
+    pub issues: Vec<StoreIssue>,
+    /// Number of channels loaded from the channel manager, if it loaded
+    pub num_channels: Option<usize>,
+}
+
+impl StoreReport {
+    /// Whether an issue prevents the node from starting safely
+    pub fn has_errors(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.severity == Severity::Error)
+    }
+
+    fn issue(
+        &mut self,
+        severity: Severity,
+        path: PathBuf,
+        problem: String,
+        repair: Option<Repair>,
+    ) {
+        self.issues.push(StoreIssue {
+            severity,
+            path,
+            problem,
+            repair,
+        });
+    }
+}
+
+/// Check a storage directory
+///
+/// The node must not be running. Must not be called from an async context,
+/// loading the channel state blocks.
+pub fn check_store(
+    storage_dir: &Path,
+    network: Network,
+    passphrase: Option<String>,
+) -> StoreReport {
+    let mut report = StoreReport {
+        storage_dir: storage_dir.to_path_buf(),
+        ..Default::default()
+    };
+
+    if !storage_dir.is_dir() {
+        report.issue(
+            Severity::Warning,
+            storage_dir.to_path_buf(),
+            "Storage directory does not exist, a new node will be created".to_string(),
+            None,
+        );
+        return report;
+    }
+
+    let seed_ok = check_seed(storage_dir, &mut report);
+    let sqlite_ok = check_sqlite(storage_dir, &mut report);
+
+    if seed_ok && sqlite_ok {
+        match backup::load_storage(storage_dir, network, passphrase) {
+            Ok((_, num_channels)) => report.num_channels = Some(num_channels),
+            Err(err) => report.issue(
+                Severity::Error,
+                storage_dir.join(SQLITE_FILE_NAME),
+                format!("Channel manager or monitors could not be loaded: {err:#}"),
+                None,
+            ),
+        }
+    }
+
+    for name in JSONL_FILE_NAMES {
+        check_jsonl(&storage_dir.join(name), &mut report);
+    }
+
+    for name in JSON_FILE_NAMES {
+        check_json(&storage_dir.join(name), &mut report);
+    }
+
+    check_temporary_files(storage_dir, &mut report);
+
+    report
+}
+
+fn check_seed(storage_dir: &Path, report: &mut StoreReport) -> bool {
+    let mnemonic_path = storage_dir.join(MNEMONIC_FILE_NAME);
+    let seed_path = storage_dir.join(SEED_FILE_NAME);
+
+    if mnemonic_path.is_file() {
+        return match fs::read_to_string(&mnemonic_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|words| seed::parse_mnemonic(&words))
+        {
+            Ok(_) => true,
+            Err(err) => {
+                report.issue(
+                    Severity::Error,
+                    mnemonic_path,
+                    format!("Seed mnemonic is unreadable: {err:#}"),
+                    None,
+                );
+                false
+            }
+        };
+    }
+
+    if seed_path.is_file() {
+        return match fs::metadata(&seed_path) {
+            Ok(metadata) if metadata.len() == SEED_LEN as u64 => true,
+            Ok(metadata) => {
+                report.issue(
+                    Severity::Error,
+                    seed_path,
+                    format!(
+                        "Seed file holds {} bytes, expected {}",
+                        metadata.len(),
+                        SEED_LEN
+                    ),
+                    None,
+                );
+                false
+            }
+            Err(err) => {
+                report.issue(
+                    Severity::Error,
+                    seed_path,
+                    format!("Seed file is unreadable: {err}"),
+                    None,
+                );
+                false
+            }
+        };
+    }
+
+    if storage_dir.join(SQLITE_FILE_NAME).is_file() {
+        report.issue(
+            Severity::Error,
+            storage_dir.to_path_buf(),
+            format!(
+                "Node state exists but no {SEED_FILE_NAME} or {MNEMONIC_FILE_NAME} file, the node seed is missing"
+            ),
+            None,
+        );
+    }
+
+    false
+}
+
+fn check_sqlite(storage_dir: &Path, report: &mut StoreReport) -> bool {
+    let path = storage_dir.join(SQLITE_FILE_NAME);
+    if !path.is_file() {
+        return false;
+    }
+
+    let result =
+        rusqlite::Connection::open_with_flags(&path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .and_then(|connection| {
+                connection.query_row("PRAGMA integrity_check", [], |row| row.get::<_, String>(0))
+            });
+
+    match result {
+        Ok(result) if result == "ok" => true,
+        Ok(result) => {
+            report.issue(
+                Severity::Error,
+                path,
+                format!("SQLite integrity check failed: {result}"),
+                None,
+            );
+            false
+        }
+        Err(err) => {
+            report.issue(
+                Severity::Error,
+                path,
+                format!("SQLite database could not be opened: {err}"),
+                None,
+            );
+            false
+        }
+    }
+}
+
+fn check_jsonl(path: &Path, report: &mut StoreReport) {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return;
+    };
+
+    let unreadable = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter(|line| serde_json::from_str::<serde_json::Value>(line).is_err())
+        .count();
+
+    if unreadable > 0 {
+        report.issue(
+            Severity::Warning,
+            path.to_path_buf(),
+            format!("{unreadable} unreadable lines, likely partially written"),
+            Some(Repair::KeepReadableLines),
+        );
+    }
+}
+
+fn check_json(path: &Path, report: &mut StoreReport) {
+    let Ok(contents) = fs::read(path) else {
+        return;
+    };
+
+    if serde_json::from_slice::<serde_json::Value>(&contents).is_err() {
+        report.issue(
+            Severity::Warning,
+            path.to_path_buf(),
+            "Unreadable, likely partially written".to_string(),
+            Some(Repair::Quarantine),
+        );
+    }
+}
+
+fn check_temporary_files(storage_dir: &Path, report: &mut StoreReport) {
+    let Ok(entries) = fs::read_dir(storage_dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "tmp") {
+            report.issue(
+                Severity::Warning,
+                path,
+                "Temporary file left by an interrupted write".to_string(),
+                Some(Repair::Quarantine),
+            );
+        }
+    }
+}
+
+/// Apply the repairs of a report, returning the quarantined files
+///
+/// Files are moved into a timestamped directory under [`QUARANTINE_DIR_NAME`]
+/// rather than deleted. The node must not be running.
+pub fn repair(report: &StoreReport) -> Result<Vec<PathBuf>> {
+    let quarantine_dir = report
+        .storage_dir
+        .join(QUARANTINE_DIR_NAME)
+        .join(unix_time().to_string());
+
+    let mut quarantined = vec![];
+
+    for issue in &report.issues {
+        let Some(repair) = &issue.repair else {
+            continue;
+        };
+
+        let Some(file_name) = issue.path.file_name() else {
+            continue;
+        };
+
+        fs::create_dir_all(&quarantine_dir).with_context(|| {
+            format!(
+                "Could not create quarantine directory {}",
+                quarantine_dir.display()
+            )
+        })?;
+        let target = quarantine_dir.join(file_name);
+
+        match repair {
+            Repair::Quarantine => {
+                fs::rename(&issue.path, &target)
+                    .with_context(|| format!("Could not quarantine {}", issue.path.display()))?;
+            }
+            Repair::KeepReadableLines => {
+                let contents = fs::read_to_string(&issue.path)?;
+                let readable: String = contents
+                    .lines()
+                    .filter(|line| serde_json::from_str::<serde_json::Value>(line).is_ok())
+                    .map(|line| format!("{line}\n"))
+                    .collect();
+
+                fs::rename(&issue.path, &target)
+                    .with_context(|| format!("Could not quarantine {}", issue.path.display()))?;
+                fs::write(&issue.path, readable)
+                    .with_context(|| format!("Could not rewrite {}", issue.path.display()))?;
+            }
+        }
+
+        tracing::info!("Moved {} to {}", issue.path.display(), target.display());
+        quarantined.push(target);
+    }
+
+    Ok(quarantined)
+}
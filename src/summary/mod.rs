@@ -0,0 +1,121 @@
+//! Periodic summaries of the node's activity
+//!
+//! A summary covers the payment volume, fees paid and earned, channels opened
+//! and closed and the payment failure rate over a period, with the balances
+//! at its end. Scheduled summaries are appended to a JSON lines file in the
+//! storage directory and dispatched to the notification sinks.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::activity::ActivityTotals;
+
+/// File scheduled summaries are appended to, inside the storage directory
+pub const SUMMARIES_FILE_NAME: &str = "summaries.jsonl";
+
+/// Period covered by scheduled summaries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SummaryPeriod {
+    #[default]
+    Daily,
+    Weekly,
+}
+
+impl SummaryPeriod {
+    pub fn duration(&self) -> Duration {
+        match self {
+            SummaryPeriod::Daily => Duration::from_secs(24 * 60 * 60),
+            SummaryPeriod::Weekly => Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+}
+
+impl FromStr for SummaryPeriod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "daily" => Ok(SummaryPeriod::Daily),
+            "weekly" => Ok(SummaryPeriod::Weekly),
+            other => bail!("Unknown summary period {other}, expected daily or weekly"),
+        }
+    }
+}
+
+/// Activity of the node over a period
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    /// Unix time the period started
+    pub period_start: u64,
+    /// Unix time the period ended
+    pub period_end: u64,
+    pub payments_received: u64,
+    pub received_msat: u64,
+    pub payments_sent: u64,
+    pub sent_msat: u64,
+    pub payments_failed: u64,
+    /// Routing fees paid by outgoing payments
+    pub fees_paid_msat: u64,
+    pub forwards: u64,
+    /// Fees earned on forwards
+    pub fees_earned_msat: u64,
+    pub channels_opened: u64,
+    pub channels_closed: u64,
+    /// Balances at the end of the period
+    pub onchain_balance_sat: u64,
+    pub lightning_balance_sat: u64,
+}
+
+impl Summary {
+    pub fn new(
+        totals: ActivityTotals,
+        period_start: u64,
+        period_end: u64,
+        onchain_balance_sat: u64,
+        lightning_balance_sat: u64,
+    ) -> Self {
+        Self {
+            period_start,
+            period_end,
+            payments_received: totals.payments_received,
+            received_msat: totals.received_msat,
+            payments_sent: totals.payments_sent,
+            sent_msat: totals.sent_msat,
+            payments_failed: totals.payments_failed,
+            fees_paid_msat: totals.fees_paid_msat,
+            forwards: totals.forwards,
+            fees_earned_msat: totals.fees_earned_msat,
+            channels_opened: totals.channels_opened,
+            channels_closed: totals.channels_closed,
+            onchain_balance_sat,
+            lightning_balance_sat,
+        }
+    }
+
+    /// Share of outgoing payments that failed, `None` if none were attempted
+    pub fn failure_rate(&self) -> Option<f64> {
+        let attempted = self.payments_sent + self.payments_failed;
+        (attempted > 0).then(|| self.payments_failed as f64 / attempted as f64)
+    }
+}
+
+/// Append a summary to the summaries file in the storage directory
+pub fn write_summary(storage_dir: &Path, summary: &Summary) -> Result<()> {
+    let path = storage_dir.join(SUMMARIES_FILE_NAME);
+
+    let mut line = serde_json::to_string(summary)?;
+    line.push('\n');
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| format!("Could not write summary to {}", path.display()))
+}
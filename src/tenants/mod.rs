@@ -0,0 +1,624 @@
+//! Tenants sharing one node, each with its own invoices, offers and notifications
+//!
+//! A well funded node can back several mints or apps. Every tenant gets its
+//! own payment processor port and, optionally, a management token:
+//!
+//! - Invoices, offers and deposit addresses created through a tenant's
+//!   payment processor are tagged with its id, and so are the melts it pays.
+//! - The tenant's mint only receives notifications for payments to its own
+//!   tags and can only check its own payments. The mint on the main payment
+//!   processor port receives the untagged ones.
+//! - A management request carrying a tenant token in [`TENANT_TOKEN_HEADER`]
+//!   is limited to [`TENANT_RPCS`], creates tagged invoices and offers, and
+//!   only lists the tenant's payments.
+//!
+//! Tags are kept in the storage directory. A reference belongs to the first
+//! tenant that tagged it, another tenant cannot take it over.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use cdk_common::payment::{
+    self, CreateIncomingPaymentResponse, IncomingPaymentOptions, MakePaymentResponse, MintPayment,
+    OutgoingPaymentOptions, PaymentIdentifier, PaymentQuoteResponse, WaitPaymentResponse,
+};
+use cdk_common::util::{hex, unix_time};
+use cdk_common::CurrencyUnit;
+use futures::{Stream, StreamExt};
+use ldk_node::bitcoin::hashes::{sha256, Hash};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+use tonic::body::BoxBody;
+use tonic::codegen::{http, BoxFuture, Context as TaskContext, Poll, Service};
+use tonic::server::NamedService;
+use tonic::Status;
+
+use crate::CdkLdkNode;
+
+/// File tenant tags are kept in, inside the storage directory
+pub const TENANT_TAGS_FILE_NAME: &str = "tenant_tags.jsonl";
+
+/// Metadata key a tenant passes its management token in
+pub const TENANT_TOKEN_HEADER: &str = "x-tenant-token";
+
+/// Management RPCs a tenant token may call
+pub const TENANT_RPCS: &[&str] = &[
+    "GetApiVersion",
+    "GetInfo",
+    "CreateBolt11Invoice",
+    "CreateBolt12Offer",
+    "DecodeOffer",
+    "CheckIncomingPayments",
+    "ListPayments",
+];
+
+/// Maximum length of a tenant id
+pub const MAX_TENANT_ID_LEN: usize = 64;
+
+/// Tenant of the node
+#[derive(Clone)]
+pub struct Tenant {
+    pub id: String,
+    /// Port of the tenant's payment processor, `None` when its mint does not
+    /// connect to the node
+    pub listen_port: Option<u16>,
+    /// Hash of the tenant's management token, `None` when it has none
+    token_hash: Option<sha256::Hash>,
+}
+
+impl std::fmt::Debug for Tenant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tenant")
+            .field("id", &self.id)
+            .field("listen_port", &self.listen_port)
+            .field("has_token", &self.token_hash.is_some())
+            .finish()
+    }
+}
+
+impl Tenant {
+    /// Create a tenant, failing on ids that are empty, too long or contain
+    /// characters other than ASCII letters, digits, `-` and `_`
+    pub fn new(id: String, listen_port: Option<u16>, token: Option<String>) -> Result<Self> {
+        if id.is_empty() || id.len() > MAX_TENANT_ID_LEN {
+            bail!("Tenant id must be 1 to {MAX_TENANT_ID_LEN} characters");
+        }
+        if !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("Tenant id {id} may only contain ASCII letters, digits, - and _");
+        }
+
+        let token_hash = match token {
+            Some(token) if token.is_empty() => bail!("Management token of tenant {id} is empty"),
+            Some(token) => Some(sha256::Hash::hash(token.as_bytes())),
+            None => None,
+        };
+
+        Ok(Self {
+            id,
+            listen_port,
+            token_hash,
+        })
+    }
+}
+
+/// Tenants of the node
+#[derive(Debug, Clone, Default)]
+pub struct Tenants {
+    tenants: Vec<Tenant>,
+}
+
+impl Tenants {
+    /// Fails on duplicate ids, ports or tokens
+    pub fn new(tenants: Vec<Tenant>) -> Result<Self> {
+        for (i, tenant) in tenants.iter().enumerate() {
+            for other in &tenants[..i] {
+                if tenant.id == other.id {
+                    bail!("Tenant {} is configured twice", tenant.id);
+                }
+                if tenant.listen_port.is_some() && tenant.listen_port == other.listen_port {
+                    bail!(
+                        "Tenants {} and {} listen on the same port",
+                        other.id,
+                        tenant.id
+                    );
+                }
+                if tenant.token_hash.is_some() && tenant.token_hash == other.token_hash {
+                    bail!(
+                        "Tenants {} and {} have the same management token",
+                        other.id,
+                        tenant.id
+                    );
+                }
+            }
+        }
+
+        Ok(Self { tenants })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tenants.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Tenant> {
+        self.tenants.iter()
+    }
+
+    /// Tenant a management token belongs to
+    pub fn authenticate(&self, token: &str) -> Option<&Tenant> {
+        // Compare hashes so the comparison time does not reveal the tokens
+        let hash = sha256::Hash::hash(token.as_bytes());
+        self.tenants
+            .iter()
+            .find(|tenant| tenant.token_hash == Some(hash))
+    }
+}
+
+/// Tag of an invoice, offer, deposit address or payment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantTag {
+    /// Payment hash, offer id or deposit address
+    pub reference: String,
+    pub tenant: String,
+    pub created_at: u64,
+}
+
+/// Reference a payment identifier is tagged under, `None` for identifiers
+/// that are never tagged
+pub fn reference(identifier: &PaymentIdentifier) -> Option<String> {
+    match identifier {
+        PaymentIdentifier::PaymentHash(hash) => Some(hex::encode(hash)),
+        PaymentIdentifier::OfferId(id) | PaymentIdentifier::CustomId(id) => Some(id.clone()),
+        _ => None,
+    }
+}
+
+/// Persistent record of tenant tags
+#[derive(Debug)]
+pub struct TenantTags {
+    path: PathBuf,
+    tags: Mutex<HashMap<String, String>>,
+}
+
+impl TenantTags {
+    /// Open the record in the storage directory, starting empty if none exists
+    pub fn new(storage_dir: PathBuf) -> Self {
+        let path = storage_dir.join(TENANT_TAGS_FILE_NAME);
+
+        let tags = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .filter_map(|line| match serde_json::from_str::<TenantTag>(line) {
+                    Ok(tag) => Some((tag.reference, tag.tenant)),
+                    Err(err) => {
+                        tracing::warn!(
+                            "Skipping unreadable tenant tag in {}: {}",
+                            path.display(),
+                            err
+                        );
+                        None
+                    }
+                })
+                .collect(),
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            path,
+            tags: Mutex::new(tags),
+        }
+    }
+
+    /// Tag a reference with a tenant
+    ///
+    /// Fails if the reference is tagged with another tenant, whose payments
+    /// would otherwise be handed to this one.
+    pub fn tag(&self, reference: String, tenant: &str) -> Result<()> {
+        let mut tags = self.tags.lock().expect("tenant tags lock poisoned");
+        match tags.get(&reference) {
+            Some(owner) if owner == tenant => return Ok(()),
+            Some(_) => bail!("{reference} is tagged with another tenant"),
+            None => (),
+        }
+
+        let tag = TenantTag {
+            reference,
+            tenant: tenant.to_string(),
+            created_at: unix_time(),
+        };
+        let mut line = serde_json::to_string(&tag)?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .with_context(|| format!("Could not write tenant tag to {}", self.path.display()))?;
+
+        tags.insert(tag.reference, tag.tenant);
+        Ok(())
+    }
+
+    /// Tag the reference of a payment identifier with a tenant
+    pub fn tag_identifier(&self, identifier: &PaymentIdentifier, tenant: &str) -> Result<()> {
+        match reference(identifier) {
+            Some(reference) => self.tag(reference, tenant),
+            None => Ok(()),
+        }
+    }
+
+    /// Tenant of a payment hash, offer id or deposit address
+    pub fn tenant_of(&self, reference: &str) -> Option<String> {
+        self.tags
+            .lock()
+            .expect("tenant tags lock poisoned")
+            .get(reference)
+            .cloned()
+    }
+
+    /// Tenant of a payment identifier
+    pub fn tenant_of_identifier(&self, identifier: &PaymentIdentifier) -> Option<String> {
+        reference(identifier).and_then(|reference| self.tenant_of(&reference))
+    }
+
+    /// Tenant of a received payment
+    ///
+    /// Payments to a tagged offer are tagged with the offer's tenant under
+    /// their payment hash, so they are listed for the tenant.
+    pub fn tenant_of_payment(&self, response: &WaitPaymentResponse) -> Option<String> {
+        let tenant = self.tenant_of_identifier(&response.payment_identifier)?;
+
+        if matches!(response.payment_identifier, PaymentIdentifier::OfferId(_)) {
+            if let Err(err) = self.tag(response.payment_id.clone(), &tenant) {
+                tracing::error!(
+                    "Could not tag payment {} with tenant {}: {}",
+                    response.payment_id,
+                    tenant,
+                    err
+                );
+            }
+        }
+
+        Some(tenant)
+    }
+
+    /// References tagged with a tenant
+    pub fn references(&self, tenant: &str) -> Vec<String> {
+        self.tags
+            .lock()
+            .expect("tenant tags lock poisoned")
+            .iter()
+            .filter(|(_, t)| t.as_str() == tenant)
+            .map(|(reference, _)| reference.clone())
+            .collect()
+    }
+}
+
+/// Tenant a management request is scoped to, set from its tenant token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantScope(pub String);
+
+/// Tenant a management request is scoped to, `None` for operator requests
+pub fn tenant_scope<T>(request: &tonic::Request<T>) -> Option<String> {
+    request
+        .extensions()
+        .get::<TenantScope>()
+        .map(|scope| scope.0.clone())
+}
+
+/// Management service accepting tenant tokens
+///
+/// Requests without a tenant token pass through unchanged. Requests with one
+/// are rejected unless the token is known and the RPC is one of
+/// [`TENANT_RPCS`], and carry the tenant's [`TenantScope`] otherwise.
+#[derive(Debug, Clone)]
+pub struct TenantScoped<S> {
+    inner: S,
+    tenants: Arc<Tenants>,
+}
+
+impl<S> TenantScoped<S> {
+    pub fn new(inner: S, tenants: Arc<Tenants>) -> Self {
+        Self { inner, tenants }
+    }
+}
+
+impl<S: NamedService> NamedService for TenantScoped<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S, B> Service<http::Request<B>> for TenantScoped<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        let Some(token) = request.headers().get(TENANT_TOKEN_HEADER) else {
+            return Box::pin(self.inner.call(request));
+        };
+
+        let Some(tenant) = token
+            .to_str()
+            .ok()
+            .and_then(|token| self.tenants.authenticate(token))
+            .map(|tenant| tenant.id.clone())
+        else {
+            tracing::warn!("Rejected management request with an invalid tenant token");
+            let status = Status::unauthenticated("Invalid tenant token");
+            return Box::pin(async move { Ok(status.into_http()) });
+        };
+
+        let method = request
+            .uri()
+            .path()
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        if !TENANT_RPCS.contains(&method.as_str()) {
+            let status =
+                Status::permission_denied(format!("{method} is not available to tenant {tenant}"));
+            return Box::pin(async move { Ok(status.into_http()) });
+        }
+
+        request.extensions_mut().insert(TenantScope(tenant));
+        Box::pin(self.inner.call(request))
+    }
+}
+
+/// Payment processor of a tenant's mint
+///
+/// Pays and creates payment requests through the node, tagging them with the
+/// tenant, and only streams and checks the tenant's payments.
+pub struct TenantProcessor {
+    node: Arc<CdkLdkNode>,
+    tenant: String,
+    wait_invoice_cancel_token: CancellationToken,
+    wait_invoice_is_active: Arc<AtomicBool>,
+}
+
+impl TenantProcessor {
+    pub fn new(node: Arc<CdkLdkNode>, tenant: String) -> Self {
+        // Cancelled with the node's streams, or on its own
+        let wait_invoice_cancel_token = node.wait_invoice_cancel_token.child_token();
+
+        Self {
+            node,
+            tenant,
+            wait_invoice_cancel_token,
+            wait_invoice_is_active: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Fail for identifiers not tagged with the tenant
+    fn check_tenant(&self, identifier: &PaymentIdentifier) -> Result<(), payment::Error> {
+        match self.node.tenant_tags.tenant_of_identifier(identifier) {
+            Some(tenant) if tenant == self.tenant => Ok(()),
+            _ => Err(anyhow!("Payment not found").into()),
+        }
+    }
+
+    fn tag(&self, identifier: &PaymentIdentifier) -> Result<(), payment::Error> {
+        self.node
+            .tenant_tags
+            .tag_identifier(identifier, &self.tenant)
+            .map_err(Into::into)
+    }
+}
+
+#[async_trait]
+impl MintPayment for TenantProcessor {
+    type Err = payment::Error;
+
+    async fn get_settings(&self) -> Result<serde_json::Value, Self::Err> {
+        self.node.get_settings().await
+    }
+
+    async fn create_incoming_payment_request(
+        &self,
+        unit: &CurrencyUnit,
+        options: IncomingPaymentOptions,
+    ) -> Result<CreateIncomingPaymentResponse, Self::Err> {
+        let response = self
+            .node
+            .create_incoming_payment_request(unit, options)
+            .await?;
+        self.tag(&response.request_lookup_id)?;
+        Ok(response)
+    }
+
+    async fn get_payment_quote(
+        &self,
+        unit: &CurrencyUnit,
+        options: OutgoingPaymentOptions,
+    ) -> Result<PaymentQuoteResponse, Self::Err> {
+        self.node.get_payment_quote(unit, options).await
+    }
+
+    async fn make_payment(
+        &self,
+        unit: &CurrencyUnit,
+        options: OutgoingPaymentOptions,
+    ) -> Result<MakePaymentResponse, Self::Err> {
+        let response = self.node.make_payment(unit, options).await?;
+        // The payment was made, a tagging failure must not report it failed
+        if let Err(err) = self.tag(&response.payment_lookup_id) {
+            tracing::error!(
+                "Could not tag payment {:?} with tenant {}: {}",
+                response.payment_lookup_id,
+                self.tenant,
+                err
+            );
+        }
+        Ok(response)
+    }
+
+    async fn wait_any_incoming_payment(
+        &self,
+    ) -> Result<Pin<Box<dyn Stream<Item = WaitPaymentResponse> + Send>>, Self::Err> {
+        self.wait_invoice_is_active.store(true, Ordering::SeqCst);
+
+        let tags = self.node.tenant_tags.clone();
+        let tenant = self.tenant.clone();
+        let stream = self
+            .node
+            .notifier
+            .subscribe(self.wait_invoice_cancel_token.clone())
+            .filter(move |response| {
+                let own = tags
+                    .tenant_of_identifier(&response.payment_identifier)
+                    .is_some_and(|t| t == tenant);
+                async move { own }
+            });
+        let stream = self.node.notifier.acknowledge_on_next(stream);
+
+        tracing::info!(
+            "Payment notification stream of tenant {} created, {} consumers attached",
+            self.tenant,
+            self.node.notifier.consumer_count()
+        );
+
+        let cancel_token = self.wait_invoice_cancel_token.clone();
+        let is_active = self.wait_invoice_is_active.clone();
+        tokio::spawn(async move {
+            cancel_token.cancelled().await;
+            is_active.store(false, Ordering::SeqCst);
+        });
+
+        Ok(stream)
+    }
+
+    fn is_wait_invoice_active(&self) -> bool {
+        self.wait_invoice_is_active.load(Ordering::SeqCst)
+    }
+
+    fn cancel_wait_invoice(&self) {
+        self.wait_invoice_cancel_token.cancel()
+    }
+
+    async fn check_incoming_payment_status(
+        &self,
+        payment_identifier: &PaymentIdentifier,
+    ) -> Result<Vec<WaitPaymentResponse>, Self::Err> {
+        self.check_tenant(payment_identifier)?;
+        self.node
+            .check_incoming_payment_status(payment_identifier)
+            .await
+    }
+
+    async fn check_outgoing_payment(
+        &self,
+        payment_identifier: &PaymentIdentifier,
+    ) -> Result<MakePaymentResponse, Self::Err> {
+        self.check_tenant(payment_identifier)?;
+        self.node.check_outgoing_payment(payment_identifier).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    #[test]
+    fn a_reference_cannot_be_taken_over_by_another_tenant() {
+        let tags = TenantTags::new(temp_dir("tenant-tags"));
+
+        tags.tag("hash".to_string(), "a").unwrap();
+        assert!(tags.tag("hash".to_string(), "b").is_err());
+        assert_eq!(tags.tenant_of("hash").as_deref(), Some("a"));
+
+        // Tagging again with the owner is a no-op
+        tags.tag("hash".to_string(), "a").unwrap();
+    }
+
+    #[test]
+    fn tags_are_reloaded_from_the_storage_directory() {
+        let dir = temp_dir("tenant-tags-reload");
+
+        TenantTags::new(dir.clone())
+            .tag("offer".to_string(), "a")
+            .unwrap();
+
+        let tags = TenantTags::new(dir);
+        assert_eq!(tags.tenant_of("offer").as_deref(), Some("a"));
+        assert!(tags.tag("offer".to_string(), "b").is_err());
+        assert_eq!(tags.references("a"), vec!["offer".to_string()]);
+    }
+
+    fn tenant(id: &str, listen_port: Option<u16>, token: Option<&str>) -> Tenant {
+        Tenant::new(id.to_string(), listen_port, token.map(str::to_string)).unwrap()
+    }
+
+    #[test]
+    fn tenant_ids_are_validated() {
+        assert!(Tenant::new(String::new(), None, None).is_err());
+        assert!(Tenant::new("a".repeat(MAX_TENANT_ID_LEN + 1), None, None).is_err());
+        assert!(Tenant::new("mint a".to_string(), None, None).is_err());
+        assert!(Tenant::new("mint-a".to_string(), None, Some(String::new())).is_err());
+        assert!(Tenant::new("mint_a-1".to_string(), None, None).is_ok());
+    }
+
+    #[test]
+    fn tenants_must_not_share_ids_ports_or_tokens() {
+        assert!(Tenants::new(vec![tenant("a", None, None), tenant("a", None, None)]).is_err());
+        assert!(Tenants::new(vec![
+            tenant("a", Some(8090), None),
+            tenant("b", Some(8090), None)
+        ])
+        .is_err());
+        assert!(Tenants::new(vec![
+            tenant("a", None, Some("token")),
+            tenant("b", None, Some("token"))
+        ])
+        .is_err());
+
+        // Tenants without a port or token do not clash
+        assert!(Tenants::new(vec![tenant("a", None, None), tenant("b", None, None)]).is_ok());
+    }
+
+    #[test]
+    fn tokens_authenticate_their_tenant() {
+        let tenants = Tenants::new(vec![
+            tenant("a", Some(8090), Some("token-a")),
+            tenant("b", Some(8091), Some("token-b")),
+            tenant("c", Some(8092), None),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            tenants.authenticate("token-a").map(|t| t.id.as_str()),
+            Some("a")
+        );
+        assert_eq!(
+            tenants.authenticate("token-b").map(|t| t.id.as_str()),
+            Some("b")
+        );
+        assert!(tenants.authenticate("token-c").is_none());
+        assert!(tenants.authenticate("").is_none());
+    }
+}
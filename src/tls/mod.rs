@@ -0,0 +1,100 @@
+//! TLS material of the gRPC servers
+//!
+//! The management service and the payment processor read their certificates
+//! from a TLS directory holding the server certificate and key and the CA
+//! certificate client certificates are verified against. The material is
+//! read again on rotation, so renewed certificates are picked up without
+//! restarting the node.
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+
+/// Server certificate, inside the TLS directory
+pub const SERVER_CERT_FILE_NAME: &str = "server.pem";
+
+/// Server private key, inside the TLS directory
+pub const SERVER_KEY_FILE_NAME: &str = "server.key";
+
+/// CA certificate client certificates must be signed by, inside the TLS directory
+pub const CA_CERT_FILE_NAME: &str = "ca.pem";
+
+/// Attempts to bind a restarted server while the previous one releases the port
+const BIND_ATTEMPTS: u32 = 50;
+
+/// Interval between bind attempts
+const BIND_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Certificates and key of a gRPC server, read from its TLS directory
+#[derive(Clone)]
+pub struct TlsMaterial {
+    dir: PathBuf,
+    cert: String,
+    key: String,
+    ca: String,
+}
+
+impl std::fmt::Debug for TlsMaterial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsMaterial")
+            .field("dir", &self.dir)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TlsMaterial {
+    /// Read the material from a TLS directory
+    ///
+    /// Fails if a file is missing or does not hold PEM data, so a half
+    /// written renewal is not loaded.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let read = |file_name: &str, label: &str| -> Result<String> {
+            let path = dir.join(file_name);
+            let pem = std::fs::read_to_string(&path)
+                .with_context(|| format!("Could not read {}", path.display()))?;
+            if !pem.contains(&format!("-----BEGIN {label}")) {
+                bail!("{} does not contain a PEM {}", path.display(), label);
+            }
+            Ok(pem)
+        };
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            cert: read(SERVER_CERT_FILE_NAME, "CERTIFICATE")?,
+            key: read(SERVER_KEY_FILE_NAME, "")?,
+            ca: read(CA_CERT_FILE_NAME, "CERTIFICATE")?,
+        })
+    }
+
+    /// Directory the material was read from
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Server TLS configuration requiring client certificates signed by the CA
+    pub fn server_tls_config(&self) -> ServerTlsConfig {
+        ServerTlsConfig::new()
+            .identity(Identity::from_pem(&self.cert, &self.key))
+            .client_ca_root(Certificate::from_pem(&self.ca))
+    }
+}
+
+/// Bind a listener, retrying while a server being restarted still holds the port
+pub async fn bind_with_retry(addr: SocketAddr) -> Result<tokio::net::TcpListener> {
+    let mut attempt = 1;
+
+    loop {
+        match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => return Ok(listener),
+            Err(err) if attempt < BIND_ATTEMPTS => {
+                tracing::debug!("Could not bind {} yet: {}", addr, err);
+                attempt += 1;
+                tokio::time::sleep(BIND_RETRY_INTERVAL).await;
+            }
+            Err(err) => return Err(err).with_context(|| format!("Could not bind {addr}")),
+        }
+    }
+}
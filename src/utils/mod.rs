@@ -3,6 +3,8 @@
 use std::path::PathBuf;
 
 use anyhow::Result;
+use qrcode::render::unicode;
+use qrcode::QrCode;
 use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity};
 
 /// Creates a channel for connecting to the LDK node, with optional TLS
@@ -38,7 +40,9 @@ pub fn format_payment_response(payment: &crate::proto::PaymentResponse) -> Strin
         output.push_str("Payment succeeded!\n");
         output.push_str(&format!("Payment hash: {}\n", payment.payment_hash));
         output.push_str(&format!("Payment preimage: {}\n", payment.payment_preimage));
-        output.push_str(&format!("Fee paid (msats): {}\n", payment.fee_msats));
+        output.push_str(&format!("Fee paid: {}\n", format_msat(payment.fee_msat)));
+    } else if let Some(id) = &payment.pending_approval_id {
+        output.push_str(&format!("Payment is pending approval as operation {id}\n"));
     } else {
         output.push_str(&format!(
             "Payment failed: {}\n",
@@ -52,6 +56,663 @@ pub fn format_payment_response(payment: &crate::proto::PaymentResponse) -> Strin
     output
 }
 
+/// Format the result of an onchain send for display
+pub fn format_send_onchain(response: &crate::proto::SendOnchainResponse) -> String {
+    match &response.pending_approval_id {
+        Some(id) => format!("Send is pending approval as operation {id}\n"),
+        None => format!("Transaction sent with txid: {}\n", response.txid),
+    }
+}
+
+/// Format withdrawals waiting for approval for display
+pub fn format_pending_operations(operations: &[crate::proto::PendingOperation]) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("Pending operations ({}):\n", operations.len()));
+    output.push_str("------------------\n");
+
+    for operation in operations {
+        output.push_str(&format!("ID: {}\n", operation.id));
+        output.push_str(&format!("  {}\n", operation.description));
+        output.push_str(&format!("  Amount: {} sats\n", operation.amount_sat));
+        output.push_str(&format!("  Created at: {}\n", operation.created_at));
+        output.push_str(&format!("  Expires at: {}\n", operation.expires_at));
+    }
+
+    output
+}
+
+/// Format hold invoices for display
+pub fn format_hold_invoices(invoices: &[crate::proto::HoldInvoice]) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("Hold invoices ({}):\n", invoices.len()));
+    output.push_str("-------------\n");
+
+    for invoice in invoices {
+        output.push_str(&format_hold_invoice(invoice));
+    }
+
+    output
+}
+
+/// Format a hold invoice for display
+pub fn format_hold_invoice(invoice: &crate::proto::HoldInvoice) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("Payment hash: {}\n", invoice.payment_hash));
+    output.push_str(&format!("  State: {}\n", invoice.state));
+    match invoice.amount_msat {
+        Some(amount_msat) => output.push_str(&format!("  Amount: {}\n", format_msat(amount_msat))),
+        None => output.push_str("  Amount: variable\n"),
+    }
+    if let Some(claimable_amount_msat) = invoice.claimable_amount_msat {
+        output.push_str(&format!("  Held: {}\n", format_msat(claimable_amount_msat)));
+    }
+    if let Some(claim_deadline) = invoice.claim_deadline {
+        output.push_str(&format!("  Settle before block: {claim_deadline}\n"));
+    }
+    output.push_str(&format!("  Created at: {}\n", invoice.created_at));
+
+    output
+}
+
+/// Format the result of an approved operation for display
+pub fn format_approved_operation(response: &crate::proto::ApproveOperationResponse) -> String {
+    use crate::proto::approve_operation_response::Outcome;
+
+    match &response.outcome {
+        Some(Outcome::SendOnchain(send)) => format_send_onchain(send),
+        Some(Outcome::Payment(payment)) => format_payment_response(payment),
+        None => "Operation approved\n".to_string(),
+    }
+}
+
+/// Format a decoded BOLT12 offer for display
+pub fn format_decoded_offer(offer: &crate::proto::DecodeOfferResponse) -> String {
+    let mut output = String::new();
+
+    output.push_str("Offer:\n");
+    output.push_str("------\n");
+    output.push_str(&format!("Offer ID: {}\n", offer.offer_id));
+    if let Some(description) = &offer.description {
+        output.push_str(&format!("Description: {description}\n"));
+    }
+    if let Some(issuer) = &offer.issuer {
+        output.push_str(&format!("Issuer: {issuer}\n"));
+    }
+    match (&offer.amount_msat, &offer.currency_amount) {
+        (Some(amount_msat), _) => {
+            output.push_str(&format!("Amount: {}\n", format_msat(*amount_msat)))
+        }
+        (None, Some(currency_amount)) => output.push_str(&format!("Amount: {currency_amount}\n")),
+        (None, None) => output.push_str("Amount: variable\n"),
+    }
+    output.push_str(&format!(
+        "Expires: {}\n",
+        format_optional_timestamp(offer.absolute_expiry)
+    ));
+    output.push_str(&format!("Expired: {}\n", offer.is_expired));
+    match offer.max_quantity {
+        Some(max_quantity) => output.push_str(&format!("Max quantity: {max_quantity}\n")),
+        None => output.push_str("Max quantity: unbounded\n"),
+    }
+    if let Some(issuer_signing_pubkey) = &offer.issuer_signing_pubkey {
+        output.push_str(&format!("Issuer signing pubkey: {issuer_signing_pubkey}\n"));
+    }
+    output.push_str(&format!("Blinded paths: {}\n", offer.num_blinded_paths));
+
+    output
+}
+
+/// Format a BOLT11 payment for confirmation before it is made
+pub fn format_bolt11_confirmation(
+    invoice: &ldk_node::lightning_invoice::Bolt11Invoice,
+    amount_msat: Option<u64>,
+) -> String {
+    let mut output = String::new();
+
+    output.push_str("Pay BOLT11 invoice\n");
+    match amount_msat.or(invoice.amount_milli_satoshis()) {
+        Some(amount_msat) => output.push_str(&format!("Amount: {}\n", format_msat(amount_msat))),
+        None => output.push_str("Amount: not set, the invoice has no amount\n"),
+    }
+    output.push_str(&format!("Destination: {}\n", invoice.get_payee_pub_key()));
+    output.push_str(&format!("Description: {}\n", invoice.description()));
+    output.push_str(&format!("Payment hash: {}\n", invoice.payment_hash()));
+    output.push_str("Routing fee: not known until a route is found\n");
+    if invoice.is_expired() {
+        output.push_str("Warning: the invoice has expired\n");
+    }
+
+    output
+}
+
+/// Format a BOLT12 payment for confirmation before it is made
+pub fn format_bolt12_confirmation(
+    offer: &crate::proto::DecodeOfferResponse,
+    amount_msat: Option<u64>,
+) -> String {
+    let mut output = String::new();
+
+    output.push_str("Pay BOLT12 offer\n");
+    match (amount_msat.or(offer.amount_msat), &offer.currency_amount) {
+        (Some(amount_msat), _) => {
+            output.push_str(&format!("Amount: {}\n", format_msat(amount_msat)))
+        }
+        (None, Some(currency_amount)) => output.push_str(&format!("Amount: {currency_amount}\n")),
+        (None, None) => output.push_str("Amount: not set, the offer has no amount\n"),
+    }
+    output.push_str(&format!("Offer ID: {}\n", offer.offer_id));
+    if let Some(issuer) = &offer.issuer {
+        output.push_str(&format!("Issuer: {issuer}\n"));
+    }
+    if let Some(description) = &offer.description {
+        output.push_str(&format!("Description: {description}\n"));
+    }
+    output.push_str("Routing fee: not known until a route is found\n");
+    if offer.is_expired {
+        output.push_str("Warning: the offer has expired\n");
+    }
+
+    output
+}
+
+/// Format incoming payment statuses for display
+pub fn format_incoming_payments(payments: &[crate::proto::IncomingPaymentStatus]) -> String {
+    let mut output = String::new();
+
+    for payment in payments {
+        match &payment.error {
+            Some(error) => output.push_str(&format!("{}: {}\n", payment.payment_id, error)),
+            None if payment.amount_msat > 0 => output.push_str(&format!(
+                "{}: paid {}\n",
+                payment.payment_id,
+                format_msat(payment.amount_msat)
+            )),
+            None => output.push_str(&format!("{}: unpaid\n", payment.payment_id)),
+        }
+    }
+
+    output
+}
+
+/// Format the peer list for display
+pub fn format_peers(peers: &[crate::proto::PeerInfo]) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("Peers ({}):\n", peers.len()));
+    output.push_str("------\n");
+
+    for peer in peers {
+        output.push_str(&format!("Node ID: {}\n", peer.node_id));
+        output.push_str(&format!("  Address: {}\n", peer.address));
+        output.push_str(&format!(
+            "  Connected: {}{}\n",
+            if peer.is_connected { "yes" } else { "no" },
+            peer.connected_duration_secs
+                .map(|secs| format!(" (for {secs}s)"))
+                .unwrap_or_default()
+        ));
+        output.push_str(&format!(
+            "  Persisted: {}\n",
+            if peer.is_persisted { "yes" } else { "no" }
+        ));
+        output.push_str(&format!("  Channels: {}\n", peer.num_channels));
+        output.push_str(&format!("  Reconnects: {}\n", peer.num_reconnects));
+        if let Some(at) = peer.last_reconnected_at {
+            output.push_str(&format!("  Last reconnect: {at}\n"));
+        }
+        if let Some(at) = peer.last_disconnected_at {
+            output.push_str(&format!("  Last disconnect: {at}\n"));
+        }
+        if let Some(features) = &peer.announced_features {
+            output.push_str(&format!("  Announced features: {features}\n"));
+        }
+    }
+
+    output
+}
+
+/// Format a peer diagnosis for display
+pub fn format_peer_diagnosis(diagnosis: &crate::proto::DiagnosePeerResponse) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("Peer {}:\n", diagnosis.node_id));
+    output.push_str(&format!(
+        "Address tried: {}\n",
+        diagnosis.address.as_deref().unwrap_or("none")
+    ));
+    if diagnosis.gossip_addresses.is_empty() {
+        output.push_str("Gossip addresses: none found\n");
+    } else {
+        output.push_str(&format!(
+            "Gossip addresses: {}\n",
+            diagnosis.gossip_addresses.join(", ")
+        ));
+    }
+    if let Some(alias) = &diagnosis.alias {
+        output.push_str(&format!("Alias: {alias}\n"));
+    }
+    if let Some(features) = &diagnosis.announced_features {
+        output.push_str(&format!("Announced features: {features}\n"));
+    }
+    output.push_str(&format!("Already connected: {}\n", diagnosis.was_connected));
+    output.push_str(&format!("TCP reachable: {}", diagnosis.tcp_reachable));
+    if let Some(latency) = diagnosis.tcp_latency_ms {
+        output.push_str(&format!(" ({latency} ms)"));
+    }
+    output.push('\n');
+    output.push_str(&format!("Handshake: {}", diagnosis.handshake_succeeded));
+    if let Some(latency) = diagnosis.handshake_latency_ms {
+        output.push_str(&format!(" ({latency} ms)"));
+    }
+    output.push('\n');
+    if let Some(error) = &diagnosis.error {
+        output.push_str(&format!("Error: {error}\n"));
+    }
+
+    output
+}
+
+/// Parse a channel of a batch open written as `NODE_ID@HOST:PORT=AMOUNT_SAT`
+pub fn parse_batch_channel(value: &str) -> Result<crate::proto::BatchChannel> {
+    let (target, amount_sat) = value
+        .rsplit_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected NODE_ID@HOST:PORT=AMOUNT_SAT, got {value}"))?;
+    let (node_id, address) = target
+        .split_once('@')
+        .ok_or_else(|| anyhow::anyhow!("Missing @HOST:PORT in {value}"))?;
+    let (host, port) = address
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Missing :PORT in {value}"))?;
+
+    Ok(crate::proto::BatchChannel {
+        node_id: node_id.to_string(),
+        address: host.to_string(),
+        port: port
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid port in {value}: {e}"))?,
+        amount_sat: amount_sat
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid amount in {value}: {e}"))?,
+        push_to_counter_party_msat: None,
+    })
+}
+
+/// Format the results of a batch channel open for display
+pub fn format_batch_open_channels(response: &crate::proto::BatchOpenChannelsResponse) -> String {
+    let mut output = String::new();
+
+    for channel in &response.channels {
+        match (&channel.channel_id, &channel.error) {
+            (Some(channel_id), _) => output.push_str(&format!(
+                "Opened channel with ID {} to {}\n",
+                channel_id, channel.node_id
+            )),
+            (None, error) => output.push_str(&format!(
+                "Could not open channel to {}: {}\n",
+                channel.node_id,
+                error.as_deref().unwrap_or("unknown error")
+            )),
+        }
+    }
+
+    if !response.single_transaction {
+        output.push_str("Each channel is funded by its own transaction\n");
+    }
+
+    output
+}
+
+/// Format a channel open progress update for display
+pub fn format_open_channel_progress(progress: &crate::proto::OpenChannelProgress) -> String {
+    use crate::proto::open_channel_progress::Stage;
+
+    match progress.stage() {
+        Stage::Connecting => "Connecting to peer...".to_string(),
+        Stage::FundingCreated => format!(
+            "Channel created with ID: {}",
+            progress.channel_id.as_deref().unwrap_or_default()
+        ),
+        Stage::FundingBroadcast => format!(
+            "Funding transaction broadcast: {}",
+            progress.funding_txid.as_deref().unwrap_or_default()
+        ),
+        Stage::Pending => format!(
+            "Waiting for confirmations: {}/{}",
+            progress.confirmations.unwrap_or_default(),
+            progress
+                .confirmations_required
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "?".to_string())
+        ),
+        Stage::Ready => format!(
+            "Channel {} is ready",
+            progress.channel_id.as_deref().unwrap_or_default()
+        ),
+        Stage::Failed => format!(
+            "Channel open failed: {}",
+            progress
+                .failure_reason
+                .as_deref()
+                .unwrap_or("Unknown reason")
+        ),
+    }
+}
+
+/// Format a millisatoshi amount with its satoshi equivalent
+pub fn format_msat(amount_msat: u64) -> String {
+    match amount_msat % 1000 {
+        0 => format!(
+            "{amount_msat} msat ({} sat)",
+            crate::proto::msat_to_sat(amount_msat)
+        ),
+        _ => format!(
+            "{amount_msat} msat (~{} sat)",
+            crate::proto::msat_to_sat(amount_msat)
+        ),
+    }
+}
+
+/// Format payment limits for display
+pub fn format_payment_limits(limits: &crate::proto::GetPaymentLimitsResponse) -> String {
+    let mut output = String::new();
+
+    output.push_str("Payment Limits:\n");
+    output.push_str("---------------\n");
+    output.push_str(&format!(
+        "Max receivable: {}\n",
+        format_msat(limits.max_receivable_msat)
+    ));
+    output.push_str(&format!(
+        "Max receivable (single path): {}\n",
+        format_msat(limits.max_receivable_single_path_msat)
+    ));
+    output.push_str(&format!(
+        "Max payable: {}\n",
+        format_msat(limits.max_payable_msat)
+    ));
+    output.push_str(&format!(
+        "Max payable (single path): {}\n",
+        format_msat(limits.max_payable_single_path_msat)
+    ));
+
+    output
+}
+
+/// Format a backup verification report for display
+pub fn format_backup_report(report: &crate::proto::VerifyBackupResponse) -> String {
+    let mut output = String::new();
+
+    if report.node_id_matches {
+        output.push_str("Backup verified successfully!\n");
+    } else {
+        output.push_str("Backup loads but belongs to a different node!\n");
+    }
+    output.push_str(&format!("Backup directory: {}\n", report.backup_dir));
+    output.push_str(&format!("Node ID: {}\n", report.node_id));
+    output.push_str(&format!("Channels: {}\n", report.num_channels));
+    output.push_str(&format!("Verified at: {}\n", report.verified_at));
+
+    output
+}
+
+/// Format a prune result for display
+pub fn format_prune_report(report: &crate::proto::PruneResponse) -> String {
+    let mut output = String::new();
+
+    let verb = if report.dry_run {
+        "Would prune"
+    } else {
+        "Pruned"
+    };
+    output.push_str(&format!(
+        "{verb} {} payment records\n",
+        report.payments_pruned
+    ));
+    output.push_str(&format!(
+        "{verb} {} activity records\n",
+        report.activity_records_pruned
+    ));
+    if let Some(export_path) = &report.export_path {
+        if !report.dry_run && report.payments_pruned > 0 {
+            output.push_str(&format!("Payment records exported to: {export_path}\n"));
+        }
+    }
+
+    output
+}
+
+/// Format a TLS rotation for display
+pub fn format_tls_rotation(rotation: &crate::proto::RotateTlsResponse) -> String {
+    let status = |reloaded: bool| match reloaded {
+        true => "reloaded",
+        false => "not using TLS",
+    };
+
+    format!(
+        "Management service: {}\nPayment processor: {}\n",
+        status(rotation.management_reloaded),
+        status(rotation.payment_processor_reloaded)
+    )
+}
+
+/// Format a payment listing for display
+pub fn format_payments(response: &crate::proto::ListPaymentsResponse) -> String {
+    if response.payments.is_empty() {
+        return "No payments found\n".to_string();
+    }
+
+    let mut output = String::new();
+
+    for payment in &response.payments {
+        output.push_str(&format!("Payment ID: {}\n", payment.payment_id));
+        output.push_str(&format!(
+            "  {} {} {}\n",
+            payment.direction, payment.kind, payment.status
+        ));
+        if let Some(amount_msat) = payment.amount_msat {
+            output.push_str(&format!("  Amount: {}\n", format_msat(amount_msat)));
+        }
+        if let Some(fee_paid_msat) = payment.fee_paid_msat {
+            output.push_str(&format!("  Fee: {}\n", format_msat(fee_paid_msat)));
+        }
+        if let Some(payment_hash) = &payment.payment_hash {
+            output.push_str(&format!("  Payment Hash: {payment_hash}\n"));
+        }
+        output.push_str(&format!("  Created: {}\n", payment.created_at));
+        if let Some(settled_at) = payment.settled_at {
+            output.push_str(&format!("  Settled: {settled_at}\n"));
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Format an activity summary for display
+pub fn format_summary(summary: &crate::proto::GetSummaryResponse) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!(
+        "Summary ({} to {}):\n",
+        summary.period_start, summary.period_end
+    ));
+    output.push_str("--------------\n");
+    output.push_str(&format!(
+        "Payments received: {} ({})\n",
+        summary.payments_received,
+        format_msat(summary.received_msat)
+    ));
+    output.push_str(&format!(
+        "Payments sent: {} ({})\n",
+        summary.payments_sent,
+        format_msat(summary.sent_msat)
+    ));
+    output.push_str(&format!(
+        "Payments failed: {}{}\n",
+        summary.payments_failed,
+        summary
+            .failure_rate
+            .map(|rate| format!(" ({:.1}%)", rate * 100.0))
+            .unwrap_or_default()
+    ));
+    output.push_str(&format!(
+        "Fees paid: {}\n",
+        format_msat(summary.fees_paid_msat)
+    ));
+    output.push_str(&format!(
+        "Fees earned: {} ({} forwards)\n",
+        format_msat(summary.fees_earned_msat),
+        summary.forwards
+    ));
+    output.push_str(&format!("Channels opened: {}\n", summary.channels_opened));
+    output.push_str(&format!("Channels closed: {}\n", summary.channels_closed));
+    output.push_str(&format!(
+        "Lightning balance: {} sats\n",
+        summary.lightning_balance_sat
+    ));
+    output.push_str(&format!(
+        "Onchain balance: {} sats\n",
+        summary.onchain_balance_sat
+    ));
+
+    output
+}
+
+/// Format a bench run for display
+pub fn format_bench_report(report: &crate::bench::BenchReport) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!(
+        "Payments: {} ({} concurrent)\n",
+        report.payments, report.concurrency
+    ));
+    output.push_str(&format!("Succeeded: {}\n", report.succeeded));
+    output.push_str(&format!("Failed: {}\n", report.failed));
+    output.push_str(&format!("Elapsed: {:.2}s\n", report.elapsed.as_secs_f64()));
+    output.push_str(&format!(
+        "Throughput: {:.2} payments/s\n",
+        report.throughput()
+    ));
+
+    output.push_str("\nLatency (ms)      min     mean      p50      p90      p99      max\n");
+    for (name, stats) in [
+        ("create invoice", &report.create_invoice),
+        ("pay", &report.pay),
+        ("receive", &report.receive),
+    ] {
+        let ms = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+        output.push_str(&format!(
+            "{:<14} {:>8.1} {:>8.1} {:>8.1} {:>8.1} {:>8.1} {:>8.1}\n",
+            name,
+            ms(stats.min),
+            ms(stats.mean),
+            ms(stats.p50),
+            ms(stats.p90),
+            ms(stats.p99),
+            ms(stats.max)
+        ));
+    }
+
+    if !report.failures.is_empty() {
+        output.push_str("\nFailures:\n");
+        for (reason, count) in &report.failures {
+            output.push_str(&format!("  {count} x {reason}\n"));
+        }
+    }
+
+    output
+}
+
+/// Format a storage directory check for display
+pub fn format_store_report(report: &crate::store::StoreReport) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!(
+        "Storage directory: {}\n",
+        report.storage_dir.display()
+    ));
+    if let Some(num_channels) = report.num_channels {
+        output.push_str(&format!("Channels loaded: {num_channels}\n"));
+    }
+
+    if report.issues.is_empty() {
+        output.push_str("No issues found\n");
+        return output;
+    }
+
+    for issue in &report.issues {
+        output.push_str(&format!(
+            "[{}] {}: {}\n",
+            issue.severity,
+            issue.path.display(),
+            issue.problem
+        ));
+        if issue.repair.is_some() {
+            output.push_str("  Repairable with --repair\n");
+        }
+    }
+
+    output
+}
+
+/// Format an exported seed for display
+pub fn format_seed_export(export: &crate::proto::ExportSeedResponse) -> String {
+    use crate::proto::export_seed_response::Seed;
+
+    let mut output = String::new();
+
+    match &export.seed {
+        Some(Seed::Mnemonic(mnemonic)) => {
+            output.push_str(&format!("Mnemonic: {mnemonic}\n"));
+            if export.has_passphrase {
+                output.push_str("A BIP39 passphrase is set, it is required to import this seed\n");
+            }
+        }
+        Some(Seed::RawSeedHex(raw_seed)) => {
+            output.push_str(&format!("Raw seed: {raw_seed}\n"));
+            output.push_str(
+                "Node was created before mnemonic support, move it by copying the keys_seed file\n",
+            );
+        }
+        None => output.push_str("No seed returned\n"),
+    }
+
+    output
+}
+
+/// Format onchain wallet descriptors for display
+pub fn format_wallet_descriptors(
+    descriptors: &crate::proto::GetWalletDescriptorsResponse,
+) -> String {
+    let mut output = String::new();
+
+    output.push_str("Wallet Descriptors (watch-only):\n");
+    output.push_str("--------------------------------\n");
+    output.push_str(&format!("Receive: {}\n", descriptors.external_descriptor));
+    output.push_str(&format!("Change: {}\n", descriptors.internal_descriptor));
+
+    output
+}
+
+/// Format onchain labels as BIP329 JSON lines
+pub fn format_labels_bip329(labels: &[crate::proto::Label]) -> Result<String> {
+    let mut output = String::new();
+
+    for label in labels {
+        output.push_str(&serde_json::to_string(&serde_json::json!({
+            "type": label.r#type,
+            "ref": label.r#ref,
+            "label": label.label,
+        }))?);
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
 /// Format node information for display
 pub fn format_node_info(info: &crate::proto::GetInfoResponse) -> String {
     let mut output = String::new();
@@ -61,9 +722,37 @@ pub fn format_node_info(info: &crate::proto::GetInfoResponse) -> String {
     output.push_str(&format!("Node ID: {}\n", info.node_id));
     output.push_str(&format!("Alias: {}\n", info.alias));
     output.push_str(&format!(
-        "Listening Addresses: {}\n",
-        info.listening_addresses.join(", ")
+        "Server version: {} (API version {})\n",
+        info.server_version, info.api_version
     ));
+    output.push_str(&format!(
+        "Listening: {}\n",
+        if info.is_listening { "yes" } else { "no" }
+    ));
+    if info.listening_address_details.is_empty() {
+        output.push_str(&format!(
+            "Listening Addresses: {}\n",
+            info.listening_addresses.join(", ")
+        ));
+    } else {
+        output.push_str("Listening Addresses:\n");
+        for listening in &info.listening_address_details {
+            output.push_str(&format!(
+                "  {} (bound: {}, {})\n",
+                listening.address,
+                if listening.bound_sockets.is_empty() {
+                    "unresolved".to_string()
+                } else {
+                    listening.bound_sockets.join(", ")
+                },
+                if listening.announced_in_gossip {
+                    "announced in gossip"
+                } else {
+                    "not announced"
+                }
+            ));
+        }
+    }
     output.push_str(&format!(
         "Announcement Addresses: {}\n",
         info.announcement_addresses.join(", ")
@@ -85,22 +774,210 @@ pub fn format_node_info(info: &crate::proto::GetInfoResponse) -> String {
     output
 }
 
+/// Build `pubkey@host:port` connect strings for the node
+///
+/// Announced addresses are preferred, listening addresses are used if the node
+/// doesn't announce any.
+pub fn node_connect_strings(info: &crate::proto::GetInfoResponse) -> Vec<String> {
+    let addresses = if info.announcement_addresses.is_empty() {
+        &info.listening_addresses
+    } else {
+        &info.announcement_addresses
+    };
+
+    addresses
+        .iter()
+        .map(|address| format!("{}@{}", info.node_id, address))
+        .collect()
+}
+
+/// Render data as a QR code for the terminal
+pub fn format_qr(data: &str) -> Result<String> {
+    let code = QrCode::new(data.as_bytes())?;
+
+    Ok(code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .quiet_zone(true)
+        .build())
+}
+
+/// Format node status information for display
+pub fn format_node_status(status: &crate::proto::GetNodeStatusResponse) -> String {
+    let mut output = String::new();
+
+    output.push_str("Node Status:\n");
+    output.push_str("------------\n");
+    output.push_str(&format!("State: {}\n", status.state));
+    output.push_str(&format!("Running: {}\n", status.is_running));
+    output.push_str(&format!("Listening: {}\n", status.is_listening));
+    output.push_str(&format!(
+        "Best block: {} ({})\n",
+        status.best_block_height, status.best_block_hash
+    ));
+    output.push_str(&format!(
+        "Latest lightning wallet sync: {}\n",
+        format_optional_timestamp(status.latest_lightning_wallet_sync_timestamp)
+    ));
+    output.push_str(&format!(
+        "Latest onchain wallet sync: {}\n",
+        format_optional_timestamp(status.latest_onchain_wallet_sync_timestamp)
+    ));
+    output.push_str(&format!(
+        "Latest fee rate update: {}\n",
+        format_optional_timestamp(status.latest_fee_rate_cache_update_timestamp)
+    ));
+    output.push_str(&format!(
+        "Latest RGS snapshot: {}\n",
+        format_optional_timestamp(status.latest_rgs_snapshot_timestamp)
+    ));
+
+    if let Some(chain_source) = &status.chain_source {
+        output.push_str(&format!("Chain source: {}\n", chain_source.active));
+        output.push_str(&format!("  Healthy: {}\n", chain_source.healthy));
+        if let Some(tip_height) = chain_source.tip_height {
+            output.push_str(&format!("  Tip height: {tip_height}\n"));
+        }
+        if let Some(fallback_tip_height) = chain_source.fallback_tip_height {
+            output.push_str(&format!("  Fallback tip height: {fallback_tip_height}\n"));
+        }
+        if let Some(last_error) = &chain_source.last_error {
+            output.push_str(&format!("  Last error: {last_error}\n"));
+        }
+        output.push_str(&format!(
+            "  Last checked: {}\n",
+            format_optional_timestamp(chain_source.last_checked)
+        ));
+        if !chain_source.fallbacks.is_empty() {
+            output.push_str(&format!(
+                "  Fallbacks: {}\n",
+                chain_source.fallbacks.join(", ")
+            ));
+        }
+    }
+
+    output
+}
+
+/// Format network graph status for display
+pub fn format_graph_status(graph: &crate::proto::WarmupGraphResponse) -> String {
+    let mut output = String::new();
+
+    output.push_str("Network Graph:\n");
+    output.push_str("--------------\n");
+    output.push_str(&format!("Channels: {}\n", graph.num_channels));
+    output.push_str(&format!("Nodes: {}\n", graph.num_nodes));
+    output.push_str(&format!("Ready: {}\n", graph.is_ready));
+    output.push_str(&format!(
+        "Latest channel update: {}\n",
+        format_optional_timestamp(graph.latest_update_timestamp)
+    ));
+    output.push_str(&format!(
+        "Latest RGS snapshot: {}\n",
+        format_optional_timestamp(graph.latest_rgs_snapshot_timestamp)
+    ));
+    if let Some(staleness) = graph.staleness_seconds {
+        output.push_str(&format!("Staleness: {staleness} seconds\n"));
+    }
+
+    output
+}
+
+fn format_optional_timestamp(timestamp: Option<u64>) -> String {
+    timestamp
+        .map(|t| t.to_string())
+        .unwrap_or_else(|| "never".to_string())
+}
+
+/// Format log entries, one per line
+pub fn format_log_entries(entries: &[crate::proto::LogEntry]) -> String {
+    let mut output = String::new();
+
+    for entry in entries {
+        output.push_str(&format!(
+            "{} {:>5} {}: {}\n",
+            entry.timestamp, entry.level, entry.target, entry.message
+        ));
+    }
+
+    output
+}
+
+/// Format a wallet sync report for display
+pub fn format_wallet_sync(report: &crate::proto::SyncWalletsResponse) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("Wallets synced in {} ms\n", report.duration_ms));
+    output.push_str(&format!(
+        "Best block: {} ({})\n",
+        report.best_block_height, report.best_block_hash
+    ));
+    output.push_str(&format!(
+        "Latest onchain wallet sync: {}\n",
+        format_optional_timestamp(report.latest_onchain_wallet_sync_timestamp)
+    ));
+    output.push_str(&format!(
+        "Latest lightning wallet sync: {}\n",
+        format_optional_timestamp(report.latest_lightning_wallet_sync_timestamp)
+    ));
+
+    output
+}
+
 /// Format balance information for display
 pub fn format_balance_info(balance: &crate::proto::ListBalanceResponse) -> String {
     let mut output = String::new();
 
     output.push_str(&format!(
         "Total onchain balance (sats): {}\n",
-        balance.total_onchain_balance_sats
+        balance.total_onchain_balance_sat
     ));
     output.push_str(&format!(
         "Spendable onchain balance (sats): {}\n",
-        balance.spendable_onchain_balance_sats
+        balance.spendable_onchain_balance_sat
     ));
     output.push_str(&format!(
         "Total lightning balance (sats): {}\n",
-        balance.total_lightning_balance_sats
+        balance.total_lightning_balance_sat
+    ));
+    output.push_str(&format!(
+        "Onchain reserve (sats): {}\n",
+        balance.onchain_reserve_sat
+    ));
+    output.push_str(&format!(
+        "Available above reserve (sats): {}\n",
+        balance.available_onchain_balance_sat
+    ));
+
+    output
+}
+
+/// Format the plan of a dry run for display
+pub fn format_dry_run_plan(plan: &crate::proto::DryRunPlan) -> String {
+    format!("Dry run, nothing was executed\n{}", format_plan(plan))
+}
+
+/// Format the plan of an action for display
+pub fn format_plan(plan: &crate::proto::DryRunPlan) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("Action: {}\n", plan.action));
+    output.push_str(&format!(
+        "Spendable onchain balance after (sats, before fees): {}\n",
+        plan.spendable_onchain_balance_after_sat
+    ));
+    output.push_str(&format!(
+        "Total lightning balance after (sats): {}\n",
+        plan.total_lightning_balance_after_sat
+    ));
+    output.push_str(&format!(
+        "Onchain reserve (sats): {}\n",
+        plan.onchain_reserve_sat
     ));
+    for warning in &plan.warnings {
+        output.push_str(&format!("Warning: {warning}\n"));
+    }
 
     output
 }
@@ -122,14 +999,17 @@ pub fn format_channels_info(response: &crate::proto::ListChannelsResponse) -> St
                 "  Counterparty: {}\n",
                 channel.counterparty_node_id
             ));
-            output.push_str(&format!("  Balance: {} msats\n", channel.balance_msat));
             output.push_str(&format!(
-                "  Outbound Capacity: {} msats\n",
-                channel.outbound_capacity_msat
+                "  Channel Value: {} sats\n",
+                channel.channel_value_sat
             ));
             output.push_str(&format!(
-                "  Inbound Capacity: {} msats\n",
-                channel.inbound_capacity_msat
+                "  Outbound Capacity: {}\n",
+                format_msat(channel.outbound_capacity_msat)
+            ));
+            output.push_str(&format!(
+                "  Inbound Capacity: {}\n",
+                format_msat(channel.inbound_capacity_msat)
             ));
             output.push_str(&format!("  Usable: {}\n", channel.is_usable));
             output.push_str(&format!("  Public: {}\n", channel.is_public));
@@ -145,3 +1025,168 @@ pub fn format_channels_info(response: &crate::proto::ListChannelsResponse) -> St
 
     output
 }
+
+/// Format channel balances per counterparty for display
+pub fn format_counterparty_balances(
+    response: &crate::proto::ListCounterpartyBalancesResponse,
+) -> String {
+    let mut output = String::new();
+
+    output.push_str("Channel Balances by Counterparty:\n");
+    output.push_str("--------------------------------\n");
+
+    if response.counterparties.is_empty() {
+        output.push_str("No channels found.\n");
+    }
+
+    for counterparty in &response.counterparties {
+        output.push_str(&format!("{}:\n", counterparty.counterparty_node_id));
+        output.push_str(&format!(
+            "  Channels: {} ({} usable)\n",
+            counterparty.num_channels, counterparty.num_usable_channels
+        ));
+        output.push_str(&format!("  Capacity: {} sats\n", counterparty.capacity_sat));
+        output.push_str(&format!(
+            "  Spendable Outbound: {}\n",
+            format_msat(counterparty.spendable_outbound_msat)
+        ));
+        output.push_str(&format!(
+            "  Receivable Inbound: {}\n",
+            format_msat(counterparty.receivable_inbound_msat)
+        ));
+        output.push_str(&format!(
+            "  Reserve Locked: {} sats\n",
+            counterparty.reserve_sat
+        ));
+        output.push_str(&format!(
+            "  Counterparty Reserve: {} sats\n",
+            counterparty.counterparty_reserve_sat
+        ));
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Format a channel utilization report for display
+pub fn format_channel_report(report: &crate::proto::ChannelReportResponse) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!(
+        "Channel Report ({} to {}):\n",
+        report.period_start, report.period_end
+    ));
+    output.push_str("--------------\n");
+    output.push_str(&format!(
+        "Payments sent: {} (fees paid {})\n",
+        format_msat(report.payments_sent_msat),
+        format_msat(report.fees_paid_msat)
+    ));
+    output.push_str(&format!(
+        "Payments received: {}\n",
+        format_msat(report.payments_received_msat)
+    ));
+
+    for channel in &report.channels {
+        output.push('\n');
+        output.push_str(&format!("Channel ID: {}\n", channel.channel_id));
+        output.push_str(&format!(
+            "  Counterparty: {}\n",
+            channel.counterparty_node_id
+        ));
+        output.push_str(&format!(
+            "  Channel Value: {} sats\n",
+            channel.channel_value_sat
+        ));
+        output.push_str(&format!("  Usable: {}\n", channel.is_usable));
+        output.push_str(&format!("  Forwards: {}\n", channel.num_forwards));
+        output.push_str(&format!(
+            "  Forwarded in: {}\n",
+            format_msat(channel.forwarded_in_msat)
+        ));
+        output.push_str(&format!(
+            "  Forwarded out: {}\n",
+            format_msat(channel.forwarded_out_msat)
+        ));
+        output.push_str(&format!(
+            "  Fees earned: {}\n",
+            format_msat(channel.fees_earned_msat)
+        ));
+        output.push_str(&format!("  Downtime: {}s\n", channel.downtime_secs));
+    }
+
+    output
+}
+
+/// Format a signed amount of millisatoshis
+fn format_signed_msat(amount_msat: i64) -> String {
+    let sign = if amount_msat < 0 { "-" } else { "" };
+    format!("{sign}{}", format_msat(amount_msat.unsigned_abs()))
+}
+
+pub fn format_fee_breakdown(breakdown: &crate::proto::GetFeeBreakdownResponse) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!(
+        "Fee Breakdown ({} to {}):\n",
+        breakdown.from, breakdown.to
+    ));
+    output.push_str("--------------\n");
+
+    let mut total = crate::proto::MonthlyFees {
+        month: "Total".to_string(),
+        ..Default::default()
+    };
+
+    for month in &breakdown.months {
+        total.routing_income_msat += month.routing_income_msat;
+        total.lightning_fees_paid_msat += month.lightning_fees_paid_msat;
+        total.onchain_fees_paid_msat += month.onchain_fees_paid_msat;
+        total.net_msat += month.net_msat;
+    }
+
+    for month in breakdown.months.iter().chain(std::iter::once(&total)) {
+        output.push_str(&format!("{}:\n", month.month));
+        output.push_str(&format!(
+            "  Routing income: {}\n",
+            format_msat(month.routing_income_msat)
+        ));
+        output.push_str(&format!(
+            "  Lightning fees paid: {}\n",
+            format_msat(month.lightning_fees_paid_msat)
+        ));
+        output.push_str(&format!(
+            "  Onchain fees paid: {}\n",
+            format_msat(month.onchain_fees_paid_msat)
+        ));
+        output.push_str(&format!("  Net: {}\n", format_signed_msat(month.net_msat)));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NODE_ID: &str = "02eadbd9e7557375161df8b646776a547c5cbc2e95b3071ec81553f8ec2cea3b8c";
+
+    #[test]
+    fn batch_channels_are_parsed_with_their_amount_in_sats() {
+        let channel = parse_batch_channel(&format!("{NODE_ID}@127.0.0.1:9735=100000")).unwrap();
+
+        assert_eq!(channel.node_id, NODE_ID);
+        assert_eq!(channel.address, "127.0.0.1");
+        assert_eq!(channel.port, 9735);
+        assert_eq!(channel.amount_sat, 100_000);
+        assert_eq!(channel.push_to_counter_party_msat, None);
+    }
+
+    #[test]
+    fn malformed_batch_channels_are_rejected() {
+        assert!(parse_batch_channel(&format!("{NODE_ID}@127.0.0.1:9735")).is_err());
+        assert!(parse_batch_channel("127.0.0.1:9735=100000").is_err());
+        assert!(parse_batch_channel(&format!("{NODE_ID}@127.0.0.1=100000")).is_err());
+        assert!(parse_batch_channel(&format!("{NODE_ID}@127.0.0.1:9735=1sat")).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+//! Onchain wallet descriptors
+//!
+//! LDK Node derives its onchain wallet from the node seed using the BIP84
+//! templates. The same derivation is repeated here so the public descriptors
+//! can be handed to watch-only tools without exposing the spend keys.
+
+use anyhow::{Context, Result};
+use bdk_wallet::descriptor::DescriptorTemplate;
+use bdk_wallet::template::Bip84;
+use bdk_wallet::KeychainKind;
+use ldk_node::bitcoin::bip32::Xpriv;
+use ldk_node::bitcoin::Network;
+
+use crate::seed::NodeSeed;
+
+/// Public descriptors of the onchain wallet
+#[derive(Debug, Clone)]
+pub struct WalletDescriptors {
+    /// Descriptor for receive addresses
+    pub external: String,
+    /// Descriptor for change addresses
+    pub internal: String,
+}
+
+/// Derive the public descriptors of the onchain wallet from the node seed
+pub fn wallet_descriptors(seed: &NodeSeed, network: Network) -> Result<WalletDescriptors> {
+    let seed_bytes = seed.seed_bytes()?;
+    let xprv = Xpriv::new_master(network, &seed_bytes).context("Could not derive master key")?;
+
+    Ok(WalletDescriptors {
+        external: public_descriptor(xprv, KeychainKind::External, network)?,
+        internal: public_descriptor(xprv, KeychainKind::Internal, network)?,
+    })
+}
+
+fn public_descriptor(xprv: Xpriv, keychain: KeychainKind, network: Network) -> Result<String> {
+    // The secret keys end up in the key map, the descriptor itself only holds
+    // the extended public key with its origin
+    let (descriptor, _key_map, _networks) = Bip84(xprv, keychain)
+        .build(network)
+        .context("Could not build wallet descriptor")?;
+
+    Ok(descriptor.to_string())
+}
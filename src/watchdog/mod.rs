@@ -0,0 +1,158 @@
+//! Watchdog for stuck outgoing payments
+//!
+//! An outgoing payment stays pending while its HTLCs are unresolved, which
+//! keeps the melt and the channel capital locked up. The watchdog flags
+//! outgoing payments pending for longer than a threshold with a
+//! `stuck_payment` alert, resolved once the payment leaves the pending state.
+//!
+//! With the `abandon` policy, stuck payments are also abandoned once they have
+//! been pending for the melt cancellation window, see [`crate::abandon`]. LDK
+//! Node refuses to pay a payment hash again, so stuck payments are not retried.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use cdk_common::util::hex;
+use ldk_node::payment::PaymentDetails;
+
+use crate::notifications::sinks::NodeNotification;
+
+/// Default interval between checks for stuck payments
+pub const DEFAULT_WATCHDOG_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Default time an outgoing payment is pending before it is flagged as stuck
+pub const DEFAULT_STUCK_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// What the watchdog does about stuck payments
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StuckPaymentPolicy {
+    /// Raise an alert
+    #[default]
+    Alert,
+    /// Raise an alert and abandon the payment once the melt cancellation
+    /// window has passed
+    Abandon,
+}
+
+impl FromStr for StuckPaymentPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "alert" => Ok(StuckPaymentPolicy::Alert),
+            "abandon" => Ok(StuckPaymentPolicy::Abandon),
+            other => bail!("Unknown stuck payment policy {other}, expected alert or abandon"),
+        }
+    }
+}
+
+/// Stuck payment watchdog settings
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    /// Flag outgoing payments pending for longer than this
+    pub stuck_after: Duration,
+    pub policy: StuckPaymentPolicy,
+    /// Interval between checks
+    pub interval: Duration,
+}
+
+/// Outgoing payment pending for longer than the threshold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StuckPayment {
+    /// Hex encoded payment id
+    pub payment_id: String,
+    pub amount_msat: Option<u64>,
+    /// Seconds since the payment's latest update
+    pub pending_for: u64,
+}
+
+/// Payments among `payments` pending for longer than `stuck_after`
+///
+/// Only pending outgoing payments are expected.
+pub fn stuck_payments(
+    payments: &[PaymentDetails],
+    stuck_after: Duration,
+    now: u64,
+) -> Vec<StuckPayment> {
+    payments
+        .iter()
+        .filter_map(|payment| {
+            let pending_for = now.saturating_sub(payment.latest_update_timestamp);
+            (pending_for > stuck_after.as_secs()).then(|| StuckPayment {
+                payment_id: hex::encode(payment.id.0),
+                amount_msat: payment.amount_msat,
+                pending_for,
+            })
+        })
+        .collect()
+}
+
+/// Tracks which stuck payments have been flagged
+#[derive(Debug)]
+pub struct PaymentWatchdog {
+    config: WatchdogConfig,
+    flagged: Mutex<HashSet<String>>,
+}
+
+impl PaymentWatchdog {
+    pub fn new(config: WatchdogConfig) -> Self {
+        Self {
+            config,
+            flagged: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn config(&self) -> &WatchdogConfig {
+        &self.config
+    }
+
+    /// Compare the stuck payments with the flagged ones, returning an alert
+    /// for every payment that got stuck or is no longer stuck
+    pub fn check(&self, stuck: &[StuckPayment], now: u64) -> Vec<NodeNotification> {
+        let mut flagged = self.flagged.lock().expect("watchdog lock poisoned");
+        let mut alerts = vec![];
+
+        for payment in stuck {
+            if flagged.insert(payment.payment_id.clone()) {
+                tracing::warn!(
+                    "Payment {} pending for {}s",
+                    payment.payment_id,
+                    payment.pending_for
+                );
+                alerts.push(NodeNotification::Alert {
+                    rule: "stuck_payment".to_string(),
+                    message: format!(
+                        "Payment {} of {} msat pending for {}s",
+                        payment.payment_id,
+                        payment.amount_msat.unwrap_or_default(),
+                        payment.pending_for
+                    ),
+                    resolved: false,
+                    timestamp: now,
+                });
+            }
+        }
+
+        let unstuck: Vec<String> = flagged
+            .iter()
+            .filter(|id| !stuck.iter().any(|payment| &payment.payment_id == *id))
+            .cloned()
+            .collect();
+
+        for payment_id in unstuck {
+            tracing::info!("Payment {} no longer stuck", payment_id);
+            flagged.remove(&payment_id);
+            alerts.push(NodeNotification::Alert {
+                rule: "stuck_payment".to_string(),
+                message: format!("Payment {payment_id} no longer stuck"),
+                resolved: true,
+                timestamp: now,
+            });
+        }
+
+        alerts
+    }
+}